@@ -0,0 +1,92 @@
+//! The cost of building and polling a `LocalFutureObj`.
+//!
+//! Run with `cargo bench --bench local_obj`. Each poll bench polls its obj
+//! 100 times per iteration, and each construction bench builds one obj.
+#![feature(test, pin, arbitrary_self_types, futures_api)]
+#![cfg(feature = "alloc")]
+
+extern crate specialized_futures;
+extern crate test;
+
+use std::mem::PinMut;
+use specialized_futures::{Context, Future, LocalFutureObj, Spawn};
+use specialized_futures::spawn::NoSpawn;
+use specialized_futures::task::{Poll, noop_local_waker, noop_waker};
+use test::Bencher;
+
+/// A future which is ready on every poll, so that it can be polled
+/// repeatedly.
+struct AlwaysReady(u64);
+
+impl Future<dyn Spawn> for AlwaysReady {
+    type Output = u64;
+
+    fn poll(mut self: PinMut<Self>, _cx: &mut Context) -> Poll<u64> {
+        self.0 = self.0.wrapping_add(1);
+        Poll::Ready(self.0)
+    }
+}
+
+/// A future which never completes.
+struct Spin(u64);
+
+impl Future<dyn Spawn> for Spin {
+    type Output = u64;
+
+    fn poll(mut self: PinMut<Self>, _cx: &mut Context) -> Poll<u64> {
+        self.0 = self.0.wrapping_add(1);
+        Poll::Pending
+    }
+}
+
+fn bench_polls(b: &mut Bencher, mut obj: LocalFutureObj<u64, dyn Spawn>) {
+    let (lw, w) = (noop_local_waker(), noop_waker());
+    let mut spawner = NoSpawn;
+    let mut cx = Context::new(&lw, &w, &mut spawner as &mut dyn Spawn);
+    b.iter(|| {
+        for _ in 0..100 {
+            test::black_box(PinMut::new(&mut obj).poll(&mut cx));
+        }
+    });
+}
+
+#[bench]
+fn build_obj_from_mut_ref(b: &mut Bencher) {
+    let mut future = Spin(0);
+    b.iter(|| {
+        let obj: LocalFutureObj<u64, dyn Spawn> = LocalFutureObj::new(&mut future);
+        test::black_box(obj);
+    });
+}
+
+#[bench]
+fn build_obj_from_box(b: &mut Bencher) {
+    b.iter(|| {
+        let obj: LocalFutureObj<u64, dyn Spawn> = LocalFutureObj::new(Box::new(Spin(0)));
+        test::black_box(obj)
+    });
+}
+
+#[bench]
+fn poll_ready_obj(b: &mut Bencher) {
+    bench_polls(b, LocalFutureObj::new(Box::new(AlwaysReady(0))));
+}
+
+#[bench]
+fn poll_pending_obj(b: &mut Bencher) {
+    bench_polls(b, LocalFutureObj::new(Box::new(Spin(0))));
+}
+
+#[bench]
+fn poll_pending_future_directly(b: &mut Bencher) {
+    // The baseline without an obj's indirect call.
+    let (lw, w) = (noop_local_waker(), noop_waker());
+    let mut spawner = NoSpawn;
+    let mut cx = Context::new(&lw, &w, &mut spawner as &mut dyn Spawn);
+    let mut future = Spin(0);
+    b.iter(|| {
+        for _ in 0..100 {
+            test::black_box(PinMut::new(&mut future).poll(&mut cx));
+        }
+    });
+}
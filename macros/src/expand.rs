@@ -0,0 +1,436 @@
+//! Expansion of `#[specialized]`, written against `proc_macro2` so that it
+//! can be tested outside of a procedural macro.
+
+use proc_macro2::{Delimiter, Span, TokenStream as TokenStream2, TokenTree};
+use syn::{Expr, ExprClosure, ExprVerbatim, Ident, Item, ItemFn, Macro, ReturnType, Stmt, Type, parse2};
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::visit_mut::{self, VisitMut};
+
+pub fn expand(attr: TokenStream2, item: TokenStream2) -> TokenStream2 {
+    match expand_fn(attr, item) {
+        Ok(tokens) => tokens,
+        Err(error) => error.into_tokens(),
+    }
+}
+
+/// An error reported through `compile_error!` at the given span.
+struct Error {
+    span: Span,
+    message: &'static str,
+}
+
+impl Error {
+    fn new(span: Span, message: &'static str) -> Error {
+        Error { span, message }
+    }
+
+    fn into_tokens(self) -> TokenStream2 {
+        let message = self.message;
+        quote_spanned!(self.span=> compile_error!(#message);)
+    }
+}
+
+const MISPLACED_AWAIT: &str = "`await_!` can only be used at the start of a statement, of the \
+                               initializer of a `let`, or of the final expression of a \
+                               #[specialized] function";
+
+fn parse_spawner(attr: TokenStream2) -> Result<Type, Error> {
+    let mut tokens: Vec<TokenTree> = attr.into_iter().collect();
+    // Some compilers pass the parentheses around the arguments along.
+    if tokens.len() == 1 {
+        let inner = match tokens[0] {
+            TokenTree::Group(ref group) if group.delimiter() == Delimiter::Parenthesis => {
+                Some(group.stream())
+            }
+            _ => None,
+        };
+        if let Some(inner) = inner {
+            tokens = inner.into_iter().collect();
+        }
+    }
+    if tokens.is_empty() {
+        return Ok(parse_quote!(::specialized_futures::macro_support::DynSpawn));
+    }
+
+    let is_key = match (&tokens[0], tokens.get(1)) {
+        (&TokenTree::Ident(ref key), Some(&TokenTree::Punct(ref eq))) => {
+            key == "spawner" && eq.as_char() == '='
+        }
+        _ => false,
+    };
+    if !is_key {
+        return Err(Error::new(tokens[0].span(), "expected `spawner = <type>`"));
+    }
+    let span = tokens[0].span();
+    parse2(tokens[2..].iter().cloned().collect())
+        .map_err(|_| Error::new(span, "expected a type after `spawner =`"))
+}
+
+fn expand_fn(attr: TokenStream2, item: TokenStream2) -> Result<TokenStream2, Error> {
+    let spawner = parse_spawner(attr)?;
+    let item: ItemFn = parse2(item)
+        .map_err(|_| Error::new(Span::call_site(), "#[specialized] can only be applied to a function"))?;
+    if let Some(constness) = item.constness {
+        return Err(Error::new(constness.span(), "a #[specialized] function can't be `const`"));
+    }
+
+    let ItemFn { attrs, vis, unsafety, abi, ident, decl, block, .. } = item;
+    let output = match decl.output {
+        ReturnType::Default => quote!(()),
+        ReturnType::Type(_, ref ty) => quote!(#ty),
+    };
+    let body = Body::split(block.stmts)?;
+    let machine = body.into_state_machine(&ident, &spawner, &output);
+
+    let generics = &decl.generics;
+    let where_clause = &decl.generics.where_clause;
+    let inputs = &decl.inputs;
+    Ok(quote! {
+        #(#attrs)*
+        #vis #unsafety #abi fn #ident #generics(#inputs)
+            -> impl ::specialized_futures::Future<#spawner, Output = #output>
+            #where_clause
+        {
+            #machine
+        }
+    })
+}
+
+/// An `await_!` which ends one segment of the body and starts the next.
+struct Await {
+    future: Expr,
+    /// The parameter of the closure which receives the future's output.
+    input: TokenStream2,
+}
+
+/// A function body, split at its awaits.
+struct Body {
+    /// One more segment than there are awaits.
+    segments: Vec<Vec<Stmt>>,
+    awaits: Vec<Await>,
+}
+
+impl Body {
+    fn split(stmts: Vec<Stmt>) -> Result<Body, Error> {
+        let mut body = Body { segments: vec![Vec::new()], awaits: Vec::new() };
+        for stmt in stmts {
+            match split_at_await(&stmt)? {
+                Some((awaited, prelude)) => {
+                    body.awaits.push(awaited);
+                    body.segments.push(prelude);
+                }
+                None => {
+                    check_no_await(&stmt)?;
+                    body.segments.last_mut().unwrap().push(stmt);
+                }
+            }
+        }
+        Ok(body)
+    }
+
+    fn into_state_machine(self, name: &Ident, spawner: &Type, output: &TokenStream2) -> TokenStream2 {
+        let Body { segments, awaits } = self;
+        let count = awaits.len();
+        let support = quote!(::specialized_futures::macro_support);
+        let option = quote!(::specialized_futures::core_reexport::option::Option);
+        let result = quote!(::specialized_futures::core_reexport::result::Result);
+        let pin_mut = quote!(::specialized_futures::core_reexport::mem::PinMut);
+        let poll = quote!(::specialized_futures::task::Poll);
+        let future = quote!(::specialized_futures::Future);
+
+        let k: Vec<Ident> = (0..count + 1).map(|i| Ident::new(&format!("__K{}", i), Span::call_site())).collect();
+        let f: Vec<Ident> = (0..count + 1).map(|i| Ident::new(&format!("__F{}", i), Span::call_site())).collect();
+        let state: Vec<Ident> = (0..count + 1).map(|i| Ident::new(&format!("Await{}", i), Span::call_site())).collect();
+
+        // What the closure for each segment returns: the next await, or the
+        // output of the whole function after the last segment.
+        let returns: Vec<TokenStream2> = (0..count + 1)
+            .map(|i| if i == count {
+                quote!(__T)
+            } else {
+                let (f, k) = (&f[i + 1], &k[i + 1]);
+                quote!(#support::Step<#f, #k, __T>)
+            })
+            .collect();
+        let transitions: Vec<TokenStream2> = (0..count + 1)
+            .map(|i| if i == count {
+                quote!(#result::Err(next))
+            } else {
+                let state = &state[i + 1];
+                quote! {
+                    match next {
+                        #support::Step::Await(future, then) => {
+                            #result::Ok(__State::#state(future, #option::Some(then)))
+                        }
+                        #support::Step::Done(output) => #result::Err(output),
+                    }
+                }
+            })
+            .collect();
+
+        let mut params = vec![quote!(__K0)];
+        let mut variants = vec![quote!(Start(#option<__K0>))];
+        let mut bounds = vec![{
+            let returns = &returns[0];
+            quote!(__K0: FnOnce() -> #returns)
+        }];
+        let mut arms = vec![{
+            let transition = &transitions[0];
+            quote! {
+                __State::Start(then) => {
+                    let next = (then.take().expect(PANICKED))();
+                    #transition
+                }
+            }
+        }];
+        for i in 1..count + 1 {
+            let (f, k, state, returns, transition) = (&f[i], &k[i], &state[i], &returns[i], &transitions[i]);
+            params.push(quote!(#f, #k));
+            variants.push(quote!(#state(#f, #option<#k>)));
+            bounds.push(quote!(#f: #future<__Sp>, #k: FnOnce(#f::Output) -> #returns));
+            arms.push(quote! {
+                __State::#state(future, then) => {
+                    let output = match unsafe { #pin_mut::new_unchecked(future) }.poll(cx) {
+                        #poll::Ready(output) => output,
+                        #poll::Pending => return #poll::Pending,
+                    };
+                    let next = (then.take().expect(PANICKED))(output);
+                    #transition
+                }
+            });
+        }
+
+        let start = segment_closures(segments, awaits, spawner, output);
+        let params = &params;
+        // Without awaits there is nothing to poll with the context.
+        let cx = Ident::new(if count == 0 { "_cx" } else { "cx" }, Span::call_site());
+        let polled_after_completion = format!("`{}` polled after completion", name);
+        let advance = quote! {
+            match next {
+                #result::Ok(state) => *this = state,
+                #result::Err(output) => {
+                    *this = __State::Done;
+                    return #poll::Ready(output);
+                }
+            }
+        };
+        let poll_fn = quote! {
+            fn poll(self: #pin_mut<Self>, #cx: &mut ::specialized_futures::Context<__Sp>) -> #poll<__T> {
+                const PANICKED: &str = "polled after a panic";
+                let this = unsafe { #pin_mut::get_mut_unchecked(self) };
+                loop {
+                    // The old state is dropped in place, so each future
+                    // stays pinned where the state machine is.
+                    let next = match this {
+                        #(#arms)*
+                        __State::Done => panic!(#polled_after_completion),
+                    };
+                    #advance
+                }
+            }
+        };
+        quote! {
+            enum __State<#(#params),*> {
+                #(#variants,)*
+                Done,
+            }
+
+            impl<__Sp, __T, #(#params),*> #future<__Sp> for __State<#(#params),*>
+                where __Sp: ::specialized_futures::Spawn + ?Sized,
+                      #(#bounds,)*
+            {
+                type Output = __T;
+
+                #poll_fn
+            }
+
+            __State::Start(#option::Some(#start))
+        }
+    }
+}
+
+/// Build the closure for the first segment, which nests the closures for
+/// all of the others.
+fn segment_closures(segments: Vec<Vec<Stmt>>, awaits: Vec<Await>, spawner: &Type, output: &TokenStream2) -> TokenStream2 {
+    let support = quote!(::specialized_futures::macro_support);
+    let mut inputs: Vec<Option<TokenStream2>> = vec![None];
+    let mut futures = Vec::new();
+    for awaited in awaits {
+        inputs.push(Some(awaited.input));
+        futures.push(awaited.future);
+    }
+
+    let mut segments = segments.into_iter().zip(inputs).rev();
+    // The last segment returns the function's output, so its `return`s and
+    // `?`s can be left alone.
+    let (stmts, input) = segments.next().unwrap();
+    let input = input.unwrap_or_else(TokenStream2::new);
+    let mut next = quote!(move |#input| -> #output { #(#stmts)* });
+
+    for ((mut stmts, input), mut future) in segments.zip(futures.into_iter().rev()) {
+        for stmt in &mut stmts {
+            EarlyReturn.visit_stmt_mut(stmt);
+        }
+        EarlyReturn.visit_expr_mut(&mut future);
+        let input = input.unwrap_or_else(TokenStream2::new);
+        next = quote! {
+            move |#input| {
+                #(#stmts)*
+                #support::await_step::<#spawner, _, _, _, _>(#future, #next)
+            }
+        };
+    }
+    next
+}
+
+/// Split off the await which `stmt` begins with, returning it along with
+/// the rest of the statement, which begins the next segment.
+fn split_at_await(stmt: &Stmt) -> Result<Option<(Await, Vec<Stmt>)>, Error> {
+    let mut rest = stmt.clone();
+    let future = match rest {
+        Stmt::Local(ref mut local) => match local.init {
+            Some((_, ref mut init)) => {
+                if let Expr::Macro(ref mac) = **init {
+                    if is_await(&mac.mac) {
+                        // Bind the output directly.
+                        let pats = &local.pats;
+                        let ty = local.ty.as_ref().map(|&(_, ref ty)| quote!(: #ty));
+                        let future = parse_await(&mac.mac)?;
+                        return Ok(Some((Await { future, input: quote!(#pats #ty) }, Vec::new())));
+                    }
+                }
+                take_leading_await(init)?
+            }
+            None => None,
+        },
+        Stmt::Semi(ref mut expr, _) => {
+            if let Expr::Macro(ref mac) = *expr {
+                if is_await(&mac.mac) {
+                    let future = parse_await(&mac.mac)?;
+                    return Ok(Some((Await { future, input: quote!(_) }, Vec::new())));
+                }
+            }
+            take_leading_await(expr)?
+        }
+        Stmt::Expr(ref mut expr) => take_leading_await(expr)?,
+        Stmt::Item(Item::Macro(ref item)) if item.ident.is_none() && is_await(&item.mac) => {
+            let future = parse_await(&item.mac)?;
+            return Ok(Some((Await { future, input: quote!(_) }, Vec::new())));
+        }
+        Stmt::Item(_) => None,
+    };
+    match future {
+        Some(future) => {
+            check_no_await(&rest)?;
+            Ok(Some((Await { future, input: quote!(__output) }, vec![rest])))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Replace the `await_!` which `expr` begins with, if any, by `__output`,
+/// returning the future it awaits.
+///
+/// Nothing in `expr` is evaluated before that `await_!`, so the rest of
+/// `expr` can run once the future is done.
+fn take_leading_await(expr: &mut Expr) -> Result<Option<Expr>, Error> {
+    let future = match *expr {
+        Expr::Macro(ref mac) if is_await(&mac.mac) => parse_await(&mac.mac)?,
+        Expr::MethodCall(ref mut call) => return take_leading_await(&mut call.receiver),
+        Expr::Try(ref mut tried) => return take_leading_await(&mut tried.expr),
+        Expr::Field(ref mut field) => return take_leading_await(&mut field.base),
+        Expr::Binary(ref mut binary) => return take_leading_await(&mut binary.left),
+        Expr::Index(ref mut index) => return take_leading_await(&mut index.expr),
+        Expr::Cast(ref mut cast) => return take_leading_await(&mut cast.expr),
+        Expr::Paren(ref mut paren) => return take_leading_await(&mut paren.expr),
+        // Assigning to a local doesn't evaluate anything before the value.
+        Expr::Assign(ref mut assign) if is_local(&assign.left) => {
+            return take_leading_await(&mut assign.right)
+        }
+        Expr::AssignOp(ref mut assign) if is_local(&assign.left) => {
+            return take_leading_await(&mut assign.right)
+        }
+        _ => return Ok(None),
+    };
+    *expr = parse_quote!(__output);
+    Ok(Some(future))
+}
+
+fn is_local(expr: &Expr) -> bool {
+    match *expr {
+        Expr::Path(ref path) => path.qself.is_none() && path.path.segments.len() == 1,
+        _ => false,
+    }
+}
+
+fn is_await(mac: &Macro) -> bool {
+    mac.path.segments.len() == 1 && mac.path.segments[0].ident == "await_"
+}
+
+fn parse_await(mac: &Macro) -> Result<Expr, Error> {
+    let future: Expr = parse2(mac.tts.clone())
+        .map_err(|_| Error::new(mac.span(), "expected `await_!(<future>)`"))?;
+    find_await(&future)
+        .map_or(Ok(future), |span| Err(Error::new(span, MISPLACED_AWAIT)))
+}
+
+fn check_no_await(stmt: &Stmt) -> Result<(), Error> {
+    let mut finder = FindAwait(None);
+    finder.visit_stmt(stmt);
+    finder.0.map_or(Ok(()), |span| Err(Error::new(span, MISPLACED_AWAIT)))
+}
+
+fn find_await(expr: &Expr) -> Option<Span> {
+    let mut finder = FindAwait(None);
+    finder.visit_expr(expr);
+    finder.0
+}
+
+/// Finds the first `await_!` in a statement or expression.
+struct FindAwait(Option<Span>);
+
+impl<'ast> Visit<'ast> for FindAwait {
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        if self.0.is_none() && is_await(mac) {
+            self.0 = Some(mac.span());
+        }
+        visit::visit_macro(self, mac)
+    }
+}
+
+/// Rewrites the `return`s and `?`s in a segment which ends in an await, so
+/// that they return `Step::Done` with the function's output instead.
+struct EarlyReturn;
+
+impl VisitMut for EarlyReturn {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        visit_mut::visit_expr_mut(self, expr);
+        let step = quote!(::specialized_futures::macro_support::Step);
+        let result = quote!(::specialized_futures::core_reexport::result::Result);
+        let tts = match *expr {
+            Expr::Return(ref ret) => {
+                let value = ret.expr.as_ref().map_or_else(|| quote!(()), |value| quote!(#value));
+                quote!(return #step::Done(#value))
+            }
+            Expr::Try(ref tried) => {
+                let inner = &tried.expr;
+                quote! {
+                    match #inner {
+                        #result::Ok(__value) => __value,
+                        #result::Err(__error) => return #step::Done(#result::Err(
+                            ::specialized_futures::core_reexport::convert::From::from(__error)
+                        )),
+                    }
+                }
+            }
+            _ => return,
+        };
+        *expr = Expr::Verbatim(ExprVerbatim { tts });
+    }
+
+    // Closures and nested items return from themselves.
+    fn visit_expr_closure_mut(&mut self, _closure: &mut ExprClosure) {}
+
+    fn visit_item_mut(&mut self, _item: &mut Item) {}
+}
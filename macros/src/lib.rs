@@ -0,0 +1,58 @@
+//! The `#[specialized]` attribute, which `specialized-futures` re-exports
+//! when its `macros` feature is enabled.
+//!
+//! The attribute turns a function whose body awaits other futures with
+//! `await_!` into one returning a hand-rolled state machine. Each stretch of
+//! the body between two awaits becomes a closure, which owns whatever locals
+//! it uses and hands back the next future to wait on along with the closure
+//! to run once that future is done. A generated enum holds the pending
+//! future and the closure after it, and its `poll` drives them in turn.
+#![feature(proc_macro)]
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+#[macro_use]
+extern crate syn;
+
+use proc_macro::TokenStream;
+
+mod expand;
+
+/// Write a function returning a `Future<S>` as if it were synchronous code,
+/// waiting on other futures with `await_!`.
+///
+/// ```ignore
+/// #[specialized(spawner = LocalSpawner)]
+/// fn connect(addr: Addr) -> Result<Vec<Message>, Error> {
+///     let conn = await_!(Connection::open(addr))?;
+///     if conn.is_closed() {
+///         return Ok(Vec::new());
+///     }
+///     let greeting = await_!(conn.read())?;
+///     Ok(vec![greeting])
+/// }
+/// ```
+///
+/// The function above returns
+/// `impl Future<LocalSpawner, Output = Result<Vec<Message>, Error>>`, and
+/// the futures it awaits are polled with the same `Context<LocalSpawner>`.
+/// Without a `spawner` argument the function returns a `Future<dyn Spawn>`.
+/// Nothing in the body runs until the returned future is first polled.
+/// Crates using the attribute need `#![feature(proc_macro)]`.
+///
+/// `await_!(future)` may begin a statement, the initializer of a `let`, or
+/// the final expression of the body, as in `let n = await_!(read)?.len();`.
+/// The rest of that statement runs once the future is done. Awaiting
+/// anywhere else, e.g. inside a loop, a block, a closure or the arguments
+/// of a call, is a compile error.
+///
+/// Locals are moved into the state machine when they are used after an
+/// await, so a local can't be borrowed across one. Before the last await,
+/// `?` only works on `Result`s, and the function must return a `Result` to
+/// use it.
+#[proc_macro_attribute]
+pub fn specialized(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand::expand(attr.into(), item.into()).into()
+}
@@ -0,0 +1,170 @@
+//! Checks the code `#[specialized]` expands to.
+#![recursion_limit = "128"]
+
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+#[macro_use]
+extern crate syn;
+
+#[path = "../src/expand.rs"]
+mod expand;
+
+use proc_macro2::TokenStream;
+
+fn expand(attr: TokenStream, item: TokenStream) -> String {
+    expand::expand(attr, item).to_string()
+}
+
+fn assert_contains(expansion: &str, expected: &TokenStream) {
+    let expected = expected.to_string();
+    assert!(expansion.contains(&expected), "expected `{}` in:\n{}", expected, expansion);
+}
+
+fn assert_lacks(expansion: &str, unexpected: &TokenStream) {
+    let unexpected = unexpected.to_string();
+    assert!(!expansion.contains(&unexpected), "unexpected `{}` in:\n{}", unexpected, expansion);
+}
+
+#[test]
+fn signature_returns_a_specialized_future() {
+    let expansion = expand(quote!(spawner = LocalSpawner), quote! {
+        pub fn answer<T: Clone>(value: T) -> T where T: Send { value }
+    });
+    assert_contains(&expansion, &quote! {
+        pub fn answer<T: Clone>(value: T)
+            -> impl ::specialized_futures::Future<LocalSpawner, Output = T>
+            where T: Send
+    });
+}
+
+#[test]
+fn spawner_defaults_to_dyn_spawn() {
+    let expansion = expand(quote!(), quote!(fn unit() {}));
+    assert_contains(&expansion, &quote! {
+        -> impl ::specialized_futures::Future<::specialized_futures::macro_support::DynSpawn, Output = ()>
+    });
+    // Older compilers pass the parentheses along with the arguments.
+    assert_eq!(expand(quote!((spawner = S)), quote!(fn f() {})), expand(quote!(spawner = S), quote!(fn f() {})));
+}
+
+#[test]
+fn one_state_per_await() {
+    let expansion = expand(quote!(), quote!(fn none() -> u32 { 1 }));
+    assert_contains(&expansion, &quote!(enum __State<__K0> { Start(::specialized_futures::core_reexport::option::Option<__K0>), Done, }));
+    assert_lacks(&expansion, &quote!(Await1));
+
+    let expansion = expand(quote!(), quote! {
+        fn two() -> u32 {
+            await_!(a);
+            await_!(b);
+            1
+        }
+    });
+    assert_contains(&expansion, &quote!(enum __State<__K0, __F1, __K1, __F2, __K2>));
+    assert_contains(&expansion, &quote!(__K2: FnOnce(__F2::Output) -> __T));
+    assert_lacks(&expansion, &quote!(Await3));
+}
+
+#[test]
+fn segments_become_nested_closures() {
+    let expansion = expand(quote!(spawner = S), quote! {
+        fn f() -> u32 {
+            let a = 1;
+            let b: u32 = await_!(first(a));
+            b + 1
+        }
+    });
+    assert_contains(&expansion, &quote! {
+        __State::Start(::specialized_futures::core_reexport::option::Option::Some(move | | {
+            let a = 1;
+            ::specialized_futures::macro_support::await_step::<S, _, _, _, _>(
+                first(a),
+                move |b: u32| -> u32 { b + 1 }
+            )
+        }))
+    });
+}
+
+#[test]
+fn rest_of_statement_runs_after_the_await() {
+    let expansion = expand(quote!(), quote! {
+        fn f() -> usize {
+            let n = await_!(read())?.len();
+            n
+        }
+    });
+    assert_contains(&expansion, &quote! {
+        move |__output| -> usize {
+            let n = __output?.len();
+            n
+        }
+    });
+}
+
+#[test]
+fn early_returns_before_an_await_finish_the_future() {
+    let expansion = expand(quote!(), quote! {
+        fn f(skip: bool) -> Result<u32, E> {
+            if skip {
+                return Ok(0);
+            }
+            let v = check()?;
+            let w = await_!(g(v));
+            return Ok(w);
+        }
+    });
+    assert_contains(&expansion, &quote! {
+        if skip {
+            return ::specialized_futures::macro_support::Step::Done(Ok(0));
+        }
+    });
+    assert_contains(&expansion, &quote! {
+        match check() {
+            ::specialized_futures::core_reexport::result::Result::Ok(__value) => __value,
+            ::specialized_futures::core_reexport::result::Result::Err(__error) => return ::specialized_futures::macro_support::Step::Done(
+                ::specialized_futures::core_reexport::result::Result::Err(
+                    ::specialized_futures::core_reexport::convert::From::from(__error)
+                )
+            ),
+        }
+    });
+    // After the last await, the closure returns the function's own output.
+    assert_contains(&expansion, &quote!(move |w| -> Result<u32, E> { return Ok(w); }));
+}
+
+#[test]
+fn closures_keep_their_own_returns() {
+    let expansion = expand(quote!(), quote! {
+        fn f() -> u32 {
+            let g = |x: Option<u32>| { let y = x?; return Some(y); };
+            await_!(h(g));
+            1
+        }
+    });
+    assert_contains(&expansion, &quote!(|x: Option<u32>| { let y = x?; return Some(y); }));
+}
+
+fn assert_error(expansion: &str, message: &str) {
+    assert!(expansion.starts_with("compile_error !"), "expected an error in:\n{}", expansion);
+    assert!(expansion.contains(message), "expected `{}` in:\n{}", message, expansion);
+}
+
+#[test]
+fn misplaced_awaits_are_errors() {
+    let misplaced = "`await_!` can only be used at the start of a statement";
+    assert_error(&expand(quote!(), quote!(fn f() { loop { await_!(a); } })), misplaced);
+    assert_error(&expand(quote!(), quote!(fn f() { let x = g(await_!(a)); })), misplaced);
+    assert_error(&expand(quote!(), quote!(fn f() { let x = await_!(a) + await_!(b); })), misplaced);
+    assert_error(&expand(quote!(), quote!(fn f() { await_!(await_!(a)); })), misplaced);
+    assert_error(&expand(quote!(), quote!(fn f() { let c = || await_!(a); })), misplaced);
+}
+
+#[test]
+fn bad_input_is_an_error() {
+    assert_error(&expand(quote!(executor = S), quote!(fn f() {})), "expected `spawner = <type>`");
+    assert_error(&expand(quote!(spawner =), quote!(fn f() {})), "expected a type after `spawner =`");
+    assert_error(&expand(quote!(), quote!(struct S;)), "can only be applied to a function");
+    assert_error(&expand(quote!(), quote!(const fn f() {})), "function can");
+    assert_error(&expand(quote!(), quote!(fn f() { await_!(); })), "expected `await_!(<future>)`");
+}
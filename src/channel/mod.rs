@@ -0,0 +1,4 @@
+//! Asynchronous channels for communicating between tasks.
+
+pub mod oneshot;
+pub mod mpsc;
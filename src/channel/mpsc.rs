@@ -0,0 +1,511 @@
+//! A multi-producer, single-consumer queue for sending values across
+//! asynchronous tasks.
+//!
+//! The `Receiver` half is a `Stream` of the values sent. Bounded senders
+//! created by `channel` implement `Sink` and apply backpressure through
+//! `poll_ready`; unbounded senders created by `unbounded` can always send
+//! synchronously with `unbounded_send`.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use std::sync::{Arc, Mutex};
+use sink::Sink;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll, Waker, AtomicWaker};
+use spawn::Spawn;
+
+struct State<T> {
+    queue: VecDeque<T>,
+    /// `None` for unbounded channels.
+    capacity: Option<usize>,
+    /// Slots promised to senders by `poll_ready` but not yet filled.
+    reserved: usize,
+    num_senders: usize,
+    next_sender_id: u64,
+    rx_closed: bool,
+    parked_senders: VecDeque<(u64, Waker)>,
+}
+
+impl<T> State<T> {
+    fn has_capacity(&self) -> bool {
+        match self.capacity {
+            Some(capacity) => self.queue.len() + self.reserved < capacity,
+            None => true,
+        }
+    }
+
+    /// Unpark the first parked sender if a slot is free for it, returning its
+    /// waker. The caller must wake it only after releasing the lock, so the
+    /// woken task doesn't immediately contend for it.
+    fn take_next_sender(&mut self) -> Option<Waker> {
+        if self.has_capacity() {
+            self.parked_senders.pop_front().map(|(_, waker)| waker)
+        } else {
+            None
+        }
+    }
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    rx_task: AtomicWaker,
+}
+
+fn new_shared<T>(capacity: Option<usize>) -> Arc<Shared<T>> {
+    Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::new(),
+            capacity,
+            reserved: 0,
+            num_senders: 1,
+            next_sender_id: 1,
+            rx_closed: false,
+            parked_senders: VecDeque::new(),
+        }),
+        rx_task: AtomicWaker::new(),
+    })
+}
+
+/// The kind of failure that can occur when sending on a channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SendErrorKind {
+    Full,
+    Disconnected,
+}
+
+/// The error type for `Sender`s used as `Sink`s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SendError {
+    kind: SendErrorKind,
+}
+
+impl SendError {
+    /// Returns `true` if this error is a result of the channel being full.
+    pub fn is_full(&self) -> bool {
+        self.kind == SendErrorKind::Full
+    }
+
+    /// Returns `true` if this error is a result of the receiver being
+    /// dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.kind == SendErrorKind::Disconnected
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_full() {
+            f.write_str("send failed because channel is full")
+        } else {
+            f.write_str("send failed because receiver is gone")
+        }
+    }
+}
+
+impl Error for SendError {}
+
+/// The error type returned from `try_send` and `unbounded_send`, carrying
+/// the value which could not be sent.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TrySendError<T> {
+    err: SendError,
+    val: T,
+}
+
+impl<T> TrySendError<T> {
+    /// Returns `true` if this error is a result of the channel being full.
+    pub fn is_full(&self) -> bool {
+        self.err.is_full()
+    }
+
+    /// Returns `true` if this error is a result of the receiver being
+    /// dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.err.is_disconnected()
+    }
+
+    /// Returns the message that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.val
+    }
+
+    /// Drops the message and converts into a `SendError`.
+    pub fn into_send_error(self) -> SendError {
+        self.err
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TrySendError")
+            .field("kind", &self.err.kind)
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.err, f)
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
+
+/// Creates a bounded mpsc channel for communicating between asynchronous
+/// tasks.
+///
+/// At most `buffer` messages can be queued at once; further sends wait in
+/// `poll_ready` until the receiver takes a message, and each message taken
+/// wakes exactly one waiting sender.
+///
+/// # Panics
+///
+/// Panics if `buffer` is zero.
+pub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>) {
+    assert!(buffer > 0, "mpsc channel requires a buffer of at least 1");
+    let shared = new_shared(Some(buffer));
+    let tx = Sender { shared: shared.clone(), id: 0, has_reservation: false };
+    (tx, Receiver { shared, done: false })
+}
+
+/// Creates an unbounded mpsc channel for communicating between asynchronous
+/// tasks.
+///
+/// Sending on an unbounded channel never waits, so nothing limits how many
+/// messages may be queued.
+pub fn unbounded<T>() -> (UnboundedSender<T>, Receiver<T>) {
+    let shared = new_shared(None);
+    (UnboundedSender { shared: shared.clone() }, Receiver { shared, done: false })
+}
+
+/// The transmission end of a bounded mpsc channel.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+    id: u64,
+    has_reservation: bool,
+}
+
+impl<T> Unpin for Sender<T> {}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sender")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+impl<T> Sender<T> {
+    /// Polls the channel to determine if there is guaranteed capacity to send
+    /// at least one item without waiting.
+    ///
+    /// Once this returns `Poll::Ready(Ok(()))`, a slot is reserved for this
+    /// sender until it sends an item or is dropped.
+    pub fn poll_ready<S: Spawn + ?Sized>(
+        &mut self,
+        cx: &mut Context<S>,
+    ) -> Poll<Result<(), SendError>> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.rx_closed {
+            return Poll::Ready(Err(SendError { kind: SendErrorKind::Disconnected }));
+        }
+        if self.has_reservation {
+            return Poll::Ready(Ok(()));
+        }
+        let id = self.id;
+        if state.has_capacity() {
+            state.reserved += 1;
+            state.parked_senders.retain(|&(parked, _)| parked != id);
+            self.has_reservation = true;
+            Poll::Ready(Ok(()))
+        } else {
+            let waker = cx.waker().clone();
+            let parked = state.parked_senders.iter().position(|&(parked, _)| parked == id);
+            match parked {
+                Some(i) => state.parked_senders[i].1 = waker,
+                None => state.parked_senders.push_back((id, waker)),
+            }
+            Poll::Pending
+        }
+    }
+
+    /// Attempts to send a message on this `Sender`, returning the message if
+    /// there was an error.
+    pub fn try_send(&mut self, msg: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.rx_closed {
+            let err = SendError { kind: SendErrorKind::Disconnected };
+            return Err(TrySendError { err, val: msg });
+        }
+        if self.has_reservation {
+            self.has_reservation = false;
+            state.reserved -= 1;
+        } else if !state.has_capacity() {
+            let err = SendError { kind: SendErrorKind::Full };
+            return Err(TrySendError { err, val: msg });
+        }
+        state.queue.push_back(msg);
+        drop(state);
+        self.shared.rx_task.wake();
+        Ok(())
+    }
+
+    /// Returns whether the receiver has been dropped or closed.
+    pub fn is_closed(&self) -> bool {
+        self.shared.state.lock().unwrap().rx_closed
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Sender<T> {
+        let mut state = self.shared.state.lock().unwrap();
+        state.num_senders += 1;
+        let id = state.next_sender_id;
+        state.next_sender_id += 1;
+        Sender { shared: self.shared.clone(), id, has_reservation: false }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        let id = self.id;
+        state.parked_senders.retain(|&(parked, _)| parked != id);
+        if self.has_reservation {
+            state.reserved -= 1;
+        }
+        // This sender may have been woken for a slot it will now never use,
+        // so pass the wakeup on.
+        let next = state.take_next_sender();
+        state.num_senders -= 1;
+        let last = state.num_senders == 0;
+        drop(state);
+        if let Some(waker) = next {
+            waker.wake();
+        }
+        if last {
+            self.shared.rx_task.wake();
+        }
+    }
+}
+
+impl<T, S: Spawn + ?Sized> Sink<T, S> for Sender<T> {
+    type Error = SendError;
+
+    fn poll_ready(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), SendError>> {
+        (*self).poll_ready(cx)
+    }
+
+    fn start_send(mut self: PinMut<Self>, msg: T) -> Result<(), SendError> {
+        self.try_send(msg).map_err(|e| e.err)
+    }
+
+    fn poll_flush(self: PinMut<Self>, _: &mut Context<S>) -> Poll<Result<(), SendError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: PinMut<Self>, _: &mut Context<S>) -> Poll<Result<(), SendError>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The transmission end of an unbounded mpsc channel.
+pub struct UnboundedSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Unpin for UnboundedSender<T> {}
+
+impl<T> fmt::Debug for UnboundedSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UnboundedSender")
+            .finish()
+    }
+}
+
+impl<T> UnboundedSender<T> {
+    /// Sends a message along this channel.
+    ///
+    /// This never waits; it only fails if the receiver has been dropped or
+    /// closed, in which case the message is returned.
+    pub fn unbounded_send(&self, msg: T) -> Result<(), TrySendError<T>> {
+        let mut state = self.shared.state.lock().unwrap();
+        if state.rx_closed {
+            let err = SendError { kind: SendErrorKind::Disconnected };
+            return Err(TrySendError { err, val: msg });
+        }
+        state.queue.push_back(msg);
+        drop(state);
+        self.shared.rx_task.wake();
+        Ok(())
+    }
+
+    /// Returns whether the receiver has been dropped or closed.
+    pub fn is_closed(&self) -> bool {
+        self.shared.state.lock().unwrap().rx_closed
+    }
+}
+
+impl<T> Clone for UnboundedSender<T> {
+    fn clone(&self) -> UnboundedSender<T> {
+        self.shared.state.lock().unwrap().num_senders += 1;
+        UnboundedSender { shared: self.shared.clone() }
+    }
+}
+
+impl<T> Drop for UnboundedSender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.num_senders -= 1;
+        if state.num_senders == 0 {
+            drop(state);
+            self.shared.rx_task.wake();
+        }
+    }
+}
+
+impl<T, S: Spawn + ?Sized> Sink<T, S> for UnboundedSender<T> {
+    type Error = SendError;
+
+    fn poll_ready(self: PinMut<Self>, _: &mut Context<S>) -> Poll<Result<(), SendError>> {
+        if self.is_closed() {
+            Poll::Ready(Err(SendError { kind: SendErrorKind::Disconnected }))
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(self: PinMut<Self>, msg: T) -> Result<(), SendError> {
+        self.unbounded_send(msg).map_err(|e| e.err)
+    }
+
+    fn poll_flush(self: PinMut<Self>, _: &mut Context<S>) -> Poll<Result<(), SendError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: PinMut<Self>, _: &mut Context<S>) -> Poll<Result<(), SendError>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The receiving end of an mpsc channel.
+///
+/// This is a `Stream` of the messages sent, which terminates once every
+/// sender has been dropped and the queue is empty.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    done: bool,
+}
+
+impl<T> Unpin for Receiver<T> {}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Closes the receiving half of the channel, without dropping it.
+    ///
+    /// Further sends fail with a disconnected error, and every sender waiting
+    /// for capacity is woken to observe it. Messages already queued can still
+    /// be received.
+    pub fn close(&mut self) {
+        let parked = {
+            let mut state = self.shared.state.lock().unwrap();
+            state.rx_closed = true;
+            ::core::mem::replace(&mut state.parked_senders, VecDeque::new())
+        };
+        for (_, waker) in parked {
+            waker.wake();
+        }
+    }
+
+    /// Tries to receive the next message without waiting.
+    ///
+    /// Returns `Ok(Some(msg))` if a message was queued, `Ok(None)` if the
+    /// channel is finished, and `Err(TryRecvError)` if no message is
+    /// available yet.
+    pub fn try_next(&mut self) -> Result<Option<T>, TryRecvError> {
+        let mut state = self.shared.state.lock().unwrap();
+        match state.queue.pop_front() {
+            Some(msg) => {
+                let next = state.take_next_sender();
+                drop(state);
+                if let Some(waker) = next {
+                    waker.wake();
+                }
+                Ok(Some(msg))
+            }
+            None if state.num_senders == 0 => {
+                self.done = true;
+                Ok(None)
+            }
+            None => Err(TryRecvError { _hidden: () }),
+        }
+    }
+}
+
+impl<T, S: Spawn + ?Sized> Stream<S> for Receiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<T>> {
+        let this = &mut *self;
+        if this.done {
+            return Poll::Ready(None);
+        }
+        // Register before checking, so a concurrent send is seen either here
+        // or through the waker.
+        this.shared.rx_task.register(cx.waker());
+        let mut state = this.shared.state.lock().unwrap();
+        match state.queue.pop_front() {
+            Some(msg) => {
+                let next = state.take_next_sender();
+                drop(state);
+                if let Some(waker) = next {
+                    waker.wake();
+                }
+                Poll::Ready(Some(msg))
+            }
+            None if state.num_senders == 0 => {
+                drop(state);
+                this.done = true;
+                Poll::Ready(None)
+            }
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<T> FusedStream for Receiver<T> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// The error type returned from `Receiver::try_next` when no message is
+/// available yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TryRecvError {
+    _hidden: (),
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("receiver channel is empty")
+    }
+}
+
+impl Error for TryRecvError {}
@@ -0,0 +1,192 @@
+//! A channel for sending a single value between asynchronous tasks.
+
+use std::error::Error;
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use std::sync::{Arc, Mutex};
+use future::{Future, FusedFuture};
+use task::{Context, Poll, AtomicWaker};
+use spawn::Spawn;
+
+struct State<T> {
+    data: Option<T>,
+    /// Set once the sender has sent its value or been dropped.
+    complete: bool,
+    /// Set once the receiver has been dropped.
+    canceled: bool,
+}
+
+struct Inner<T> {
+    state: Mutex<State<T>>,
+    rx_task: AtomicWaker,
+    tx_task: AtomicWaker,
+}
+
+/// Creates a new one-shot channel for sending a single value across
+/// asynchronous tasks.
+///
+/// The `Sender` half can send a value once with `send`, and the `Receiver`
+/// half is a future resolving to that value. Both halves may be moved to
+/// other threads.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Inner {
+        state: Mutex::new(State {
+            data: None,
+            complete: false,
+            canceled: false,
+        }),
+        rx_task: AtomicWaker::new(),
+        tx_task: AtomicWaker::new(),
+    });
+    (Sender { inner: inner.clone() }, Receiver { inner, done: false })
+}
+
+/// The sending half of a one-shot channel, created by `channel`.
+pub struct Sender<T> {
+    inner: Arc<Inner<T>>,
+}
+
+impl<T> Unpin for Sender<T> {}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sender")
+            .field("canceled", &self.is_canceled())
+            .finish()
+    }
+}
+
+impl<T> Sender<T> {
+    /// Completes this oneshot with a successful result.
+    ///
+    /// If the `Receiver` has already been dropped, the value cannot be
+    /// delivered and is returned as `Err`.
+    pub fn send(self, t: T) -> Result<(), T> {
+        let mut state = self.inner.state.lock().unwrap();
+        if state.canceled {
+            return Err(t);
+        }
+        state.data = Some(t);
+        // Dropping `self` afterwards marks the channel complete and wakes the
+        // receiver.
+        Ok(())
+    }
+
+    /// Polls this `Sender` half to detect whether its associated `Receiver`
+    /// has been dropped.
+    ///
+    /// Returns `Poll::Ready(())` once the receiver is gone. Otherwise the
+    /// current task is registered to be woken when that happens.
+    pub fn poll_cancel<S: Spawn + ?Sized>(&mut self, cx: &mut Context<S>) -> Poll<()> {
+        // Register before checking, so a concurrent cancellation is seen
+        // either here or through the waker.
+        self.inner.tx_task.register(cx.waker());
+        if self.is_canceled() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+
+    /// Tests to see whether this `Sender`'s corresponding `Receiver` has been
+    /// dropped.
+    pub fn is_canceled(&self) -> bool {
+        self.inner.state.lock().unwrap().canceled
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        self.inner.state.lock().unwrap().complete = true;
+        self.inner.rx_task.wake();
+    }
+}
+
+/// Error returned from a `Receiver` when the corresponding `Sender` is
+/// dropped without sending a value.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Canceled;
+
+impl fmt::Display for Canceled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("oneshot canceled")
+    }
+}
+
+impl Error for Canceled {}
+
+/// The receiving half of a one-shot channel, created by `channel`.
+///
+/// This is a future resolving to the value sent, or to `Err(Canceled)` if
+/// the `Sender` was dropped without sending one.
+pub struct Receiver<T> {
+    inner: Arc<Inner<T>>,
+    done: bool,
+}
+
+impl<T> Unpin for Receiver<T> {}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Receiver")
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Gracefully close this receiver, preventing any subsequent attempts to
+    /// send to it.
+    ///
+    /// A value already sent can still be received after calling this.
+    pub fn close(&mut self) {
+        self.inner.state.lock().unwrap().canceled = true;
+        self.inner.tx_task.wake();
+    }
+
+    /// Attempts to receive a value outside of the context of a task.
+    ///
+    /// Returns `Ok(None)` if no value has been sent yet, and `Err(Canceled)`
+    /// if the sender was dropped without sending a value.
+    pub fn try_recv(&mut self) -> Result<Option<T>, Canceled> {
+        let mut state = self.inner.state.lock().unwrap();
+        match state.data.take() {
+            Some(data) => Ok(Some(data)),
+            None if state.complete => Err(Canceled),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<T, S: Spawn + ?Sized> Future<S> for Receiver<T> {
+    type Output = Result<T, Canceled>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<T, Canceled>> {
+        // Register before checking, so a concurrent send is seen either here
+        // or through the waker.
+        self.inner.rx_task.register(cx.waker());
+        let result = {
+            let mut state = self.inner.state.lock().unwrap();
+            match state.data.take() {
+                Some(data) => Ok(data),
+                None if state.complete => Err(Canceled),
+                None => return Poll::Pending,
+            }
+        };
+        self.done = true;
+        Poll::Ready(result)
+    }
+}
+
+impl<T> FusedFuture for Receiver<T> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
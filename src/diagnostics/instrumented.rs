@@ -0,0 +1,155 @@
+use core::fmt;
+use core::intrinsics::type_name;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use core::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A report of a single poll which took longer than the threshold given to
+/// `FutureExt::instrument_polls`.
+#[derive(Debug, Copy, Clone)]
+pub struct SlowPoll {
+    /// How long the poll took.
+    pub duration: Duration,
+    /// The index of the poll, starting from zero for the first poll.
+    pub poll_index: usize,
+    /// The type name of the instrumented future.
+    pub type_name: &'static str,
+}
+
+#[derive(Debug, Default)]
+struct Stats {
+    polls: usize,
+    slow_polls: usize,
+    total_time: Duration,
+    max_time: Duration,
+}
+
+/// A handle to the cumulative poll statistics of an `Instrumented` future.
+///
+/// The handle stays valid after the future has completed or been dropped,
+/// so it can be read once a task has finished. Times are only recorded
+/// when the future was given a threshold.
+#[derive(Debug, Clone)]
+pub struct PollStats {
+    inner: Arc<Mutex<Stats>>,
+}
+
+impl PollStats {
+    /// The number of times the future has been polled.
+    pub fn polls(&self) -> usize {
+        self.inner.lock().unwrap().polls
+    }
+
+    /// The number of polls which exceeded the threshold.
+    pub fn slow_polls(&self) -> usize {
+        self.inner.lock().unwrap().slow_polls
+    }
+
+    /// The total time spent polling the future.
+    pub fn total_time(&self) -> Duration {
+        self.inner.lock().unwrap().total_time
+    }
+
+    /// The duration of the longest poll.
+    pub fn max_time(&self) -> Duration {
+        self.inner.lock().unwrap().max_time
+    }
+}
+
+/// Future for the `instrument_polls` combinator, which times each poll of
+/// the inner future.
+///
+/// This is created by the `FutureExt::instrument_polls` method.
+pub struct Instrumented<Fut, F> {
+    future: Fut,
+    threshold: Option<Duration>,
+    callback: F,
+    stats: PollStats,
+}
+
+impl<Fut: Unpin, F> Unpin for Instrumented<Fut, F> {}
+
+impl<Fut: fmt::Debug, F> fmt::Debug for Instrumented<Fut, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Instrumented")
+            .field("future", &self.future)
+            .field("threshold", &self.threshold)
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+impl<Fut, F> Instrumented<Fut, F> {
+    pub(crate) fn new(future: Fut, threshold: Option<Duration>, callback: F) -> Instrumented<Fut, F> {
+        Instrumented {
+            future,
+            threshold,
+            callback,
+            stats: PollStats { inner: Arc::new(Mutex::new(Stats::default())) },
+        }
+    }
+
+    /// Get a handle to this future's poll statistics.
+    pub fn stats(&self) -> PollStats {
+        self.stats.clone()
+    }
+}
+
+impl<Fut: FusedFuture, F> FusedFuture for Instrumented<Fut, F> {
+    fn is_terminated(&self) -> bool {
+        self.future.is_terminated()
+    }
+}
+
+impl<S, Fut, F> Future<S> for Instrumented<Fut, F>
+    where S: Spawn + ?Sized,
+          Fut: Future<S>,
+          F: FnMut(SlowPoll)
+{
+    type Output = Fut::Output;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Fut::Output> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        let future = unsafe { PinMut::new_unchecked(&mut this.future) };
+
+        // Without a threshold, only the poll count is kept, so that no clock
+        // is read.
+        let threshold = match this.threshold {
+            Some(threshold) => threshold,
+            None => {
+                this.stats.inner.lock().unwrap().polls += 1;
+                return future.poll(cx);
+            }
+        };
+
+        let start = Instant::now();
+        let res = future.poll(cx);
+        let duration = start.elapsed();
+
+        let poll_index = {
+            let mut stats = this.stats.inner.lock().unwrap();
+            stats.polls += 1;
+            stats.total_time += duration;
+            if duration > stats.max_time {
+                stats.max_time = duration;
+            }
+            if duration > threshold {
+                stats.slow_polls += 1;
+            }
+            stats.polls - 1
+        };
+        if duration > threshold {
+            (this.callback)(SlowPoll {
+                duration,
+                poll_index,
+                type_name: unsafe { type_name::<Fut>() },
+            });
+        }
+        res
+    }
+}
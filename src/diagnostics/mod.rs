@@ -0,0 +1,4 @@
+//! Tools for finding misbehaving futures.
+
+mod instrumented;
+pub use self::instrumented::{Instrumented, PollStats, SlowPoll};
@@ -0,0 +1,166 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::mem::PinMut;
+use core::sync::atomic::{AtomicBool, AtomicUsize};
+use core::sync::atomic::Ordering::SeqCst;
+use future::{Future, FutureExt, FutureObj, LocalFutureObj};
+use task::{Context, Poll, AtomicWaker};
+use spawn::{Spawn, SpawnObjError, SpawnErrorKind, ShutdownSpawn};
+
+#[derive(Debug)]
+struct Shared {
+    running: AtomicUsize,
+    shutdown: AtomicBool,
+    drained: AtomicWaker,
+}
+
+/// A spawned task which releases its slot in the `Bounded` spawner when it
+/// finishes or is dropped by the executor.
+struct Tracked {
+    /// Only taken when the inner spawner rejects the task.
+    future: Option<FutureObj<'static, (), dyn Spawn>>,
+    shared: Arc<Shared>,
+}
+
+impl Future for Tracked {
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<()> {
+        match &mut self.future {
+            Some(future) => future.poll_unpin(cx),
+            None => panic!("Tracked polled after its future was taken"),
+        }
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        if self.shared.running.fetch_sub(1, SeqCst) == 1 {
+            self.shared.drained.wake();
+        }
+    }
+}
+
+/// A spawner which limits the number of tasks spawned through it that may
+/// be alive at once.
+///
+/// Spawning while `limit` tasks are alive fails with
+/// `SpawnErrorKind::queue_full()`, and the error carries the original future
+/// so the spawn can be retried later. A task's slot is released when the
+/// inner executor drops it, normally just after it completes.
+///
+/// Clones of a `Bounded` spawner share the same limit. `ShutdownSpawn` is
+/// implemented by tracking the tasks spawned through the wrapper, so
+/// draining waits for those tasks only, not for every task on the inner
+/// executor.
+#[derive(Debug, Clone)]
+pub struct Bounded<S> {
+    inner: S,
+    limit: usize,
+    shared: Arc<Shared>,
+}
+
+impl<S: Spawn> Bounded<S> {
+    /// Wrap `inner` so that at most `limit` tasks spawned through the
+    /// returned spawner are alive at any time.
+    pub fn new(inner: S, limit: usize) -> Bounded<S> {
+        Bounded {
+            inner,
+            limit,
+            shared: Arc::new(Shared {
+                running: AtomicUsize::new(0),
+                shutdown: AtomicBool::new(false),
+                drained: AtomicWaker::new(),
+            }),
+        }
+    }
+
+    /// The maximum number of tasks which may be alive at once.
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The number of tasks spawned through this spawner which are still
+    /// alive.
+    pub fn running(&self) -> usize {
+        self.shared.running.load(SeqCst)
+    }
+
+    /// Get a reference to the inner spawner.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner spawner.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Consume this wrapper, returning the inner spawner.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Spawn> Spawn for Bounded<S> {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        if self.shared.shutdown.load(SeqCst) {
+            return Err(SpawnObjError { kind: SpawnErrorKind::shutdown(), future });
+        }
+        let mut running = self.shared.running.load(SeqCst);
+        loop {
+            if running >= self.limit {
+                return Err(SpawnObjError { kind: SpawnErrorKind::queue_full(), future });
+            }
+            match self.shared.running.compare_exchange(running, running + 1, SeqCst, SeqCst) {
+                Ok(_) => break,
+                Err(actual) => running = actual,
+            }
+        }
+
+        let task = Tracked { future: Some(future), shared: self.shared.clone() };
+        // Downcastable, so that the caller's future can be recovered if the
+        // inner spawner rejects the task. `Tracked` is `Send`.
+        let task = unsafe { LocalFutureObj::new_downcastable(Box::new(task)).into_future_obj() };
+        self.inner.spawn_obj(task).map_err(|SpawnObjError { kind, future }| {
+            let future = match LocalFutureObj::from(future).downcast::<Tracked>() {
+                // Dropping the emptied task releases the slot.
+                Ok(mut task) => task.future.take().unwrap(),
+                // The inner spawner handed back some other future; it's
+                // returned as it is.
+                Err(future) => unsafe { future.into_future_obj() },
+            };
+            SpawnObjError { kind, future }
+        })
+    }
+
+    fn status(&self) -> Result<(), SpawnErrorKind> {
+        if self.shared.shutdown.load(SeqCst) {
+            return Err(SpawnErrorKind::shutdown());
+        }
+        if self.shared.running.load(SeqCst) >= self.limit {
+            return Err(SpawnErrorKind::queue_full());
+        }
+        self.inner.status()
+    }
+}
+
+impl<S: Spawn> ShutdownSpawn for Bounded<S> {
+    fn begin_shutdown(&mut self) {
+        self.shared.shutdown.store(true, SeqCst);
+    }
+
+    fn poll_drained(&self, cx: &mut Context) -> Poll<()> {
+        // Register before checking, so that a task finishing in between
+        // still wakes us.
+        self.shared.drained.register(cx.waker());
+        if self.shared.running.load(SeqCst) == 0 {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,608 @@
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+#[cfg(feature = "debug")]
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::Unpin;
+use std::mem::PinMut;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+use std::task::local_waker_from_nonlocal;
+use std::time::Duration;
+#[cfg(all(unix, feature = "reactor"))]
+use std::time::Instant;
+use future::{Future, FutureObj, LocalFutureObj};
+use stream::{Stream, FuturesUnordered};
+use task::{Context, Poll, Waker, LocalWaker};
+use spawn::{Spawn, SpawnLocal, SpawnShared, SpawnObjError, SpawnErrorKind, TimerSpawn};
+use spawn::{ShutdownSpawn, SpawnNamed, StrongSpawn, WeakSpawn};
+use spawn::{Metrics, MetricsSnapshot, TaskStats};
+use timer::{Timer, TimerHandle, Sleep};
+#[cfg(all(unix, feature = "reactor"))]
+use reactor::{Reactor, ReactorHandle, Readiness};
+#[cfg(all(unix, feature = "reactor"))]
+use spawn::{ReactorSpawn, RawSource, Ready};
+use super::task_info::report_panic;
+#[cfg(feature = "debug")]
+use super::task_info::{TaskInfo, TaskState};
+use super::thread_notify::ThreadNotify;
+use super::with_spawner::WithSpawner;
+
+/// The state a `LocalPool` shares with its spawners.
+struct Shared {
+    /// Tasks spawned since the pool last moved them into `pool`.
+    incoming: RefCell<Vec<LocalTask>>,
+    /// The number of tasks spawned and not yet completed.
+    tasks: Cell<usize>,
+    /// Set by `ShutdownSpawn::begin_shutdown`, after which no new tasks are
+    /// accepted.
+    shutdown: Cell<bool>,
+    /// Woken when `tasks` drops to zero.
+    drained: RefCell<Option<Waker>>,
+    metrics: Metrics,
+    /// The tasks which haven't completed or been dropped, by spawn order,
+    /// for `LocalPool::tasks`.
+    #[cfg(feature = "debug")]
+    registry: RefCell<BTreeMap<usize, TaskInfo>>,
+    #[cfg(feature = "debug")]
+    next_id: Cell<usize>,
+}
+
+impl Shared {
+    fn task_done(&self) {
+        self.tasks.set(self.tasks.get() - 1);
+        if self.tasks.get() == 0 {
+            let waker = self.drained.borrow_mut().take();
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// A task spawned onto a `LocalPool`.
+struct LocalTask {
+    future: LocalFutureObj<'static, (), dyn Spawn>,
+    /// The name from `SpawnNamed`, for panic reports.
+    name: Option<Cow<'static, str>>,
+    metrics: Metrics,
+    stats: TaskStats,
+    /// The task's key in `Shared::registry`.
+    #[cfg(feature = "debug")]
+    id: usize,
+    #[cfg(feature = "debug")]
+    shared: Weak<Shared>,
+}
+
+// The future is never pinned, as it is `Unpin` itself.
+impl Unpin for LocalTask {}
+
+impl LocalTask {
+    #[cfg(feature = "debug")]
+    fn update_info<F: FnOnce(&mut TaskInfo)>(&self, f: F) {
+        if let Some(shared) = self.shared.upgrade() {
+            if let Some(info) = shared.registry.borrow_mut().get_mut(&self.id) {
+                f(info);
+            }
+        }
+    }
+}
+
+impl Future<LocalSpawner> for LocalTask {
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<LocalSpawner>) -> Poll<()> {
+        #[cfg(feature = "debug")]
+        self.update_info(|info| {
+            info.state = TaskState::Running;
+            info.polls += 1;
+        });
+        let ret = {
+            let this = &mut *self;
+            let (future, metrics, stats) = (&mut this.future, &this.metrics, &mut this.stats);
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                metrics.poll(stats, || PinMut::new(future).poll(cx))
+            }))
+        };
+        #[cfg(feature = "debug")]
+        self.update_info(|info| info.state = TaskState::Idle);
+        // A task which panics is treated as complete, so the pool drops it
+        // without polling it again.
+        ret.unwrap_or_else(|payload| {
+            if let Some(name) = &self.name {
+                report_panic(name, &*payload);
+            }
+            Poll::Ready(())
+        })
+    }
+}
+
+impl Drop for LocalTask {
+    fn drop(&mut self) {
+        self.metrics.task_dropped();
+        #[cfg(feature = "debug")]
+        {
+            if let Some(shared) = self.shared.upgrade() {
+                shared.registry.borrow_mut().remove(&self.id);
+            }
+        }
+    }
+}
+
+/// A single-threaded task pool.
+///
+/// Tasks are spawned through a `LocalSpawner`, obtained from `spawner`, and
+/// are only ever polled from the thread which runs the pool, so they need
+/// not be `Send`. Every task, as well as the future passed to `run_until`,
+/// is polled with a `Context<LocalSpawner>`, so futures specialized to this
+/// pool can call its inherent methods on `cx.spawner()`.
+///
+/// The pool only makes progress while one of its `run` methods is being
+/// called. When no task can make progress, the thread is parked until one
+/// of them is woken or the pool's timer, which backs `TimerSpawn` for
+/// `LocalSpawner`, next fires.
+///
+/// A spawned task which panics is dropped, and the panic is caught rather
+/// than unwinding out of the `run` method, so other tasks keep running. A
+/// panic in the future passed to `run_until` does propagate. Tasks spawned
+/// with `SpawnNamed` are named in a report of the panic on stderr, and,
+/// with the `debug` feature, in the list returned by `tasks`.
+///
+/// `ShutdownSpawn::begin_shutdown` on any of the pool's spawners stops the
+/// pool accepting tasks, while the tasks it has already accepted keep
+/// running whenever the pool is run; `ShutdownSpawn::drain` resolves once
+/// the last of them completes.
+///
+/// With the `reactor` feature, on Unix, the pool also runs a `Reactor`,
+/// which backs `ReactorSpawn` for `LocalSpawner`. While sources are
+/// registered with it, the pool blocks on the reactor rather than parking
+/// the thread, so it wakes as soon as one of them is ready.
+pub struct LocalPool {
+    pool: FuturesUnordered<LocalTask>,
+    shared: Rc<Shared>,
+    notify: Arc<ThreadNotify>,
+    timer: Timer,
+    #[cfg(all(unix, feature = "reactor"))]
+    reactor: Reactor,
+    /// The spawner behind `strong_spawner` and `weak_spawner`.
+    handle: StrongSpawn<LocalSpawner>,
+}
+
+impl fmt::Debug for LocalPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LocalPool")
+            .field("tasks", &self.shared.tasks.get())
+            .finish()
+    }
+}
+
+impl LocalPool {
+    /// Create a new, empty pool, which must be run on the current thread.
+    ///
+    /// # Panics
+    ///
+    /// With the `reactor` feature, this panics if the reactor can't be
+    /// created.
+    pub fn new() -> LocalPool {
+        LocalPool::with_metrics(Metrics::new())
+    }
+
+    /// Create a new, empty pool which records its metrics into `metrics`,
+    /// e.g. to be called back as each task completes with
+    /// `Metrics::on_task_completed`.
+    ///
+    /// The pool's queue includes tasks which have been woken, which it
+    /// only counts when a snapshot is taken with `LocalPool::metrics`; the
+    /// `queued` count of `metrics` itself only includes tasks which haven't
+    /// been polled yet.
+    ///
+    /// # Panics
+    ///
+    /// With the `reactor` feature, this panics if the reactor can't be
+    /// created.
+    pub fn with_metrics(metrics: Metrics) -> LocalPool {
+        #[cfg(all(unix, feature = "reactor"))]
+        let reactor = Reactor::new().expect("failed to create the pool's reactor");
+        // Wakeups have to interrupt a wait on the reactor as well as a park.
+        #[cfg(all(unix, feature = "reactor"))]
+        let notify = ThreadNotify::with_interrupt(reactor.waker());
+        #[cfg(not(all(unix, feature = "reactor")))]
+        let notify = ThreadNotify::current();
+        let timer = Timer::new();
+        timer.set_waker(&Waker::from(notify.clone()));
+        let shared = Rc::new(Shared {
+            incoming: RefCell::new(Vec::new()),
+            tasks: Cell::new(0),
+            shutdown: Cell::new(false),
+            drained: RefCell::new(None),
+            metrics,
+            #[cfg(feature = "debug")]
+            registry: RefCell::new(BTreeMap::new()),
+            #[cfg(feature = "debug")]
+            next_id: Cell::new(0),
+        });
+        let handle = StrongSpawn::new(LocalSpawner {
+            shared: Rc::downgrade(&shared),
+            timer: timer.handle(),
+            #[cfg(all(unix, feature = "reactor"))]
+            reactor: reactor.handle(),
+        });
+        LocalPool {
+            pool: FuturesUnordered::new(),
+            shared,
+            notify,
+            timer,
+            #[cfg(all(unix, feature = "reactor"))]
+            reactor,
+            handle,
+        }
+    }
+
+    /// Get a spawner for this pool.
+    ///
+    /// Spawning through the spawner after the pool has been dropped fails
+    /// with `SpawnErrorKind::shutdown()`.
+    pub fn spawner(&self) -> LocalSpawner {
+        LocalSpawner {
+            shared: Rc::downgrade(&self.shared),
+            timer: self.timer.handle(),
+            #[cfg(all(unix, feature = "reactor"))]
+            reactor: self.reactor.handle(),
+        }
+    }
+
+    /// Get a shared handle to a spawner for this pool.
+    ///
+    /// Like the spawner itself, the handle doesn't keep the pool alive:
+    /// once the pool has been dropped, spawning through it fails with
+    /// `SpawnErrorKind::shutdown()`.
+    pub fn strong_spawner(&self) -> StrongSpawn<LocalSpawner> {
+        self.handle.clone()
+    }
+
+    /// Get a weak handle to a spawner for this pool.
+    ///
+    /// The handle can't be upgraded once the pool and every handle from
+    /// `strong_spawner` have been dropped, and spawning through it fails
+    /// with `SpawnErrorKind::shutdown()` once the pool is gone.
+    pub fn weak_spawner(&self) -> WeakSpawn<LocalSpawner> {
+        self.handle.downgrade()
+    }
+
+    /// Take a snapshot of the pool's metrics.
+    ///
+    /// `queued` counts the tasks waiting to be polled, whether they were
+    /// just spawned or were woken.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        let mut snapshot = self.shared.metrics.snapshot();
+        snapshot.queued += self.pool.ready_len();
+        snapshot
+    }
+
+    /// List the tasks spawned onto the pool which haven't completed yet, in
+    /// the order they were spawned.
+    ///
+    /// A task is only reported as `TaskState::Queued` until it is first
+    /// polled; after that it is `Idle` whenever it isn't being polled, even
+    /// if it has been woken.
+    #[cfg(feature = "debug")]
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        self.shared.registry.borrow().values().cloned().collect()
+    }
+
+    /// Run every spawned task to completion, including tasks spawned while
+    /// running.
+    ///
+    /// This blocks the current thread while tasks are waiting on external
+    /// events.
+    pub fn run(&mut self) {
+        let local_waker = local_waker_from_nonlocal(self.notify.clone());
+        let waker = Waker::from(self.notify.clone());
+        while !self.poll_pool(&local_waker, &waker) {
+            self.park();
+        }
+    }
+
+    /// Run the given future to completion on the current thread, polling
+    /// spawned tasks in the meantime, and return its output.
+    ///
+    /// Returns as soon as the future completes, even if spawned tasks are
+    /// still pending; they stay in the pool and run again the next time the
+    /// pool is run.
+    pub fn run_until<F: Future<LocalSpawner>>(&mut self, future: F) -> F::Output {
+        pin_mut!(future);
+        let local_waker = local_waker_from_nonlocal(self.notify.clone());
+        let waker = Waker::from(self.notify.clone());
+        loop {
+            {
+                let mut spawner = self.spawner();
+                let mut cx = Context::new(&local_waker, &waker, &mut spawner);
+                if let Poll::Ready(output) = future.reborrow().poll(&mut cx) {
+                    return output;
+                }
+            }
+            self.poll_pool(&local_waker, &waker);
+            self.park();
+        }
+    }
+
+    /// Poll every task which can make progress, without blocking, until
+    /// none can.
+    ///
+    /// Returns `true` if every task spawned on the pool has completed.
+    pub fn run_until_stalled(&mut self) -> bool {
+        let local_waker = local_waker_from_nonlocal(self.notify.clone());
+        let waker = Waker::from(self.notify.clone());
+        loop {
+            if self.poll_pool(&local_waker, &waker) {
+                return true;
+            }
+            // Tasks which woke themselves, or each other, while being polled,
+            // or whose timers fired or sources became ready, can still make
+            // progress.
+            self.timer.turn();
+            #[cfg(all(unix, feature = "reactor"))]
+            {
+                if self.reactor.has_registrations() {
+                    self.turn_reactor(Some(Duration::from_secs(0)));
+                }
+            }
+            if !self.notify.take_wakeup() {
+                return false;
+            }
+        }
+    }
+
+    /// Block until a task is woken or a timer fires, firing expired timers.
+    fn park(&self) {
+        let next = self.timer.turn();
+        #[cfg(all(unix, feature = "reactor"))]
+        {
+            if self.reactor.has_registrations() {
+                // A wakeup from here on interrupts the turn, and one from
+                // before it means there is work to do already.
+                if !self.notify.take_wakeup() {
+                    let now = Instant::now();
+                    let timeout = next.map(|next| {
+                        if next > now { next - now } else { Duration::from_secs(0) }
+                    });
+                    self.turn_reactor(timeout);
+                }
+                return;
+            }
+        }
+        self.notify.park(next);
+    }
+
+    #[cfg(all(unix, feature = "reactor"))]
+    fn turn_reactor(&self, timeout: Option<Duration>) {
+        self.reactor.turn(timeout).expect("failed to poll the pool's reactor");
+    }
+
+    /// Poll the tasks in the pool until none of them can make progress.
+    /// Returns `true` if the pool is empty.
+    fn poll_pool(&mut self, local_waker: &LocalWaker, waker: &Waker) -> bool {
+        let mut spawner = self.spawner();
+        loop {
+            // Move tasks spawned since the last iteration into the pool.
+            // The borrow must end before any task is polled, as polling may
+            // spawn.
+            let incoming = ::core::mem::replace(&mut *self.shared.incoming.borrow_mut(), Vec::new());
+            // New tasks are polled straight away rather than queued, so one
+            // which completes on its first poll is never boxed into the pool
+            // and costs no trip through its ready queue.
+            for task in incoming {
+                self.shared.metrics.task_dequeued();
+                let mut cx = Context::new(local_waker, waker, &mut spawner);
+                if self.pool.push_and_poll(task, &mut cx).is_ready() {
+                    self.shared.task_done();
+                }
+            }
+
+            let ret = {
+                let mut cx = Context::new(local_waker, waker, &mut spawner);
+                PinMut::new(&mut self.pool).poll_next(&mut cx)
+            };
+            match ret {
+                Poll::Ready(Some(())) => {
+                    self.shared.task_done();
+                    continue;
+                }
+                Poll::Ready(None) | Poll::Pending => {
+                    if self.shared.incoming.borrow().is_empty() {
+                        return self.pool.is_empty();
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for LocalPool {
+    fn default() -> LocalPool {
+        LocalPool::new()
+    }
+}
+
+/// Run a future to completion on the current thread.
+///
+/// This creates a `LocalPool` for the duration of the call, so the future
+/// may spawn tasks onto it through `cx.spawner()`. Tasks which haven't
+/// completed by the time the future does are dropped.
+pub fn block_on<F: Future<LocalSpawner>>(future: F) -> F::Output {
+    LocalPool::new().run_until(future)
+}
+
+/// A spawner for a `LocalPool`.
+///
+/// The spawner is a cheap handle which can be cloned freely, but it can't
+/// leave the pool's thread. It accepts `Send` futures through `Spawn` as
+/// well as non-`Send` ones through `SpawnLocal`.
+#[derive(Clone)]
+pub struct LocalSpawner {
+    shared: Weak<Shared>,
+    timer: TimerHandle,
+    #[cfg(all(unix, feature = "reactor"))]
+    reactor: ReactorHandle,
+}
+
+impl fmt::Debug for LocalSpawner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LocalSpawner")
+            .finish()
+    }
+}
+
+impl LocalSpawner {
+    /// Spawn a task which is polled with a spawner of its own, made by
+    /// `spawner_factory`, instead of with the pool's `LocalSpawner`.
+    ///
+    /// The factory receives a spawner for this pool, so the task's spawner
+    /// can wrap it to configure how the task spawns, e.g. to limit it with
+    /// `Bounded` or tag its children with a priority, while still spawning
+    /// onto this pool. Other tasks are unaffected.
+    pub fn spawn_obj_with_spawner<Sp, F>(
+        &mut self,
+        future: LocalFutureObj<'static, (), Sp>,
+        spawner_factory: F,
+    ) -> Result<(), SpawnObjError<LocalFutureObj<'static, (), Sp>>>
+        where Sp: Spawn + 'static,
+              F: FnOnce(LocalSpawner) -> Sp
+    {
+        let spawner = spawner_factory(self.clone());
+        let task = WithSpawner::new(future, spawner);
+        // Downcastable, so that the future can be handed back if the pool
+        // refuses it.
+        let task = LocalFutureObj::new_downcastable(Box::new(task));
+        self.push(task, None).map_err(|SpawnObjError { kind, future }| {
+            let task = future.downcast::<WithSpawner<LocalFutureObj<'static, (), Sp>, Sp>>()
+                .unwrap_or_else(|_| unreachable!());
+            SpawnObjError { kind, future: task.into_future() }
+        })
+    }
+
+    fn push(
+        &self,
+        future: LocalFutureObj<'static, (), dyn Spawn>,
+        name: Option<Cow<'static, str>>,
+    ) -> Result<(), SpawnObjError<LocalFutureObj<'static, (), dyn Spawn>>> {
+        match self.shared.upgrade() {
+            Some(ref shared) if !shared.shutdown.get() => {
+                #[cfg(feature = "debug")]
+                let id = {
+                    let id = shared.next_id.get();
+                    shared.next_id.set(id + 1);
+                    shared.registry.borrow_mut().insert(id, TaskInfo {
+                        name: name.clone(),
+                        state: TaskState::Queued,
+                        polls: 0,
+                    });
+                    id
+                };
+                shared.metrics.task_spawned();
+                shared.metrics.task_enqueued();
+                shared.incoming.borrow_mut().push(LocalTask {
+                    future,
+                    name,
+                    metrics: shared.metrics.clone(),
+                    stats: TaskStats::default(),
+                    #[cfg(feature = "debug")]
+                    id,
+                    #[cfg(feature = "debug")]
+                    shared: self.shared.clone(),
+                });
+                shared.tasks.set(shared.tasks.get() + 1);
+                Ok(())
+            }
+            _ => Err(SpawnObjError { kind: SpawnErrorKind::shutdown(), future }),
+        }
+    }
+}
+
+impl Spawn for LocalSpawner {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawn_obj_shared(future)
+    }
+
+    fn status(&self) -> Result<(), SpawnErrorKind> {
+        match self.shared.upgrade() {
+            Some(ref shared) if !shared.shutdown.get() => Ok(()),
+            _ => Err(SpawnErrorKind::shutdown()),
+        }
+    }
+}
+
+impl SpawnShared for LocalSpawner {
+    fn spawn_obj_shared(
+        &self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        // The future is `Send`, so this only converts it back.
+        self.push(future.into(), None).map_err(|SpawnObjError { kind, future }| {
+            SpawnObjError { kind, future: unsafe { future.into_future_obj() } }
+        })
+    }
+}
+
+impl SpawnLocal for LocalSpawner {
+    fn spawn_obj_local(
+        &mut self,
+        future: LocalFutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<LocalFutureObj<'static, (), dyn Spawn>>> {
+        self.push(future, None)
+    }
+}
+
+impl SpawnNamed for LocalSpawner {
+    fn spawn_obj_named(
+        &mut self,
+        name: Cow<'static, str>,
+        future: FutureObj<'static, (), dyn Spawn>,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        // The future is `Send`, so this only converts it back.
+        self.push(future.into(), Some(name)).map_err(|SpawnObjError { kind, future }| {
+            SpawnObjError { kind, future: unsafe { future.into_future_obj() } }
+        })
+    }
+}
+
+impl ShutdownSpawn for LocalSpawner {
+    fn begin_shutdown(&mut self) {
+        if let Some(shared) = self.shared.upgrade() {
+            shared.shutdown.set(true);
+        }
+    }
+
+    fn poll_drained(&self, cx: &mut Context) -> Poll<()> {
+        match self.shared.upgrade() {
+            // The pool's tasks were dropped along with it.
+            None => Poll::Ready(()),
+            Some(ref shared) if shared.tasks.get() == 0 => Poll::Ready(()),
+            Some(shared) => {
+                *shared.drained.borrow_mut() = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl TimerSpawn for LocalSpawner {
+    type Sleep = Sleep;
+
+    fn sleep(&mut self, duration: Duration) -> Sleep {
+        self.timer.sleep(duration)
+    }
+}
+
+#[cfg(all(unix, feature = "reactor"))]
+impl ReactorSpawn for LocalSpawner {
+    type Readiness = Readiness;
+
+    fn register(&mut self, source: RawSource, interest: Ready) -> Readiness {
+        self.reactor.register(source, interest)
+    }
+}
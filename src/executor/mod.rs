@@ -0,0 +1,26 @@
+//! Executors, and spawner adapters which control how tasks reach an
+//! executor.
+
+mod bounded;
+pub use self::bounded::Bounded;
+
+#[cfg(feature = "std")]
+mod local_pool;
+#[cfg(feature = "std")]
+pub use self::local_pool::{LocalPool, LocalSpawner, block_on};
+
+#[cfg(feature = "std")]
+mod thread_pool;
+#[cfg(feature = "std")]
+pub use self::thread_pool::{ThreadPool, ThreadPoolBuilder};
+
+#[cfg(feature = "std")]
+mod task_info;
+#[cfg(feature = "debug")]
+pub use self::task_info::{TaskInfo, TaskState};
+
+#[cfg(feature = "std")]
+mod with_spawner;
+
+#[cfg(feature = "std")]
+mod thread_notify;
@@ -0,0 +1,44 @@
+use std::any::Any;
+#[cfg(feature = "debug")]
+use std::borrow::Cow;
+
+/// A snapshot of a task spawned onto a pool, as returned by the pool's
+/// `tasks` method.
+#[cfg(feature = "debug")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskInfo {
+    /// The name the task was spawned with through `SpawnNamed`, if any.
+    pub name: Option<Cow<'static, str>>,
+    /// What the task was doing when the snapshot was taken.
+    pub state: TaskState,
+    /// How many times the task has been polled.
+    pub polls: usize,
+}
+
+/// The state of a task in a `TaskInfo`.
+#[cfg(feature = "debug")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TaskState {
+    /// The task is waiting for the pool to poll it, either because it was
+    /// just spawned or because it was woken.
+    Queued,
+    /// The task is being polled.
+    Running,
+    /// The task is waiting for a wakeup.
+    Idle,
+}
+
+/// Report that the task named `name` panicked.
+///
+/// The panic hook has already reported the panic itself, but not which task
+/// it came from.
+pub(super) fn report_panic(name: &str, payload: &(dyn Any + Send)) {
+    let message = match payload.downcast_ref::<&str>() {
+        Some(message) => *message,
+        None => match payload.downcast_ref::<String>() {
+            Some(message) => &message[..],
+            None => "Box<Any>",
+        },
+    };
+    eprintln!("task '{}' panicked at '{}'", name, message);
+}
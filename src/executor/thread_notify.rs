@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::Wake;
+use std::thread::{self, Thread};
+use std::time::Instant;
+use task::Waker;
+
+/// Wakes a thread which parks while it has nothing to do.
+pub(super) struct ThreadNotify {
+    thread: Thread,
+    woken: AtomicBool,
+    /// Woken along with the thread, to interrupt a wait other than `park`,
+    /// e.g. on a reactor.
+    interrupt: Option<Waker>,
+}
+
+impl ThreadNotify {
+    /// Create a notifier for the current thread.
+    pub(super) fn current() -> Arc<ThreadNotify> {
+        Arc::new(ThreadNotify {
+            thread: thread::current(),
+            woken: AtomicBool::new(false),
+            interrupt: None,
+        })
+    }
+
+    /// Create a notifier for the current thread which also wakes
+    /// `interrupt`.
+    #[cfg(all(unix, feature = "reactor"))]
+    pub(super) fn with_interrupt(interrupt: Waker) -> Arc<ThreadNotify> {
+        Arc::new(ThreadNotify {
+            thread: thread::current(),
+            woken: AtomicBool::new(false),
+            interrupt: Some(interrupt),
+        })
+    }
+
+    /// Consume a wakeup, returning whether there was one.
+    pub(super) fn take_wakeup(&self) -> bool {
+        self.woken.swap(false, Ordering::Acquire)
+    }
+
+    /// Block until the thread is woken, unless that already happened, or
+    /// until `deadline` passes.
+    pub(super) fn park(&self, deadline: Option<Instant>) {
+        while !self.take_wakeup() {
+            match deadline {
+                None => thread::park(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return;
+                    }
+                    thread::park_timeout(deadline - now);
+                }
+            }
+        }
+    }
+}
+
+impl Wake for ThreadNotify {
+    fn wake(arc_self: &Arc<ThreadNotify>) {
+        if !arc_self.woken.swap(true, Ordering::Release) {
+            arc_self.thread.unpark();
+            if let Some(interrupt) = &arc_self.interrupt {
+                interrupt.wake();
+            }
+        }
+    }
+}
@@ -0,0 +1,879 @@
+use std::borrow::Cow;
+#[cfg(feature = "debug")]
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::mem::PinMut;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+#[cfg(feature = "debug")]
+use std::sync::Weak;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::task::{Wake, local_waker_from_nonlocal};
+use std::thread;
+use std::time::Duration;
+use future::{Future, FutureObj, LocalFutureObj};
+use task::{Context, Poll, Waker, AtomicWaker};
+use spawn::{Spawn, SpawnShared, SpawnObjError, SpawnErrorKind, SpawnBlocking, BlockingTask, TimerSpawn};
+use spawn::{ShutdownSpawn, StrongSpawn, WeakSpawn};
+use spawn::{Priority, SpawnNamed, SpawnPriority};
+use spawn::{Metrics, MetricsSnapshot, TaskStats};
+use timer::{Timer, Sleep};
+use super::task_info::report_panic;
+#[cfg(feature = "debug")]
+use super::task_info::{TaskInfo, TaskState};
+use super::thread_notify::ThreadNotify;
+use super::with_spawner::WithSpawner;
+
+/// How many times in a row a non-empty queue may be passed over for a
+/// higher priority one before it is served anyway.
+const AGING_LIMIT: usize = 8;
+
+/// The tasks waiting for a worker, one queue per priority.
+struct RunQueue {
+    /// Indexed by `level`.
+    queues: [VecDeque<Arc<Task>>; 3],
+    /// For each queue, how many times in a row it has been passed over
+    /// while it had tasks waiting.
+    skipped: [usize; 3],
+    /// Set when the pool closes, after which nothing more is queued and the
+    /// workers stop once the queues are empty.
+    closed: bool,
+}
+
+/// The index of a priority's queue, highest priority first.
+fn level(priority: Priority) -> usize {
+    match priority {
+        Priority::High => 0,
+        Priority::Normal => 1,
+        Priority::Low => 2,
+    }
+}
+
+impl RunQueue {
+    /// Take the next task: the oldest one of the highest priority, unless a
+    /// lower priority has been passed over `AGING_LIMIT` times in a row, in
+    /// which case its oldest task is promoted ahead of the rest.
+    fn pop(&mut self) -> Option<Arc<Task>> {
+        let mut pick = (0..3).find(|&level| !self.queues[level].is_empty())?;
+        if let Some(aged) = (pick + 1..3).rev().find(|&level| {
+            self.skipped[level] >= AGING_LIMIT && !self.queues[level].is_empty()
+        }) {
+            pick = aged;
+        }
+        for level in pick + 1..3 {
+            if !self.queues[level].is_empty() {
+                self.skipped[level] += 1;
+            }
+        }
+        self.skipped[pick] = 0;
+        self.queues[pick].pop_front()
+    }
+}
+
+/// How long an idle blocking thread waits for more work before it exits.
+const BLOCKING_KEEP_ALIVE: Duration = Duration::from_secs(10);
+
+/// The queue of blocking closures and the threads which run them.
+struct Blocking {
+    queue: VecDeque<BlockingTask>,
+    /// The number of blocking threads alive.
+    threads: usize,
+    /// The number of blocking threads waiting for a closure.
+    idle: usize,
+}
+
+struct PoolState {
+    queue: Mutex<RunQueue>,
+    /// Signalled when a task is queued or the pool closes.
+    run_ready: Condvar,
+    /// The number of `ThreadPool` handles alive. The workers are stopped
+    /// when it drops to zero.
+    handles: AtomicUsize,
+    size: usize,
+    timer: Timer,
+    /// Wakes the thread driving the timer, once it has started.
+    timer_waker: Mutex<Option<Waker>>,
+    closed: AtomicBool,
+    /// Set by `ShutdownSpawn::begin_shutdown`, after which no new tasks are
+    /// accepted.
+    shutdown: AtomicBool,
+    /// The number of tasks spawned and not yet completed or dropped.
+    tasks: AtomicUsize,
+    /// Woken when `tasks` drops to zero.
+    drained: AtomicWaker,
+    blocking: Mutex<Blocking>,
+    /// Signalled when a blocking closure is queued or the pool closes.
+    blocking_ready: Condvar,
+    max_blocking_threads: usize,
+    name_prefix: Option<String>,
+    /// The spawner behind `ThreadPool::strong_spawner` and `weak_spawner`.
+    /// It holds a handle which doesn't count towards `handles`, and is
+    /// dropped when the pool closes.
+    spawner: Mutex<Option<StrongSpawn<ThreadPool>>>,
+    metrics: Metrics,
+    /// The tasks which haven't completed or been dropped, by spawn order,
+    /// for `ThreadPool::tasks`.
+    #[cfg(feature = "debug")]
+    registry: Mutex<BTreeMap<usize, Weak<Task>>>,
+    #[cfg(feature = "debug")]
+    next_id: AtomicUsize,
+}
+
+impl PoolState {
+    /// Queue a task for the workers, handing it back if the pool has closed.
+    fn push(&self, task: Arc<Task>) -> Result<(), Arc<Task>> {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.closed {
+            return Err(task);
+        }
+        queue.queues[level(task.priority)].push_back(task);
+        self.metrics.task_enqueued();
+        self.run_ready.notify_one();
+        Ok(())
+    }
+
+    /// Wait for the next task to run, or return `None` once the worker
+    /// should stop.
+    fn next_task(&self) -> Option<Arc<Task>> {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if let Some(task) = queue.pop() {
+                self.metrics.task_dequeued();
+                return Some(task);
+            }
+            if queue.closed {
+                return None;
+            }
+            queue = self.run_ready.wait(queue).unwrap();
+        }
+    }
+
+    /// Whether new tasks are refused, because the pool was shut down or
+    /// every handle to it was dropped.
+    fn is_shut_down(&self) -> bool {
+        self.shutdown.load(Ordering::SeqCst) || self.closed.load(Ordering::SeqCst)
+    }
+
+    fn task_done(&self, _task: &Task) {
+        #[cfg(feature = "debug")]
+        self.registry.lock().unwrap().remove(&_task.id);
+        if self.tasks.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.drained.wake();
+        }
+    }
+
+    fn drive_timer(&self) {
+        let notify = ThreadNotify::current();
+        let waker = Waker::from(notify.clone());
+        self.timer.set_waker(&waker);
+        *self.timer_waker.lock().unwrap() = Some(waker);
+        while !self.closed.load(Ordering::SeqCst) {
+            let next = self.timer.turn();
+            notify.park(next);
+        }
+    }
+
+    fn close(&self) {
+        // A task polled after the last handle was dropped can clone and drop
+        // a handle of its own, which mustn't close the pool again.
+        if self.closed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // Its handle refers back to this state.
+        drop(self.spawner.lock().unwrap().take());
+        {
+            // The workers finish the tasks queued so far, but no more: tasks
+            // woken from now on are dropped instead of being queued.
+            let mut queue = self.queue.lock().unwrap();
+            queue.closed = true;
+            self.run_ready.notify_all();
+        }
+        if let Some(waker) = self.timer_waker.lock().unwrap().take() {
+            waker.wake();
+        }
+        // Idle blocking threads exit; busy ones do once the queue is empty.
+        let _blocking = self.blocking.lock().unwrap();
+        self.blocking_ready.notify_all();
+    }
+
+    fn spawn_blocking(state: &Arc<PoolState>, task: BlockingTask) {
+        let mut blocking = state.blocking.lock().unwrap();
+        if state.closed.load(Ordering::SeqCst) {
+            // Dropping the task cancels its handle.
+            return;
+        }
+        blocking.queue.push_back(task);
+        if blocking.idle > 0 {
+            state.blocking_ready.notify_one();
+            return;
+        }
+        if blocking.threads == state.max_blocking_threads {
+            // The closure waits for one of the busy threads.
+            return;
+        }
+
+        let mut thread = thread::Builder::new();
+        if let Some(name_prefix) = &state.name_prefix {
+            thread = thread.name(format!("{}blocking", name_prefix));
+        }
+        let thread_state = state.clone();
+        match thread.spawn(move || thread_state.run_blocking()) {
+            Ok(_) => blocking.threads += 1,
+            Err(_) => {
+                if blocking.threads == 0 {
+                    // Nothing would ever run the queue, so cancel it.
+                    blocking.queue.clear();
+                }
+            }
+        }
+    }
+
+    fn run_blocking(&self) {
+        let mut blocking = self.blocking.lock().unwrap();
+        loop {
+            if let Some(task) = blocking.queue.pop_front() {
+                drop(blocking);
+                task.run();
+                blocking = self.blocking.lock().unwrap();
+                continue;
+            }
+            if self.closed.load(Ordering::SeqCst) {
+                break;
+            }
+            blocking.idle += 1;
+            let (guard, timeout) = self.blocking_ready.wait_timeout(blocking, BLOCKING_KEEP_ALIVE).unwrap();
+            blocking = guard;
+            blocking.idle -= 1;
+            if timeout.timed_out() && blocking.queue.is_empty() {
+                break;
+            }
+        }
+        blocking.threads -= 1;
+    }
+
+    fn work(&self, index: usize, pool: &ThreadPool, hooks: &Hooks) {
+        if let Some(after_start) = &hooks.after_start {
+            after_start(index);
+        }
+        while let Some(task) = self.next_task() {
+            task.run(pool);
+        }
+        if let Some(before_stop) = &hooks.before_stop {
+            before_stop(index);
+        }
+    }
+}
+
+/// A task spawned onto a `ThreadPool`, which doubles as its own waker.
+struct Task {
+    future: Mutex<Option<FutureObj<'static, (), dyn Spawn>>>,
+    state: AtomicUsize,
+    priority: Priority,
+    /// Only touched by the worker polling the task.
+    stats: Mutex<TaskStats>,
+    /// The name from `SpawnNamed`, for panic reports and `ThreadPool::tasks`.
+    name: Option<Cow<'static, str>>,
+    #[cfg(feature = "debug")]
+    polls: AtomicUsize,
+    #[cfg(feature = "debug")]
+    id: usize,
+    pool: Arc<PoolState>,
+}
+
+// The task is waiting for a wakeup.
+const IDLE: usize = 0;
+// The task has been queued for the workers and not yet picked up.
+const QUEUED: usize = 1;
+// A worker is polling the task.
+const POLLING: usize = 2;
+// The task was woken while being polled, so it must be polled again.
+const REPOLL: usize = 3;
+// The task has completed and its future has been dropped.
+const COMPLETE: usize = 4;
+
+impl Task {
+    fn run(self: Arc<Self>, pool: &ThreadPool) {
+        self.state.store(POLLING, Ordering::SeqCst);
+        let local_waker = local_waker_from_nonlocal(self.clone());
+        let waker = Waker::from(self.clone());
+        let mut spawner = pool.clone();
+        let mut future = self.future.lock().unwrap();
+        let mut stats = self.stats.lock().unwrap();
+        loop {
+            let ret = match &mut *future {
+                Some(future) => {
+                    #[cfg(feature = "debug")]
+                    self.polls.fetch_add(1, Ordering::Relaxed);
+                    let mut cx = Context::new(&local_waker, &waker, &mut spawner);
+                    let metrics = &self.pool.metrics;
+                    let stats = &mut *stats;
+                    // A task which panics is treated as complete, so it is
+                    // never polled again after the panic.
+                    panic::catch_unwind(AssertUnwindSafe(|| {
+                        metrics.poll(stats, || PinMut::new(future).poll(&mut cx))
+                    }))
+                        .unwrap_or_else(|payload| {
+                            if let Some(name) = &self.name {
+                                report_panic(name, &*payload);
+                            }
+                            Poll::Ready(())
+                        })
+                }
+                None => return,
+            };
+            if let Poll::Ready(()) = ret {
+                *future = None;
+                self.state.store(COMPLETE, Ordering::SeqCst);
+                self.pool.task_done(&self);
+                return;
+            }
+            // Go idle, unless the task was woken while it was being polled.
+            match self.state.compare_exchange(POLLING, IDLE, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => return,
+                Err(_) => self.state.store(POLLING, Ordering::SeqCst),
+            }
+        }
+    }
+}
+
+impl Drop for Task {
+    fn drop(&mut self) {
+        self.pool.metrics.task_dropped();
+        // The pool dropped the task before it completed.
+        let pending = match self.future.get_mut() {
+            Ok(future) => future.is_some(),
+            Err(_) => false,
+        };
+        if pending {
+            self.pool.task_done(self);
+        }
+    }
+}
+
+impl Wake for Task {
+    fn wake(arc_self: &Arc<Task>) {
+        let mut state = arc_self.state.load(Ordering::SeqCst);
+        loop {
+            let next = match state {
+                IDLE => QUEUED,
+                POLLING => REPOLL,
+                _ => return,
+            };
+            match arc_self.state.compare_exchange(state, next, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(IDLE) => {
+                    // Once the pool has closed, the task is dropped instead.
+                    arc_self.pool.push(arc_self.clone()).ok();
+                    return;
+                }
+                Ok(_) => return,
+                Err(actual) => state = actual,
+            }
+        }
+    }
+}
+
+type Hook = Arc<dyn Fn(usize) + Send + Sync>;
+
+#[derive(Clone, Default)]
+struct Hooks {
+    after_start: Option<Hook>,
+    before_stop: Option<Hook>,
+}
+
+/// A pool of worker threads which run tasks.
+///
+/// `ThreadPool` is a handle to the pool: clones share the same workers, and
+/// the workers stop once every handle has been dropped, after finishing the
+/// tasks already queued for them. Tasks which haven't completed by then are
+/// dropped, and spawning from them fails with `SpawnErrorKind::shutdown()`.
+/// Running tasks hold a handle themselves, as the spawner of the
+/// `Context<ThreadPool>` they are polled with, so a task which is being
+/// polled keeps the pool alive.
+///
+/// Tasks are polled by whichever worker is free when they are woken, so
+/// spawned futures must be `Send`. The pool also runs a timer on a thread of
+/// its own, which backs its `TimerSpawn` implementation. A task which
+/// panics is dropped, and the panic is caught so that the worker keeps
+/// running other tasks. Tasks spawned with `SpawnNamed` are named in a
+/// report of the panic on stderr, and, with the `debug` feature, in the
+/// list returned by `tasks`.
+///
+/// Tasks spawned with `SpawnPriority` are queued by priority, and tasks
+/// spawned otherwise at `Priority::Normal`. Workers take the highest
+/// priority task waiting, but a queue which is passed over several times in
+/// a row has its oldest task run next, so lower priority work still makes
+/// progress under a steady stream of higher priority tasks.
+///
+/// `ShutdownSpawn::begin_shutdown` stops the pool accepting tasks through
+/// any of its handles, including from the tasks it is running, while the
+/// tasks it has already accepted keep running; `ShutdownSpawn::drain`
+/// resolves once the last of them completes.
+///
+/// Closures passed to `SpawnBlocking::spawn_blocking` never run on the
+/// workers. They run on a separate set of threads which are started on
+/// demand, up to the limit set with `ThreadPoolBuilder::max_blocking_threads`,
+/// and exit after being idle for a while.
+pub struct ThreadPool {
+    state: Arc<PoolState>,
+    /// Whether this handle counts towards keeping the workers running.
+    counted: bool,
+}
+
+impl fmt::Debug for ThreadPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ThreadPool")
+            .field("size", &self.state.size)
+            .finish()
+    }
+}
+
+impl ThreadPool {
+    /// Create a pool with the default configuration.
+    ///
+    /// This is equivalent to `ThreadPool::builder().create()`.
+    pub fn new() -> io::Result<ThreadPool> {
+        ThreadPool::builder().create()
+    }
+
+    /// Create a builder for configuring a pool.
+    pub fn builder() -> ThreadPoolBuilder {
+        ThreadPoolBuilder::new()
+    }
+
+    /// The number of worker threads in the pool.
+    pub fn size(&self) -> usize {
+        self.state.size
+    }
+
+    /// Take a snapshot of the pool's metrics.
+    ///
+    /// `queued` counts the tasks waiting for a worker, whether they were
+    /// just spawned or were woken.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        self.state.metrics.snapshot()
+    }
+
+    /// List the tasks spawned onto the pool which haven't completed yet, in
+    /// the order they were spawned.
+    ///
+    /// Tasks keep running while the list is built, so it may be slightly
+    /// out of date by the time it is returned.
+    #[cfg(feature = "debug")]
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        let tasks: Vec<Arc<Task>> = self.state.registry.lock().unwrap()
+            .values()
+            .filter_map(Weak::upgrade)
+            .collect();
+        // Built outside the lock, as dropping the last reference to a task
+        // takes it again.
+        tasks.iter().map(|task| TaskInfo {
+            name: task.name.clone(),
+            state: match task.state.load(Ordering::SeqCst) {
+                IDLE => TaskState::Idle,
+                QUEUED => TaskState::Queued,
+                _ => TaskState::Running,
+            },
+            polls: task.polls.load(Ordering::Relaxed),
+        }).collect()
+    }
+
+    /// A handle which doesn't keep the workers running, for the pool's own
+    /// use.
+    fn uncounted(state: Arc<PoolState>) -> ThreadPool {
+        ThreadPool { state, counted: false }
+    }
+
+    /// Get a shared handle to the pool's spawner.
+    ///
+    /// Unlike a `ThreadPool` handle, the returned handle doesn't keep the
+    /// workers running: once every `ThreadPool` handle has been dropped,
+    /// spawning through it fails with `SpawnErrorKind::shutdown()`.
+    pub fn strong_spawner(&self) -> StrongSpawn<ThreadPool> {
+        match &*self.state.spawner.lock().unwrap() {
+            Some(spawner) => spawner.clone(),
+            None => StrongSpawn::new(ThreadPool::uncounted(self.state.clone())),
+        }
+    }
+
+    /// Get a weak handle to the pool's spawner.
+    ///
+    /// The handle can't be upgraded once every `ThreadPool` handle has been
+    /// dropped, and spawning through it then fails with
+    /// `SpawnErrorKind::shutdown()`.
+    pub fn weak_spawner(&self) -> WeakSpawn<ThreadPool> {
+        match &*self.state.spawner.lock().unwrap() {
+            Some(spawner) => spawner.downgrade(),
+            None => WeakSpawn::new(),
+        }
+    }
+
+    /// Spawn a task which is polled with a spawner of its own, made by
+    /// `spawner_factory`, instead of with a `ThreadPool` handle.
+    ///
+    /// The factory receives a handle to this pool, so the task's spawner
+    /// can wrap it to configure how the task spawns, e.g. to limit it with
+    /// `Bounded` or tag its children with a priority, while still spawning
+    /// onto this pool. Other tasks are unaffected.
+    pub fn spawn_obj_with_spawner<Sp, F>(
+        &self,
+        future: FutureObj<'static, (), Sp>,
+        spawner_factory: F,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), Sp>>>
+        where Sp: Spawn + Send + 'static,
+              F: FnOnce(ThreadPool) -> Sp
+    {
+        let spawner = spawner_factory(self.clone());
+        let task = WithSpawner::new(future, spawner);
+        // Downcastable, so that the future can be handed back if the pool
+        // refuses it. The task is `Send`, as its future and spawner are.
+        let task = unsafe { LocalFutureObj::new_downcastable(Box::new(task)).into_future_obj() };
+        self.spawn_obj_shared(task).map_err(|SpawnObjError { kind, future }| {
+            let task = LocalFutureObj::from(future)
+                .downcast::<WithSpawner<FutureObj<'static, (), Sp>, Sp>>()
+                .unwrap_or_else(|_| unreachable!());
+            SpawnObjError { kind, future: task.into_future() }
+        })
+    }
+
+    fn spawn_task(
+        &self,
+        future: FutureObj<'static, (), dyn Spawn>,
+        priority: Priority,
+        name: Option<Cow<'static, str>>,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        if self.state.is_shut_down() {
+            return Err(SpawnObjError { kind: SpawnErrorKind::shutdown(), future });
+        }
+        self.state.tasks.fetch_add(1, Ordering::SeqCst);
+        let task = Arc::new(Task {
+            future: Mutex::new(Some(future)),
+            state: AtomicUsize::new(QUEUED),
+            priority,
+            stats: Mutex::new(TaskStats::default()),
+            name,
+            #[cfg(feature = "debug")]
+            polls: AtomicUsize::new(0),
+            #[cfg(feature = "debug")]
+            id: self.state.next_id.fetch_add(1, Ordering::Relaxed),
+            pool: self.state.clone(),
+        });
+        #[cfg(feature = "debug")]
+        self.state.registry.lock().unwrap().insert(task.id, Arc::downgrade(&task));
+        // A task the pool refuses below is counted as spawned and dropped.
+        self.state.metrics.task_spawned();
+        match self.state.push(task) {
+            Ok(()) => Ok(()),
+            // The pool closed in the meantime. The task was never shared, so
+            // its future can be handed back.
+            Err(task) => {
+                let future = task.future.lock().unwrap().take().unwrap();
+                self.state.task_done(&task);
+                Err(SpawnObjError { kind: SpawnErrorKind::shutdown(), future })
+            }
+        }
+    }
+}
+
+impl Clone for ThreadPool {
+    fn clone(&self) -> ThreadPool {
+        // Clones always count, including those of the handles the pool
+        // polls its tasks with.
+        self.state.handles.fetch_add(1, Ordering::SeqCst);
+        ThreadPool { state: self.state.clone(), counted: true }
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        if self.counted && self.state.handles.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.state.close();
+        }
+    }
+}
+
+impl TimerSpawn for ThreadPool {
+    type Sleep = Sleep;
+
+    fn sleep(&mut self, duration: Duration) -> Sleep {
+        self.state.timer.handle().sleep(duration)
+    }
+}
+
+impl Spawn for ThreadPool {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawn_obj_shared(future)
+    }
+
+    fn status(&self) -> Result<(), SpawnErrorKind> {
+        if self.state.is_shut_down() {
+            Err(SpawnErrorKind::shutdown())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl SpawnBlocking for ThreadPool {
+    fn spawn_blocking_task(&mut self, task: BlockingTask) {
+        PoolState::spawn_blocking(&self.state, task)
+    }
+}
+
+impl SpawnShared for ThreadPool {
+    fn spawn_obj_shared(
+        &self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawn_task(future, Priority::Normal, None)
+    }
+}
+
+impl SpawnPriority for ThreadPool {
+    fn spawn_obj_with_priority(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>,
+        priority: Priority,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawn_task(future, priority, None)
+    }
+}
+
+impl SpawnNamed for ThreadPool {
+    fn spawn_obj_named(
+        &mut self,
+        name: Cow<'static, str>,
+        future: FutureObj<'static, (), dyn Spawn>,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawn_task(future, Priority::Normal, Some(name))
+    }
+}
+
+impl ShutdownSpawn for ThreadPool {
+    fn begin_shutdown(&mut self) {
+        self.state.shutdown.store(true, Ordering::SeqCst);
+    }
+
+    fn poll_drained(&self, cx: &mut Context) -> Poll<()> {
+        // Register before checking, so that a task finishing in between
+        // still wakes us.
+        self.state.drained.register(cx.waker());
+        if self.state.tasks.load(Ordering::SeqCst) == 0 {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// A builder for configuring a `ThreadPool`.
+///
+/// The builder can be reused, and cloned, to create several pools with the
+/// same configuration.
+#[derive(Clone)]
+pub struct ThreadPoolBuilder {
+    pool_size: usize,
+    stack_size: Option<usize>,
+    name_prefix: Option<String>,
+    max_blocking_threads: usize,
+    hooks: Hooks,
+    metrics: Option<Metrics>,
+}
+
+impl fmt::Debug for ThreadPoolBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ThreadPoolBuilder")
+            .field("pool_size", &self.pool_size)
+            .field("stack_size", &self.stack_size)
+            .field("name_prefix", &self.name_prefix)
+            .field("max_blocking_threads", &self.max_blocking_threads)
+            .finish()
+    }
+}
+
+impl ThreadPoolBuilder {
+    /// Create a builder with the default configuration: one worker per
+    /// available CPU, unnamed threads with the platform's default stack
+    /// size, up to 64 threads for blocking closures, and no hooks.
+    pub fn new() -> ThreadPoolBuilder {
+        ThreadPoolBuilder {
+            pool_size: available_parallelism(),
+            stack_size: None,
+            name_prefix: None,
+            max_blocking_threads: 64,
+            hooks: Hooks::default(),
+            metrics: None,
+        }
+    }
+
+    /// Set the number of worker threads.
+    ///
+    /// `create` fails if this is zero.
+    pub fn pool_size(&mut self, size: usize) -> &mut Self {
+        self.pool_size = size;
+        self
+    }
+
+    /// Set the stack size, in bytes, of the worker threads.
+    pub fn stack_size(&mut self, stack_size: usize) -> &mut Self {
+        self.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Set the maximum number of threads running blocking closures at once.
+    ///
+    /// Closures submitted while that many are running wait for one of them
+    /// to finish. `create` fails if this is zero.
+    pub fn max_blocking_threads(&mut self, max: usize) -> &mut Self {
+        self.max_blocking_threads = max;
+        self
+    }
+
+    /// Name the worker threads with the given prefix followed by the
+    /// worker's index, e.g. `my-pool-0`. The timer thread is named with the
+    /// prefix followed by `timer`, and blocking threads with the prefix
+    /// followed by `blocking`.
+    pub fn name_prefix<S: Into<String>>(&mut self, name_prefix: S) -> &mut Self {
+        self.name_prefix = Some(name_prefix.into());
+        self
+    }
+
+    /// Run `f` on each worker thread as it starts, before it polls any
+    /// task. `f` receives the worker's index.
+    pub fn after_start<F>(&mut self, f: F) -> &mut Self
+        where F: Fn(usize) + Send + Sync + 'static
+    {
+        self.hooks.after_start = Some(Arc::new(f));
+        self
+    }
+
+    /// Run `f` on each worker thread just before it stops, after it has
+    /// polled its last task. `f` receives the worker's index.
+    pub fn before_stop<F>(&mut self, f: F) -> &mut Self
+        where F: Fn(usize) + Send + Sync + 'static
+    {
+        self.hooks.before_stop = Some(Arc::new(f));
+        self
+    }
+
+    /// Record the pool's metrics into `metrics`, e.g. to be called back as
+    /// each task completes with `Metrics::on_task_completed`.
+    ///
+    /// Every pool created by the builder then records into the same
+    /// counters. By default each pool gets counters of its own.
+    pub fn metrics(&mut self, metrics: Metrics) -> &mut Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Create a pool with this configuration, starting its workers.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `io::ErrorKind::InvalidInput` if the pool size or the
+    /// maximum number of blocking threads is zero, and with the underlying
+    /// error if a worker thread can't be spawned.
+    pub fn create(&self) -> io::Result<ThreadPool> {
+        if self.pool_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a thread pool needs at least one worker",
+            ));
+        }
+        if self.max_blocking_threads == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a thread pool needs at least one blocking thread",
+            ));
+        }
+        let pool = ThreadPool {
+            state: Arc::new(PoolState {
+                queue: Mutex::new(RunQueue {
+                    queues: Default::default(),
+                    skipped: [0; 3],
+                    closed: false,
+                }),
+                run_ready: Condvar::new(),
+                handles: AtomicUsize::new(1),
+                timer: Timer::new(),
+                timer_waker: Mutex::new(None),
+                closed: AtomicBool::new(false),
+                shutdown: AtomicBool::new(false),
+                tasks: AtomicUsize::new(0),
+                drained: AtomicWaker::new(),
+                blocking: Mutex::new(Blocking { queue: VecDeque::new(), threads: 0, idle: 0 }),
+                blocking_ready: Condvar::new(),
+                max_blocking_threads: self.max_blocking_threads,
+                name_prefix: self.name_prefix.clone(),
+                size: self.pool_size,
+                spawner: Mutex::new(None),
+                metrics: self.metrics.clone().unwrap_or_default(),
+                #[cfg(feature = "debug")]
+                registry: Mutex::new(BTreeMap::new()),
+                #[cfg(feature = "debug")]
+                next_id: AtomicUsize::new(0),
+            }),
+            counted: true,
+        };
+        *pool.state.spawner.lock().unwrap() = Some(StrongSpawn::new(ThreadPool::uncounted(pool.state.clone())));
+        let mut timer_thread = thread::Builder::new();
+        if let Some(name_prefix) = &self.name_prefix {
+            timer_thread = timer_thread.name(format!("{}timer", name_prefix));
+        }
+        let state = pool.state.clone();
+        timer_thread.spawn(move || state.drive_timer())?;
+
+        for index in 0..self.pool_size {
+            let mut thread = thread::Builder::new();
+            if let Some(name_prefix) = &self.name_prefix {
+                thread = thread.name(format!("{}{}", name_prefix, index));
+            }
+            if let Some(stack_size) = self.stack_size {
+                thread = thread.stack_size(stack_size);
+            }
+            // Workers don't count as handles, or dropping the last handle
+            // couldn't stop them.
+            let state = pool.state.clone();
+            let hooks = self.hooks.clone();
+            thread.spawn(move || {
+                let handle = ThreadPool::uncounted(state.clone());
+                state.work(index, &handle, &hooks)
+            })?;
+        }
+        Ok(pool)
+    }
+}
+
+impl Default for ThreadPoolBuilder {
+    fn default() -> ThreadPoolBuilder {
+        ThreadPoolBuilder::new()
+    }
+}
+
+/// The number of CPUs available to run workers on, or 1 if it can't be
+/// determined.
+fn available_parallelism() -> usize {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const SC_NPROCESSORS_ONLN: ::std::os::raw::c_int = 84;
+    #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+    const SC_NPROCESSORS_ONLN: ::std::os::raw::c_int = 58;
+
+    #[cfg(any(target_os = "linux", target_os = "android",
+              target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+    {
+        extern "C" {
+            fn sysconf(name: ::std::os::raw::c_int) -> ::std::os::raw::c_long;
+        }
+        let n = unsafe { sysconf(SC_NPROCESSORS_ONLN) };
+        if n > 0 { n as usize } else { 1 }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android",
+                  target_os = "macos", target_os = "ios", target_os = "freebsd")))]
+    {
+        1
+    }
+}
@@ -0,0 +1,41 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::Future;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A task which is polled with its own spawner, rather than the one its
+/// executor polls it with.
+pub(super) struct WithSpawner<Fut, Sp> {
+    future: Fut,
+    spawner: Sp,
+}
+
+impl<Fut: Unpin, Sp> Unpin for WithSpawner<Fut, Sp> {}
+
+impl<Fut, Sp> WithSpawner<Fut, Sp>
+    where Fut: Future<Sp, Output = ()> + Unpin,
+          Sp: Spawn
+{
+    pub(super) fn new(future: Fut, spawner: Sp) -> WithSpawner<Fut, Sp> {
+        WithSpawner { future, spawner }
+    }
+
+    /// Take the task's future back, dropping its spawner.
+    pub(super) fn into_future(self) -> Fut {
+        self.future
+    }
+}
+
+impl<Fut, Sp> Future for WithSpawner<Fut, Sp>
+    where Fut: Future<Sp, Output = ()> + Unpin,
+          Sp: Spawn
+{
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<()> {
+        let this = &mut *self;
+        let mut cx = cx.with_spawner(&mut this.spawner);
+        PinMut::new(&mut this.future).poll(&mut cx)
+    }
+}
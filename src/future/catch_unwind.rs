@@ -0,0 +1,60 @@
+use core::any::Any;
+use core::mem::PinMut;
+use std::panic::{catch_unwind, UnwindSafe, AssertUnwindSafe};
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `catch_unwind` combinator.
+///
+/// This is created by the `FutureExt::catch_unwind` method.
+#[derive(Debug)]
+pub struct CatchUnwind<Fut> {
+    future: Option<Fut>,
+}
+
+impl<Fut> CatchUnwind<Fut> {
+    pub(super) fn new(future: Fut) -> CatchUnwind<Fut> {
+        CatchUnwind { future: Some(future) }
+    }
+
+    #[cfg_attr(feature = "cargo-clippy", allow(needless_lifetimes))]
+    fn future<'a>(self: PinMut<'a, Self>) -> PinMut<'a, Option<Fut>> {
+        unsafe { PinMut::map_unchecked(self, |x| &mut x.future) }
+    }
+}
+
+impl<S, Fut> Future<S> for CatchUnwind<Fut>
+    where S: Spawn + ?Sized,
+          Fut: Future<S> + UnwindSafe
+{
+    type Output = Result<Fut::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        let res = {
+            let future = unsafe {
+                match PinMut::get_mut_unchecked(self.reborrow().future()) {
+                    Some(future) => PinMut::new_unchecked(future),
+                    None => panic!("CatchUnwind polled after completion"),
+                }
+            };
+            catch_unwind(AssertUnwindSafe(|| future.poll(cx)))
+        };
+
+        let output = match res {
+            Ok(Poll::Pending) => return Poll::Pending,
+            Ok(Poll::Ready(output)) => Ok(output),
+            Err(payload) => Err(payload),
+        };
+        // Whether the future finished or panicked, it must never be polled
+        // again, so drop it in place now.
+        PinMut::set(self.future(), None);
+        Poll::Ready(output)
+    }
+}
+
+impl<Fut> FusedFuture for CatchUnwind<Fut> {
+    fn is_terminated(&self) -> bool {
+        self.future.is_none()
+    }
+}
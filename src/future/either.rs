@@ -0,0 +1,44 @@
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Combines two different futures yielding the same output type into a
+/// single type.
+///
+/// This is useful when a function may return one of several futures
+/// depending on a runtime condition, and is also what `select` uses to
+/// report which of its inputs completed first.
+#[derive(Debug, Clone)]
+pub enum Either<A, B> {
+    /// First branch of the type
+    Left(A),
+    /// Second branch of the type
+    Right(B),
+}
+
+impl<S, A, B> Future<S> for Either<A, B>
+    where S: Spawn + ?Sized,
+          A: Future<S>,
+          B: Future<S, Output = A::Output>
+{
+    type Output = A::Output;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<A::Output> {
+        unsafe {
+            match PinMut::get_mut_unchecked(self) {
+                Either::Left(a) => PinMut::new_unchecked(a).poll(cx),
+                Either::Right(b) => PinMut::new_unchecked(b).poll(cx),
+            }
+        }
+    }
+}
+
+impl<A: FusedFuture, B: FusedFuture> FusedFuture for Either<A, B> {
+    fn is_terminated(&self) -> bool {
+        match self {
+            Either::Left(a) => a.is_terminated(),
+            Either::Right(b) => b.is_terminated(),
+        }
+    }
+}
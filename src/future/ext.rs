@@ -0,0 +1,267 @@
+#[cfg(feature = "std")]
+use std::panic::UnwindSafe;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use core::time::Duration;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use future::{Future, Either, Timeout, IntoStream, FlattenStream, Race, race};
+use stream::Stream;
+#[cfg(feature = "alloc")]
+use future::{FutureObj, LocalFutureObj};
+#[cfg(feature = "std")]
+use future::{CatchUnwind, SpawnRemote, WithCancellation};
+#[cfg(feature = "std")]
+use lock::CancellationToken;
+use task::{Context, Poll, noop_local_waker, noop_waker};
+use spawn::{Spawn, NoSpawn, TimerSpawn};
+#[cfg(feature = "std")]
+use diagnostics::{Instrumented, SlowPoll};
+#[cfg(feature = "test-util")]
+use test::PollGuard;
+
+/// An extension trait for `Future`s that provides a variety of convenient
+/// adapters.
+pub trait FutureExt<S: Spawn + ?Sized = dyn Spawn>: Future<S> {
+    /// A convenience for calling `Future::poll` on `Unpin` future types.
+    ///
+    /// This saves pinning a future stored in a plain field by hand:
+    ///
+    /// ```
+    /// #![feature(pin, arbitrary_self_types, futures_api)]
+    /// # #[macro_use] extern crate specialized_futures;
+    /// use std::mem::PinMut;
+    /// use specialized_futures::{Future, FutureExt, Context, Spawn};
+    /// use specialized_futures::future::Ready;
+    /// use specialized_futures::task::Poll;
+    ///
+    /// struct Sum3 {
+    ///     a: Ready<i32>,
+    ///     b: Ready<i32>,
+    ///     c: Ready<i32>,
+    /// }
+    ///
+    /// impl Future for Sum3 {
+    ///     type Output = i32;
+    ///
+    ///     fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<i32> {
+    ///         let a = ready!(self.a.poll_unpin(cx));
+    ///         let b = ready!(self.b.poll_unpin(cx));
+    ///         let c = ready!(self.c.poll_unpin(cx));
+    ///         Poll::Ready(a + b + c)
+    ///     }
+    /// }
+    /// # fn main() {}
+    /// ```
+    fn poll_unpin(&mut self, cx: &mut Context<S>) -> Poll<Self::Output>
+        where Self: Unpin + Sized
+    {
+        PinMut::new(self).poll(cx)
+    }
+
+    /// Wrap this future in an `Either` future, making it the left-hand variant
+    /// of that `Either`.
+    ///
+    /// This can be used in combination with the `right_future` method to write
+    /// `if` statements that evaluate to different futures in different
+    /// branches.
+    fn left_future<B>(self) -> Either<Self, B>
+        where B: Future<S, Output = Self::Output>,
+              Self: Sized
+    {
+        Either::Left(self)
+    }
+
+    /// Wrap this future in an `Either` future, making it the right-hand variant
+    /// of that `Either`.
+    ///
+    /// This can be used in combination with the `left_future` method to write
+    /// `if` statements that evaluate to different futures in different
+    /// branches.
+    fn right_future<A>(self) -> Either<A, Self>
+        where A: Future<S, Output = Self::Output>,
+              Self: Sized
+    {
+        Either::Right(self)
+    }
+
+    /// Box this future into a `FutureObj`, erasing its type.
+    ///
+    /// This always allocates. A future which is already a `Box` or
+    /// `PinBox` should be converted with `FutureObj::from` instead, which
+    /// takes over the existing allocation, and an existing obj needs no
+    /// conversion at all. Boxing an obj again also costs an extra indirect
+    /// call on every poll.
+    #[cfg(feature = "alloc")]
+    fn boxed<'a>(self) -> FutureObj<'a, Self::Output, S>
+        where Self: Sized + Send + 'a
+    {
+        FutureObj::new(Box::new(self))
+    }
+
+    /// Box this future into a `LocalFutureObj`, erasing its type.
+    ///
+    /// This is the same as `boxed`, but for futures which aren't `Send`.
+    #[cfg(feature = "alloc")]
+    fn boxed_local<'a>(self) -> LocalFutureObj<'a, Self::Output, S>
+        where Self: Sized + 'a
+    {
+        LocalFutureObj::new(Box::new(self))
+    }
+
+    /// Catches unwinding panics while polling the future.
+    ///
+    /// In general, panics within a future can propagate all the way out to
+    /// the task level, taking down the executor thread that polled it. This
+    /// combinator catches a panic raised by any call to the inner future's
+    /// `poll` and resolves to `Err` with the panic payload instead. Once a
+    /// panic has been caught the inner future is dropped, and polling the
+    /// combinator again panics.
+    ///
+    /// Note that this method requires the `UnwindSafe` bound from the standard
+    /// library. Futures which are not unwind safe may be wrapped in
+    /// `AssertUnwindSafe` at the caller's discretion.
+    #[cfg(feature = "std")]
+    fn catch_unwind(self) -> CatchUnwind<Self>
+        where Self: Sized + UnwindSafe
+    {
+        CatchUnwind::new(self)
+    }
+
+    /// Evaluates and consumes the future, returning the resulting output if
+    /// the future is ready after the first call to `poll`.
+    ///
+    /// The future is polled once with a `Context` whose waker does nothing
+    /// and whose spawner, `NoSpawn`, rejects every task. If it is not ready,
+    /// `None` is returned. The future is dropped before this method returns
+    /// in either case, so a pending future is cancelled.
+    fn now_or_never(self) -> Option<<Self as Future<dyn Spawn>>::Output>
+        where Self: Sized + Future<dyn Spawn>
+    {
+        let local_waker = noop_local_waker();
+        let waker = noop_waker();
+        let mut spawner = NoSpawn;
+        let mut cx = Context::new(&local_waker, &waker, &mut spawner as &mut dyn Spawn);
+
+        let future = self;
+        pin_mut!(future);
+        match Future::<dyn Spawn>::poll(future, &mut cx) {
+            Poll::Ready(x) => Some(x),
+            Poll::Pending => None,
+        }
+    }
+
+    /// Offloads this future to the spawner of the task that polls the
+    /// returned future, and waits for its output.
+    ///
+    /// On its first poll, the returned future spawns `self` through
+    /// `cx.spawner()` and then resolves once the spawned task has finished,
+    /// with that task's output. If the spawned future panics, the panic is
+    /// propagated to the task awaiting the returned future.
+    ///
+    /// If the spawner refuses the task (for example because the executor has
+    /// been shut down), the work is not lost: the returned future instead
+    /// polls it inline, within the awaiting task. If the spawner accepts the
+    /// task but the executor drops it before it completes, the returned
+    /// future panics, since the output will never arrive.
+    #[cfg(feature = "std")]
+    fn spawn_remote(self) -> SpawnRemote<<Self as Future<dyn Spawn>>::Output>
+        where Self: Sized + Future<dyn Spawn> + Send + 'static,
+              <Self as Future<dyn Spawn>>::Output: Send + 'static
+    {
+        SpawnRemote::new(self)
+    }
+
+    /// Limits the time this future may take to complete.
+    ///
+    /// The timer is requested from the executor through `cx.spawner()` on the
+    /// first poll, so this is only available when the spawner implements
+    /// `TimerSpawn`. The returned future resolves to `Err(TimedOut)` if
+    /// `duration` elapses before `self` completes, in which case `self` is
+    /// dropped along with the returned future.
+    fn timeout(self, duration: Duration) -> Timeout<Self, S>
+        where Self: Sized,
+              S: TimerSpawn
+    {
+        Timeout::new(self, duration)
+    }
+
+    /// Wait for either this future or `other` to complete, dropping the
+    /// one which loses.
+    ///
+    /// See the `race` function for details on poll order.
+    fn race<B>(self, other: B) -> Race<Self, B>
+        where B: Future<S, Output = Self::Output>,
+              Self: Sized
+    {
+        race(self, other)
+    }
+
+    /// Convert this future into a single-element stream.
+    ///
+    /// The returned stream yields the future's output and then terminates.
+    fn into_stream(self) -> IntoStream<Self>
+        where Self: Sized
+    {
+        IntoStream::new(self)
+    }
+
+    /// Flatten the execution of this future when its output is a stream.
+    ///
+    /// The returned stream waits for this future to complete, then yields
+    /// the items of the stream it produced.
+    fn flatten_stream(self) -> FlattenStream<Self, S>
+        where Self: Sized,
+              Self::Output: Stream<S>
+    {
+        FlattenStream::new(self)
+    }
+
+    /// Run this future until it completes or `token` is cancelled.
+    ///
+    /// The returned future resolves to `Some(output)` if this future
+    /// completes first, or to `None` if the token is cancelled first, in
+    /// which case this future is dropped without being polled again.
+    #[cfg(feature = "std")]
+    fn with_cancellation(self, token: CancellationToken) -> WithCancellation<Self>
+        where Self: Sized
+    {
+        WithCancellation::new(self, token)
+    }
+
+    /// Times every poll of this future, calling `callback` whenever a poll
+    /// takes longer than `threshold`.
+    ///
+    /// This is meant for finding futures which block their executor by
+    /// doing synchronous work in `poll`. The callback receives the duration
+    /// and index of the slow poll along with the future's type name, and
+    /// cumulative statistics are available through `Instrumented::stats`.
+    /// With a threshold of `None` polls are only counted, not timed.
+    #[cfg(feature = "std")]
+    fn instrument_polls<F>(self, threshold: Option<Duration>, callback: F) -> Instrumented<Self, F>
+        where F: FnMut(SlowPoll),
+              Self: Sized
+    {
+        Instrumented::new(self, threshold, callback)
+    }
+
+    /// Wraps the future in a diagnostic guard which panics as soon as the
+    /// future is misused.
+    ///
+    /// Polling the returned future after it has completed, or re-entrantly
+    /// from inside one of its own polls, panics immediately with the
+    /// wrapped future's type name and the number of polls performed, rather
+    /// than leaving the wrapped future to fail in some confusing way. It is
+    /// otherwise transparent, which makes it useful for checking that a
+    /// combinator never re-polls its completed children.
+    ///
+    /// This method is only available with the `test-util` feature.
+    #[cfg(feature = "test-util")]
+    fn guard_polls(self) -> PollGuard<Self>
+        where Self: Sized
+    {
+        PollGuard::new(self)
+    }
+}
+
+impl<S: Spawn + ?Sized, F: Future<S> + ?Sized> FutureExt<S> for F {}
@@ -0,0 +1,79 @@
+use core::fmt;
+use core::mem::PinMut;
+use future::Future;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+enum State<Fut, St> {
+    Future(Fut),
+    Stream(St),
+    Done,
+}
+
+/// Stream for the `flatten_stream` combinator.
+///
+/// This is created by the `FutureExt::flatten_stream` method.
+pub struct FlattenStream<Fut: Future<S>, S: Spawn + ?Sized> {
+    state: State<Fut, Fut::Output>,
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> FlattenStream<Fut, S> {
+    pub(super) fn new(future: Fut) -> FlattenStream<Fut, S> {
+        FlattenStream { state: State::Future(future) }
+    }
+}
+
+impl<Fut, S> fmt::Debug for FlattenStream<Fut, S>
+    where Fut: Future<S> + fmt::Debug,
+          Fut::Output: fmt::Debug,
+          S: Spawn + ?Sized
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.state {
+            State::Future(fut) => f.debug_tuple("FlattenStream::Future").field(fut).finish(),
+            State::Stream(st) => f.debug_tuple("FlattenStream::Stream").field(st).finish(),
+            State::Done => f.debug_tuple("FlattenStream::Done").finish(),
+        }
+    }
+}
+
+impl<Fut, S> Stream<S> for FlattenStream<Fut, S>
+    where Fut: Future<S>,
+          Fut::Output: Stream<S>,
+          S: Spawn + ?Sized
+{
+    type Item = <Fut::Output as Stream<S>>::Item;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Self::Item>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        loop {
+            let next = match &mut this.state {
+                State::Future(fut) => {
+                    let fut = unsafe { PinMut::new_unchecked(fut) };
+                    State::Stream(ready!(fut.poll(cx)))
+                }
+                State::Stream(st) => {
+                    let st = unsafe { PinMut::new_unchecked(st) };
+                    match ready!(st.poll_next(cx)) {
+                        Some(item) => return Poll::Ready(Some(item)),
+                        None => State::Done,
+                    }
+                }
+                State::Done => return Poll::Ready(None),
+            };
+            // The old state is dropped in place, so the stream ends up pinned
+            // where the future used to be.
+            this.state = next;
+        }
+    }
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> FusedStream for FlattenStream<Fut, S> {
+    fn is_terminated(&self) -> bool {
+        match self.state {
+            State::Done => true,
+            _ => false,
+        }
+    }
+}
@@ -1,5 +1,7 @@
-use std::mem::PinMut;
-use std::marker::Unpin;
+use core::mem::PinMut;
+use core::marker::Unpin;
+#[cfg(feature = "std")]
+use std::panic::AssertUnwindSafe;
 use task::{Context, Poll};
 use spawn::Spawn;
 
@@ -23,4 +25,35 @@ impl<'a, S: Spawn + ?Sized, F: ?Sized + Future<S>> Future<S> for PinMut<'a, F> {
     fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
         F::poll((*self).reborrow(), cx)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "std")]
+impl<S: Spawn + ?Sized, F: Future<S>> Future<S> for AssertUnwindSafe<F> {
+    type Output = F::Output;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        unsafe { PinMut::map_unchecked(self, |x| &mut x.0) }.poll(cx)
+    }
+}
+
+/// A `Future` which tracks whether or not it has completed.
+///
+/// Polling a future after it has returned `Poll::Ready` is a logic error for
+/// most futures; `FusedFuture` lets combinators check whether a future may
+/// still be polled.
+pub trait FusedFuture {
+    /// Returns `true` if the underlying future should no longer be polled.
+    fn is_terminated(&self) -> bool;
+}
+
+impl<'a, F: ?Sized + FusedFuture> FusedFuture for &'a mut F {
+    fn is_terminated(&self) -> bool {
+        F::is_terminated(&**self)
+    }
+}
+
+impl<'a, F: ?Sized + FusedFuture> FusedFuture for PinMut<'a, F> {
+    fn is_terminated(&self) -> bool {
+        F::is_terminated(&**self)
+    }
+}
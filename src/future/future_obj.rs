@@ -8,10 +8,16 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::fmt;
+use core::fmt;
 use future::Future;
-use std::marker::{PhantomData, Unpin};
-use std::mem::PinMut;
+use core::marker::{PhantomData, Unpin};
+use core::mem::PinMut;
+#[cfg(feature = "alloc")]
+use core::any::TypeId;
+#[cfg(feature = "alloc")]
+use core::mem;
+#[cfg(feature = "alloc")]
+use alloc::boxed::{Box, PinBox};
 use task::{Context, Poll};
 use spawn::Spawn;
 
@@ -57,6 +63,146 @@ impl<'a, T, S: Spawn + ?Sized> LocalFutureObj<'a, T, S> {
     }
 }
 
+#[cfg(feature = "alloc")]
+impl<'a, T, S: Spawn + ?Sized> LocalFutureObj<'a, T, S> {
+    /// Create a `LocalFutureObj` which can later be turned back into the
+    /// boxed future with `downcast`.
+    ///
+    /// The box is kept as it is, and the future's `TypeId` is recorded in a
+    /// small header allocated beside it, which the obj points to. Objs made
+    /// with `new` are unaffected.
+    pub fn new_downcastable<F>(future: Box<F>) -> LocalFutureObj<'a, T, S>
+        where F: Future<S, Output = T> + 'static
+    {
+        let tagged = Box::new(Tagged {
+            type_id: TypeId::of::<F>(),
+            drop_fn: drop_tagged_future::<F>,
+            future: Box::into_raw(future) as *mut (),
+        });
+        LocalFutureObj {
+            ptr: Box::into_raw(tagged) as *mut (),
+            poll_fn: poll_tagged::<F, T, S>,
+            drop_fn: drop_tagged,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Recover the boxed future this obj was made from.
+    ///
+    /// This only succeeds if the obj was made by `new_downcastable` from a
+    /// future of exactly the type `F`; otherwise the obj is returned intact.
+    /// The original box is handed back, so the future isn't moved, but it
+    /// may already have been polled and so must be `Unpin` to be released
+    /// from the obj's pinning.
+    #[cfg_attr(feature = "cargo-clippy", allow(cast_ptr_alignment))]
+    pub fn downcast<F>(self) -> Result<Box<F>, Self>
+        where F: Unpin + 'static
+    {
+        if self.drop_fn as usize != drop_tagged as usize {
+            return Err(self);
+        }
+        let ptr = self.ptr as *mut Tagged;
+        if unsafe { (*ptr).type_id } != TypeId::of::<F>() {
+            return Err(self);
+        }
+        mem::forget(self);
+        let tagged = unsafe { Box::from_raw(ptr) };
+        Ok(unsafe { Box::from_raw(tagged.future as *mut F) })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T: 'a, S: Spawn + ?Sized + 'a> LocalFutureObj<'a, T, S> {
+    /// Convert this obj into one whose output is mapped through `f`.
+    ///
+    /// `f` is called once, when the future completes. The future isn't
+    /// moved or re-boxed: the returned obj points at a shim holding this
+    /// obj's pointer, its poll and drop functions, and `f`, and its own
+    /// vtable calls through to the original one, applying `f` on `Ready`.
+    /// The shim is the only new allocation, and the future is still dropped
+    /// by its original drop function.
+    pub fn map_output<U, F>(self, f: F) -> LocalFutureObj<'a, U, S>
+        where F: FnOnce(T) -> U + 'a
+    {
+        let shim = Box::new(MapShim {
+            ptr: self.ptr,
+            poll_fn: self.poll_fn,
+            drop_fn: self.drop_fn,
+            f: Some(f),
+        });
+        // The shim now owns the future.
+        mem::forget(self);
+        LocalFutureObj {
+            ptr: Box::into_raw(shim) as *mut (),
+            poll_fn: poll_map::<T, U, S, F>,
+            drop_fn: drop_map::<T, S, F>,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// What an obj made by `LocalFutureObj::map_output` points to: the vtable
+/// of the obj it was made from, and the mapping closure.
+#[cfg(feature = "alloc")]
+struct MapShim<T, S: Spawn + ?Sized, F> {
+    ptr: *mut (),
+    poll_fn: unsafe fn(*mut (), &mut Context<S>) -> Poll<T>,
+    drop_fn: unsafe fn(*mut ()),
+    f: Option<F>,
+}
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "cargo-clippy", allow(cast_ptr_alignment))]
+unsafe fn poll_map<T, U, S, F>(ptr: *mut (), cx: &mut Context<S>) -> Poll<U>
+    where F: FnOnce(T) -> U,
+          S: Spawn + ?Sized
+{
+    let shim = &mut *(ptr as *mut MapShim<T, S, F>);
+    let output = ready!((shim.poll_fn)(shim.ptr, cx));
+    let f = shim.f.take().expect("map_output obj polled after completion");
+    Poll::Ready(f(output))
+}
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "cargo-clippy", allow(cast_ptr_alignment))]
+unsafe fn drop_map<T, S: Spawn + ?Sized, F>(ptr: *mut ()) {
+    let shim = Box::from_raw(ptr as *mut MapShim<T, S, F>);
+    (shim.drop_fn)(shim.ptr)
+}
+
+/// The header an obj made by `LocalFutureObj::new_downcastable` points to.
+#[cfg(feature = "alloc")]
+struct Tagged {
+    type_id: TypeId,
+    /// Drops the boxed future.
+    drop_fn: unsafe fn(*mut ()),
+    future: *mut (),
+}
+
+#[cfg(feature = "alloc")]
+#[cfg_attr(feature = "cargo-clippy", allow(cast_ptr_alignment))]
+unsafe fn poll_tagged<F, T, S>(ptr: *mut (), cx: &mut Context<S>) -> Poll<T>
+    where F: Future<S, Output = T>,
+          S: Spawn + ?Sized
+{
+    PinMut::new_unchecked(&mut *((*(ptr as *mut Tagged)).future as *mut F)).poll(cx)
+}
+
+// Every downcastable obj shares this one, non-generic drop function, which
+// is how `downcast` tells them apart from other objs.
+#[cfg(feature = "alloc")]
+#[inline(never)]
+#[cfg_attr(feature = "cargo-clippy", allow(cast_ptr_alignment))]
+unsafe fn drop_tagged(ptr: *mut ()) {
+    let tagged = Box::from_raw(ptr as *mut Tagged);
+    (tagged.drop_fn)(tagged.future)
+}
+
+#[cfg(feature = "alloc")]
+unsafe fn drop_tagged_future<F>(ptr: *mut ()) {
+    drop(Box::from_raw(ptr as *mut F))
+}
+
 impl<'a, T, S: Spawn + ?Sized> fmt::Debug for LocalFutureObj<'a, T, S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("LocalFutureObj")
@@ -82,6 +228,36 @@ impl<'a, T, S: Spawn + ?Sized> Future<S> for LocalFutureObj<'a, T, S> {
     }
 }
 
+// An obj built for `dyn Spawn` only needs a `Context<dyn Spawn>`, which can
+// be made from the context of any sized spawner. This lets it be polled by
+// executors specialized to a concrete spawner, and stored in their queues
+// without re-boxing the future, e.g. as `LocalFutureObj::new(&mut obj)`.
+impl<'a, 'b, T, S: Spawn + 'b> Future<S> for LocalFutureObj<'a, T, dyn Spawn + 'b> {
+    type Output = T;
+
+    #[inline]
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<T> {
+        let (local_waker, waker) = (cx.local_waker(), cx.waker());
+        let mut cx = Context::new(local_waker, waker, cx.spawner() as &mut (dyn Spawn + 'b));
+        <Self as Future<dyn Spawn + 'b>>::poll(self, &mut cx)
+    }
+}
+
+impl<'a, 'b: 'a, T: 'a> LocalFutureObj<'a, T, dyn Spawn + 'b> {
+    /// Convert an obj built for `dyn Spawn` into one specialized to the
+    /// spawner type `S`.
+    ///
+    /// Each poll of the returned obj hands the inner future a
+    /// `Context<dyn Spawn>` made from the `Context<S>` it is given. Only the
+    /// obj itself is moved into a new allocation; the future it points to
+    /// stays where it is. Without the `alloc` feature, the same conversion is
+    /// available by borrowing, as `LocalFutureObj::new(&mut obj)`.
+    #[cfg(feature = "alloc")]
+    pub fn specialize<S: Spawn + 'b>(self) -> LocalFutureObj<'a, T, S> {
+        LocalFutureObj::new(Box::new(self))
+    }
+}
+
 impl<'a, T, S: Spawn + ?Sized> Drop for LocalFutureObj<'a, T, S> {
     fn drop(&mut self) {
         unsafe {
@@ -101,10 +277,27 @@ impl<'a, T, S: Spawn + ?Sized> Drop for LocalFutureObj<'a, T, S> {
 ///   this feature is used are currently not object safe due to current compiler
 ///   limitations. (See tracking issue for arbitray self types for more
 ///   information #44874)
+///
+/// A `FutureObj` is `Send` and `Sync` whatever its spawner and output
+/// types are. The spawner is only borrowed while polling, and the output is
+/// made on the polling thread and returned to it, so neither ever crosses
+/// threads inside the obj; only the future does, and it must be `Send`.
 pub struct FutureObj<'a, T, S: Spawn + ?Sized>(LocalFutureObj<'a, T, S>);
 
 impl<'a, T, S: Spawn + ?Sized> Unpin for FutureObj<'a, T, S> {}
-unsafe impl<'a, T, S: Spawn> Send for FutureObj<'a, T, S> {}
+
+// SAFETY: `FutureObj::new` requires the future behind the obj to be `Send`,
+// and the obj stores nothing else. The spawner is only borrowed for the
+// duration of `poll` and is never stored, so this holds for unsized
+// spawners too, which is what lets the `FutureObj<'static, (), dyn Spawn>`
+// taken by `Spawn::spawn_obj` move between worker threads. `T` is only
+// produced by `poll`, on whichever thread polls, and handed straight back
+// to that caller, so it needn't be `Send` either.
+unsafe impl<'a, T, S: Spawn + ?Sized> Send for FutureObj<'a, T, S> {}
+// SAFETY: every method touching the future takes the obj by value or by
+// `PinMut`, and `Debug` prints no fields, so a `&FutureObj` gives no access
+// to the future at all and sharing one between threads is harmless.
+unsafe impl<'a, T, S: Spawn + ?Sized> Sync for FutureObj<'a, T, S> {}
 
 impl<'a, T, S: Spawn + ?Sized> FutureObj<'a, T, S> {
     /// Create a `FutureObj` from a custom trait object representation.
@@ -114,6 +307,19 @@ impl<'a, T, S: Spawn + ?Sized> FutureObj<'a, T, S> {
     }
 }
 
+#[cfg(feature = "alloc")]
+impl<'a, T: 'a, S: Spawn + ?Sized + 'a> FutureObj<'a, T, S> {
+    /// Convert this obj into one whose output is mapped through `f`.
+    ///
+    /// See `LocalFutureObj::map_output` for details.
+    pub fn map_output<U, F>(self, f: F) -> FutureObj<'a, U, S>
+        where F: FnOnce(T) -> U + Send + 'a
+    {
+        // The future is `Send`, and so is the closure.
+        unsafe { self.0.map_output(f).into_future_obj() }
+    }
+}
+
 impl<'a, T, S: Spawn + ?Sized> fmt::Debug for FutureObj<'a, T, S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("FutureObj")
@@ -131,6 +337,27 @@ impl<'a, T, S: Spawn + ?Sized> Future<S> for FutureObj<'a, T, S> {
     }
 }
 
+impl<'a, 'b, T, S: Spawn + 'b> Future<S> for FutureObj<'a, T, dyn Spawn + 'b> {
+    type Output = T;
+
+    #[inline]
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<T> {
+        let pinned_field = unsafe { PinMut::map_unchecked(self, |x| &mut x.0) };
+        Future::<S>::poll(pinned_field, cx)
+    }
+}
+
+impl<'a, 'b: 'a, T: 'a> FutureObj<'a, T, dyn Spawn + 'b> {
+    /// Convert an obj built for `dyn Spawn` into one specialized to the
+    /// spawner type `S`.
+    ///
+    /// See `LocalFutureObj::specialize` for details.
+    #[cfg(feature = "alloc")]
+    pub fn specialize<S: Spawn + 'b>(self) -> FutureObj<'a, T, S> {
+        FutureObj(self.0.specialize())
+    }
+}
+
 /// A custom implementation of a future trait object for `FutureObj`, providing
 /// a hand-rolled vtable.
 ///
@@ -176,4 +403,80 @@ unsafe impl<'a, T, F, S: Spawn + ?Sized> UnsafeFutureObj<'a, T, S> for &'a mut F
     }
 
     unsafe fn drop(_ptr: *mut ()) {}
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<'a, T, F, S: Spawn + ?Sized> UnsafeFutureObj<'a, T, S> for Box<F>
+    where F: Future<S, Output = T> + 'a
+{
+    fn into_raw(self) -> *mut () {
+        Box::into_raw(self) as *mut ()
+    }
+
+    unsafe fn poll(ptr: *mut (), cx: &mut Context<S>) -> Poll<T> {
+        PinMut::new_unchecked(&mut *(ptr as *mut F)).poll(cx)
+    }
+
+    unsafe fn drop(ptr: *mut ()) {
+        drop(Box::from_raw(ptr as *mut F))
+    }
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<'a, T, F, S: Spawn + ?Sized> UnsafeFutureObj<'a, T, S> for PinBox<F>
+    where F: Future<S, Output = T> + 'a
+{
+    fn into_raw(self) -> *mut () {
+        PinBox::into_raw(self) as *mut ()
+    }
+
+    unsafe fn poll(ptr: *mut (), cx: &mut Context<S>) -> Poll<T> {
+        PinMut::new_unchecked(&mut *(ptr as *mut F)).poll(cx)
+    }
+
+    unsafe fn drop(ptr: *mut ()) {
+        drop(PinBox::from_raw(ptr as *mut F))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T, F, S: Spawn + ?Sized> From<Box<F>> for LocalFutureObj<'a, T, S>
+    where F: Future<S, Output = T> + 'a
+{
+    /// Converts a boxed future into an obj which takes over its allocation.
+    fn from(boxed: Box<F>) -> LocalFutureObj<'a, T, S> {
+        LocalFutureObj::new(boxed)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T, F, S: Spawn + ?Sized> From<PinBox<F>> for LocalFutureObj<'a, T, S>
+    where F: Future<S, Output = T> + 'a
+{
+    /// Converts a pinned, boxed future into an obj which takes over its
+    /// allocation.
+    fn from(boxed: PinBox<F>) -> LocalFutureObj<'a, T, S> {
+        LocalFutureObj::new(boxed)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T, F, S: Spawn + ?Sized> From<Box<F>> for FutureObj<'a, T, S>
+    where F: Future<S, Output = T> + Send + 'a
+{
+    /// Converts a boxed future into an obj which takes over its allocation.
+    fn from(boxed: Box<F>) -> FutureObj<'a, T, S> {
+        FutureObj::new(boxed)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, T, F, S: Spawn + ?Sized> From<PinBox<F>> for FutureObj<'a, T, S>
+    where F: Future<S, Output = T> + Send + 'a
+{
+    /// Converts a pinned, boxed future into an obj which takes over its
+    /// allocation.
+    fn from(boxed: PinBox<F>) -> FutureObj<'a, T, S> {
+        FutureObj::new(boxed)
+    }
+}
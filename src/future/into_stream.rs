@@ -0,0 +1,37 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::Future;
+use stream::{Stream, FusedStream, Once, once};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `into_stream` combinator.
+///
+/// This is created by the `FutureExt::into_stream` method.
+#[derive(Debug)]
+pub struct IntoStream<Fut> {
+    inner: Once<Fut>,
+}
+
+impl<Fut: Unpin> Unpin for IntoStream<Fut> {}
+
+impl<Fut> IntoStream<Fut> {
+    pub(super) fn new(future: Fut) -> IntoStream<Fut> {
+        IntoStream { inner: once(future) }
+    }
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> Stream<S> for IntoStream<Fut> {
+    type Item = Fut::Output;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Fut::Output>> {
+        let inner = unsafe { PinMut::map_unchecked(self, |x| &mut x.inner) };
+        inner.poll_next(cx)
+    }
+}
+
+impl<Fut> FusedStream for IntoStream<Fut> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_terminated()
+    }
+}
@@ -0,0 +1,99 @@
+#![allow(non_snake_case)]
+
+use core::fmt;
+use core::mem::PinMut;
+use future::{Future, FusedFuture, MaybeDone, maybe_done};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+macro_rules! generate {
+    ($(
+        $(#[$doc:meta])*
+        ($Join:ident, $join:ident, <$($Fut:ident),*>),
+    )*) => ($(
+        $(#[$doc])*
+        pub struct $Join<$($Fut: Future<S>,)* S: Spawn + ?Sized = dyn Spawn> {
+            $($Fut: MaybeDone<$Fut, S>,)*
+        }
+
+        impl<$($Fut,)* S> fmt::Debug for $Join<$($Fut,)* S>
+            where $($Fut: Future<S> + fmt::Debug, $Fut::Output: fmt::Debug,)*
+                  S: Spawn + ?Sized
+        {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($Join))
+                    $(.field(stringify!($Fut), &self.$Fut))*
+                    .finish()
+            }
+        }
+
+        impl<$($Fut: Future<S>,)* S: Spawn + ?Sized> $Join<$($Fut,)* S> {
+            #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+            fn new($($Fut: $Fut),*) -> $Join<$($Fut,)* S> {
+                $Join {
+                    $($Fut: maybe_done($Fut)),*
+                }
+            }
+        }
+
+        impl<$($Fut: Future<S>,)* S: Spawn + ?Sized> Future<S> for $Join<$($Fut,)* S> {
+            type Output = ($($Fut::Output),*);
+
+            fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+                let this = unsafe { PinMut::get_mut_unchecked(self) };
+                let mut all_done = true;
+                $(
+                    all_done &= unsafe { PinMut::new_unchecked(&mut this.$Fut) }.poll(cx).is_ready();
+                )*
+
+                if all_done {
+                    Poll::Ready(($(
+                        unsafe { PinMut::new_unchecked(&mut this.$Fut) }
+                            .take_output().unwrap()
+                    ),*))
+                } else {
+                    Poll::Pending
+                }
+            }
+        }
+
+        impl<$($Fut: Future<S>,)* S: Spawn + ?Sized> FusedFuture for $Join<$($Fut,)* S> {
+            fn is_terminated(&self) -> bool {
+                // The outputs are only ever taken all at once, on completion.
+                true $(&& match self.$Fut { MaybeDone::Gone => true, _ => false })*
+            }
+        }
+
+        $(#[$doc])*
+        #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+        pub fn $join<$($Fut: Future<S>,)* S: Spawn + ?Sized>($($Fut: $Fut),*) -> $Join<$($Fut,)* S> {
+            $Join::new($($Fut),*)
+        }
+    )*)
+}
+
+generate! {
+    /// Joins the result of two futures, waiting for them both to complete.
+    ///
+    /// The returned future drives both futures concurrently and resolves to
+    /// a tuple of their outputs.
+    (Join, join, <Fut1, Fut2>),
+
+    /// Same as `join`, but with more futures.
+    (Join3, join3, <Fut1, Fut2, Fut3>),
+
+    /// Same as `join`, but with more futures.
+    (Join4, join4, <Fut1, Fut2, Fut3, Fut4>),
+
+    /// Same as `join`, but with more futures.
+    (Join5, join5, <Fut1, Fut2, Fut3, Fut4, Fut5>),
+
+    /// Same as `join`, but with more futures.
+    (Join6, join6, <Fut1, Fut2, Fut3, Fut4, Fut5, Fut6>),
+
+    /// Same as `join`, but with more futures.
+    (Join7, join7, <Fut1, Fut2, Fut3, Fut4, Fut5, Fut6, Fut7>),
+
+    /// Same as `join`, but with more futures.
+    (Join8, join8, <Fut1, Fut2, Fut3, Fut4, Fut5, Fut6, Fut7, Fut8>),
+}
@@ -0,0 +1,54 @@
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A future which, when polled, invokes a closure and yields its result.
+///
+/// Created by the `lazy` function.
+pub struct Lazy<F> {
+    f: Option<F>,
+}
+
+impl<F> Unpin for Lazy<F> {}
+
+impl<F> fmt::Debug for Lazy<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Lazy")
+            .field("completed", &self.f.is_none())
+            .finish()
+    }
+}
+
+/// Creates a new future that allows delayed execution of a closure.
+///
+/// The provided closure is only run once the future is polled, and receives
+/// the `Context<S>` of that poll, so it may interact with a concrete spawner
+/// (for instance to spawn subtasks). The closure runs exactly once; polling
+/// the future again after it has completed panics.
+pub fn lazy<S, F, R>(f: F) -> Lazy<F>
+    where S: Spawn + ?Sized,
+          F: FnOnce(&mut Context<S>) -> R
+{
+    Lazy { f: Some(f) }
+}
+
+impl<F> FusedFuture for Lazy<F> {
+    fn is_terminated(&self) -> bool {
+        self.f.is_none()
+    }
+}
+
+impl<S, F, R> Future<S> for Lazy<F>
+    where S: Spawn + ?Sized,
+          F: FnOnce(&mut Context<S>) -> R
+{
+    type Output = R;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<R> {
+        let f = self.f.take().expect("Lazy polled after completion");
+        Poll::Ready(f(cx))
+    }
+}
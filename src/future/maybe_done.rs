@@ -0,0 +1,147 @@
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::{self, PinMut};
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A future that may have completed.
+///
+/// This is created by the `maybe_done` function, and is the building block
+/// for join-style combinators: each child future is kept in a `MaybeDone`
+/// slot which is polled until it holds the output, which can then be taken
+/// out once every child has finished.
+///
+/// # Examples
+///
+/// A hand-written join of two futures:
+///
+/// ```
+/// #![feature(pin, arbitrary_self_types, futures_api)]
+/// # extern crate specialized_futures;
+/// use std::mem::PinMut;
+/// use std::task::Poll;
+/// use specialized_futures::{Future, Context, Spawn};
+/// use specialized_futures::future::{MaybeDone, maybe_done};
+///
+/// struct Join<A: Future, B: Future> {
+///     a: MaybeDone<A>,
+///     b: MaybeDone<B>,
+/// }
+///
+/// impl<A: Future, B: Future> Future for Join<A, B> {
+///     type Output = (A::Output, B::Output);
+///
+///     fn poll(self: PinMut<Self>, cx: &mut Context) -> Poll<Self::Output> {
+///         unsafe {
+///             let this = PinMut::get_mut_unchecked(self);
+///             let mut a = PinMut::new_unchecked(&mut this.a);
+///             let mut b = PinMut::new_unchecked(&mut this.b);
+///             let a_done = a.reborrow().poll(cx).is_ready();
+///             let b_done = b.reborrow().poll(cx).is_ready();
+///             if a_done && b_done {
+///                 Poll::Ready((a.take_output().unwrap(), b.take_output().unwrap()))
+///             } else {
+///                 Poll::Pending
+///             }
+///         }
+///     }
+/// }
+/// # fn main() { let _: MaybeDone<_> = maybe_done(specialized_futures::future::ready(1)); }
+/// ```
+pub enum MaybeDone<Fut: Future<S>, S: Spawn + ?Sized = dyn Spawn> {
+    /// A not-yet-completed future
+    Future(Fut),
+    /// The output of the completed future
+    Done(Fut::Output),
+    /// The empty variant after the result of a `MaybeDone` has been
+    /// taken using the `take_output` method.
+    Gone,
+}
+
+impl<Fut, S> fmt::Debug for MaybeDone<Fut, S>
+    where Fut: Future<S> + fmt::Debug,
+          Fut::Output: fmt::Debug,
+          S: Spawn + ?Sized
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MaybeDone::Future(fut) => f.debug_tuple("Future").field(fut).finish(),
+            MaybeDone::Done(output) => f.debug_tuple("Done").field(output).finish(),
+            MaybeDone::Gone => f.debug_tuple("Gone").finish(),
+        }
+    }
+}
+
+// Safe because we never generate `PinMut<Fut::Output>`
+impl<Fut: Future<S> + Unpin, S: Spawn + ?Sized> Unpin for MaybeDone<Fut, S> {}
+
+/// Wraps a future into a `MaybeDone`.
+pub fn maybe_done<Fut: Future<S>, S: Spawn + ?Sized>(future: Fut) -> MaybeDone<Fut, S> {
+    MaybeDone::Future(future)
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> MaybeDone<Fut, S> {
+    /// Returns an `Option` containing a mutable reference to the output of the
+    /// future. The output of this method will be `Some` if and only if the
+    /// inner future has been completed and `take_output` has not yet been
+    /// called.
+    #[inline]
+    #[cfg_attr(feature = "cargo-clippy", allow(needless_lifetimes))]
+    pub fn output_mut<'a>(self: PinMut<'a, Self>) -> Option<&'a mut Fut::Output> {
+        unsafe {
+            match PinMut::get_mut_unchecked(self) {
+                MaybeDone::Done(res) => Some(res),
+                _ => None,
+            }
+        }
+    }
+
+    /// Attempt to take the output of a `MaybeDone` without driving it
+    /// towards completion.
+    ///
+    /// Returns `None` if the future has not completed yet, or if its output
+    /// has already been taken.
+    #[inline]
+    pub fn take_output(self: PinMut<Self>) -> Option<Fut::Output> {
+        unsafe {
+            let this = PinMut::get_mut_unchecked(self);
+            match this {
+                MaybeDone::Done(_) => {},
+                MaybeDone::Future(_) | MaybeDone::Gone => return None,
+            }
+            match mem::replace(this, MaybeDone::Gone) {
+                MaybeDone::Done(output) => Some(output),
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> FusedFuture for MaybeDone<Fut, S> {
+    fn is_terminated(&self) -> bool {
+        match self {
+            MaybeDone::Future(_) => false,
+            MaybeDone::Done(_) | MaybeDone::Gone => true,
+        }
+    }
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> Future<S> for MaybeDone<Fut, S> {
+    type Output = ();
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<()> {
+        unsafe {
+            let this = PinMut::get_mut_unchecked(self);
+            let res = match this {
+                MaybeDone::Future(a) => ready!(PinMut::new_unchecked(a).poll(cx)),
+                MaybeDone::Done(_) => return Poll::Ready(()),
+                MaybeDone::Gone => panic!("MaybeDone polled after value taken"),
+            };
+            // The completed future is dropped in place here, which the
+            // pinning guarantees permit.
+            *this = MaybeDone::Done(res);
+            Poll::Ready(())
+        }
+    }
+}
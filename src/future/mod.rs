@@ -1,5 +1,69 @@
 mod future;
-pub use self::future::Future;
+pub use self::future::{Future, FusedFuture};
 
 mod future_obj;
-pub use self::future_obj::{FutureObj, LocalFutureObj, UnsafeFutureObj};
\ No newline at end of file
+pub use self::future_obj::{FutureObj, LocalFutureObj, UnsafeFutureObj};
+
+mod ext;
+pub use self::ext::FutureExt;
+
+mod poll_fn;
+pub use self::poll_fn::{poll_fn, PollFn};
+#[cfg(feature = "alloc")]
+pub use self::poll_fn::{from_fn_obj, from_fn_local_obj};
+
+mod ready;
+pub use self::ready::{ready, Ready};
+
+mod pending;
+pub use self::pending::{pending, Pending};
+
+mod lazy;
+pub use self::lazy::{lazy, Lazy};
+
+mod either;
+pub use self::either::Either;
+
+mod select;
+pub use self::select::{select, Select};
+
+mod select_rotation;
+#[doc(hidden)]
+pub use self::select_rotation::{__SelectRotation, __select_start};
+
+mod race;
+pub use self::race::{race, Race};
+
+mod maybe_done;
+pub use self::maybe_done::{maybe_done, MaybeDone};
+
+#[cfg(feature = "std")]
+mod catch_unwind;
+#[cfg(feature = "std")]
+pub use self::catch_unwind::CatchUnwind;
+
+mod yield_now;
+pub use self::yield_now::{yield_now, YieldNow};
+
+#[cfg(feature = "std")]
+mod spawn_remote;
+#[cfg(feature = "std")]
+pub use self::spawn_remote::SpawnRemote;
+
+#[cfg(feature = "std")]
+mod with_cancellation;
+#[cfg(feature = "std")]
+pub use self::with_cancellation::WithCancellation;
+
+mod timeout;
+pub use self::timeout::{Timeout, TimedOut};
+
+mod into_stream;
+pub use self::into_stream::IntoStream;
+
+mod flatten_stream;
+pub use self::flatten_stream::FlattenStream;
+
+mod join;
+pub use self::join::{join, join3, join4, join5, join6, join7, join8};
+pub use self::join::{Join, Join3, Join4, Join5, Join6, Join7, Join8};
@@ -0,0 +1,54 @@
+use core::fmt;
+use core::marker::{PhantomData, Unpin};
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A future which is never resolved.
+///
+/// Created by the `pending` function.
+pub struct Pending<T> {
+    _data: PhantomData<T>,
+}
+
+impl<T> Unpin for Pending<T> {}
+
+impl<T> fmt::Debug for Pending<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pending")
+            .finish()
+    }
+}
+
+impl<T> Clone for Pending<T> {
+    fn clone(&self) -> Self {
+        pending()
+    }
+}
+
+impl<T, S: Spawn + ?Sized> Future<S> for Pending<T> {
+    type Output = T;
+
+    #[inline]
+    fn poll(self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<T> {
+        Poll::Pending
+    }
+}
+
+impl<T> FusedFuture for Pending<T> {
+    fn is_terminated(&self) -> bool {
+        // It may always be polled again; it just never completes.
+        false
+    }
+}
+
+/// Creates a future which never resolves, representing a computation that
+/// never finishes.
+///
+/// The returned future always returns `Poll::Pending` and never stores the
+/// waker it is polled with, so the task polling it will never be woken on its
+/// behalf.
+pub fn pending<T>() -> Pending<T> {
+    Pending { _data: PhantomData }
+}
@@ -0,0 +1,75 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::Future;
+#[cfg(feature = "alloc")]
+use future::{FutureObj, LocalFutureObj};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A future which wraps a function returning `Poll`.
+///
+/// Created by the `poll_fn` function.
+pub struct PollFn<F> {
+    f: F,
+}
+
+impl<F> Unpin for PollFn<F> {}
+
+/// Creates a new future wrapping around a function returning `Poll`.
+///
+/// Polling the returned future delegates to the wrapped function. Because
+/// the closure receives a `Context<S>` for whatever spawner `S` the future is
+/// polled with, it can call methods specific to a concrete executor through
+/// `cx.spawner()`.
+pub fn poll_fn<T, S, F>(f: F) -> PollFn<F>
+    where S: Spawn + ?Sized,
+          F: FnMut(&mut Context<S>) -> Poll<T>
+{
+    PollFn { f }
+}
+
+/// Creates a `FutureObj` directly from a function returning `Poll`.
+///
+/// This is a shorthand for boxing a `poll_fn` future, for tiny tasks which
+/// don't warrant a future type of their own. The function is dropped exactly
+/// once, when the obj is. Without the `alloc` feature, a borrowed obj can be
+/// made instead with `LocalFutureObj::new(&mut poll_fn(f))`.
+#[cfg(feature = "alloc")]
+pub fn from_fn_obj<'a, T, S, F>(f: F) -> FutureObj<'a, T, S>
+    where S: Spawn + ?Sized,
+          F: FnMut(&mut Context<S>) -> Poll<T> + Send + 'a
+{
+    FutureObj::new(Box::new(poll_fn(f)))
+}
+
+/// Creates a `LocalFutureObj` directly from a function returning `Poll`.
+///
+/// This is the same as `from_fn_obj`, but for functions which aren't `Send`.
+#[cfg(feature = "alloc")]
+pub fn from_fn_local_obj<'a, T, S, F>(f: F) -> LocalFutureObj<'a, T, S>
+    where S: Spawn + ?Sized,
+          F: FnMut(&mut Context<S>) -> Poll<T> + 'a
+{
+    LocalFutureObj::new(Box::new(poll_fn(f)))
+}
+
+impl<F> fmt::Debug for PollFn<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PollFn")
+            .finish()
+    }
+}
+
+impl<T, S, F> Future<S> for PollFn<F>
+    where S: Spawn + ?Sized,
+          F: FnMut(&mut Context<S>) -> Poll<T>
+{
+    type Output = T;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<T> {
+        (&mut self.f)(cx)
+    }
+}
@@ -0,0 +1,75 @@
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `race` function.
+#[derive(Debug)]
+pub struct Race<A, B> {
+    a: Option<A>,
+    b: Option<B>,
+    left_first: bool,
+}
+
+/// Waits for the first of two futures with the same output type to
+/// complete, dropping the other.
+///
+/// Unlike `select`, neither future has to be `Unpin`, since the loser is
+/// never handed back: it is dropped as soon as the winner completes, so its
+/// cancellation side effects have happened by the time the race resolves.
+///
+/// The two futures take turns being polled first, starting with `a`, so
+/// that neither can starve the other when the race is polled in a loop. If
+/// both complete in the same poll, whichever was polled first wins.
+pub fn race<A, B>(a: A, b: B) -> Race<A, B> {
+    Race { a: Some(a), b: Some(b), left_first: true }
+}
+
+impl<S, A, B> Future<S> for Race<A, B>
+    where S: Spawn + ?Sized,
+          A: Future<S>,
+          B: Future<S, Output = A::Output>
+{
+    type Output = A::Output;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<A::Output> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        let left_first = this.left_first;
+        this.left_first = !left_first;
+        let output = {
+            let a = match &mut this.a {
+                Some(a) => unsafe { PinMut::new_unchecked(a) },
+                None => panic!("Race polled after completion"),
+            };
+            let b = unsafe { PinMut::new_unchecked(this.b.as_mut().unwrap()) };
+            if left_first {
+                poll_both(a, b, cx)
+            } else {
+                poll_both(b, a, cx)
+            }
+        };
+        if let Poll::Ready(_) = output {
+            // Drop the loser, and the winner along with it, in place.
+            this.a = None;
+            this.b = None;
+        }
+        output
+    }
+}
+
+fn poll_both<S, T, A, B>(a: PinMut<A>, b: PinMut<B>, cx: &mut Context<S>) -> Poll<T>
+    where S: Spawn + ?Sized,
+          A: Future<S, Output = T>,
+          B: Future<S, Output = T>
+{
+    match a.poll(cx) {
+        Poll::Ready(output) => Poll::Ready(output),
+        Poll::Pending => b.poll(cx),
+    }
+}
+
+impl<A, B> FusedFuture for Race<A, B> {
+    fn is_terminated(&self) -> bool {
+        self.a.is_none()
+    }
+}
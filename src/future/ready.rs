@@ -0,0 +1,48 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A future that is immediately ready with a value.
+///
+/// Created by the `ready` function.
+#[derive(Debug, Clone)]
+pub struct Ready<T>(Option<T>);
+
+impl<T> Unpin for Ready<T> {}
+
+impl<T> Ready<T> {
+    /// Unwraps the value from this immediately ready future.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the future has already been polled to completion, since
+    /// the value was moved out by that poll.
+    pub fn into_inner(self) -> T {
+        self.0.expect("Ready::into_inner called after the future completed")
+    }
+}
+
+impl<T, S: Spawn + ?Sized> Future<S> for Ready<T> {
+    type Output = T;
+
+    #[inline]
+    fn poll(mut self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<T> {
+        Poll::Ready(self.0.take().expect("Ready polled after completion"))
+    }
+}
+
+impl<T> FusedFuture for Ready<T> {
+    fn is_terminated(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
+/// Creates a future that is immediately ready with a value.
+///
+/// The returned future resolves on its first poll and panics if it is polled
+/// again afterwards.
+pub fn ready<T>(t: T) -> Ready<T> {
+    Ready(Some(t))
+}
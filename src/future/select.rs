@@ -0,0 +1,55 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::{Future, FutureExt, FusedFuture, Either};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `select` function.
+#[derive(Debug)]
+pub struct Select<A, B> {
+    inner: Option<(A, B)>,
+}
+
+impl<A: Unpin, B: Unpin> Unpin for Select<A, B> {}
+
+/// Waits for either one of two differently-typed futures to complete.
+///
+/// This function will return a new future which awaits for either one of both
+/// futures to complete. The returned future will finish with both the value
+/// resolved and the other, still unfinished, future.
+///
+/// If both futures are ready on the same poll, the first one wins.
+///
+/// Note that this function consumes the receiving futures and returns a
+/// wrapped version of them.
+pub fn select<A: Unpin, B: Unpin>(future1: A, future2: B) -> Select<A, B> {
+    Select { inner: Some((future1, future2)) }
+}
+
+impl<S, A, B> Future<S> for Select<A, B>
+    where S: Spawn + ?Sized,
+          A: Future<S> + Unpin,
+          B: Future<S> + Unpin
+{
+    type Output = Either<(A::Output, B), (B::Output, A)>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        let (mut a, mut b) = self.inner.take().expect("cannot poll Select twice");
+        match a.poll_unpin(cx) {
+            Poll::Ready(x) => Poll::Ready(Either::Left((x, b))),
+            Poll::Pending => match b.poll_unpin(cx) {
+                Poll::Ready(x) => Poll::Ready(Either::Right((x, a))),
+                Poll::Pending => {
+                    self.inner = Some((a, b));
+                    Poll::Pending
+                }
+            }
+        }
+    }
+}
+
+impl<A, B> FusedFuture for Select<A, B> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_none()
+    }
+}
@@ -0,0 +1,15 @@
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+// Every `select!` expands to a `static` rotation of its own, so which branch
+// a site polls first only depends on how many times that site has run, not
+// on what else in the process is selecting.
+#[doc(hidden)]
+pub type __SelectRotation = AtomicUsize;
+
+#[doc(hidden)]
+pub fn __select_start(rotation: &AtomicUsize, branches: usize) -> usize {
+    if branches == 0 {
+        return 0;
+    }
+    rotation.fetch_add(1, Ordering::Relaxed) % branches
+}
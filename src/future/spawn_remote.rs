@@ -0,0 +1,176 @@
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use future::{Future, FusedFuture, FutureObj};
+use task::{Context, Poll, Waker};
+use spawn::Spawn;
+
+/// The completion slot shared between a `SpawnRemote` and the task it
+/// spawned.
+struct Slot<T> {
+    result: Option<thread::Result<T>>,
+    /// Set if the task was dropped before it completed.
+    cancelled: bool,
+    waker: Option<Waker>,
+}
+
+/// The task actually handed to the spawner, which runs the work and stores
+/// its output (or its panic payload) in the shared slot.
+struct Remote<F: Future> {
+    future: F,
+    done: bool,
+    slot: Arc<Mutex<Slot<F::Output>>>,
+}
+
+impl<F: Future> Future for Remote<F> {
+    type Output = ();
+
+    fn poll(self: PinMut<Self>, cx: &mut Context) -> Poll<()> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        let result = {
+            let future = unsafe { PinMut::new_unchecked(&mut this.future) };
+            match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+                Ok(Poll::Pending) => return Poll::Pending,
+                Ok(Poll::Ready(output)) => Ok(output),
+                Err(payload) => Err(payload),
+            }
+        };
+
+        this.done = true;
+        let waker = {
+            let mut slot = this.slot.lock().unwrap();
+            slot.result = Some(result);
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Poll::Ready(())
+    }
+}
+
+impl<F: Future> Drop for Remote<F> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        // The executor dropped the task before it completed, so nothing
+        // else will wake the waiting future.
+        let waker = {
+            let mut slot = self.slot.lock().unwrap();
+            slot.cancelled = true;
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Future for the `spawn_remote` combinator.
+///
+/// This is created by the `FutureExt::spawn_remote` method.
+pub struct SpawnRemote<T> {
+    task: Option<FutureObj<'static, (), dyn Spawn>>,
+    spawned: bool,
+    done: bool,
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+impl<T> Unpin for SpawnRemote<T> {}
+
+impl<T> fmt::Debug for SpawnRemote<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SpawnRemote")
+            .field("spawned", &self.spawned)
+            .field("inline", &(self.spawned && self.task.is_some()))
+            .finish()
+    }
+}
+
+impl<T: Send + 'static> SpawnRemote<T> {
+    pub(super) fn new<F>(future: F) -> SpawnRemote<T>
+        where F: Future<Output = T> + Send + 'static
+    {
+        let slot = Arc::new(Mutex::new(Slot { result: None, cancelled: false, waker: None }));
+        let remote = Remote { future, done: false, slot: slot.clone() };
+        SpawnRemote {
+            task: Some(FutureObj::new(Box::new(remote))),
+            spawned: false,
+            done: false,
+            slot,
+        }
+    }
+}
+
+impl<T> SpawnRemote<T> {
+    fn poll_remote(&mut self, cx: &mut Context) -> Poll<T> {
+        if self.done {
+            panic!("SpawnRemote polled after completion");
+        }
+
+        if !self.spawned {
+            self.spawned = true;
+            let task = self.task.take().unwrap();
+            if let Err(err) = cx.spawner().spawn_obj(task) {
+                // The spawner refused the task, so keep it and drive it
+                // inline from this future instead.
+                self.task = Some(err.future);
+            }
+        }
+
+        if let Some(task) = &mut self.task {
+            ready!(poll!(*task, cx));
+        }
+        self.task = None;
+
+        let mut slot = self.slot.lock().unwrap();
+        match slot.result.take() {
+            Some(Ok(output)) => {
+                self.done = true;
+                Poll::Ready(output)
+            }
+            Some(Err(payload)) => {
+                self.done = true;
+                drop(slot);
+                panic::resume_unwind(payload)
+            }
+            None if slot.cancelled => {
+                self.done = true;
+                drop(slot);
+                panic!("the executor dropped the spawned task before it completed")
+            }
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<S: Spawn + 'static, T> Future<S> for SpawnRemote<T> {
+    type Output = T;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<T> {
+        let (local_waker, waker) = (cx.local_waker(), cx.waker());
+        let mut cx = Context::new(local_waker, waker, cx.spawner() as &mut dyn Spawn);
+        self.poll_remote(&mut cx)
+    }
+}
+
+impl<T> Future for SpawnRemote<T> {
+    type Output = T;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<T> {
+        self.poll_remote(cx)
+    }
+}
+
+impl<T> FusedFuture for SpawnRemote<T> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,89 @@
+#[cfg(feature = "std")]
+use std::error::Error;
+use core::fmt;
+use core::mem::PinMut;
+use core::time::Duration;
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::TimerSpawn;
+
+/// Error returned by `Timeout` when its deadline elapses before the wrapped
+/// future completes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimedOut {
+    _hidden: (),
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("future timed out")
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for TimedOut {}
+
+/// Future for the `timeout` combinator.
+///
+/// This is created by the `FutureExt::timeout` method.
+pub struct Timeout<Fut, S: TimerSpawn + ?Sized> {
+    future: Fut,
+    duration: Duration,
+    sleep: Option<S::Sleep>,
+    done: bool,
+}
+
+impl<Fut, S: TimerSpawn + ?Sized> Timeout<Fut, S> {
+    pub(super) fn new(future: Fut, duration: Duration) -> Timeout<Fut, S> {
+        Timeout { future, duration, sleep: None, done: false }
+    }
+}
+
+impl<Fut: fmt::Debug, S: TimerSpawn + ?Sized> fmt::Debug for Timeout<Fut, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Timeout")
+            .field("future", &self.future)
+            .field("duration", &self.duration)
+            .finish()
+    }
+}
+
+impl<Fut, S> Future<S> for Timeout<Fut, S>
+    where Fut: Future<S>,
+          S: TimerSpawn + ?Sized
+{
+    type Output = Result<Fut::Output, TimedOut>;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if this.done {
+            panic!("Timeout polled after completion");
+        }
+
+        let future = unsafe { PinMut::new_unchecked(&mut this.future) };
+        if let Poll::Ready(output) = future.poll(cx) {
+            this.done = true;
+            return Poll::Ready(Ok(output));
+        }
+
+        if this.sleep.is_none() {
+            this.sleep = Some(cx.spawner().sleep(this.duration));
+        }
+        let sleep = unsafe {
+            PinMut::new_unchecked(this.sleep.as_mut().unwrap())
+        };
+        match sleep.poll(cx) {
+            Poll::Ready(()) => {
+                this.done = true;
+                Poll::Ready(Err(TimedOut { _hidden: () }))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<Fut, S: TimerSpawn + ?Sized> FusedFuture for Timeout<Fut, S> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,58 @@
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use lock::CancellationToken;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `with_cancellation` combinator.
+///
+/// This is created by the `FutureExt::with_cancellation` method.
+#[derive(Debug)]
+pub struct WithCancellation<Fut> {
+    future: Option<Fut>,
+    token: CancellationToken,
+    waiter: Option<u64>,
+}
+
+impl<Fut> WithCancellation<Fut> {
+    pub(super) fn new(future: Fut, token: CancellationToken) -> WithCancellation<Fut> {
+        WithCancellation { future: Some(future), token, waiter: None }
+    }
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> Future<S> for WithCancellation<Fut> {
+    type Output = Option<Fut::Output>;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        let output = {
+            let future = match &mut this.future {
+                Some(future) => unsafe { PinMut::new_unchecked(future) },
+                None => panic!("WithCancellation polled after completion"),
+            };
+            if let Poll::Ready(()) = this.token.poll_cancelled(&mut this.waiter, cx) {
+                None
+            } else if let Poll::Ready(output) = future.poll(cx) {
+                Some(output)
+            } else {
+                return Poll::Pending;
+            }
+        };
+        // The future is dropped in place, as pinning permits.
+        this.future = None;
+        this.token.remove_waiter(&mut this.waiter);
+        Poll::Ready(output)
+    }
+}
+
+impl<Fut> FusedFuture for WithCancellation<Fut> {
+    fn is_terminated(&self) -> bool {
+        self.future.is_none()
+    }
+}
+
+impl<Fut> Drop for WithCancellation<Fut> {
+    fn drop(&mut self) {
+        self.token.remove_waiter(&mut self.waiter);
+    }
+}
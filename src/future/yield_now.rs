@@ -0,0 +1,46 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `yield_now` function.
+#[derive(Debug)]
+pub struct YieldNow {
+    yielded: bool,
+    done: bool,
+}
+
+impl Unpin for YieldNow {}
+
+/// Creates a future which yields control back to the executor once.
+///
+/// The first poll wakes the current task and returns `Poll::Pending`, so the
+/// task is immediately rescheduled behind any other ready tasks. The second
+/// poll resolves with `()`.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false, done: false }
+}
+
+impl<S: Spawn + ?Sized> Future<S> for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<()> {
+        if self.done {
+            panic!("YieldNow polled after completion");
+        }
+        if self.yielded {
+            self.done = true;
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.local_waker().wake();
+        Poll::Pending
+    }
+}
+
+impl FusedFuture for YieldNow {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,99 @@
+//! Auto-trait guarantees made by this crate, and helpers for relying on
+//! them at API boundaries.
+//!
+//! The properties below are promised to executor authors. Each positive
+//! property is asserted at compile time in this module, so a change which
+//! breaks one fails to build the crate:
+//!
+//! - `FutureObj<'a, T, S>` and `StreamObj<'a, T, S>` are `Send` and `Sync`
+//!   for every spawner `S`, including `dyn Spawn`.
+//! - `LocalFutureObj`, `LocalStreamObj` and `Unpin` futures wrapped in them
+//!   are `Unpin`, as are `FutureObj` and `StreamObj`.
+//! - `SpawnErrorKind` is `Send` and `Sync`.
+//!
+//! The negative properties can't be asserted from within the crate, so they
+//! are checked by the programs in `tests/compile-fail` instead:
+//! `LocalFutureObj` and `LocalStreamObj` are not `Send`, and neither is
+//! `Context<S>` for any spawner `S`. Combinators, `AssertSendFuture`
+//! included, are only `Unpin` when what they wrap is. `tests/guarantees.rs`
+//! checks the positive properties from outside the crate as well, along with
+//! `Unpin` being preserved by combinators.
+
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::{Future, FusedFuture, FutureObj, LocalFutureObj};
+use stream::{StreamObj, LocalStreamObj};
+use task::{Context, Poll};
+use spawn::{Spawn, SpawnErrorKind};
+
+/// A future which is statically known to be `Send`.
+///
+/// `AssertSendFuture::new` only accepts `Send` futures, so wrapping a future
+/// at an API boundary turns an accidental loss of `Send`, e.g. from holding
+/// an `Rc` across a yield point, into an error at the place where the future
+/// is built rather than somewhere downstream. It is otherwise transparent.
+pub struct AssertSendFuture<F> {
+    future: F,
+}
+
+impl<F: Unpin> Unpin for AssertSendFuture<F> {}
+
+impl<F: fmt::Debug> fmt::Debug for AssertSendFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("AssertSendFuture")
+            .field(&self.future)
+            .finish()
+    }
+}
+
+impl<F: Send> AssertSendFuture<F> {
+    /// Wrap a `Send` future.
+    pub fn new(future: F) -> AssertSendFuture<F> {
+        AssertSendFuture { future }
+    }
+
+    /// Consume the wrapper, returning the future.
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+impl<F: Future<S>, S: Spawn + ?Sized> Future<S> for AssertSendFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<F::Output> {
+        unsafe { PinMut::map_unchecked(self, |x| &mut x.future) }.poll(cx)
+    }
+}
+
+impl<F: FusedFuture> FusedFuture for AssertSendFuture<F> {
+    fn is_terminated(&self) -> bool {
+        self.future.is_terminated()
+    }
+}
+
+fn assert_send<T: ?Sized + Send>() {}
+fn assert_sync<T: ?Sized + Sync>() {}
+fn assert_unpin<T: ?Sized + Unpin>() {}
+
+// Never called; it only has to type-check.
+#[allow(dead_code)]
+fn guarantees<S: Spawn>() {
+    assert_send::<FutureObj<'static, (), dyn Spawn>>();
+    assert_sync::<FutureObj<'static, (), dyn Spawn>>();
+    assert_send::<FutureObj<'static, (), S>>();
+    assert_sync::<FutureObj<'static, (), S>>();
+    assert_send::<StreamObj<'static, (), dyn Spawn>>();
+    assert_sync::<StreamObj<'static, (), dyn Spawn>>();
+
+    assert_unpin::<FutureObj<'static, (), dyn Spawn>>();
+    assert_unpin::<LocalFutureObj<'static, (), dyn Spawn>>();
+    assert_unpin::<StreamObj<'static, (), dyn Spawn>>();
+    assert_unpin::<LocalStreamObj<'static, (), dyn Spawn>>();
+
+    assert_send::<SpawnErrorKind>();
+    assert_sync::<SpawnErrorKind>();
+
+    assert_send::<AssertSendFuture<FutureObj<'static, (), dyn Spawn>>>();
+}
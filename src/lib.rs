@@ -1,10 +1,91 @@
+//! Futures which are specialized on the spawner they are polled with.
+//!
+//! # Toolchain
+//!
+//! The crate is built on the `futures_api`, `pin` and `arbitrary_self_types`
+//! features of the nightly named in `rust-toolchain`, and there is no mode
+//! for other toolchains. `Future::poll` takes `self: PinMut<Self>`, so
+//! crates implementing it need `arbitrary_self_types` as well. Moving to
+//! `Pin<&mut Self>` means porting the task and waker plumbing to a
+//! toolchain which has it, rather than adding a feature switch, as no
+//! toolchain has both `Pin` and the task API the crate uses.
+
 #![feature(futures_api, pin, arbitrary_self_types)]
+// `type_name`, for the panic messages of `PollGuard`.
+#![cfg_attr(feature = "test-util", feature(core_intrinsics))]
+// `type_name`, for the slow-poll reports of `instrument_polls`.
+#![cfg_attr(feature = "std", feature(core_intrinsics))]
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "alloc", feature(alloc))]
+// Re-exporting the `#[specialized]` attribute.
+#![cfg_attr(feature = "macros", feature(use_extern_macros))]
+#![cfg_attr(feature = "cargo-clippy", allow(module_inception))]
+
+#[cfg(feature = "std")]
+extern crate core;
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "macros")]
+extern crate specialized_futures_macros;
+
+#[doc(hidden)]
+pub mod core_reexport {
+    pub use core::*;
+}
+
+#[macro_use]
+mod macros;
+
+#[cfg(feature = "macros")]
+pub use specialized_futures_macros::specialized;
+
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub mod macro_support;
+
+pub mod future;
+pub use self::future::{Future, FutureExt, FutureObj, LocalFutureObj, UnsafeFutureObj};
+
+pub mod try_future;
+pub use self::try_future::{TryFuture, TryFutureExt};
 
-mod future;
-pub use self::future::{Future, FutureObj, LocalFutureObj, UnsafeFutureObj};
+#[cfg(feature = "std")]
+pub mod channel;
 
-mod task;
+#[cfg(feature = "alloc")]
+pub mod lock;
+
+pub mod sink;
+pub use self::sink::{Sink, SinkExt};
+
+pub mod stream;
+pub use self::stream::{Stream, StreamExt};
+
+pub mod task;
 pub use self::task::Context;
 
-mod spawn;
-pub use self::spawn::{Spawn, SpawnLocal};
\ No newline at end of file
+pub mod guarantees;
+
+pub mod spawn;
+pub use self::spawn::{Spawn, SpawnLocal, TimerSpawn};
+#[cfg(feature = "alloc")]
+pub use self::spawn::{SpawnExt, LocalSpawnExt};
+#[cfg(feature = "std")]
+pub use self::spawn::ReactorSpawn;
+
+#[cfg(feature = "alloc")]
+pub mod executor;
+
+#[cfg(feature = "std")]
+pub mod timer;
+
+#[cfg(feature = "std")]
+pub mod diagnostics;
+
+#[cfg(feature = "test-util")]
+pub mod test;
+
+#[cfg(all(unix, feature = "reactor"))]
+pub mod reactor;
@@ -0,0 +1,231 @@
+// Adapted from `futures-util/src/lock/bilock.rs` in futures-rs
+// (https://github.com/rust-lang-nursery/futures-rs).
+//
+// Copyright (c) 2016 Alex Crichton
+// Copyright (c) 2017 The Rust Project Developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+#[cfg(feature = "std")]
+use std::error::Error;
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use core::ops::{Deref, DerefMut};
+use alloc::sync::Arc;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering::SeqCst;
+use future::Future;
+use task::{Context, Poll, Waker};
+use spawn::Spawn;
+
+/// A type of futures-powered synchronization primitive which is a mutex
+/// between two possible owners.
+///
+/// This primitive is not as generic as a full-blown mutex but is sufficient
+/// for many use cases where there are only two possible owners of a resource,
+/// such as the two halves of a split stream/sink. Its whole state lives in a
+/// single atomic word: `0` when unlocked, `1` when locked, and otherwise a
+/// pointer to the boxed waker of the other half, which is waiting for the
+/// lock.
+#[derive(Debug)]
+pub struct BiLock<T> {
+    inner: Arc<Inner<T>>,
+}
+
+#[derive(Debug)]
+struct Inner<T> {
+    state: AtomicUsize,
+    value: Option<UnsafeCell<T>>,
+}
+
+unsafe impl<T: Send> Send for Inner<T> {}
+unsafe impl<T: Send> Sync for Inner<T> {}
+
+impl<T> Unpin for BiLock<T> {}
+
+impl<T> BiLock<T> {
+    /// Creates a new `BiLock` protecting the provided data.
+    ///
+    /// Two handles to the lock are returned, and these are the only two
+    /// handles that will ever be available to the lock.
+    pub fn new(t: T) -> (BiLock<T>, BiLock<T>) {
+        let inner = Arc::new(Inner {
+            state: AtomicUsize::new(0),
+            value: Some(UnsafeCell::new(t)),
+        });
+
+        (BiLock { inner: inner.clone() }, BiLock { inner })
+    }
+
+    /// Attempt to acquire this lock, returning `Pending` if it can't be
+    /// acquired.
+    ///
+    /// If the lock is held by the other half, the current task is registered
+    /// to be woken when the lock is released. Only one task can be parked at
+    /// a time, which is sufficient since there is only one other half.
+    pub fn poll_lock<S: Spawn + ?Sized>(&self, cx: &mut Context<S>) -> Poll<BiLockGuard<T>> {
+        loop {
+            match self.inner.state.swap(1, SeqCst) {
+                // Woohoo, we grabbed the lock!
+                0 => return Poll::Ready(BiLockGuard { bilock: self }),
+
+                // Oops, someone else has locked the lock
+                1 => {}
+
+                // A task was previously blocked on this lock, likely our task,
+                // so we need to update that task.
+                n => unsafe {
+                    drop(Box::from_raw(n as *mut Waker));
+                }
+            }
+
+            let me = Box::new(cx.waker().clone());
+            let me = Box::into_raw(me) as usize;
+
+            match self.inner.state.compare_exchange(1, me, SeqCst, SeqCst) {
+                // The lock is still locked, but we've now parked ourselves, so
+                // just report that we're scheduled to receive a notification.
+                Ok(_) => return Poll::Pending,
+
+                // Oops, looks like the lock was unlocked after our swap above
+                // and before the compare_exchange. Deallocate what we just
+                // allocated and go through the loop again.
+                Err(0) => unsafe {
+                    drop(Box::from_raw(me as *mut Waker));
+                },
+
+                // The top of this loop set the previous state to 1, so if we
+                // failed the CAS above then it's because the previous value was
+                // *not* zero or one. This indicates that a task was blocked,
+                // but we're trying to acquire the lock and there's only one
+                // other reference of the lock, so it should be impossible for
+                // that task to ever block itself.
+                Err(n) => panic!("invalid state: {}", n),
+            }
+        }
+    }
+
+    /// Acquire this lock asynchronously.
+    ///
+    /// The returned future resolves with a guard once the lock is held.
+    pub fn lock(&self) -> BiLockAcquire<T> {
+        BiLockAcquire { bilock: self }
+    }
+
+    /// Attempts to put the two "halves" of a `BiLock<T>` back together and
+    /// recover the original value. Succeeds only if the two `BiLock<T>`s
+    /// originated from the same call to `BiLock::new`.
+    pub fn reunite(self, other: BiLock<T>) -> Result<T, ReuniteError<T>> {
+        if Arc::ptr_eq(&self.inner, &other.inner) {
+            drop(other);
+            let inner = Arc::try_unwrap(self.inner)
+                .ok()
+                .expect("futures: try_unwrap failed in BiLock<T>::reunite");
+            Ok(unsafe { inner.into_value() })
+        } else {
+            Err(ReuniteError(self, other))
+        }
+    }
+
+    fn unlock(&self) {
+        match self.inner.state.swap(0, SeqCst) {
+            // we've locked the lock, shouldn't be possible for us to see an
+            // unlocked lock.
+            0 => panic!("invalid unlocked state"),
+
+            // Ok, no one else tried to get the lock, we're done.
+            1 => {}
+
+            // Another task has parked themselves on this lock, let's wake them
+            // up as its now their turn.
+            n => unsafe {
+                Box::from_raw(n as *mut Waker).wake();
+            }
+        }
+    }
+}
+
+impl<T> Inner<T> {
+    unsafe fn into_value(mut self) -> T {
+        self.value.take().unwrap().into_inner()
+    }
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        assert_eq!(self.state.load(SeqCst), 0);
+    }
+}
+
+/// Error indicating two `BiLock<T>`s were not two halves of a whole, and
+/// thus could not be `reunite`d.
+pub struct ReuniteError<T>(pub BiLock<T>, pub BiLock<T>);
+
+impl<T> fmt::Debug for ReuniteError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ReuniteError")
+            .field(&"...")
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for ReuniteError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "tried to reunite two BiLocks that don't form a pair")
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Error for ReuniteError<T> {}
+
+/// Returned RAII guard from the `poll_lock` method.
+///
+/// This structure acts as a sentinel to the data in the `BiLock<T>` itself,
+/// implementing `Deref` and `DerefMut` to `T`. When dropped, the lock will be
+/// unlocked.
+#[derive(Debug)]
+pub struct BiLockGuard<'a, T: 'a> {
+    bilock: &'a BiLock<T>,
+}
+
+impl<'a, T> Deref for BiLockGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.bilock.inner.value.as_ref().unwrap().get() }
+    }
+}
+
+impl<'a, T> DerefMut for BiLockGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.bilock.inner.value.as_ref().unwrap().get() }
+    }
+}
+
+impl<'a, T> Drop for BiLockGuard<'a, T> {
+    fn drop(&mut self) {
+        self.bilock.unlock();
+    }
+}
+
+/// Future for the `BiLock::lock` method.
+#[derive(Debug)]
+pub struct BiLockAcquire<'a, T: 'a> {
+    bilock: &'a BiLock<T>,
+}
+
+impl<'a, T> Unpin for BiLockAcquire<'a, T> {}
+
+impl<'a, T, S: Spawn + ?Sized> Future<S> for BiLockAcquire<'a, T> {
+    type Output = BiLockGuard<'a, T>;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        self.bilock.poll_lock(cx)
+    }
+}
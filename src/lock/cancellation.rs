@@ -0,0 +1,200 @@
+use alloc::sync::{Arc, Weak};
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex as StdMutex;
+use future::{Future, FusedFuture};
+use task::{Context, Poll, Waker};
+use spawn::Spawn;
+
+struct Waiter {
+    id: u64,
+    waker: Waker,
+}
+
+struct State {
+    next_id: u64,
+    waiters: Vec<Waiter>,
+    children: Vec<Weak<Node>>,
+}
+
+struct Node {
+    cancelled: AtomicBool,
+    state: StdMutex<State>,
+}
+
+impl Node {
+    fn new(cancelled: bool) -> Node {
+        Node {
+            cancelled: AtomicBool::new(cancelled),
+            state: StdMutex::new(State {
+                next_id: 0,
+                waiters: Vec::new(),
+                children: Vec::new(),
+            }),
+        }
+    }
+
+    fn cancel(&self) {
+        let (waiters, children) = {
+            let mut state = self.state.lock().unwrap();
+            if self.cancelled.swap(true, Ordering::AcqRel) {
+                return;
+            }
+            (state.waiters.split_off(0), state.children.split_off(0))
+        };
+        for waiter in waiters {
+            waiter.waker.wake();
+        }
+        for child in children {
+            if let Some(child) = child.upgrade() {
+                child.cancel();
+            }
+        }
+    }
+
+    /// Check for cancellation, registering the current task under `waiter`
+    /// if it hasn't happened yet.
+    fn poll_cancelled<S: Spawn + ?Sized>(
+        &self,
+        waiter: &mut Option<u64>,
+        cx: &mut Context<S>,
+    ) -> Poll<()> {
+        if self.cancelled.load(Ordering::Acquire) {
+            return Poll::Ready(());
+        }
+        let mut state = self.state.lock().unwrap();
+        // Checked again under the lock, which `cancel` takes first.
+        if self.cancelled.load(Ordering::Acquire) {
+            return Poll::Ready(());
+        }
+        let waker = cx.waker().clone();
+        if let Some(id) = *waiter {
+            if let Some(entry) = state.waiters.iter_mut().find(|entry| entry.id == id) {
+                entry.waker = waker;
+                return Poll::Pending;
+            }
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        state.waiters.push(Waiter { id, waker });
+        *waiter = Some(id);
+        Poll::Pending
+    }
+
+    fn remove_waiter(&self, waiter: &mut Option<u64>) {
+        if let Some(id) = waiter.take() {
+            let mut state = self.state.lock().unwrap();
+            state.waiters.retain(|entry| entry.id != id);
+        }
+    }
+}
+
+/// A token which can be used to signal cancellation to any number of
+/// tasks.
+///
+/// Clones of a token share its state: cancelling any clone cancels them
+/// all. Tasks can check for cancellation with `is_cancelled`, or wait for it
+/// with the `cancelled` future, e.g. in a `select!`. Tokens made with
+/// `child_token` are cancelled along with their parent, but can also be
+/// cancelled on their own without affecting it.
+#[derive(Clone)]
+pub struct CancellationToken {
+    node: Arc<Node>,
+}
+
+impl fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> CancellationToken {
+        CancellationToken::new()
+    }
+}
+
+impl CancellationToken {
+    /// Create a new token, which isn't cancelled.
+    pub fn new() -> CancellationToken {
+        CancellationToken { node: Arc::new(Node::new(false)) }
+    }
+
+    /// Create a token which is cancelled whenever this one is.
+    ///
+    /// If this token is already cancelled, so is the child.
+    pub fn child_token(&self) -> CancellationToken {
+        let mut state = self.node.state.lock().unwrap();
+        if self.is_cancelled() {
+            return CancellationToken { node: Arc::new(Node::new(true)) };
+        }
+        let child = Arc::new(Node::new(false));
+        // Forget children which have been dropped, so that a long-lived
+        // parent doesn't accumulate them.
+        state.children.retain(|child| child.upgrade().is_some());
+        state.children.push(Arc::downgrade(&child));
+        CancellationToken { node: child }
+    }
+
+    /// Cancel this token and all of its children, waking every task
+    /// waiting on them.
+    pub fn cancel(&self) {
+        self.node.cancel()
+    }
+
+    /// Whether this token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.node.cancelled.load(Ordering::Acquire)
+    }
+
+    /// A future which completes once this token is cancelled.
+    pub fn cancelled(&self) -> Cancelled {
+        Cancelled { token: self, waiter: None }
+    }
+
+    pub(crate) fn poll_cancelled<S: Spawn + ?Sized>(
+        &self,
+        waiter: &mut Option<u64>,
+        cx: &mut Context<S>,
+    ) -> Poll<()> {
+        self.node.poll_cancelled(waiter, cx)
+    }
+
+    pub(crate) fn remove_waiter(&self, waiter: &mut Option<u64>) {
+        self.node.remove_waiter(waiter)
+    }
+}
+
+/// Future for the `CancellationToken::cancelled` method.
+#[derive(Debug)]
+pub struct Cancelled<'a> {
+    token: &'a CancellationToken,
+    waiter: Option<u64>,
+}
+
+impl<'a> Unpin for Cancelled<'a> {}
+
+impl<'a, S: Spawn + ?Sized> Future<S> for Cancelled<'a> {
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<()> {
+        let this = &mut *self;
+        this.token.poll_cancelled(&mut this.waiter, cx)
+    }
+}
+
+impl<'a> FusedFuture for Cancelled<'a> {
+    fn is_terminated(&self) -> bool {
+        self.token.is_cancelled()
+    }
+}
+
+impl<'a> Drop for Cancelled<'a> {
+    fn drop(&mut self) {
+        self.token.remove_waiter(&mut self.waiter);
+    }
+}
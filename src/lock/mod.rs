@@ -0,0 +1,19 @@
+//! Futures-aware synchronization primitives.
+
+#[cfg(feature = "std")]
+mod mutex;
+#[cfg(feature = "std")]
+pub use self::mutex::{Mutex, MutexGuard, LockFuture};
+
+#[cfg(feature = "std")]
+mod once_cell;
+#[cfg(feature = "std")]
+pub use self::once_cell::{OnceCell, GetOrInit};
+
+#[cfg(feature = "std")]
+mod cancellation;
+#[cfg(feature = "std")]
+pub use self::cancellation::{CancellationToken, Cancelled};
+
+mod bilock;
+pub use self::bilock::{BiLock, BiLockGuard, BiLockAcquire, ReuniteError};
@@ -0,0 +1,228 @@
+use core::cell::UnsafeCell;
+use std::collections::VecDeque;
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use core::ops::{Deref, DerefMut};
+use std::sync::Mutex as StdMutex;
+use future::{Future, FusedFuture};
+use task::{Context, Poll, Waker};
+use spawn::Spawn;
+
+struct Waiter {
+    id: u64,
+    waker: Option<Waker>,
+    /// Set when the lock has been handed directly to this waiter.
+    granted: bool,
+}
+
+struct State {
+    locked: bool,
+    next_id: u64,
+    waiters: VecDeque<Waiter>,
+}
+
+impl State {
+    /// Releases the lock, handing it to the first waiter if there is one.
+    fn unlock(&mut self) {
+        match self.waiters.front_mut() {
+            Some(waiter) => {
+                waiter.granted = true;
+                if let Some(waker) = waiter.waker.take() {
+                    waker.wake();
+                }
+            }
+            None => self.locked = false,
+        }
+    }
+}
+
+/// A futures-aware mutex.
+///
+/// Tasks waiting for the lock are queued in FIFO order, and the lock is
+/// handed directly to the longest-waiting task when it is released, so a
+/// task can never be starved by later arrivals.
+pub struct Mutex<T: ?Sized> {
+    state: StdMutex<State>,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+impl<T: ?Sized> fmt::Debug for Mutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("Mutex")
+            .field("locked", &state.locked)
+            .field("waiters", &state.waiters.len())
+            .finish()
+    }
+}
+
+impl<T> Mutex<T> {
+    /// Creates a new futures-aware mutex.
+    pub fn new(t: T) -> Mutex<T> {
+        Mutex {
+            state: StdMutex::new(State {
+                locked: false,
+                next_id: 0,
+                waiters: VecDeque::new(),
+            }),
+            value: UnsafeCell::new(t),
+        }
+    }
+
+    /// Consumes this mutex, returning the underlying data.
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    /// Attempt to acquire the lock immediately.
+    ///
+    /// Fails if the lock is held, or if other tasks are already waiting for
+    /// it.
+    pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+        let mut state = self.state.lock().unwrap();
+        if !state.locked && state.waiters.is_empty() {
+            state.locked = true;
+            Some(MutexGuard { mutex: self })
+        } else {
+            None
+        }
+    }
+
+    /// Acquire the lock asynchronously.
+    ///
+    /// The returned future resolves with a guard once the lock is held.
+    /// Dropping the future gives up its place in the queue, releasing the
+    /// lock again if it had already been handed over.
+    pub fn lock(&self) -> LockFuture<T> {
+        LockFuture { mutex: Some(self), waiter: None }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `Mutex` mutably, no actual locking needs to
+    /// take place.
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.value.get() }
+    }
+}
+
+/// Future for the `Mutex::lock` method.
+pub struct LockFuture<'a, T: ?Sized + 'a> {
+    /// `None` once the guard has been returned.
+    mutex: Option<&'a Mutex<T>>,
+    /// Our place in the waiter queue, if we have one.
+    waiter: Option<u64>,
+}
+
+impl<'a, T: ?Sized> Unpin for LockFuture<'a, T> {}
+
+impl<'a, T: ?Sized> fmt::Debug for LockFuture<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LockFuture")
+            .field("queued", &self.waiter.is_some())
+            .finish()
+    }
+}
+
+impl<'a, T: ?Sized, S: Spawn + ?Sized> Future<S> for LockFuture<'a, T> {
+    type Output = MutexGuard<'a, T>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<MutexGuard<'a, T>> {
+        let mutex = self.mutex.expect("LockFuture polled after completion");
+        let mut state = mutex.state.lock().unwrap();
+        match self.waiter {
+            None => {
+                if !state.locked && state.waiters.is_empty() {
+                    state.locked = true;
+                } else {
+                    let id = state.next_id;
+                    state.next_id += 1;
+                    state.waiters.push_back(Waiter {
+                        id,
+                        waker: Some(cx.waker().clone()),
+                        granted: false,
+                    });
+                    drop(state);
+                    self.waiter = Some(id);
+                    return Poll::Pending;
+                }
+            }
+            Some(id) => {
+                let pos = state.waiters.iter().position(|w| w.id == id).unwrap();
+                if state.waiters[pos].granted {
+                    state.waiters.remove(pos);
+                } else {
+                    pending!(state.waiters[pos].waker = Some(cx.waker().clone()));
+                }
+            }
+        }
+        drop(state);
+        self.waiter = None;
+        self.mutex = None;
+        Poll::Ready(MutexGuard { mutex })
+    }
+}
+
+impl<'a, T: ?Sized> FusedFuture for LockFuture<'a, T> {
+    fn is_terminated(&self) -> bool {
+        self.mutex.is_none()
+    }
+}
+
+impl<'a, T: ?Sized> Drop for LockFuture<'a, T> {
+    fn drop(&mut self) {
+        if let (Some(mutex), Some(id)) = (self.mutex, self.waiter) {
+            let mut state = mutex.state.lock().unwrap();
+            let pos = state.waiters.iter().position(|w| w.id == id).unwrap();
+            let waiter = state.waiters.remove(pos).unwrap();
+            // The lock was handed to us but never taken, so pass it on.
+            if waiter.granted {
+                state.unlock();
+            }
+        }
+    }
+}
+
+/// An RAII guard returned by the `lock` and `try_lock` methods.
+///
+/// When this structure is dropped (falls out of scope), the lock is released
+/// and handed to the next waiting task, if any.
+pub struct MutexGuard<'a, T: ?Sized + 'a> {
+    mutex: &'a Mutex<T>,
+}
+
+unsafe impl<'a, T: ?Sized + Sync> Sync for MutexGuard<'a, T> {}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for MutexGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MutexGuard")
+            .field("value", &&**self)
+            .finish()
+    }
+}
+
+impl<'a, T: ?Sized> Deref for MutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.mutex.state.lock().unwrap().unlock();
+    }
+}
@@ -0,0 +1,219 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::mem::PinMut;
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex as StdMutex;
+use future::{Future, FusedFuture};
+use task::{Context, Poll, Waker};
+use spawn::Spawn;
+
+struct Waiter {
+    id: u64,
+    waker: Waker,
+}
+
+struct State {
+    /// Set while some `GetOrInit` future is running its initializer.
+    initializing: bool,
+    next_id: u64,
+    waiters: Vec<Waiter>,
+}
+
+impl State {
+    fn wake_all(&mut self) {
+        for waiter in self.waiters.drain(..) {
+            waiter.waker.wake();
+        }
+    }
+
+    fn remove(&mut self, id: u64) {
+        self.waiters.retain(|waiter| waiter.id != id);
+    }
+}
+
+/// A cell which is initialized at most once, by the first of any number of
+/// tasks racing to initialize it.
+///
+/// Only one task runs its initializer at a time; the others wait until it
+/// has finished. If the initializing future is dropped before completing,
+/// one of the waiting tasks takes over and runs its own initializer, so
+/// cancelling the first caller never leaves the others waiting forever.
+///
+/// Once the cell is initialized, reading it is a single atomic load.
+pub struct OnceCell<T> {
+    initialized: AtomicBool,
+    state: StdMutex<State>,
+    value: UnsafeCell<Option<T>>,
+}
+
+unsafe impl<T: Send> Send for OnceCell<T> {}
+unsafe impl<T: Send + Sync> Sync for OnceCell<T> {}
+
+impl<T: fmt::Debug> fmt::Debug for OnceCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OnceCell")
+            .field("value", &self.get())
+            .finish()
+    }
+}
+
+impl<T> Default for OnceCell<T> {
+    fn default() -> OnceCell<T> {
+        OnceCell::new()
+    }
+}
+
+impl<T> OnceCell<T> {
+    /// Creates a new, uninitialized cell.
+    pub fn new() -> OnceCell<T> {
+        OnceCell {
+            initialized: AtomicBool::new(false),
+            state: StdMutex::new(State {
+                initializing: false,
+                next_id: 0,
+                waiters: Vec::new(),
+            }),
+            value: UnsafeCell::new(None),
+        }
+    }
+
+    /// Returns the value, if the cell has been initialized.
+    pub fn get(&self) -> Option<&T> {
+        if self.initialized.load(Ordering::Acquire) {
+            unsafe { (*self.value.get()).as_ref() }
+        } else {
+            None
+        }
+    }
+
+    /// Initializes the cell with `value`.
+    ///
+    /// Fails, returning the value, if the cell is already initialized or an
+    /// initializer is currently running.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let mut state = self.state.lock().unwrap();
+        if state.initializing || self.initialized.load(Ordering::Acquire) {
+            return Err(value);
+        }
+        self.complete(&mut state, value);
+        Ok(())
+    }
+
+    /// Returns the value, initializing the cell with the output of the
+    /// future returned by `f` if it isn't initialized yet.
+    ///
+    /// `f` is only called if this caller ends up running the initializer,
+    /// and at most once.
+    pub fn get_or_init<F, Fut>(&self, f: F) -> GetOrInit<T, F, Fut>
+        where F: FnOnce() -> Fut
+    {
+        GetOrInit { cell: self, init: Some(f), future: None, waiter: None }
+    }
+
+    /// Consumes the cell, returning the value if it was initialized.
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+
+    /// Stores the value and wakes every waiting task. Must be called with
+    /// the state locked, and only once.
+    fn complete(&self, state: &mut State, value: T) {
+        unsafe {
+            *self.value.get() = Some(value);
+        }
+        self.initialized.store(true, Ordering::Release);
+        state.initializing = false;
+        state.wake_all();
+    }
+}
+
+/// Future for the `OnceCell::get_or_init` method.
+pub struct GetOrInit<'a, T: 'a, F, Fut> {
+    cell: &'a OnceCell<T>,
+    init: Option<F>,
+    /// The initializer, while this future is the one running it.
+    future: Option<Fut>,
+    waiter: Option<u64>,
+}
+
+impl<'a, T, F, Fut> fmt::Debug for GetOrInit<'a, T, F, Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("GetOrInit")
+            .field("initializing", &self.future.is_some())
+            .finish()
+    }
+}
+
+impl<'a, T, F, Fut, S> Future<S> for GetOrInit<'a, T, F, Fut>
+    where F: FnOnce() -> Fut,
+          Fut: Future<S, Output = T>,
+          S: Spawn + ?Sized
+{
+    type Output = &'a T;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<&'a T> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        let cell = this.cell;
+        if let Some(value) = cell.get() {
+            return Poll::Ready(value);
+        }
+
+        if this.future.is_none() {
+            let mut state = cell.state.lock().unwrap();
+            if let Some(id) = this.waiter.take() {
+                state.remove(id);
+            }
+            if cell.initialized.load(Ordering::Acquire) {
+                drop(state);
+                return Poll::Ready(cell.get().unwrap());
+            }
+            if state.initializing {
+                let id = state.next_id;
+                state.next_id += 1;
+                state.waiters.push(Waiter { id, waker: cx.waker().clone() });
+                this.waiter = Some(id);
+                return Poll::Pending;
+            }
+            // Nobody else is initializing the cell, so this future takes
+            // over.
+            state.initializing = true;
+            drop(state);
+            let init = this.init.take().expect("GetOrInit polled after completion");
+            this.future = Some(init());
+        }
+
+        let value = {
+            let future = unsafe { PinMut::new_unchecked(this.future.as_mut().unwrap()) };
+            ready!(future.poll(cx))
+        };
+        this.future = None;
+        let mut state = cell.state.lock().unwrap();
+        cell.complete(&mut state, value);
+        drop(state);
+        Poll::Ready(cell.get().unwrap())
+    }
+}
+
+impl<'a, T, F, Fut> FusedFuture for GetOrInit<'a, T, F, Fut> {
+    fn is_terminated(&self) -> bool {
+        self.init.is_none() && self.future.is_none()
+    }
+}
+
+impl<'a, T, F, Fut> Drop for GetOrInit<'a, T, F, Fut> {
+    fn drop(&mut self) {
+        if self.future.is_none() && self.waiter.is_none() {
+            return;
+        }
+        let mut state = self.cell.state.lock().unwrap();
+        if let Some(id) = self.waiter.take() {
+            state.remove(id);
+        }
+        if self.future.is_some() {
+            // The initializer was abandoned. Wake the waiters so that one of
+            // them takes over.
+            state.initializing = false;
+            state.wake_all();
+        }
+    }
+}
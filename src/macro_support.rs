@@ -0,0 +1,28 @@
+//! Items used by the code which `#[specialized]` generates. Not public API.
+
+use future::Future;
+use spawn::Spawn;
+
+/// The default spawner, which can't be written `dyn ::specialized_futures::Spawn`
+/// in a 2015 edition crate.
+pub type DynSpawn = dyn Spawn;
+
+/// What a segment of a `#[specialized]` function returns: either the next
+/// future to await along with the rest of the function, or the function's
+/// output.
+pub enum Step<F, K, T> {
+    Await(F, K),
+    Done(T),
+}
+
+/// Await `future`, then continue with `then`.
+///
+/// The bound on `then` is what lets the compiler infer the type of its
+/// argument.
+pub fn await_step<S, F, K, R, T>(future: F, then: K) -> Step<F, K, T>
+    where S: Spawn + ?Sized,
+          F: Future<S>,
+          K: FnOnce(F::Output) -> R
+{
+    Step::Await(future, then)
+}
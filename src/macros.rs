@@ -0,0 +1,376 @@
+/// Pins a value on the stack.
+///
+/// Each identifier is moved into a hidden local and rebound to a `PinMut`
+/// pointing at it. Because the original binding is shadowed, the value can
+/// never be moved again after it has been pinned, which makes this safe even
+/// for `!Unpin` futures.
+///
+/// ```
+/// #![feature(pin, arbitrary_self_types, futures_api)]
+/// # #[macro_use] extern crate specialized_futures;
+/// # use specialized_futures::future::ready;
+/// # fn main() {
+/// let fut = ready(1);
+/// pin_mut!(fut);
+/// let _: ::std::mem::PinMut<_> = fut;
+/// # }
+/// ```
+#[macro_export]
+macro_rules! pin_mut {
+    ($($x:ident),* $(,)*) => { $(
+        // Move the value to ensure that it is owned
+        let mut $x = $x;
+        // Shadow the original binding so that it can't be directly accessed
+        // ever again.
+        #[allow(unused_mut)]
+        let mut $x = unsafe {
+            $crate::core_reexport::mem::PinMut::new_unchecked(&mut $x)
+        };
+    )* }
+}
+
+/// Extracts the successful value of a `Poll<T>`, returning `Poll::Pending`
+/// from the enclosing function if it isn't ready yet.
+///
+/// This is the early return found all over hand-written `poll` methods:
+///
+/// ```ignore
+/// let output = ready!(PinMut::new(&mut self.inner).poll(cx));
+/// ```
+///
+/// The expansion refers to this crate's `Poll` by absolute path, so it works
+/// alongside an identically named macro from another crate.
+#[macro_export]
+macro_rules! ready {
+    ($e:expr) => {
+        match $e {
+            $crate::task::Poll::Ready(t) => t,
+            $crate::task::Poll::Pending => return $crate::task::Poll::Pending,
+        }
+    };
+}
+
+/// Returns `Poll::Pending` from the enclosing function.
+///
+/// An optional expression is evaluated first; this is where a waker is
+/// usually registered, e.g. `pending!(self.waker.register(cx.waker()))`.
+/// Returning `Pending` without arranging for a wakeup means the task will
+/// never be polled again.
+#[macro_export]
+macro_rules! pending {
+    () => {
+        return $crate::task::Poll::Pending
+    };
+    ($register:expr) => {{
+        $register;
+        return $crate::task::Poll::Pending
+    }};
+}
+
+/// Polls a future, typically a field of the future being implemented,
+/// without having to spell out the pinning.
+///
+/// `poll!(self.inner, cx)` borrows the field, pins the borrow on the stack
+/// with `pin_mut!` and polls it, evaluating to the resulting `Poll`. Since
+/// the field itself is only reached through a plain `&mut`, its type must be
+/// `Unpin`; `!Unpin` fields need an unsafe projection instead. It works for
+/// any spawner type the context is specialized to.
+#[macro_export]
+macro_rules! poll {
+    ($fut:expr, $cx:expr) => {{
+        let __fut = &mut $fut;
+        pin_mut!(__fut);
+        $crate::Future::poll(__fut, $cx)
+    }};
+}
+
+/// Polls multiple futures simultaneously, resolving to a tuple of all
+/// results once every future has completed.
+///
+/// Between two and eight futures are accepted. The macro evaluates to a
+/// future (one of the `future::Join*` combinators), so it can be stored,
+/// returned from a function or driven from inside a `poll_fn`. Each child is
+/// kept pinned in a `MaybeDone` slot and only the children that haven't
+/// finished yet are polled on each wakeup.
+///
+/// ```
+/// #![feature(pin, arbitrary_self_types, futures_api)]
+/// # #[macro_use] extern crate specialized_futures;
+/// # use specialized_futures::FutureExt;
+/// # use specialized_futures::future::ready;
+/// # fn main() {
+/// let joined = join!(ready(1), ready("two"), ready(3.0));
+/// assert_eq!(joined.now_or_never(), Some((1, "two", 3.0)));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! join {
+    ($a:expr, $b:expr $(,)*) => {
+        $crate::future::join($a, $b)
+    };
+    ($a:expr, $b:expr, $c:expr $(,)*) => {
+        $crate::future::join3($a, $b, $c)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr $(,)*) => {
+        $crate::future::join4($a, $b, $c, $d)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr, $e:expr $(,)*) => {
+        $crate::future::join5($a, $b, $c, $d, $e)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $f:expr $(,)*) => {
+        $crate::future::join6($a, $b, $c, $d, $e, $f)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $f:expr, $g:expr $(,)*) => {
+        $crate::future::join7($a, $b, $c, $d, $e, $f, $g)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $f:expr, $g:expr, $h:expr $(,)*) => {
+        $crate::future::join8($a, $b, $c, $d, $e, $f, $g, $h)
+    };
+}
+
+/// Polls multiple fallible futures simultaneously, resolving to a tuple of
+/// all `Ok` values or to the first error encountered.
+///
+/// This is the `TryFuture` counterpart of `join!`: every future must share
+/// the same error type, and as soon as one of them fails the others are
+/// dropped and the error is returned.
+///
+/// ```
+/// #![feature(pin, arbitrary_self_types, futures_api)]
+/// # #[macro_use] extern crate specialized_futures;
+/// # use specialized_futures::FutureExt;
+/// # use specialized_futures::future::ready;
+/// # fn main() {
+/// let joined = try_join!(ready(Ok::<i32, &str>(1)), ready(Err::<i32, &str>("oops")));
+/// assert_eq!(joined.now_or_never(), Some(Err("oops")));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! try_join {
+    ($a:expr, $b:expr $(,)*) => {
+        $crate::try_future::try_join($a, $b)
+    };
+    ($a:expr, $b:expr, $c:expr $(,)*) => {
+        $crate::try_future::try_join3($a, $b, $c)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr $(,)*) => {
+        $crate::try_future::try_join4($a, $b, $c, $d)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr, $e:expr $(,)*) => {
+        $crate::try_future::try_join5($a, $b, $c, $d, $e)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $f:expr $(,)*) => {
+        $crate::try_future::try_join6($a, $b, $c, $d, $e, $f)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $f:expr, $g:expr $(,)*) => {
+        $crate::try_future::try_join7($a, $b, $c, $d, $e, $f, $g)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $f:expr, $g:expr, $h:expr $(,)*) => {
+        $crate::try_future::try_join8($a, $b, $c, $d, $e, $f, $g, $h)
+    };
+}
+
+/// Polls several futures at once, running the arm of whichever one
+/// completes first.
+///
+/// This is meant for manual `poll` implementations and `poll_fn` closures:
+/// the first argument is the identifier of the `&mut Context` currently in
+/// scope, followed by the arms:
+///
+/// - `pattern = future => expression,` for each branch. The future must be
+///   a place expression (usually a local variable) whose type implements
+///   `FusedFuture + Unpin`, so that it can be selected on repeatedly, e.g.
+///   in a loop, without being polled again after completion.
+/// - `complete => expression,` (optional) runs when every branch is already
+///   terminated. If it is omitted, `select!` panics in that case.
+/// - `default => expression,` (optional) runs when no branch is ready right
+///   now. If it is omitted, `select!` makes the enclosing function return
+///   `Poll::Pending`, so it must be used in a function returning `Poll`.
+///
+/// The first branch found to be ready wins. To keep any branch from
+/// starving the others, each time a `select!` runs it starts polling at the
+/// next branch, rotating through them in declaration order. The rotation is
+/// kept per `select!`, starting at the first branch, so one site's order
+/// doesn't depend on any other; `select_biased!` always starts at the first
+/// branch. Branches whose future is already terminated are skipped. If a
+/// ready output doesn't match its arm's pattern, it is discarded and the
+/// next branch is polled.
+///
+/// Arm expressions are expanded in place, so `return`, `break` and
+/// `continue` in them refer to the enclosing function or loop.
+///
+/// ```
+/// #![feature(pin, arbitrary_self_types, futures_api)]
+/// # #[macro_use] extern crate specialized_futures;
+/// # use specialized_futures::FutureExt;
+/// # use std::mem::PinMut;
+/// # use specialized_futures::future::{poll_fn, ready, maybe_done};
+/// # use specialized_futures::task::Poll;
+/// # fn main() {
+/// let mut a = maybe_done(ready(1));
+/// let mut b = maybe_done(ready(2));
+/// let mut total = 0;
+/// let summed = poll_fn(move |cx| loop {
+///     select! { cx;
+///         () = a => total += PinMut::new(&mut a).take_output().unwrap(),
+///         () = b => total += PinMut::new(&mut b).take_output().unwrap(),
+///         complete => return Poll::Ready(total),
+///     }
+/// });
+/// assert_eq!(summed.now_or_never(), Some(3));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! select {
+    ($cx:ident; $($tokens:tt)*) => {
+        __select_internal!(@parse rotate $cx; []; []; []; $($tokens)*)
+    };
+}
+
+// The machinery shared by `select!` and `select_biased!`. It is invoked
+// without a `$crate::` prefix because macro paths are still feature-gated,
+// so `#[macro_use]` must import it alongside the public macros.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __select_internal {
+    (@parse $mode:ident $cx:ident; [$($arms:tt)*]; [$($complete:tt)*]; [$($default:tt)*];) => {
+        __select_internal!(@emit $mode $cx; [$($arms)*]; [$($complete)*]; [$($default)*])
+    };
+    (@parse $mode:ident $cx:ident; [$($arms:tt)*]; []; [$($default:tt)*];
+        complete => $body:expr) => {
+        __select_internal!(@parse $mode $cx; [$($arms)*]; [$body]; [$($default)*];)
+    };
+    (@parse $mode:ident $cx:ident; [$($arms:tt)*]; []; [$($default:tt)*];
+        complete => $body:expr, $($rest:tt)*) => {
+        __select_internal!(@parse $mode $cx; [$($arms)*]; [$body]; [$($default)*];
+            $($rest)*)
+    };
+    (@parse $mode:ident $cx:ident; [$($arms:tt)*]; [$($complete:tt)*]; [];
+        default => $body:expr) => {
+        __select_internal!(@parse $mode $cx; [$($arms)*]; [$($complete)*]; [$body];)
+    };
+    (@parse $mode:ident $cx:ident; [$($arms:tt)*]; [$($complete:tt)*]; [];
+        default => $body:expr, $($rest:tt)*) => {
+        __select_internal!(@parse $mode $cx; [$($arms)*]; [$($complete)*]; [$body];
+            $($rest)*)
+    };
+    (@parse $mode:ident $cx:ident; [$($arms:tt)*]; [$($complete:tt)*]; [$($default:tt)*];
+        $p:pat = $f:expr => $body:expr) => {
+        __select_internal!(@parse $mode $cx; [$($arms)* ($p, $f, $body)];
+            [$($complete)*]; [$($default)*];)
+    };
+    (@parse $mode:ident $cx:ident; [$($arms:tt)*]; [$($complete:tt)*]; [$($default:tt)*];
+        $p:pat = $f:expr => $body:expr, $($rest:tt)*) => {
+        __select_internal!(@parse $mode $cx; [$($arms)* ($p, $f, $body)];
+            [$($complete)*]; [$($default)*]; $($rest)*)
+    };
+
+    // Branches from the rotating start index to the end are polled first,
+    // then those before it, so every branch is polled at most once.
+    (@emit rotate $cx:ident; [$($arms:tt)*]; [$($complete:tt)*]; [$($default:tt)*]) => {{
+        let mut __all_terminated = true;
+        static __ROTATION: $crate::future::__SelectRotation =
+            $crate::future::__SelectRotation::new(0);
+        let __start = $crate::future::__select_start(&__ROTATION,
+            0usize $(+ __select_internal!(@one $arms))*);
+        __select_internal!(@poll $cx; __all_terminated; __start; (0usize);
+            [$($arms)*]; [$($arms)*]; [$($complete)*]; [$($default)*])
+    }};
+    (@emit biased $cx:ident; [$($arms:tt)*]; [$($complete:tt)*]; [$($default:tt)*]) => {{
+        let mut __all_terminated = true;
+        let __start = 0usize;
+        __select_internal!(@poll $cx; __all_terminated; __start; (0usize);
+            [$($arms)*]; []; [$($complete)*]; [$($default)*])
+    }};
+    (@one $arm:tt) => { 1usize };
+
+    (@poll $cx:ident; $all:ident; $start:ident; $idx:expr;
+        [($p:pat, $f:expr, $body:expr) $($rest:tt)*]; [$($second:tt)*];
+        [$($complete:tt)*]; [$($default:tt)*]) => {
+        if let $crate::task::Poll::Ready($p) = {
+            let __fut = &mut $f;
+            if $idx < $start || $crate::future::FusedFuture::is_terminated(&*__fut) {
+                $crate::task::Poll::Pending
+            } else {
+                $all = false;
+                $crate::Future::poll($crate::core_reexport::mem::PinMut::new(__fut), &mut *$cx)
+            }
+        } {
+            $body
+        } else {
+            __select_internal!(@poll $cx; $all; $start; ($idx + 1); [$($rest)*];
+                [$($second)*]; [$($complete)*]; [$($default)*])
+        }
+    };
+    (@poll $cx:ident; $all:ident; $start:ident; $idx:expr; []; [$($second:tt)*];
+        [$($complete:tt)*]; [$($default:tt)*]) => {
+        __select_internal!(@wrap $cx; $all; $start; (0usize); [$($second)*];
+            [$($complete)*]; [$($default)*])
+    };
+
+    (@wrap $cx:ident; $all:ident; $start:ident; $idx:expr;
+        [($p:pat, $f:expr, $body:expr) $($rest:tt)*];
+        [$($complete:tt)*]; [$($default:tt)*]) => {
+        if let $crate::task::Poll::Ready($p) = {
+            let __fut = &mut $f;
+            if $idx >= $start || $crate::future::FusedFuture::is_terminated(&*__fut) {
+                $crate::task::Poll::Pending
+            } else {
+                $all = false;
+                $crate::Future::poll($crate::core_reexport::mem::PinMut::new(__fut), &mut *$cx)
+            }
+        } {
+            $body
+        } else {
+            __select_internal!(@wrap $cx; $all; $start; ($idx + 1); [$($rest)*];
+                [$($complete)*]; [$($default)*])
+        }
+    };
+    (@wrap $cx:ident; $all:ident; $start:ident; $idx:expr; [];
+        [$($complete:tt)*]; [$($default:tt)*]) => {
+        if $all {
+            __select_internal!(@complete $($complete)*)
+        } else {
+            __select_internal!(@default $($default)*)
+        }
+    };
+
+    (@complete) => {
+        panic!("all futures in select! were completed, but no `complete =>` handler was provided")
+    };
+    (@complete $body:expr) => { $body };
+    (@default) => { return $crate::task::Poll::Pending };
+    (@default $body:expr) => { $body };
+}
+
+/// Like `select!`, but always polls branches in the order in which they are
+/// declared.
+///
+/// This gives earlier branches strict priority, which is useful when, say, a
+/// control channel must always be handled before data. The flip side is
+/// that a branch which is always ready starves every branch after it, so
+/// prefer `select!` unless the priority is needed.
+///
+/// The syntax, including the `complete` and `default` arms, is the same as
+/// for `select!`, and branches whose future is already terminated are
+/// skipped in the same way.
+#[macro_export]
+macro_rules! select_biased {
+    ($cx:ident; $($tokens:tt)*) => {
+        __select_internal!(@parse biased $cx; []; []; []; $($tokens)*)
+    };
+}
+
+/// Awaits a future inside a `#[specialized]` function.
+///
+/// The attribute rewrites every `await_!` in the function's body before
+/// this macro could be expanded, so reaching this definition means the
+/// `await_!` is somewhere the attribute doesn't look, e.g. in another
+/// macro's arguments or outside a `#[specialized]` function.
+#[cfg(feature = "macros")]
+#[macro_export]
+macro_rules! await_ {
+    ($($tokens:tt)*) => {
+        compile_error!("`await_!` can only be used in the body of a #[specialized] function")
+    };
+}
@@ -0,0 +1,402 @@
+//! A reference IO reactor for executors providing `ReactorSpawn`, built on
+//! `poll(2)`.
+//!
+//! The executor owning a `Reactor` should call `Reactor::turn` from its run
+//! loop whenever it would otherwise go to sleep, passing the time until its
+//! next timer (if any) as the timeout, so that it blocks on IO readiness
+//! rather than on a condition variable. A blocked `turn` is interrupted by
+//! new registrations, and by the `Waker` returned from `Reactor::waker`,
+//! which the executor can use to wake tasks from other threads.
+
+use std::collections::HashMap;
+use core::fmt;
+use std::io;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use std::os::raw::{c_int, c_short, c_void};
+use std::os::unix::io::RawFd;
+use std::sync::{Arc, Mutex};
+use std::task::Wake;
+use core::time::Duration;
+use future::{Future, FusedFuture, FutureObj};
+use task::{Context, Poll, Waker};
+use spawn::{Spawn, SpawnObjError, SpawnErrorKind, ReactorSpawn, Ready};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[allow(non_camel_case_types)]
+type nfds_t = ::std::os::raw::c_ulong;
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+#[allow(non_camel_case_types)]
+type nfds_t = ::std::os::raw::c_uint;
+
+#[repr(C)]
+#[allow(non_camel_case_types)]
+struct pollfd {
+    fd: c_int,
+    events: c_short,
+    revents: c_short,
+}
+
+const POLLIN: c_short = 0x1;
+const POLLOUT: c_short = 0x4;
+const POLLERR: c_short = 0x8;
+const POLLHUP: c_short = 0x10;
+const POLLNVAL: c_short = 0x20;
+
+const F_GETFL: c_int = 3;
+const F_SETFL: c_int = 4;
+const F_SETFD: c_int = 2;
+const FD_CLOEXEC: c_int = 1;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const O_NONBLOCK: c_int = 0o4000;
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const O_NONBLOCK: c_int = 0x4;
+
+extern "C" {
+    fn poll(fds: *mut pollfd, nfds: nfds_t, timeout: c_int) -> c_int;
+    fn pipe(fds: *mut c_int) -> c_int;
+    fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+    fn read(fd: c_int, buf: *mut c_void, count: usize) -> isize;
+    fn write(fd: c_int, buf: *const c_void, count: usize) -> isize;
+    fn close(fd: c_int) -> c_int;
+}
+
+/// A self-pipe which is polled alongside the registered sources, so that
+/// writing to it interrupts a blocked `turn`.
+struct Wakeup {
+    read: RawFd,
+    write: RawFd,
+}
+
+impl Wakeup {
+    fn new() -> io::Result<Wakeup> {
+        let mut fds = [0; 2];
+        if unsafe { pipe(fds.as_mut_ptr()) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Constructed first, so that the pipe is closed if configuring it
+        // fails.
+        let wakeup = Wakeup { read: fds[0], write: fds[1] };
+        for &fd in &fds {
+            unsafe {
+                let flags = fcntl(fd, F_GETFL);
+                if flags < 0
+                    || fcntl(fd, F_SETFL, flags | O_NONBLOCK) < 0
+                    || fcntl(fd, F_SETFD, FD_CLOEXEC) < 0
+                {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+        Ok(wakeup)
+    }
+
+    fn notify(&self) {
+        // If the pipe is full, a wakeup is already pending, so errors can be
+        // ignored.
+        let byte = 1u8;
+        unsafe { write(self.write, &byte as *const u8 as *const c_void, 1); }
+    }
+
+    fn drain(&self) {
+        let mut buf = [0u8; 64];
+        while unsafe { read(self.read, buf.as_mut_ptr() as *mut c_void, buf.len()) } > 0 {}
+    }
+}
+
+impl Wake for Wakeup {
+    fn wake(arc_self: &Arc<Self>) {
+        arc_self.notify();
+    }
+}
+
+impl Drop for Wakeup {
+    fn drop(&mut self) {
+        unsafe {
+            close(self.read);
+            close(self.write);
+        }
+    }
+}
+
+struct Registration {
+    fd: RawFd,
+    interest: Ready,
+    waker: Option<Waker>,
+    result: Option<io::Result<Ready>>,
+}
+
+struct State {
+    next_id: u64,
+    registrations: HashMap<u64, Registration>,
+}
+
+/// An IO reactor which resolves `Readiness` futures as the OS reports events.
+pub struct Reactor {
+    state: Arc<Mutex<State>>,
+    wakeup: Arc<Wakeup>,
+}
+
+impl fmt::Debug for Reactor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Reactor")
+            .finish()
+    }
+}
+
+impl Reactor {
+    /// Create a new `Reactor` with no registrations.
+    ///
+    /// This fails if the pipe used to interrupt `turn` can't be created.
+    pub fn new() -> io::Result<Reactor> {
+        Ok(Reactor {
+            state: Arc::new(Mutex::new(State {
+                next_id: 0,
+                registrations: HashMap::new(),
+            })),
+            wakeup: Arc::new(Wakeup::new()?),
+        })
+    }
+
+    /// Get a handle through which sources can be registered.
+    pub fn handle(&self) -> ReactorHandle {
+        ReactorHandle { state: self.state.clone(), wakeup: self.wakeup.clone() }
+    }
+
+    /// Get a waker which interrupts `turn`, from any thread.
+    ///
+    /// If the reactor isn't blocked in `turn` when the waker is woken, the
+    /// next call to `turn` returns immediately instead.
+    pub fn waker(&self) -> Waker {
+        Waker::from(self.wakeup.clone())
+    }
+
+    /// Returns `true` if any registration is waiting for events.
+    pub fn has_registrations(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.registrations.values().any(|r| r.result.is_none())
+    }
+
+    /// Block until at least one registered source is ready, `timeout`
+    /// elapses, or the reactor is woken, then wake the tasks waiting on every
+    /// ready source.
+    ///
+    /// A `timeout` of `None` blocks indefinitely, unless nothing is
+    /// registered, in which case nothing could ever end the wait except a
+    /// wakeup and this returns immediately. Returns the number of
+    /// registrations that were resolved.
+    pub fn turn(&self, timeout: Option<Duration>) -> io::Result<usize> {
+        let (ids, mut fds): (Vec<u64>, Vec<pollfd>) = {
+            let state = self.state.lock().unwrap();
+            state.registrations.iter()
+                .filter(|(_, r)| r.result.is_none())
+                .map(|(&id, r)| {
+                    let mut events = 0;
+                    if r.interest.is_readable() { events |= POLLIN; }
+                    if r.interest.is_writable() { events |= POLLOUT; }
+                    (id, pollfd { fd: r.fd, events, revents: 0 })
+                })
+                .unzip()
+        };
+        if ids.is_empty() && timeout.is_none() {
+            return Ok(0);
+        }
+        fds.push(pollfd { fd: self.wakeup.read, events: POLLIN, revents: 0 });
+
+        let timeout = match timeout {
+            None => -1,
+            Some(d) => {
+                let ms = d.as_secs()
+                    .saturating_mul(1_000)
+                    .saturating_add(u64::from((d.subsec_nanos() + 999_999) / 1_000_000));
+                if ms > c_int::max_value() as u64 { c_int::max_value() } else { ms as c_int }
+            }
+        };
+
+        let n = unsafe { poll(fds.as_mut_ptr(), fds.len() as nfds_t, timeout) };
+        if n < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                return Ok(0);
+            }
+            return Err(err);
+        }
+
+        if fds.last().map_or(false, |fd| fd.revents != 0) {
+            self.wakeup.drain();
+        }
+
+        let mut resolved = 0;
+        let mut state = self.state.lock().unwrap();
+        for (id, fd) in ids.into_iter().zip(fds) {
+            if fd.revents == 0 {
+                continue;
+            }
+            // The registration may have been dropped while we were blocked.
+            let registration = match state.registrations.get_mut(&id) {
+                Some(registration) => registration,
+                None => continue,
+            };
+            let result = if fd.revents & POLLNVAL != 0 {
+                Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid file descriptor"))
+            } else {
+                let mut ready = Ready::empty();
+                if fd.revents & (POLLIN | POLLERR | POLLHUP) != 0 {
+                    ready = ready | Ready::readable();
+                }
+                if fd.revents & (POLLOUT | POLLERR | POLLHUP) != 0 {
+                    ready = ready | Ready::writable();
+                }
+                Ok(ready)
+            };
+            registration.result = Some(result);
+            if let Some(waker) = registration.waker.take() {
+                waker.wake();
+            }
+            resolved += 1;
+        }
+        Ok(resolved)
+    }
+}
+
+/// A cloneable handle to a `Reactor`.
+#[derive(Clone)]
+pub struct ReactorHandle {
+    state: Arc<Mutex<State>>,
+    wakeup: Arc<Wakeup>,
+}
+
+impl fmt::Debug for ReactorHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReactorHandle")
+            .finish()
+    }
+}
+
+impl ReactorHandle {
+    /// Wait for any of the events in `interest` to occur on `fd`.
+    ///
+    /// A `turn` blocked on the reactor is interrupted, so that it starts
+    /// polling `fd` too.
+    pub fn register(&self, fd: RawFd, interest: Ready) -> Readiness {
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            state.registrations.insert(id, Registration {
+                fd,
+                interest,
+                waker: None,
+                result: None,
+            });
+            id
+        };
+        self.wakeup.notify();
+        Readiness { state: self.state.clone(), id, done: false }
+    }
+}
+
+/// A future which resolves once its registered source reports readiness.
+///
+/// Dropping a `Readiness` deregisters it from its reactor.
+pub struct Readiness {
+    state: Arc<Mutex<State>>,
+    id: u64,
+    done: bool,
+}
+
+impl Unpin for Readiness {}
+
+impl fmt::Debug for Readiness {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Readiness")
+            .finish()
+    }
+}
+
+impl<S: Spawn + ?Sized> Future<S> for Readiness {
+    type Output = io::Result<Ready>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<io::Result<Ready>> {
+        if self.done {
+            panic!("Readiness polled after completion");
+        }
+        let result = {
+            let mut state = self.state.lock().unwrap();
+            let registration = state.registrations.get_mut(&self.id).unwrap();
+            match registration.result.take() {
+                Some(result) => result,
+                None => {
+                    registration.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+        };
+        self.done = true;
+        Poll::Ready(result)
+    }
+}
+
+impl FusedFuture for Readiness {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl Drop for Readiness {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.registrations.remove(&self.id);
+        }
+    }
+}
+
+/// A spawner combining another spawner with a `Reactor`, so that tasks
+/// spawned through it can use `ReactorSpawn`.
+#[derive(Debug, Clone)]
+pub struct ReactorSpawner<S> {
+    spawner: S,
+    reactor: ReactorHandle,
+}
+
+impl<S: Spawn> ReactorSpawner<S> {
+    /// Create a new `ReactorSpawner` spawning onto `spawner` and registering
+    /// sources with the reactor behind `reactor`.
+    pub fn new(spawner: S, reactor: ReactorHandle) -> ReactorSpawner<S> {
+        ReactorSpawner { spawner, reactor }
+    }
+
+    /// Get a reference to the underlying spawner.
+    pub fn get_ref(&self) -> &S {
+        &self.spawner
+    }
+
+    /// Get a mutable reference to the underlying spawner.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.spawner
+    }
+}
+
+impl<S: Spawn> Spawn for ReactorSpawner<S> {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawner.spawn_obj(future)
+    }
+
+    fn status(&self) -> Result<(), SpawnErrorKind> {
+        self.spawner.status()
+    }
+}
+
+impl<S: Spawn> ReactorSpawn for ReactorSpawner<S> {
+    type Readiness = Readiness;
+
+    fn register(&mut self, fd: RawFd, interest: Ready) -> Readiness {
+        self.reactor.register(fd, interest)
+    }
+}
@@ -0,0 +1,48 @@
+use core::marker::Unpin;
+use future::Future;
+use sink::{Sink, Send, SendAll, With};
+use stream::Stream;
+use spawn::Spawn;
+
+/// An extension trait for `Sink`s that provides a variety of convenient
+/// combinator functions.
+pub trait SinkExt<Item, S: Spawn + ?Sized = dyn Spawn>: Sink<Item, S> {
+    /// A future that completes after the given item has been fully processed
+    /// into the sink, including flushing.
+    fn send(&mut self, item: Item) -> Send<Self, Item>
+        where Self: Unpin
+    {
+        Send::new(self, item)
+    }
+
+    /// A future that completes after the given stream has been fully
+    /// processed into the sink, including flushing.
+    ///
+    /// Items are only handed to the sink once `poll_ready` reports that it
+    /// can accept them, so a slow sink applies backpressure to the stream.
+    /// The sink is flushed once the stream finishes, but not closed.
+    fn send_all<'a, St>(&'a mut self, stream: &'a mut St) -> SendAll<'a, Self, St, S>
+        where St: ?Sized + Stream<S, Item = Item> + Unpin,
+              Self: Unpin
+    {
+        SendAll::new(self, stream)
+    }
+
+    /// Composes a function in front of the sink.
+    ///
+    /// Each item sent to the returned sink is passed to `f`, and the item
+    /// the returned future resolves to is sent on to this sink. Only one
+    /// item is transformed at a time: the returned sink isn't ready for
+    /// another until the previous item has reached this sink. Errors from
+    /// `f` and from this sink are both reported as `E`.
+    fn with<U, Fut, F, E>(self, f: F) -> With<Self, Item, U, Fut, F>
+        where F: FnMut(U) -> Fut,
+              Fut: Future<S, Output = Result<Item, E>>,
+              E: From<Self::Error>,
+              Self: Sized
+    {
+        With::new(self, f)
+    }
+}
+
+impl<Item, S: Spawn + ?Sized, Si: Sink<Item, S> + ?Sized> SinkExt<Item, S> for Si {}
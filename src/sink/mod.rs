@@ -0,0 +1,14 @@
+mod sink;
+pub use self::sink::Sink;
+
+mod ext;
+pub use self::ext::SinkExt;
+
+mod send;
+pub use self::send::Send;
+
+mod send_all;
+pub use self::send_all::SendAll;
+
+mod with;
+pub use self::with::With;
@@ -0,0 +1,49 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::Future;
+use sink::Sink;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `SinkExt::send` method.
+#[derive(Debug)]
+pub struct Send<'a, Si: ?Sized + 'a, Item> {
+    sink: &'a mut Si,
+    item: Option<Item>,
+}
+
+impl<'a, Si: ?Sized + Unpin, Item> Unpin for Send<'a, Si, Item> {}
+
+impl<'a, Si: ?Sized + Unpin, Item> Send<'a, Si, Item> {
+    pub(super) fn new(sink: &'a mut Si, item: Item) -> Send<'a, Si, Item> {
+        Send { sink, item: Some(item) }
+    }
+}
+
+impl<'a, S, Si, Item> Future<S> for Send<'a, Si, Item>
+    where S: Spawn + ?Sized,
+          Si: ?Sized + Sink<Item, S> + Unpin
+{
+    type Output = Result<(), Si::Error>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if let Some(item) = this.item.take() {
+            match PinMut::new(&mut *this.sink).poll_ready(cx) {
+                Poll::Ready(Ok(())) => {
+                    if let Err(e) = PinMut::new(&mut *this.sink).start_send(item) {
+                        return Poll::Ready(Err(e));
+                    }
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => {
+                    this.item = Some(item);
+                    return Poll::Pending;
+                }
+            }
+        }
+
+        // The item has been handed over; wait for it to be flushed.
+        PinMut::new(&mut *this.sink).poll_flush(cx)
+    }
+}
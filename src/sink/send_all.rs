@@ -0,0 +1,109 @@
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::Future;
+use sink::Sink;
+use stream::Stream;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `SinkExt::send_all` method.
+pub struct SendAll<'a, Si, St, S: Spawn + ?Sized = dyn Spawn>
+    where Si: ?Sized + 'a,
+          St: ?Sized + Stream<S> + 'a
+{
+    sink: &'a mut Si,
+    stream: &'a mut St,
+    buffered: Option<St::Item>,
+    stream_done: bool,
+}
+
+impl<'a, Si, St, S> Unpin for SendAll<'a, Si, St, S>
+    where Si: ?Sized + Unpin,
+          St: ?Sized + Stream<S> + Unpin,
+          S: Spawn + ?Sized
+{}
+
+impl<'a, Si, St, S> fmt::Debug for SendAll<'a, Si, St, S>
+    where Si: ?Sized + fmt::Debug,
+          St: ?Sized + Stream<S> + fmt::Debug,
+          St::Item: fmt::Debug,
+          S: Spawn + ?Sized
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SendAll")
+            .field("sink", &self.sink)
+            .field("stream", &self.stream)
+            .field("buffered", &self.buffered)
+            .finish()
+    }
+}
+
+impl<'a, Si, St, S> SendAll<'a, Si, St, S>
+    where Si: ?Sized + Sink<St::Item, S> + Unpin,
+          St: ?Sized + Stream<S> + Unpin,
+          S: Spawn + ?Sized
+{
+    pub(super) fn new(sink: &'a mut Si, stream: &'a mut St) -> SendAll<'a, Si, St, S> {
+        SendAll { sink, stream, buffered: None, stream_done: false }
+    }
+
+    /// Hands `item` to the sink if it is ready for it, buffering it otherwise.
+    fn try_start_send(
+        &mut self,
+        cx: &mut Context<S>,
+        item: St::Item,
+    ) -> Poll<Result<(), Si::Error>> {
+        debug_assert!(self.buffered.is_none());
+        match PinMut::new(&mut *self.sink).poll_ready(cx) {
+            Poll::Ready(Ok(())) => {
+                Poll::Ready(PinMut::new(&mut *self.sink).start_send(item))
+            }
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => {
+                self.buffered = Some(item);
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<'a, Si, St, S> Future<S> for SendAll<'a, Si, St, S>
+    where Si: ?Sized + Sink<St::Item, S> + Unpin,
+          St: ?Sized + Stream<S> + Unpin,
+          S: Spawn + ?Sized
+{
+    type Output = Result<(), Si::Error>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        // An item left over from a previous poll must be sent first.
+        if let Some(item) = this.buffered.take() {
+            if let Err(e) = ready!(this.try_start_send(cx, item)) {
+                return Poll::Ready(Err(e));
+            }
+        }
+
+        while !this.stream_done {
+            match PinMut::new(&mut *this.stream).poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    if let Err(e) = ready!(this.try_start_send(cx, item)) {
+                        return Poll::Ready(Err(e));
+                    }
+                }
+                Poll::Ready(None) => this.stream_done = true,
+                Poll::Pending => {
+                    // Make progress on what was already sent while waiting
+                    // for the stream.
+                    return match PinMut::new(&mut *this.sink).poll_flush(cx) {
+                        Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+                        _ => Poll::Pending,
+                    };
+                }
+            }
+        }
+
+        PinMut::new(&mut *this.sink).poll_flush(cx)
+    }
+}
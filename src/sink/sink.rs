@@ -0,0 +1,92 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A value into which other values can be sent asynchronously.
+///
+/// Sending happens in up to three phases: `poll_ready` reports whether the
+/// sink can accept an item, `start_send` hands one item over, and
+/// `poll_flush` drives buffered items to their destination.
+///
+/// Every call to `start_send` must be preceded by a call to `poll_ready`
+/// which returned `Poll::Ready(Ok(()))`, with no other `start_send` in
+/// between. Sinks may return an error or panic if this contract is violated.
+/// The combinators in this crate never violate it.
+pub trait Sink<Item, S: Spawn + ?Sized = dyn Spawn> {
+    /// The type of value produced by the sink when an error occurs.
+    type Error;
+
+    /// Attempts to prepare the sink to receive a value.
+    ///
+    /// This method must be called and return `Poll::Ready(Ok(()))` prior to
+    /// each call to `start_send`. When it returns `Poll::Pending`, the
+    /// current task is woken once the sink may have become ready.
+    fn poll_ready(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), Self::Error>>;
+
+    /// Begin the process of sending a value to the sink.
+    ///
+    /// Each call must be preceded by a successful call to `poll_ready`. The
+    /// item is not guaranteed to have reached its destination until
+    /// `poll_flush` returns `Poll::Ready(Ok(()))`.
+    fn start_send(self: PinMut<Self>, item: Item) -> Result<(), Self::Error>;
+
+    /// Flush any remaining output from this sink.
+    ///
+    /// Returns `Poll::Ready(Ok(()))` once every item sent so far has been
+    /// processed. When it returns `Poll::Pending`, the current task is woken
+    /// once more progress may be made.
+    fn poll_flush(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), Self::Error>>;
+
+    /// Flush any remaining output and close this sink, if necessary.
+    ///
+    /// Returns `Poll::Ready(Ok(()))` once the sink is closed. No more items
+    /// may be sent to a sink after `poll_close` has been called.
+    fn poll_close(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), Self::Error>>;
+}
+
+impl<'a, Item, S, Si> Sink<Item, S> for &'a mut Si
+    where S: Spawn + ?Sized,
+          Si: ?Sized + Sink<Item, S> + Unpin
+{
+    type Error = Si::Error;
+
+    fn poll_ready(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), Self::Error>> {
+        Si::poll_ready(PinMut::new(&mut **self), cx)
+    }
+
+    fn start_send(mut self: PinMut<Self>, item: Item) -> Result<(), Self::Error> {
+        Si::start_send(PinMut::new(&mut **self), item)
+    }
+
+    fn poll_flush(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), Self::Error>> {
+        Si::poll_flush(PinMut::new(&mut **self), cx)
+    }
+
+    fn poll_close(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), Self::Error>> {
+        Si::poll_close(PinMut::new(&mut **self), cx)
+    }
+}
+
+impl<'a, Item, S, Si> Sink<Item, S> for PinMut<'a, Si>
+    where S: Spawn + ?Sized,
+          Si: ?Sized + Sink<Item, S>
+{
+    type Error = Si::Error;
+
+    fn poll_ready(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), Self::Error>> {
+        Si::poll_ready((*self).reborrow(), cx)
+    }
+
+    fn start_send(mut self: PinMut<Self>, item: Item) -> Result<(), Self::Error> {
+        Si::start_send((*self).reborrow(), item)
+    }
+
+    fn poll_flush(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), Self::Error>> {
+        Si::poll_flush((*self).reborrow(), cx)
+    }
+
+    fn poll_close(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), Self::Error>> {
+        Si::poll_close((*self).reborrow(), cx)
+    }
+}
@@ -0,0 +1,109 @@
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::PinMut;
+use future::Future;
+use sink::Sink;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Sink for the `SinkExt::with` method.
+pub struct With<Si, Item, U, Fut, F> {
+    sink: Si,
+    f: F,
+    pending: Option<Fut>,
+    _marker: PhantomData<fn(U) -> Item>,
+}
+
+impl<Si, Item, U, Fut, F> With<Si, Item, U, Fut, F> {
+    pub(super) fn new(sink: Si, f: F) -> With<Si, Item, U, Fut, F> {
+        With { sink, f, pending: None, _marker: PhantomData }
+    }
+
+    /// Acquires a reference to the underlying sink.
+    pub fn get_ref(&self) -> &Si {
+        &self.sink
+    }
+
+    /// Consumes this combinator, returning the underlying sink.
+    pub fn into_inner(self) -> Si {
+        self.sink
+    }
+}
+
+impl<Si: fmt::Debug, Item, U, Fut: fmt::Debug, F> fmt::Debug for With<Si, Item, U, Fut, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("With")
+            .field("sink", &self.sink)
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
+
+impl<Si, Item, U, Fut, F> With<Si, Item, U, Fut, F> {
+    /// Drive the transformation of the last item given to `start_send`, and
+    /// hand its result to the underlying sink.
+    fn poll_pending<E, S>(&mut self, cx: &mut Context<S>) -> Poll<Result<(), E>>
+        where Si: Sink<Item, S>,
+              Fut: Future<S, Output = Result<Item, E>>,
+              E: From<Si::Error>,
+              S: Spawn + ?Sized
+    {
+        let result = match &mut self.pending {
+            Some(fut) => ready!(unsafe { PinMut::new_unchecked(fut) }.poll(cx)),
+            None => return Poll::Ready(Ok(())),
+        };
+        // The future is dropped in place, as pinning permits. The sink was
+        // made ready before the future was created, in `poll_ready`.
+        self.pending = None;
+        let item = match result {
+            Ok(item) => item,
+            Err(e) => return Poll::Ready(Err(e)),
+        };
+        let sink = unsafe { PinMut::new_unchecked(&mut self.sink) };
+        Poll::Ready(sink.start_send(item).map_err(E::from))
+    }
+}
+
+impl<Si, Item, U, Fut, F, E, S> Sink<U, S> for With<Si, Item, U, Fut, F>
+    where Si: Sink<Item, S>,
+          F: FnMut(U) -> Fut,
+          Fut: Future<S, Output = Result<Item, E>>,
+          E: From<Si::Error>,
+          S: Spawn + ?Sized
+{
+    type Error = E;
+
+    fn poll_ready(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), E>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if let Err(e) = ready!(this.poll_pending(cx)) {
+            return Poll::Ready(Err(e));
+        }
+        let sink = unsafe { PinMut::new_unchecked(&mut this.sink) };
+        Poll::Ready(ready!(sink.poll_ready(cx)).map_err(E::from))
+    }
+
+    fn start_send(self: PinMut<Self>, item: U) -> Result<(), E> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        assert!(this.pending.is_none(), "With::start_send called without poll_ready");
+        this.pending = Some((this.f)(item));
+        Ok(())
+    }
+
+    fn poll_flush(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), E>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if let Err(e) = ready!(this.poll_pending(cx)) {
+            return Poll::Ready(Err(e));
+        }
+        let sink = unsafe { PinMut::new_unchecked(&mut this.sink) };
+        Poll::Ready(ready!(sink.poll_flush(cx)).map_err(E::from))
+    }
+
+    fn poll_close(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), E>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if let Err(e) = ready!(this.poll_pending(cx)) {
+            return Poll::Ready(Err(e));
+        }
+        let sink = unsafe { PinMut::new_unchecked(&mut this.sink) };
+        Poll::Ready(ready!(sink.poll_close(cx)).map_err(E::from))
+    }
+}
@@ -0,0 +1,112 @@
+use alloc::boxed::Box;
+use core::fmt;
+use core::mem::PinMut;
+use std::panic::{self, AssertUnwindSafe};
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::{Spawn, JoinHandle, JoinError};
+use spawn::join::Completer;
+
+/// A blocking closure waiting to be run by a `SpawnBlocking` spawner.
+///
+/// Executors should call `run` on a thread set aside for blocking work, not
+/// on one that polls futures. Dropping the task without running it
+/// resolves its handle to `Err(JoinError::Cancelled)`.
+pub struct BlockingTask {
+    run: Box<dyn RunOnce + Send>,
+}
+
+impl BlockingTask {
+    /// Run the closure to completion on the current thread.
+    ///
+    /// A panic in the closure is caught and reported through the handle.
+    pub fn run(self) {
+        self.run.run_once()
+    }
+}
+
+impl fmt::Debug for BlockingTask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BlockingTask").finish()
+    }
+}
+
+/// A handle to a blocking closure, which resolves to the closure's result.
+///
+/// This is created by `SpawnBlocking::spawn_blocking`. A panic in the
+/// closure resolves the handle to `Err(JoinError::Panicked(..))`, as with
+/// `JoinHandle`.
+#[derive(Debug)]
+pub struct BlockingHandle<T> {
+    inner: JoinHandle<T>,
+}
+
+impl<T> BlockingHandle<T> {
+    /// Abort the closure if it hasn't started running yet.
+    ///
+    /// A closure that is already running can't be interrupted, but its
+    /// result is discarded and the handle resolves to
+    /// `Err(JoinError::Cancelled)`.
+    pub fn abort(&self) {
+        self.inner.abort()
+    }
+}
+
+impl<S: Spawn + ?Sized, T> Future<S> for BlockingHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        PinMut::new(&mut self.inner).poll(cx)
+    }
+}
+
+impl<T> FusedFuture for BlockingHandle<T> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_terminated()
+    }
+}
+
+/// A spawner which can run blocking closures off the threads that poll
+/// futures.
+pub trait SpawnBlocking: Spawn {
+    /// Hand a blocking task to the executor.
+    ///
+    /// If the executor can't accept it, dropping the task resolves its
+    /// handle with `JoinError::Cancelled`.
+    fn spawn_blocking_task(&mut self, task: BlockingTask);
+
+    /// Run the given closure on a thread where blocking is allowed,
+    /// returning a handle which resolves to its result.
+    fn spawn_blocking<T, F>(&mut self, f: F) -> BlockingHandle<T>
+        where T: Send + 'static,
+              F: FnOnce() -> T + Send + 'static
+    {
+        let (completer, inner) = JoinHandle::completer();
+        self.spawn_blocking_task(BlockingTask {
+            run: Box::new(move || run_blocking(completer, f)),
+        });
+        BlockingHandle { inner }
+    }
+}
+
+fn run_blocking<T, F: FnOnce() -> T>(completer: Completer<T>, f: F) {
+    if completer.is_aborted() {
+        return;
+    }
+    let result = panic::catch_unwind(AssertUnwindSafe(f))
+        .map_err(JoinError::Panicked);
+    completer.complete(result);
+}
+
+/// `Box<dyn FnOnce()>` can't be called directly, so the closure is boxed
+/// behind a trait whose method takes the box by value.
+trait RunOnce {
+    fn run_once(self: Box<Self>);
+}
+
+impl<F: FnOnce()> RunOnce for F {
+    #[cfg_attr(feature = "cargo-clippy", allow(boxed_local))]
+    fn run_once(self: Box<Self>) {
+        (*self)()
+    }
+}
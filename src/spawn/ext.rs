@@ -0,0 +1,111 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use future::{Future, FutureObj, LocalFutureObj};
+use spawn::{Spawn, SpawnLocal, SpawnErrorKind, SpawnPriority, Priority, SpawnNamed};
+#[cfg(feature = "std")]
+use spawn::JoinHandle;
+
+/// Extension trait for `Spawn`.
+pub trait SpawnExt: Spawn {
+    /// Spawns a task that polls the given future to completion.
+    ///
+    /// This method boxes the future into a `FutureObj` and passes it to
+    /// `spawn_obj`. If the spawn fails, the future is dropped and only the
+    /// reason is returned; call `spawn_obj` directly to get the future back.
+    fn spawn<Fut>(&mut self, future: Fut) -> Result<(), SpawnErrorKind>
+        where Fut: Future<Output = ()> + Send + 'static
+    {
+        self.spawn_obj(FutureObj::new(Box::new(future)))
+            .map_err(|err| err.kind)
+    }
+
+    /// Spawns a task that polls the given future to completion, returning
+    /// a `JoinHandle` which resolves to its output.
+    ///
+    /// Panics in the future are caught and reported through the handle
+    /// rather than propagated into the executor.
+    #[cfg(feature = "std")]
+    fn spawn_with_handle<Fut>(&mut self, future: Fut) -> Result<JoinHandle<Fut::Output>, SpawnErrorKind>
+        where Fut: Future + Send + 'static,
+              Fut::Output: Send
+    {
+        let (task, handle) = JoinHandle::pair(future);
+        self.spawn_obj(FutureObj::new(Box::new(task)))
+            .map(|()| handle)
+            .map_err(|err| err.kind)
+    }
+
+    /// Spawns a task with the given priority that polls the given future to
+    /// completion.
+    ///
+    /// Like `spawn`, this boxes the future and only returns the reason if
+    /// the spawn fails.
+    fn spawn_with_priority<Fut>(&mut self, future: Fut, priority: Priority)
+        -> Result<(), SpawnErrorKind>
+        where Fut: Future<Output = ()> + Send + 'static,
+              Self: SpawnPriority
+    {
+        self.spawn_obj_with_priority(FutureObj::new(Box::new(future)), priority)
+            .map_err(|err| err.kind)
+    }
+
+    /// Spawns a named task that polls the given future to completion.
+    ///
+    /// Like `spawn`, this boxes the future and only returns the reason if
+    /// the spawn fails.
+    fn spawn_named<Fut, N>(&mut self, name: N, future: Fut) -> Result<(), SpawnErrorKind>
+        where Fut: Future<Output = ()> + Send + 'static,
+              N: Into<Cow<'static, str>>,
+              Self: SpawnNamed
+    {
+        self.spawn_obj_named(name.into(), FutureObj::new(Box::new(future)))
+            .map_err(|err| err.kind)
+    }
+}
+
+impl<Sp: Spawn + ?Sized> SpawnExt for Sp {}
+
+/// Extension trait for `SpawnLocal`.
+pub trait LocalSpawnExt: SpawnLocal {
+    /// Spawns a task that polls the given future to completion on the
+    /// current thread.
+    ///
+    /// This method boxes the future into a `LocalFutureObj` and passes it to
+    /// `spawn_obj_local`, so the future need not be `Send`.
+    fn spawn_local<Fut>(&mut self, future: Fut) -> Result<(), SpawnErrorKind>
+        where Fut: Future<Output = ()> + 'static
+    {
+        self.spawn_obj_local(LocalFutureObj::new(Box::new(future)))
+            .map_err(|err| err.kind)
+    }
+
+    /// Spawns a task that polls the given `LocalFutureObj` to completion on
+    /// the current thread.
+    ///
+    /// Unlike `spawn_obj_local`, the obj is dropped if the spawn fails and
+    /// only the reason is returned.
+    fn spawn_local_obj(&mut self, future: LocalFutureObj<'static, (), dyn Spawn>)
+        -> Result<(), SpawnErrorKind>
+    {
+        self.spawn_obj_local(future)
+            .map_err(|err| err.kind)
+    }
+
+    /// Spawns a task that polls the given future to completion on the
+    /// current thread, returning a `JoinHandle` which resolves to its output.
+    ///
+    /// The handle is only `Send` if the output is, so a non-`Send` result
+    /// can't leave the thread that produced it.
+    #[cfg(feature = "std")]
+    fn spawn_local_with_handle<Fut>(&mut self, future: Fut)
+        -> Result<JoinHandle<Fut::Output>, SpawnErrorKind>
+        where Fut: Future + 'static
+    {
+        let (task, handle) = JoinHandle::pair(future);
+        self.spawn_obj_local(LocalFutureObj::new(Box::new(task)))
+            .map(|()| handle)
+            .map_err(|err| err.kind)
+    }
+}
+
+impl<Sp: SpawnLocal + ?Sized> LocalSpawnExt for Sp {}
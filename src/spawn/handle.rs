@@ -0,0 +1,155 @@
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, Weak};
+#[cfg(feature = "std")]
+use future::FutureObj;
+use spawn::Spawn;
+#[cfg(feature = "std")]
+use spawn::{SpawnObjError, SpawnErrorKind, SpawnShared, ShutdownSpawn};
+#[cfg(feature = "std")]
+use task::{Context, Poll};
+
+/// A spawner which can be cloned and sent to other threads, so that a
+/// future can keep a handle to its executor for use outside of `poll`.
+///
+/// This is implemented for every `Spawn + Clone + Send` type. Handles which
+/// shouldn't keep their executor alive, such as `WeakSpawn`, report
+/// `SpawnErrorKind::shutdown()` once the executor is gone.
+pub trait SpawnHandle: Spawn + Clone + Send {}
+
+impl<Sp: Spawn + Clone + Send> SpawnHandle for Sp {}
+
+/// A shared, owning handle to a spawner.
+///
+/// Every clone refers to the same spawner, which lives for as long as any
+/// `StrongSpawn` does. `WeakSpawn` handles made with `downgrade` don't keep
+/// it alive.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct StrongSpawn<S> {
+    inner: Arc<Mutex<S>>,
+}
+
+#[cfg(feature = "std")]
+impl<S> Clone for StrongSpawn<S> {
+    fn clone(&self) -> StrongSpawn<S> {
+        StrongSpawn { inner: self.inner.clone() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Spawn> StrongSpawn<S> {
+    /// Move `spawner` into a new shared handle.
+    pub fn new(spawner: S) -> StrongSpawn<S> {
+        StrongSpawn { inner: Arc::new(Mutex::new(spawner)) }
+    }
+
+    /// Create a weak handle to the same spawner.
+    pub fn downgrade(&self) -> WeakSpawn<S> {
+        WeakSpawn { inner: Arc::downgrade(&self.inner) }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Spawn> Spawn for StrongSpawn<S> {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawn_obj_shared(future)
+    }
+
+    fn status(&self) -> Result<(), SpawnErrorKind> {
+        self.inner.lock().unwrap().status()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Spawn> SpawnShared for StrongSpawn<S> {
+    fn spawn_obj_shared(
+        &self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.inner.lock().unwrap().spawn_obj(future)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: ShutdownSpawn> ShutdownSpawn for StrongSpawn<S> {
+    fn begin_shutdown(&mut self) {
+        self.inner.lock().unwrap().begin_shutdown()
+    }
+
+    fn poll_drained(&self, cx: &mut Context) -> Poll<()> {
+        self.inner.lock().unwrap().poll_drained(cx)
+    }
+}
+
+/// A weak handle to a spawner shared through `StrongSpawn`.
+///
+/// A `WeakSpawn` doesn't keep the spawner alive. Once every `StrongSpawn`
+/// has been dropped, spawning through it fails with
+/// `SpawnErrorKind::shutdown()` and `upgrade` returns `None`.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct WeakSpawn<S> {
+    inner: Weak<Mutex<S>>,
+}
+
+#[cfg(feature = "std")]
+impl<S> Clone for WeakSpawn<S> {
+    fn clone(&self) -> WeakSpawn<S> {
+        WeakSpawn { inner: self.inner.clone() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Spawn> Default for WeakSpawn<S> {
+    fn default() -> WeakSpawn<S> {
+        WeakSpawn::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Spawn> WeakSpawn<S> {
+    /// Create a weak handle which refers to no spawner, so that spawning
+    /// through it always fails with `SpawnErrorKind::shutdown()`.
+    pub fn new() -> WeakSpawn<S> {
+        WeakSpawn { inner: Weak::new() }
+    }
+
+    /// Attempt to get a strong handle to the spawner, returning `None` if
+    /// it has already been dropped.
+    pub fn upgrade(&self) -> Option<StrongSpawn<S>> {
+        self.inner.upgrade().map(|inner| StrongSpawn { inner })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Spawn> Spawn for WeakSpawn<S> {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawn_obj_shared(future)
+    }
+
+    fn status(&self) -> Result<(), SpawnErrorKind> {
+        match self.upgrade() {
+            Some(strong) => strong.status(),
+            None => Err(SpawnErrorKind::shutdown()),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Spawn> SpawnShared for WeakSpawn<S> {
+    fn spawn_obj_shared(
+        &self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        match self.upgrade() {
+            Some(strong) => strong.spawn_obj_shared(future),
+            None => Err(SpawnObjError { kind: SpawnErrorKind::shutdown(), future }),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use std::any::Any;
+use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use future::{Future, FusedFuture};
+use task::{Context, Poll, Waker};
+use spawn::Spawn;
+
+/// The reason a spawned task failed to produce its output.
+pub enum JoinError {
+    /// The task panicked. The payload is the value the panic was raised
+    /// with.
+    Panicked(Box<dyn Any + Send>),
+    /// The task was dropped by the executor before it completed.
+    Cancelled,
+}
+
+impl JoinError {
+    /// Whether the task panicked.
+    pub fn is_panic(&self) -> bool {
+        match self {
+            JoinError::Panicked(_) => true,
+            JoinError::Cancelled => false,
+        }
+    }
+
+    /// Whether the task was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        match self {
+            JoinError::Panicked(_) => false,
+            JoinError::Cancelled => true,
+        }
+    }
+
+    /// Consume the error, returning the panic payload, e.g. to resume the
+    /// panic with `std::panic::resume_unwind`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the task was cancelled rather than panicking.
+    pub fn into_panic(self) -> Box<dyn Any + Send> {
+        match self {
+            JoinError::Panicked(payload) => payload,
+            JoinError::Cancelled => panic!("called `JoinError::into_panic` on a cancelled task"),
+        }
+    }
+}
+
+impl fmt::Debug for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JoinError::Panicked(_) => f.debug_tuple("Panicked").field(&"..").finish(),
+            JoinError::Cancelled => f.debug_tuple("Cancelled").finish(),
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JoinError::Panicked(_) => write!(f, "task panicked"),
+            JoinError::Cancelled => write!(f, "task was cancelled"),
+        }
+    }
+}
+
+impl Error for JoinError {}
+
+/// The completion slot shared between a `JoinHandle` and its task.
+struct Slot<T> {
+    result: Option<Result<T, JoinError>>,
+    /// Wakes the `JoinHandle` when a result is stored.
+    waker: Option<Waker>,
+    /// Wakes the task so it can observe an abort.
+    task_waker: Option<Waker>,
+    /// Set once the task has completed or been aborted. Whichever happens
+    /// first decides the result.
+    finished: bool,
+    aborted: bool,
+}
+
+type SharedSlot<T> = Arc<Mutex<Slot<T>>>;
+
+fn complete<T>(slot: &SharedSlot<T>, result: Result<T, JoinError>) {
+    let waker = {
+        let mut slot = slot.lock().unwrap();
+        if slot.finished {
+            return;
+        }
+        slot.finished = true;
+        slot.result = Some(result);
+        slot.waker.take()
+    };
+    // Woken outside the lock, since the waker may poll the handle inline.
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// The task handed to the spawner, which catches panics in the future it
+/// runs and reports its outcome to the `JoinHandle`.
+pub(crate) struct JoinTask<F: Future> {
+    future: Option<F>,
+    slot: SharedSlot<F::Output>,
+}
+
+impl<F: Future> Future for JoinTask<F> {
+    type Output = ();
+
+    fn poll(self: PinMut<Self>, cx: &mut Context) -> Poll<()> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        {
+            let mut slot = this.slot.lock().unwrap();
+            if slot.aborted {
+                drop(slot);
+                this.future = None;
+                return Poll::Ready(());
+            }
+            slot.task_waker = Some(cx.waker().clone());
+        }
+        let result = {
+            let future = match &mut this.future {
+                Some(future) => unsafe { PinMut::new_unchecked(future) },
+                None => panic!("JoinTask polled after completion"),
+            };
+            match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+                Ok(Poll::Pending) => return Poll::Pending,
+                Ok(Poll::Ready(output)) => Ok(output),
+                Err(payload) => Err(JoinError::Panicked(payload)),
+            }
+        };
+        // The future is dropped in place, as pinning permits.
+        this.future = None;
+        complete(&this.slot, result);
+        Poll::Ready(())
+    }
+}
+
+impl<F: Future> Drop for JoinTask<F> {
+    fn drop(&mut self) {
+        if self.future.is_some() {
+            // The executor dropped the task before it completed.
+            self.future = None;
+            complete(&self.slot, Err(JoinError::Cancelled));
+        }
+    }
+}
+
+/// The sending half of a `JoinHandle`, for work that isn't a future.
+///
+/// Dropping it without completing reports `JoinError::Cancelled`.
+pub(crate) struct Completer<T> {
+    slot: Option<SharedSlot<T>>,
+}
+
+impl<T> Completer<T> {
+    /// Whether the handle aborted before a result was stored.
+    pub(crate) fn is_aborted(&self) -> bool {
+        self.slot.as_ref().map_or(false, |slot| slot.lock().unwrap().aborted)
+    }
+
+    pub(crate) fn complete(mut self, result: Result<T, JoinError>) {
+        if let Some(slot) = self.slot.take() {
+            complete(&slot, result);
+        }
+    }
+}
+
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot.take() {
+            complete(&slot, Err(JoinError::Cancelled));
+        }
+    }
+}
+
+/// A handle to a spawned task, which resolves to the task's output.
+///
+/// This is created by `SpawnExt::spawn_with_handle`. The handle resolves to
+/// `Err(JoinError::Panicked(..))` if the task panics, in which case the
+/// panic doesn't reach the executor, and to `Err(JoinError::Cancelled)` if
+/// the executor drops the task before it completes or the task is aborted.
+///
+/// By default, dropping the handle detaches the task, which keeps running.
+/// Use `cancel_on_drop` to abort the task when the handle is dropped
+/// instead.
+pub struct JoinHandle<T> {
+    slot: SharedSlot<T>,
+    done: bool,
+    cancel_on_drop: bool,
+}
+
+impl<T> Unpin for JoinHandle<T> {}
+
+impl<T> fmt::Debug for JoinHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("JoinHandle")
+            .field("done", &self.done)
+            .field("cancel_on_drop", &self.cancel_on_drop)
+            .finish()
+    }
+}
+
+impl<T> JoinHandle<T> {
+    /// Create a handle along with the task that reports to it.
+    pub(crate) fn pair<F>(future: F) -> (JoinTask<F>, JoinHandle<T>)
+        where F: Future<Output = T>
+    {
+        let (slot, handle) = JoinHandle::new();
+        (JoinTask { future: Some(future), slot }, handle)
+    }
+
+    /// Create a handle along with a `Completer` that reports to it.
+    pub(crate) fn completer() -> (Completer<T>, JoinHandle<T>) {
+        let (slot, handle) = JoinHandle::new();
+        (Completer { slot: Some(slot) }, handle)
+    }
+
+    fn new() -> (SharedSlot<T>, JoinHandle<T>) {
+        let slot = Arc::new(Mutex::new(Slot {
+            result: None,
+            waker: None,
+            task_waker: None,
+            finished: false,
+            aborted: false,
+        }));
+        (slot.clone(), JoinHandle { slot, done: false, cancel_on_drop: false })
+    }
+
+    /// Abort the task when this handle is dropped, rather than detaching
+    /// it.
+    pub fn cancel_on_drop(mut self) -> JoinHandle<T> {
+        self.cancel_on_drop = true;
+        self
+    }
+
+    /// Abort the task.
+    ///
+    /// The task's future is dropped the next time the executor polls it, and
+    /// this handle resolves to `Err(JoinError::Cancelled)`. If the task has
+    /// already completed, this does nothing and the handle resolves to the
+    /// task's output.
+    pub fn abort(&self) {
+        let (task_waker, waker) = {
+            let mut slot = self.slot.lock().unwrap();
+            if slot.finished {
+                return;
+            }
+            slot.finished = true;
+            slot.aborted = true;
+            slot.result = Some(Err(JoinError::Cancelled));
+            (slot.task_waker.take(), slot.waker.take())
+        };
+        if let Some(waker) = task_waker {
+            waker.wake();
+        }
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        if self.cancel_on_drop && !self.done {
+            self.abort();
+        }
+    }
+}
+
+impl<S: Spawn + ?Sized, T> Future<S> for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        if self.done {
+            panic!("JoinHandle polled after completion");
+        }
+        let result = {
+            let mut slot = self.slot.lock().unwrap();
+            match slot.result.take() {
+                Some(result) => result,
+                None => {
+                    slot.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+        };
+        self.done = true;
+        Poll::Ready(result)
+    }
+}
+
+impl<T> FusedFuture for JoinHandle<T> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
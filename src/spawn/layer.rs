@@ -0,0 +1,267 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+#[cfg(feature = "std")]
+use std::any::Any;
+#[cfg(feature = "std")]
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use future::{Future, FutureObj, LocalFutureObj};
+use task::{Context, Poll};
+use spawn::{Spawn, SpawnLocal, SpawnObjError, SpawnErrorKind};
+
+/// A transformation applied to every task spawned through a `Layered`
+/// spawner, e.g. to trace, measure or supervise it.
+///
+/// Both methods should wrap the task in the same way; there are two only
+/// because sendable and local tasks are represented by different obj types.
+pub trait SpawnLayer {
+    /// Wrap a task spawned through `Spawn::spawn_obj`.
+    fn wrap(&mut self, future: FutureObj<'static, (), dyn Spawn>) -> FutureObj<'static, (), dyn Spawn>;
+
+    /// Wrap a task spawned through `SpawnLocal::spawn_obj_local`.
+    fn wrap_local(&mut self, future: LocalFutureObj<'static, (), dyn Spawn>) -> LocalFutureObj<'static, (), dyn Spawn>;
+
+    /// Compose this layer with another, which is applied to tasks after
+    /// this one, and so ends up outermost.
+    fn and_then<L: SpawnLayer>(self, other: L) -> Stack<Self, L>
+        where Self: Sized
+    {
+        Stack { first: self, second: other }
+    }
+}
+
+/// Two layers applied one after the other, created by
+/// `SpawnLayer::and_then`.
+#[derive(Debug, Clone)]
+pub struct Stack<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: SpawnLayer, B: SpawnLayer> SpawnLayer for Stack<A, B> {
+    fn wrap(&mut self, future: FutureObj<'static, (), dyn Spawn>) -> FutureObj<'static, (), dyn Spawn> {
+        let future = self.first.wrap(future);
+        self.second.wrap(future)
+    }
+
+    fn wrap_local(&mut self, future: LocalFutureObj<'static, (), dyn Spawn>) -> LocalFutureObj<'static, (), dyn Spawn> {
+        let future = self.first.wrap_local(future);
+        self.second.wrap_local(future)
+    }
+}
+
+/// A spawner which passes every task through a `SpawnLayer` before handing
+/// it to the inner spawner.
+///
+/// If the inner spawner rejects a task, the error carries the wrapped task.
+#[derive(Debug, Clone)]
+pub struct Layered<S, L> {
+    inner: S,
+    layer: L,
+}
+
+impl<S: Spawn, L: SpawnLayer> Layered<S, L> {
+    /// Apply `layer` to every task spawned through `inner`.
+    pub fn new(inner: S, layer: L) -> Layered<S, L> {
+        Layered { inner, layer }
+    }
+
+    /// Get a reference to the inner spawner.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the inner spawner.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Consume this wrapper, returning the inner spawner.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Spawn, L: SpawnLayer> Spawn for Layered<S, L> {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        let future = self.layer.wrap(future);
+        self.inner.spawn_obj(future)
+    }
+
+    fn status(&self) -> Result<(), SpawnErrorKind> {
+        self.inner.status()
+    }
+}
+
+impl<S: SpawnLocal, L: SpawnLayer> SpawnLocal for Layered<S, L> {
+    fn spawn_obj_local(
+        &mut self,
+        future: LocalFutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<LocalFutureObj<'static, (), dyn Spawn>>> {
+        let future = self.layer.wrap_local(future);
+        self.inner.spawn_obj_local(future)
+    }
+
+    fn status_local(&self) -> Result<(), SpawnErrorKind> {
+        self.inner.status_local()
+    }
+}
+
+/// An event in the life of a task, reported by the `hooks` layer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TaskEvent {
+    /// The task is being polled for the first time.
+    Started,
+    /// The task has completed, or was dropped after it started.
+    Finished,
+}
+
+/// A layer which calls `hook` when each task starts and finishes.
+///
+/// This is useful for entering and exiting tracing spans or keeping task
+/// metrics.
+pub fn hooks<F>(hook: F) -> Hooks<F>
+    where F: Fn(TaskEvent) + Send + Sync + 'static
+{
+    Hooks { hook: Arc::new(hook) }
+}
+
+/// The layer created by `hooks`.
+pub struct Hooks<F> {
+    hook: Arc<F>,
+}
+
+impl<F> Clone for Hooks<F> {
+    fn clone(&self) -> Hooks<F> {
+        Hooks { hook: self.hook.clone() }
+    }
+}
+
+impl<F> fmt::Debug for Hooks<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .finish()
+    }
+}
+
+struct Hooked<O, F: Fn(TaskEvent)> {
+    future: O,
+    hook: Arc<F>,
+    started: bool,
+}
+
+impl<O: Unpin, F: Fn(TaskEvent)> Unpin for Hooked<O, F> {}
+
+impl<O, F> Future for Hooked<O, F>
+    where O: Future<Output = ()> + Unpin,
+          F: Fn(TaskEvent)
+{
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<()> {
+        if !self.started {
+            self.started = true;
+            (self.hook)(TaskEvent::Started);
+        }
+        poll!(self.future, cx)
+    }
+}
+
+impl<O, F: Fn(TaskEvent)> Drop for Hooked<O, F> {
+    fn drop(&mut self) {
+        if self.started {
+            (self.hook)(TaskEvent::Finished);
+        }
+    }
+}
+
+impl<F> SpawnLayer for Hooks<F>
+    where F: Fn(TaskEvent) + Send + Sync + 'static
+{
+    fn wrap(&mut self, future: FutureObj<'static, (), dyn Spawn>) -> FutureObj<'static, (), dyn Spawn> {
+        FutureObj::new(Box::new(Hooked { future, hook: self.hook.clone(), started: false }))
+    }
+
+    fn wrap_local(&mut self, future: LocalFutureObj<'static, (), dyn Spawn>) -> LocalFutureObj<'static, (), dyn Spawn> {
+        LocalFutureObj::new(Box::new(Hooked { future, hook: self.hook.clone(), started: false }))
+    }
+}
+
+/// A layer which catches panics in tasks and passes their payloads to
+/// `handler`, so that a panicking task doesn't take down the executor.
+///
+/// A task which panics is treated as complete.
+#[cfg(feature = "std")]
+pub fn catch_panics<H>(handler: H) -> CatchPanics<H>
+    where H: Fn(Box<dyn Any + Send>) + Send + Sync + 'static
+{
+    CatchPanics { handler: Arc::new(handler) }
+}
+
+/// The layer created by `catch_panics`.
+#[cfg(feature = "std")]
+pub struct CatchPanics<H> {
+    handler: Arc<H>,
+}
+
+#[cfg(feature = "std")]
+impl<H> Clone for CatchPanics<H> {
+    fn clone(&self) -> CatchPanics<H> {
+        CatchPanics { handler: self.handler.clone() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<H> fmt::Debug for CatchPanics<H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CatchPanics")
+            .finish()
+    }
+}
+
+#[cfg(feature = "std")]
+struct Caught<O, H> {
+    future: O,
+    handler: Arc<H>,
+}
+
+#[cfg(feature = "std")]
+impl<O: Unpin, H> Unpin for Caught<O, H> {}
+
+#[cfg(feature = "std")]
+impl<O, H> Future for Caught<O, H>
+    where O: Future<Output = ()> + Unpin,
+          H: Fn(Box<dyn Any + Send>)
+{
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<()> {
+        let this = &mut *self;
+        let future = &mut this.future;
+        match catch_unwind(AssertUnwindSafe(|| poll!(*future, cx))) {
+            Ok(poll) => poll,
+            Err(payload) => {
+                (this.handler)(payload);
+                Poll::Ready(())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<H> SpawnLayer for CatchPanics<H>
+    where H: Fn(Box<dyn Any + Send>) + Send + Sync + 'static
+{
+    fn wrap(&mut self, future: FutureObj<'static, (), dyn Spawn>) -> FutureObj<'static, (), dyn Spawn> {
+        FutureObj::new(Box::new(Caught { future, handler: self.handler.clone() }))
+    }
+
+    fn wrap_local(&mut self, future: LocalFutureObj<'static, (), dyn Spawn>) -> LocalFutureObj<'static, (), dyn Spawn> {
+        LocalFutureObj::new(Box::new(Caught { future, handler: self.handler.clone() }))
+    }
+}
@@ -1,4 +1,4 @@
-use spawn::{Spawn, SpawnObjError};
+use spawn::{Spawn, SpawnObjError, SpawnErrorKind};
 use future::LocalFutureObj;
 
 pub trait SpawnLocal: Spawn {
@@ -6,6 +6,15 @@ pub trait SpawnLocal: Spawn {
         &mut self,
         future: LocalFutureObj<'static, (), dyn Spawn>
     ) -> Result<(), SpawnObjError<LocalFutureObj<'static, (), dyn Spawn>>>;
+
+    /// Determine whether the executor is able to spawn new local tasks.
+    ///
+    /// This defaults to `status`, but executors which keep local tasks
+    /// separately may report their capacity here.
+    #[inline]
+    fn status_local(&self) -> Result<(), SpawnErrorKind> {
+        self.status()
+    }
 }
 
 
@@ -0,0 +1,243 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+use std::time::Instant;
+use future::{Future, FutureObj, LocalFutureObj};
+use task::{Context, Poll};
+use spawn::{Spawn, SpawnLayer};
+
+/// The upper bounds of the poll duration buckets in `MetricsSnapshot`,
+/// in microseconds. Polls longer than the last bound fall in the final
+/// bucket.
+pub const POLL_BUCKET_BOUNDS_US: [u64; 5] = [10, 100, 1_000, 10_000, 100_000];
+
+/// Statistics for a completed task, passed to the callback given to
+/// `Metrics::on_task_completed`.
+#[derive(Debug, Copy, Clone)]
+pub struct TaskCompleted {
+    /// How many times the task was polled.
+    pub polls: u64,
+    /// The total time spent polling the task.
+    pub busy_time: Duration,
+}
+
+/// A point-in-time copy of the counters kept by `Metrics`, or by an
+/// executor's own metrics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Tasks spawned.
+    pub spawned: usize,
+    /// Tasks which ran to completion.
+    pub completed: usize,
+    /// Tasks spawned and not yet dropped by the executor.
+    pub alive: usize,
+    /// Tasks waiting for the executor to poll them: the backlog it has yet
+    /// to get to. The crate's executors count every task in their queues,
+    /// including those woken to be polled again. The `Metrics` layer can't
+    /// see wakeups, so it only counts tasks which haven't been polled yet.
+    pub queued: usize,
+    /// Polls of all tasks.
+    pub polls: usize,
+    /// Polls by duration, bucketed by `POLL_BUCKET_BOUNDS_US`.
+    pub poll_buckets: [usize; 6],
+}
+
+struct Counters {
+    spawned: AtomicUsize,
+    queued: AtomicUsize,
+    completed: AtomicUsize,
+    dropped: AtomicUsize,
+    polls: AtomicUsize,
+    poll_buckets: [AtomicUsize; 6],
+    on_completed: Option<Box<dyn Fn(TaskCompleted) + Send + Sync>>,
+}
+
+/// A layer which counts the tasks spawned through it and times their polls.
+///
+/// Clones share their counters, so one clone can be installed with
+/// `Layered` while another is kept to take snapshots. Updating the counters
+/// only touches atomics, without allocating.
+#[derive(Clone)]
+pub struct Metrics {
+    counters: Arc<Counters>,
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Metrics")
+            .field("snapshot", &self.snapshot())
+            .finish()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Metrics {
+        Metrics::new()
+    }
+}
+
+impl Metrics {
+    /// Create a new set of counters, all zero.
+    pub fn new() -> Metrics {
+        Metrics::with_callback(None)
+    }
+
+    /// Create a new set of counters which also calls `callback` whenever a
+    /// task completes.
+    pub fn on_task_completed<F>(callback: F) -> Metrics
+        where F: Fn(TaskCompleted) + Send + Sync + 'static
+    {
+        Metrics::with_callback(Some(Box::new(callback)))
+    }
+
+    fn with_callback(on_completed: Option<Box<dyn Fn(TaskCompleted) + Send + Sync>>) -> Metrics {
+        Metrics {
+            counters: Arc::new(Counters {
+                spawned: AtomicUsize::new(0),
+                queued: AtomicUsize::new(0),
+                completed: AtomicUsize::new(0),
+                dropped: AtomicUsize::new(0),
+                polls: AtomicUsize::new(0),
+                poll_buckets: [
+                    AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0),
+                    AtomicUsize::new(0), AtomicUsize::new(0), AtomicUsize::new(0),
+                ],
+                on_completed,
+            }),
+        }
+    }
+
+    /// Read the current values of the counters.
+    ///
+    /// The counters are read one at a time, so a snapshot taken while tasks
+    /// are running may be slightly inconsistent.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let c = &self.counters;
+        let spawned = c.spawned.load(Ordering::Relaxed);
+        let dropped = c.dropped.load(Ordering::Relaxed);
+        let mut poll_buckets = [0; 6];
+        for (bucket, counter) in poll_buckets.iter_mut().zip(c.poll_buckets.iter()) {
+            *bucket = counter.load(Ordering::Relaxed);
+        }
+        MetricsSnapshot {
+            spawned,
+            completed: c.completed.load(Ordering::Relaxed),
+            alive: spawned.saturating_sub(dropped),
+            queued: c.queued.load(Ordering::Relaxed),
+            polls: c.polls.load(Ordering::Relaxed),
+            poll_buckets,
+        }
+    }
+
+    fn measure<O>(&self, future: O) -> Measured<O> {
+        self.task_spawned();
+        self.task_enqueued();
+        Measured {
+            future,
+            metrics: self.clone(),
+            stats: TaskStats::default(),
+        }
+    }
+
+    // The methods below are how executors record into a set of counters
+    // from their own task wrappers and queues.
+
+    pub(crate) fn task_spawned(&self) {
+        self.counters.spawned.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn task_enqueued(&self) {
+        self.counters.queued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn task_dequeued(&self) {
+        self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn task_dropped(&self) {
+        self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Time a poll of a task, counting it in `stats` as well, and count the
+    /// task as completed if it finished.
+    pub(crate) fn poll<F>(&self, stats: &mut TaskStats, poll: F) -> Poll<()>
+        where F: FnOnce() -> Poll<()>
+    {
+        stats.polls += 1;
+        let start = Instant::now();
+        let ret = poll();
+        let elapsed = start.elapsed();
+
+        stats.busy_time += elapsed;
+        let c = &self.counters;
+        c.polls.fetch_add(1, Ordering::Relaxed);
+        c.poll_buckets[bucket(elapsed)].fetch_add(1, Ordering::Relaxed);
+        if ret.is_ready() {
+            c.completed.fetch_add(1, Ordering::Relaxed);
+            if let Some(callback) = &c.on_completed {
+                callback(TaskCompleted { polls: stats.polls, busy_time: stats.busy_time });
+            }
+        }
+        ret
+    }
+}
+
+/// What a task wrapper keeps about its own task, for `TaskCompleted`.
+#[derive(Debug, Default)]
+pub(crate) struct TaskStats {
+    polls: u64,
+    busy_time: Duration,
+}
+
+struct Measured<O> {
+    future: O,
+    metrics: Metrics,
+    stats: TaskStats,
+}
+
+fn bucket(duration: Duration) -> usize {
+    let micros = duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros());
+    POLL_BUCKET_BOUNDS_US.iter()
+        .position(|&bound| micros < bound)
+        .unwrap_or(POLL_BUCKET_BOUNDS_US.len())
+}
+
+impl<O: Unpin> Unpin for Measured<O> {}
+
+impl<O> Future for Measured<O>
+    where O: Future<Output = ()> + Unpin
+{
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<()> {
+        let this = &mut *self;
+        if this.stats.polls == 0 {
+            this.metrics.task_dequeued();
+        }
+        let future = &mut this.future;
+        this.metrics.poll(&mut this.stats, || poll!(*future, cx))
+    }
+}
+
+impl<O> Drop for Measured<O> {
+    fn drop(&mut self) {
+        if self.stats.polls == 0 {
+            self.metrics.task_dequeued();
+        }
+        self.metrics.task_dropped();
+    }
+}
+
+impl SpawnLayer for Metrics {
+    fn wrap(&mut self, future: FutureObj<'static, (), dyn Spawn>) -> FutureObj<'static, (), dyn Spawn> {
+        FutureObj::new(Box::new(self.measure(future)))
+    }
+
+    fn wrap_local(&mut self, future: LocalFutureObj<'static, (), dyn Spawn>) -> LocalFutureObj<'static, (), dyn Spawn> {
+        LocalFutureObj::new(Box::new(self.measure(future)))
+    }
+}
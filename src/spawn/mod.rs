@@ -1,10 +1,77 @@
-use std::fmt;
+use core::fmt;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
 use future::FutureObj;
-use std::ops::{Deref, DerefMut};
+#[cfg(feature = "alloc")]
+use core::ops::{Deref, DerefMut};
 
 mod local;
 pub use self::local::SpawnLocal;
 
+#[cfg(feature = "std")]
+mod join;
+#[cfg(feature = "std")]
+pub use self::join::{JoinHandle, JoinError};
+
+#[cfg(feature = "std")]
+mod blocking;
+#[cfg(feature = "std")]
+pub use self::blocking::{SpawnBlocking, BlockingHandle, BlockingTask};
+
+mod shared;
+pub use self::shared::SpawnShared;
+
+mod priority;
+pub use self::priority::{SpawnPriority, Priority};
+
+#[cfg(feature = "alloc")]
+mod layer;
+#[cfg(feature = "alloc")]
+pub use self::layer::{SpawnLayer, Layered, Stack, TaskEvent, hooks, Hooks};
+#[cfg(feature = "std")]
+pub use self::layer::{catch_panics, CatchPanics};
+
+#[cfg(feature = "std")]
+mod metrics;
+#[cfg(feature = "std")]
+pub use self::metrics::{Metrics, MetricsSnapshot, TaskCompleted, POLL_BUCKET_BOUNDS_US};
+#[cfg(feature = "std")]
+pub(crate) use self::metrics::TaskStats;
+
+#[cfg(feature = "alloc")]
+mod named;
+#[cfg(feature = "alloc")]
+pub use self::named::SpawnNamed;
+
+mod shutdown;
+pub use self::shutdown::{ShutdownSpawn, Drain};
+
+#[cfg(feature = "std")]
+mod scope;
+#[cfg(feature = "std")]
+pub use self::scope::{scope, Scope, ScopeHandle};
+
+mod handle;
+pub use self::handle::SpawnHandle;
+#[cfg(feature = "std")]
+pub use self::handle::{StrongSpawn, WeakSpawn};
+
+#[cfg(feature = "alloc")]
+mod ext;
+#[cfg(feature = "alloc")]
+pub use self::ext::{SpawnExt, LocalSpawnExt};
+
+mod timer;
+pub use self::timer::TimerSpawn;
+
+#[cfg(feature = "std")]
+mod reactor;
+#[cfg(feature = "std")]
+pub use self::reactor::{ReactorSpawn, RawSource, Ready};
+
+mod no_spawn;
+pub use self::no_spawn::NoSpawn;
+
 /// Spawns tasks that poll futures to completion onto its associated task
 /// executor.
 ///
@@ -38,6 +105,7 @@ pub trait Spawn {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<S: Spawn> Spawn for Box<S> {
     fn spawn_obj(
         &mut self,
@@ -53,13 +121,25 @@ impl<S: Spawn> Spawn for Box<S> {
 
 /// Provides the reason that an executor was unable to spawn.
 pub struct SpawnErrorKind {
-    _hidden: (),
+    inner: SpawnErrorKindInner,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum SpawnErrorKindInner {
+    Shutdown,
+    NotSupported,
+    QueueFull,
 }
 
 impl fmt::Debug for SpawnErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.inner {
+            SpawnErrorKindInner::Shutdown => "shutdown",
+            SpawnErrorKindInner::NotSupported => "not supported",
+            SpawnErrorKindInner::QueueFull => "queue full",
+        };
         f.debug_tuple("SpawnErrorKind")
-            .field(&"shutdown")
+            .field(&kind)
             .finish()
     }
 }
@@ -67,12 +147,35 @@ impl fmt::Debug for SpawnErrorKind {
 impl SpawnErrorKind {
     /// Spawning is failing because the executor has been shut down.
     pub fn shutdown() -> SpawnErrorKind {
-        SpawnErrorKind { _hidden: () }
+        SpawnErrorKind { inner: SpawnErrorKindInner::Shutdown }
     }
 
     /// Check whether this error is the `shutdown` error.
     pub fn is_shutdown(&self) -> bool {
-        true
+        self.inner == SpawnErrorKindInner::Shutdown
+    }
+
+    /// Spawning is failing because there is no executor to spawn onto, as
+    /// with `NoSpawn`.
+    pub fn not_supported() -> SpawnErrorKind {
+        SpawnErrorKind { inner: SpawnErrorKindInner::NotSupported }
+    }
+
+    /// Check whether this error is the `not_supported` error.
+    pub fn is_not_supported(&self) -> bool {
+        self.inner == SpawnErrorKindInner::NotSupported
+    }
+
+    /// Spawning is failing because the executor is at capacity. Unlike
+    /// `shutdown`, this is temporary: the spawn may succeed once some of
+    /// the running tasks have completed.
+    pub fn queue_full() -> SpawnErrorKind {
+        SpawnErrorKind { inner: SpawnErrorKindInner::QueueFull }
+    }
+
+    /// Check whether this error is the `queue_full` error.
+    pub fn is_queue_full(&self) -> bool {
+        self.inner == SpawnErrorKindInner::QueueFull
     }
 }
 
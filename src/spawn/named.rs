@@ -0,0 +1,24 @@
+use alloc::borrow::Cow;
+use future::FutureObj;
+use spawn::{Spawn, SpawnObjError};
+
+/// A spawner which can attach a name to the tasks it spawns, for use in
+/// diagnostics such as panic messages and task listings.
+///
+/// The provided method drops the name and calls `spawn_obj`, so any spawner
+/// can opt in with an empty impl.
+pub trait SpawnNamed: Spawn {
+    /// Spawns a new task with the given name and future.
+    ///
+    /// # Errors
+    ///
+    /// This fails under the same conditions as `spawn_obj`.
+    fn spawn_obj_named(
+        &mut self,
+        name: Cow<'static, str>,
+        future: FutureObj<'static, (), dyn Spawn>,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        drop(name);
+        self.spawn_obj(future)
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(feature = "alloc")]
+use spawn::SpawnNamed;
+use spawn::{Spawn, SpawnLocal, SpawnShared, SpawnPriority, SpawnObjError, SpawnErrorKind};
+use future::{FutureObj, LocalFutureObj};
+
+/// A spawner which rejects every task.
+///
+/// This is for polling futures where there is no executor to spawn onto,
+/// such as in `FutureExt::now_or_never` or in tests. Every spawn fails with
+/// `SpawnErrorKind::not_supported()`, which futures that spawn optionally
+/// can check for to degrade gracefully, e.g. by running the work inline.
+///
+/// ```
+/// #![feature(futures_api)]
+/// # extern crate specialized_futures;
+/// use specialized_futures::{Context, Spawn};
+/// use specialized_futures::spawn::NoSpawn;
+/// use specialized_futures::task::{noop_local_waker, noop_waker};
+///
+/// # fn main() {
+/// let (lw, w) = (noop_local_waker(), noop_waker());
+/// let mut spawner = NoSpawn;
+/// let mut cx = Context::new(&lw, &w, &mut spawner);
+/// assert!(cx.spawner().status().unwrap_err().is_not_supported());
+/// # }
+/// ```
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NoSpawn;
+
+impl Spawn for NoSpawn {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        Err(SpawnObjError { kind: SpawnErrorKind::not_supported(), future })
+    }
+
+    fn status(&self) -> Result<(), SpawnErrorKind> {
+        Err(SpawnErrorKind::not_supported())
+    }
+}
+
+impl SpawnLocal for NoSpawn {
+    fn spawn_obj_local(
+        &mut self,
+        future: LocalFutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<LocalFutureObj<'static, (), dyn Spawn>>> {
+        Err(SpawnObjError { kind: SpawnErrorKind::not_supported(), future })
+    }
+}
+
+impl SpawnShared for NoSpawn {
+    fn spawn_obj_shared(
+        &self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        Err(SpawnObjError { kind: SpawnErrorKind::not_supported(), future })
+    }
+}
+
+impl SpawnPriority for NoSpawn {}
+
+#[cfg(feature = "alloc")]
+impl SpawnNamed for NoSpawn {}
@@ -0,0 +1,41 @@
+use future::FutureObj;
+use spawn::{Spawn, SpawnObjError};
+
+/// The scheduling priority of a spawned task.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Batch work which should only run when nothing else is ready.
+    Low,
+    /// The priority of tasks spawned through `Spawn::spawn_obj`.
+    Normal,
+    /// Latency-critical work which should run ahead of everything else.
+    High,
+}
+
+impl Default for Priority {
+    fn default() -> Priority {
+        Priority::Normal
+    }
+}
+
+/// A spawner which can schedule tasks according to a `Priority`.
+///
+/// The provided method ignores the priority and calls `spawn_obj`, so any
+/// spawner can opt in with an empty impl. Executors which can prioritize
+/// tasks should override it, and should make sure that lower-priority work
+/// still makes progress under a steady stream of higher-priority tasks.
+pub trait SpawnPriority: Spawn {
+    /// Spawns a new task with the given future and priority.
+    ///
+    /// # Errors
+    ///
+    /// This fails under the same conditions as `spawn_obj`.
+    fn spawn_obj_with_priority(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>,
+        priority: Priority,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        let _ = priority;
+        self.spawn_obj(future)
+    }
+}
@@ -0,0 +1,91 @@
+use core::fmt;
+use std::io;
+use core::marker::Unpin;
+use core::ops;
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+#[cfg(windows)]
+use std::os::windows::io::RawSocket;
+use future::Future;
+use spawn::Spawn;
+
+/// The raw OS handle type which can be registered with a `ReactorSpawn`.
+#[cfg(unix)]
+pub type RawSource = RawFd;
+
+/// The raw OS handle type which can be registered with a `ReactorSpawn`.
+#[cfg(windows)]
+pub type RawSource = RawSocket;
+
+/// A set of IO readiness events, used both to express interest when
+/// registering a source and to report which events occurred.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Ready(u8);
+
+const READABLE: u8 = 0b01;
+const WRITABLE: u8 = 0b10;
+
+impl Ready {
+    /// Returns the empty set of events.
+    pub fn empty() -> Ready {
+        Ready(0)
+    }
+
+    /// Returns a set containing only readable readiness.
+    pub fn readable() -> Ready {
+        Ready(READABLE)
+    }
+
+    /// Returns a set containing only writable readiness.
+    pub fn writable() -> Ready {
+        Ready(WRITABLE)
+    }
+
+    /// Returns `true` if the set contains no events.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if the set includes readable readiness.
+    pub fn is_readable(self) -> bool {
+        self.0 & READABLE != 0
+    }
+
+    /// Returns `true` if the set includes writable readiness.
+    pub fn is_writable(self) -> bool {
+        self.0 & WRITABLE != 0
+    }
+}
+
+impl ops::BitOr for Ready {
+    type Output = Ready;
+
+    fn bitor(self, other: Ready) -> Ready {
+        Ready(self.0 | other.0)
+    }
+}
+
+impl fmt::Debug for Ready {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Ready")
+            .field("readable", &self.is_readable())
+            .field("writable", &self.is_writable())
+            .finish()
+    }
+}
+
+/// A spawner whose executor also runs an IO reactor.
+///
+/// Leaf futures polled with a `Context<S>` where `S: ReactorSpawn` can
+/// register OS handles for readiness notifications directly with their
+/// executor through `cx.spawner()`.
+pub trait ReactorSpawn: Spawn {
+    /// The future returned by `register`.
+    type Readiness: Future<Self, Output = io::Result<Ready>> + Unpin;
+
+    /// Waits for any of the events in `interest` to occur on `source`.
+    ///
+    /// The returned future resolves with the events that occurred once the
+    /// OS reports them. Dropping it deregisters the interest.
+    fn register(&mut self, source: RawSource, interest: Ready) -> Self::Readiness;
+}
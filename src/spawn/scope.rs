@@ -0,0 +1,122 @@
+use std::boxed::Box;
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::{Future, FusedFuture, LocalFutureObj};
+use stream::{StreamExt, FuturesUnordered};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Runs a group of tasks which may borrow from the enclosing stack frame.
+///
+/// The closure is called immediately with a `ScopeHandle`, through which it
+/// can spawn futures that borrow data living at least as long as `'a`. The
+/// returned future resolves to the closure's return value once every
+/// spawned future has completed.
+///
+/// # Mechanism
+///
+/// Scoped tasks are not handed to the executor. They are owned and polled
+/// by the `Scope` future itself, each with its own waker, so they run
+/// concurrently with each other but only on the task which polls the scope.
+/// This is what makes the borrows sound without any blocking: if the
+/// `Scope` is dropped before it completes, the unfinished children are
+/// dropped with it, so none of them can outlive the data it borrows.
+///
+/// The children are polled with the `Context` the scope is polled with, so
+/// they can still spawn `'static` tasks onto the executor through
+/// `cx.spawner()`. They never run in parallel with each other or with the
+/// task that owns the scope, though; spawn the work itself onto the
+/// executor when that matters.
+///
+/// # Panics
+///
+/// A panic in a child propagates out of the `Scope`'s `poll`, and the other
+/// children are dropped while it unwinds. Wrap children in
+/// `FutureExt::catch_unwind` to handle their panics individually.
+///
+/// ```
+/// #![feature(pin, arbitrary_self_types, futures_api)]
+/// # extern crate specialized_futures;
+/// use specialized_futures::FutureExt;
+/// use specialized_futures::future::lazy;
+/// use specialized_futures::spawn::scope;
+///
+/// # fn main() {
+/// let data = vec![1, 2, 3];
+/// let mut sum = 0;
+/// {
+///     let (sum, data) = (&mut sum, &data);
+///     let fut = scope(|s| {
+///         s.spawn(lazy(move |_| *sum = data.iter().sum()));
+///     });
+///     assert_eq!(fut.now_or_never(), Some(()));
+/// }
+/// assert_eq!(sum, 6);
+/// # }
+/// ```
+pub fn scope<'a, F, R>(f: F) -> Scope<'a, R>
+    where F: FnOnce(&mut ScopeHandle<'a>) -> R
+{
+    let mut handle = ScopeHandle { children: FuturesUnordered::new() };
+    let result = f(&mut handle);
+    Scope { result: Some(result), children: handle.children }
+}
+
+/// A handle for spawning scoped tasks, passed to the closure given to
+/// `scope`.
+pub struct ScopeHandle<'a> {
+    children: FuturesUnordered<LocalFutureObj<'a, (), dyn Spawn>>,
+}
+
+impl<'a> fmt::Debug for ScopeHandle<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ScopeHandle")
+            .field("children", &self.children.len())
+            .finish()
+    }
+}
+
+impl<'a> ScopeHandle<'a> {
+    /// Spawn a task which the scope will run to completion before it
+    /// resolves.
+    pub fn spawn<Fut>(&mut self, future: Fut)
+        where Fut: Future<Output = ()> + 'a
+    {
+        self.children.push(LocalFutureObj::new(Box::new(future)));
+    }
+}
+
+/// Future for the `scope` function.
+pub struct Scope<'a, R> {
+    result: Option<R>,
+    children: FuturesUnordered<LocalFutureObj<'a, (), dyn Spawn>>,
+}
+
+impl<'a, R> Unpin for Scope<'a, R> {}
+
+impl<'a, R> fmt::Debug for Scope<'a, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("children", &self.children.len())
+            .finish()
+    }
+}
+
+impl<'a, R> Future for Scope<'a, R> {
+    type Output = R;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<R> {
+        if self.result.is_none() {
+            panic!("Scope polled after completion");
+        }
+        while let Some(()) = ready!(self.children.poll_next_unpin(cx)) {}
+        Poll::Ready(self.result.take().unwrap())
+    }
+}
+
+impl<'a, R> FusedFuture for Scope<'a, R> {
+    fn is_terminated(&self) -> bool {
+        self.result.is_none()
+    }
+}
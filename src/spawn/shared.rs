@@ -0,0 +1,32 @@
+use future::FutureObj;
+use spawn::{Spawn, SpawnObjError, SpawnErrorKind};
+
+/// A spawner which can spawn through a shared reference.
+///
+/// This is for executors whose task queue is already thread-safe, so that a
+/// single handle can be used from several places at once without an
+/// external lock. Every `&T` where `T: SpawnShared` is itself a `Spawn`.
+pub trait SpawnShared: Spawn {
+    /// Spawns a new task with the given future, like `Spawn::spawn_obj`.
+    ///
+    /// # Errors
+    ///
+    /// This fails under the same conditions as `spawn_obj`.
+    fn spawn_obj_shared(
+        &self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>>;
+}
+
+impl<'a, Sp: SpawnShared + ?Sized> Spawn for &'a Sp {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        (**self).spawn_obj_shared(future)
+    }
+
+    fn status(&self) -> Result<(), SpawnErrorKind> {
+        (**self).status()
+    }
+}
@@ -0,0 +1,59 @@
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A spawner which can stop accepting tasks and report when the tasks it
+/// already accepted have finished.
+pub trait ShutdownSpawn: Spawn {
+    /// Stop accepting new tasks.
+    ///
+    /// After this is called, `spawn_obj` fails with
+    /// `SpawnErrorKind::shutdown()`, including for tasks spawned by the
+    /// tasks which are still running. Tasks which were already accepted are
+    /// still run to completion.
+    fn begin_shutdown(&mut self);
+
+    /// Check whether every task accepted before `begin_shutdown` has
+    /// finished.
+    ///
+    /// If they haven't, the current task is woken once the last of them
+    /// finishes.
+    fn poll_drained(&self, cx: &mut Context) -> Poll<()>;
+
+    /// A future which resolves once every accepted task has finished.
+    ///
+    /// This is usually called after `begin_shutdown`; otherwise new tasks
+    /// may keep the future from resolving.
+    fn drain(&self) -> Drain<Self>
+        where Self: Sized
+    {
+        Drain { spawner: self, done: false }
+    }
+}
+
+/// Future for the `ShutdownSpawn::drain` method.
+#[derive(Debug)]
+pub struct Drain<'a, Sp: 'a> {
+    spawner: &'a Sp,
+    done: bool,
+}
+
+impl<'a, Sp: ShutdownSpawn + 'a> Future for Drain<'a, Sp> {
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<()> {
+        if self.done {
+            panic!("Drain polled after completion");
+        }
+        ready!(self.spawner.poll_drained(cx));
+        self.done = true;
+        Poll::Ready(())
+    }
+}
+
+impl<'a, Sp> FusedFuture for Drain<'a, Sp> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,26 @@
+use core::marker::Unpin;
+use core::time::Duration;
+use future::Future;
+use spawn::Spawn;
+
+/// A spawner whose executor can also provide timers.
+///
+/// Futures polled with a `Context<S>` where `S: TimerSpawn` can request a
+/// sleep directly from their executor through `cx.spawner()`, instead of
+/// depending on a global timer.
+pub trait TimerSpawn: Spawn {
+    /// The future returned by `sleep`.
+    ///
+    /// `Interval` keeps its sleep in an `Option`, replacing it at every tick,
+    /// and is polled through `&mut self` by `next_tick` and `poll_tick`.
+    /// Requiring `Unpin` lets it do so without being pinned itself, which
+    /// keeps `Interval` and `NextTick` `Unpin` too.
+    /// A sleep is normally a handle to an entry in the executor's timer, so
+    /// the bound costs implementations nothing.
+    type Sleep: Future<Self, Output = ()> + Unpin;
+
+    /// Creates a future which resolves once `duration` has elapsed.
+    ///
+    /// Dropping the returned future before it resolves cancels the timer.
+    fn sleep(&mut self, duration: Duration) -> Self::Sleep;
+}
@@ -0,0 +1,88 @@
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::Future;
+use stream::{Stream, StreamExt, FusedStream, FuturesUnordered};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `StreamExt::buffer_unordered` method.
+pub struct BufferUnordered<St, S: Spawn + ?Sized = dyn Spawn>
+    where St: Stream<S>
+{
+    stream: St,
+    in_progress: FuturesUnordered<St::Item>,
+    max: usize,
+    stream_done: bool,
+}
+
+impl<St, S> Unpin for BufferUnordered<St, S>
+    where St: Stream<S> + Unpin,
+          S: Spawn + ?Sized
+{}
+
+impl<St, S> fmt::Debug for BufferUnordered<St, S>
+    where St: Stream<S> + fmt::Debug,
+          S: Spawn + ?Sized
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufferUnordered")
+            .field("stream", &self.stream)
+            .field("in_progress", &self.in_progress)
+            .field("max", &self.max)
+            .finish()
+    }
+}
+
+impl<St, S> BufferUnordered<St, S>
+    where St: Stream<S>,
+          St::Item: Future<S>,
+          S: Spawn + ?Sized
+{
+    pub(super) fn new(stream: St, n: usize) -> BufferUnordered<St, S> {
+        assert!(n > 0, "buffer_unordered requires a buffer size of at least 1");
+        BufferUnordered {
+            stream,
+            in_progress: FuturesUnordered::new(),
+            max: n,
+            stream_done: false,
+        }
+    }
+}
+
+impl<St, S> Stream<S> for BufferUnordered<St, S>
+    where St: Stream<S>,
+          St::Item: Future<S>,
+          S: Spawn + ?Sized
+{
+    type Item = <St::Item as Future<S>>::Output;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Self::Item>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+
+        // Fill the buffer, but never beyond `max` futures in flight.
+        while !this.stream_done && this.in_progress.len() < this.max {
+            let stream = unsafe { PinMut::new_unchecked(&mut this.stream) };
+            match stream.poll_next(cx) {
+                Poll::Ready(Some(fut)) => this.in_progress.push(fut),
+                Poll::Ready(None) => this.stream_done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        match this.in_progress.poll_next_unpin(cx) {
+            Poll::Ready(Some(output)) => Poll::Ready(Some(output)),
+            Poll::Ready(None) if this.stream_done => Poll::Ready(None),
+            _ => Poll::Pending,
+        }
+    }
+}
+
+impl<St, S> FusedStream for BufferUnordered<St, S>
+    where St: Stream<S>,
+          S: Spawn + ?Sized
+{
+    fn is_terminated(&self) -> bool {
+        self.stream_done && self.in_progress.is_empty()
+    }
+}
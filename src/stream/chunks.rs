@@ -0,0 +1,120 @@
+use alloc::vec::Vec;
+use core::mem::{self, PinMut};
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `chunks` combinator.
+///
+/// This is created by the `StreamExt::chunks` method.
+#[derive(Debug)]
+pub struct Chunks<St, T> {
+    stream: St,
+    items: Vec<T>,
+    cap: usize,
+    done: bool,
+}
+
+impl<St, T> Chunks<St, T> {
+    pub(super) fn new(stream: St, cap: usize) -> Chunks<St, T> {
+        assert!(cap > 0, "chunks requires a chunk size of at least 1");
+        Chunks { stream, items: Vec::with_capacity(cap), cap, done: false }
+    }
+
+    fn take(&mut self) -> Vec<T> {
+        let cap = self.cap;
+        mem::replace(&mut self.items, Vec::with_capacity(cap))
+    }
+}
+
+impl<St: Stream<S>, S: Spawn + ?Sized> Stream<S> for Chunks<St, St::Item> {
+    type Item = Vec<St::Item>;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Self::Item>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if this.done {
+            return Poll::Ready(None);
+        }
+        loop {
+            let item = ready!(unsafe { PinMut::new_unchecked(&mut this.stream) }.poll_next(cx));
+            match item {
+                Some(item) => {
+                    this.items.push(item);
+                    if this.items.len() >= this.cap {
+                        return Poll::Ready(Some(this.take()));
+                    }
+                }
+                None => {
+                    this.done = true;
+                    // The final chunk may be short, but is never empty.
+                    return if this.items.is_empty() {
+                        Poll::Ready(None)
+                    } else {
+                        Poll::Ready(Some(mem::replace(&mut this.items, Vec::new())))
+                    };
+                }
+            }
+        }
+    }
+}
+
+impl<St, T> FusedStream for Chunks<St, T> {
+    fn is_terminated(&self) -> bool {
+        self.done && self.items.is_empty()
+    }
+}
+
+/// Stream for the `ready_chunks` combinator.
+///
+/// This is created by the `StreamExt::ready_chunks` method.
+#[derive(Debug)]
+pub struct ReadyChunks<St> {
+    stream: St,
+    cap: usize,
+    done: bool,
+}
+
+impl<St> ReadyChunks<St> {
+    pub(super) fn new(stream: St, cap: usize) -> ReadyChunks<St> {
+        assert!(cap > 0, "ready_chunks requires a chunk size of at least 1");
+        ReadyChunks { stream, cap, done: false }
+    }
+}
+
+impl<St: Stream<S>, S: Spawn + ?Sized> Stream<S> for ReadyChunks<St> {
+    type Item = Vec<St::Item>;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Self::Item>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if this.done {
+            return Poll::Ready(None);
+        }
+        let mut items = Vec::new();
+        while items.len() < this.cap {
+            let stream = unsafe { PinMut::new_unchecked(&mut this.stream) };
+            match stream.poll_next(cx) {
+                Poll::Ready(Some(item)) => items.push(item),
+                Poll::Ready(None) => {
+                    this.done = true;
+                    break;
+                }
+                // The stream has registered the task to be woken, which is
+                // only needed if nothing was collected.
+                Poll::Pending => break,
+            }
+        }
+        if !items.is_empty() {
+            Poll::Ready(Some(items))
+        } else if this.done {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<St> FusedStream for ReadyChunks<St> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,53 @@
+use core::marker::Unpin;
+use core::mem::{self, PinMut};
+use future::{Future, FusedFuture};
+use stream::Stream;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `StreamExt::collect` method.
+#[derive(Debug)]
+pub struct Collect<St, C> {
+    stream: St,
+    collection: C,
+    done: bool,
+}
+
+impl<St: Unpin, C> Unpin for Collect<St, C> {}
+
+impl<St, C: Default> Collect<St, C> {
+    pub(super) fn new(stream: St) -> Collect<St, C> {
+        Collect { stream, collection: Default::default(), done: false }
+    }
+}
+
+impl<S, St, C> Future<S> for Collect<St, C>
+    where S: Spawn + ?Sized,
+          St: Stream<S>,
+          C: Default + Extend<St::Item>
+{
+    type Output = C;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<C> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if this.done {
+            panic!("Collect polled after completion");
+        }
+        loop {
+            let stream = unsafe { PinMut::new_unchecked(&mut this.stream) };
+            match ready!(stream.poll_next(cx)) {
+                Some(item) => this.collection.extend(Some(item)),
+                None => {
+                    this.done = true;
+                    return Poll::Ready(mem::replace(&mut this.collection, Default::default()));
+                }
+            }
+        }
+    }
+}
+
+impl<St, C> FusedFuture for Collect<St, C> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,41 @@
+use core::fmt;
+use core::marker::{PhantomData, Unpin};
+use core::mem::PinMut;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `empty` function.
+pub struct Empty<T> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T> Unpin for Empty<T> {}
+
+impl<T> fmt::Debug for Empty<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Empty")
+            .finish()
+    }
+}
+
+/// Creates a stream which contains no elements.
+///
+/// The returned stream will always return `Ready(None)` when polled.
+pub fn empty<T>() -> Empty<T> {
+    Empty { _phantom: PhantomData }
+}
+
+impl<T, S: Spawn + ?Sized> Stream<S> for Empty<T> {
+    type Item = T;
+
+    fn poll_next(self: PinMut<Self>, _: &mut Context<S>) -> Poll<Option<T>> {
+        Poll::Ready(None)
+    }
+}
+
+impl<T> FusedStream for Empty<T> {
+    fn is_terminated(&self) -> bool {
+        true
+    }
+}
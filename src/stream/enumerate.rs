@@ -0,0 +1,40 @@
+use core::mem::PinMut;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `StreamExt::enumerate` method.
+#[derive(Debug)]
+pub struct Enumerate<St> {
+    stream: St,
+    count: usize,
+}
+
+impl<St> Enumerate<St> {
+    pub(super) fn new(stream: St) -> Enumerate<St> {
+        Enumerate { stream, count: 0 }
+    }
+}
+
+impl<St: Stream<S>, S: Spawn + ?Sized> Stream<S> for Enumerate<St> {
+    type Item = (usize, St::Item);
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Self::Item>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        let stream = unsafe { PinMut::new_unchecked(&mut this.stream) };
+        match ready!(stream.poll_next(cx)) {
+            Some(item) => {
+                let count = this.count;
+                this.count += 1;
+                Poll::Ready(Some((count, item)))
+            }
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+impl<St: FusedStream> FusedStream for Enumerate<St> {
+    fn is_terminated(&self) -> bool {
+        self.stream.is_terminated()
+    }
+}
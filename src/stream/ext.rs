@@ -0,0 +1,252 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::Future;
+use sink::Sink;
+use stream::{Stream, Next, Map, Filter, Fuse, Select, Forward, Collect, ForEach, select};
+use stream::{Zip, Enumerate};
+#[cfg(feature = "alloc")]
+use stream::{Chunks, ReadyChunks, SplitSink, SplitStream};
+#[cfg(feature = "std")]
+use stream::{ForEachConcurrent, ForEachSpawned, BufferUnordered, Buffered};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// An extension trait for `Stream`s that provides a variety of convenient
+/// combinator functions.
+pub trait StreamExt<S: Spawn + ?Sized = dyn Spawn>: Stream<S> {
+    /// A convenience for calling `Stream::poll_next` on `Unpin` stream
+    /// types, such as streams stored in plain fields of a hand-written
+    /// combinator.
+    fn poll_next_unpin(&mut self, cx: &mut Context<S>) -> Poll<Option<Self::Item>>
+        where Self: Unpin + Sized
+    {
+        PinMut::new(self).poll_next(cx)
+    }
+
+    /// Creates a future that resolves to the next item in the stream.
+    ///
+    /// The returned future resolves to `None` once the stream has finished.
+    fn next(&mut self) -> Next<Self>
+        where Self: Sized + Unpin
+    {
+        Next::new(self)
+    }
+
+    /// Maps this stream's items to a different type, returning a new stream of
+    /// the resulting type.
+    fn map<T, F>(self, f: F) -> Map<Self, F>
+        where F: FnMut(Self::Item) -> T,
+              Self: Sized
+    {
+        Map::new(self, f)
+    }
+
+    /// Filters the values produced by this stream according to the provided
+    /// asynchronous predicate.
+    ///
+    /// Each item is passed by reference to `f`, and the item is yielded only
+    /// if the future returned by `f` resolves to `true`.
+    fn filter<Fut, F>(self, f: F) -> Filter<Self, Fut, F, S>
+        where F: FnMut(&Self::Item) -> Fut,
+              Fut: Future<S, Output = bool>,
+              Self: Sized
+    {
+        Filter::new(self, f)
+    }
+
+    /// Pairs up the items of this stream with those of `other`.
+    ///
+    /// The returned stream ends as soon as either stream ends. If the other
+    /// stream had already produced an item by then, that item is dropped.
+    fn zip<St>(self, other: St) -> Zip<Self, St, S>
+        where St: Stream<S>,
+              Self: Sized
+    {
+        Zip::new(self, other)
+    }
+
+    /// Pairs each item of this stream with its index, starting from zero.
+    fn enumerate(self) -> Enumerate<Self>
+        where Self: Sized
+    {
+        Enumerate::new(self)
+    }
+
+    /// Fuses this stream, so that it yields `None` forever once the
+    /// underlying stream has ended, without polling it again.
+    ///
+    /// The returned stream implements `FusedStream`, so it can be used with
+    /// `select!` and polled through `next` in a loop whatever the
+    /// underlying stream does after ending.
+    fn fuse(self) -> Fuse<Self>
+        where Self: Sized
+    {
+        Fuse::new(self)
+    }
+
+    /// Groups the items of this stream into vectors of `cap` items.
+    ///
+    /// Each chunk is only yielded once it is full, except for the final
+    /// chunk, which holds whatever was left when the stream ended. No more
+    /// than `cap` items are buffered at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero.
+    #[cfg(feature = "alloc")]
+    fn chunks(self, cap: usize) -> Chunks<Self, Self::Item>
+        where Self: Sized
+    {
+        Chunks::new(self, cap)
+    }
+
+    /// Groups the items of this stream which are immediately available into
+    /// vectors of at most `cap` items.
+    ///
+    /// Unlike `chunks`, this never waits for a chunk to fill up: each chunk
+    /// holds the items which were ready when it was polled, and it is only
+    /// `Pending` when no items at all are ready. Chunks are never empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero.
+    #[cfg(feature = "alloc")]
+    fn ready_chunks(self, cap: usize) -> ReadyChunks<Self>
+        where Self: Sized
+    {
+        ReadyChunks::new(self, cap)
+    }
+
+    /// Merges this stream with another stream of the same item type.
+    ///
+    /// See the `select` function for details.
+    fn select<St>(self, other: St) -> Select<Self, St>
+        where St: Stream<S, Item = Self::Item>,
+              Self: Sized
+    {
+        select(self, other)
+    }
+
+    /// Collects all of the values of this stream into a collection.
+    ///
+    /// The returned future resolves with the collection once the stream has
+    /// finished.
+    fn collect<C: Default + Extend<Self::Item>>(self) -> Collect<Self, C>
+        where Self: Sized
+    {
+        Collect::new(self)
+    }
+
+    /// Runs this stream to completion, executing the provided asynchronous
+    /// closure for each element on the stream.
+    ///
+    /// The future returned by `f` for one item is driven to completion before
+    /// the next item is pulled from the stream.
+    fn for_each<Fut, F>(self, f: F) -> ForEach<Self, Fut, F>
+        where F: FnMut(Self::Item) -> Fut,
+              Fut: Future<S, Output = ()>,
+              Self: Sized
+    {
+        ForEach::new(self, f)
+    }
+
+    /// Runs this stream to completion, executing the provided asynchronous
+    /// closure for each element, with up to `limit` of the resulting futures
+    /// running concurrently.
+    ///
+    /// The futures are multiplexed within the task polling the returned
+    /// future. While `limit` futures are in flight, no further items are
+    /// pulled from the stream. A `limit` of `None` places no bound on the
+    /// number of concurrent futures, and neither does `Some(0)`, as a limit
+    /// of zero could never make progress. The returned future resolves once
+    /// the stream has finished and every in-flight future has completed.
+    #[cfg(feature = "std")]
+    fn for_each_concurrent<Fut, F>(self, limit: Option<usize>, f: F) -> ForEachConcurrent<Self, Fut, F>
+        where F: FnMut(Self::Item) -> Fut,
+              Fut: Future<S, Output = ()>,
+              Self: Sized
+    {
+        ForEachConcurrent::new(self, limit, f)
+    }
+
+    /// Runs this stream to completion, spawning the future returned by the
+    /// provided closure for each element as a separate task through
+    /// `cx.spawner()`.
+    ///
+    /// The returned future resolves once the stream has finished and every
+    /// spawned task has completed. If the spawner refuses a task, no further
+    /// items are pulled from the stream, and the returned future resolves
+    /// with the spawn error once the tasks already spawned have finished.
+    #[cfg(feature = "std")]
+    fn for_each_spawned<Fut, F>(self, f: F) -> ForEachSpawned<Self, F>
+        where F: FnMut(Self::Item) -> Fut,
+              Fut: Future<Output = ()> + Send + 'static,
+              Self: Sized
+    {
+        ForEachSpawned::new(self, f)
+    }
+
+    /// An adaptor for creating a buffered list of pending futures.
+    ///
+    /// If this stream's item is a future, this adaptor drives up to `n` of
+    /// those futures at once and yields their outputs in the order in which
+    /// the futures were produced by the stream. No more items are pulled from
+    /// this stream while `n` futures are in flight or waiting to be yielded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    #[cfg(feature = "std")]
+    fn buffered(self, n: usize) -> Buffered<Self, S>
+        where Self::Item: Future<S>,
+              Self: Sized
+    {
+        Buffered::new(self, n)
+    }
+
+    /// An adaptor for creating a buffered list of pending futures (unordered).
+    ///
+    /// If this stream's item is a future, this adaptor drives up to `n` of
+    /// those futures at once and yields their outputs in the order in which
+    /// they complete. No more items are pulled from this stream while `n`
+    /// futures are in flight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    #[cfg(feature = "std")]
+    fn buffer_unordered(self, n: usize) -> BufferUnordered<Self, S>
+        where Self::Item: Future<S>,
+              Self: Sized
+    {
+        BufferUnordered::new(self, n)
+    }
+
+    /// A future that sends every item of this stream into `sink`, then
+    /// closes it.
+    ///
+    /// Items are only handed to the sink once it is ready for them, and the
+    /// returned future resolves with the first error from the sink. Unlike
+    /// `SinkExt::send_all`, this takes ownership of both sides and closes
+    /// the sink once the stream has ended.
+    fn forward<Si>(self, sink: Si) -> Forward<Self, Si, S>
+        where Si: Sink<Self::Item, S> + Unpin,
+              Self: Sized + Unpin
+    {
+        Forward::new(self, sink)
+    }
+
+    /// Splits an object which is both a stream and a sink into separate
+    /// halves, so it can be read and written from different tasks.
+    ///
+    /// The halves share the object through a `BiLock`, and can be put back
+    /// together with `reunite`.
+    #[cfg(feature = "alloc")]
+    fn split<Item>(self) -> (SplitSink<Self, Item>, SplitStream<Self>)
+        where Self: Sink<Item, S> + Sized + Unpin
+    {
+        super::split::split(self)
+    }
+}
+
+impl<S: Spawn + ?Sized, St: Stream<S> + ?Sized> StreamExt<S> for St {}
@@ -0,0 +1,108 @@
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::Future;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `StreamExt::filter` method.
+pub struct Filter<St, Fut, F, S: Spawn + ?Sized = dyn Spawn>
+    where St: Stream<S>
+{
+    stream: St,
+    f: F,
+    pending_fut: Option<Fut>,
+    pending_item: Option<St::Item>,
+    done: bool,
+}
+
+impl<St, Fut, F, S> Unpin for Filter<St, Fut, F, S>
+    where St: Stream<S> + Unpin,
+          Fut: Unpin,
+          S: Spawn + ?Sized
+{}
+
+impl<St, Fut, F, S> fmt::Debug for Filter<St, Fut, F, S>
+    where St: Stream<S> + fmt::Debug,
+          St::Item: fmt::Debug,
+          Fut: fmt::Debug,
+          S: Spawn + ?Sized
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Filter")
+            .field("stream", &self.stream)
+            .field("pending_fut", &self.pending_fut)
+            .field("pending_item", &self.pending_item)
+            .finish()
+    }
+}
+
+impl<St, Fut, F, S> Filter<St, Fut, F, S>
+    where St: Stream<S>,
+          F: FnMut(&St::Item) -> Fut,
+          Fut: Future<S, Output = bool>,
+          S: Spawn + ?Sized
+{
+    pub(super) fn new(stream: St, f: F) -> Filter<St, Fut, F, S> {
+        Filter {
+            stream,
+            f,
+            pending_fut: None,
+            pending_item: None,
+            done: false,
+        }
+    }
+}
+
+impl<St, Fut, F, S> Stream<S> for Filter<St, Fut, F, S>
+    where St: Stream<S>,
+          F: FnMut(&St::Item) -> Fut,
+          Fut: Future<S, Output = bool>,
+          S: Spawn + ?Sized
+{
+    type Item = St::Item;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<St::Item>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        loop {
+            if this.pending_fut.is_none() {
+                if this.done {
+                    return Poll::Ready(None);
+                }
+                let stream = unsafe { PinMut::new_unchecked(&mut this.stream) };
+                let item = match ready!(stream.poll_next(cx)) {
+                    Some(item) => item,
+                    None => {
+                        this.done = true;
+                        return Poll::Ready(None);
+                    }
+                };
+                this.pending_fut = Some((this.f)(&item));
+                this.pending_item = Some(item);
+            }
+
+            let keep = {
+                let fut = unsafe {
+                    PinMut::new_unchecked(this.pending_fut.as_mut().unwrap())
+                };
+                ready!(fut.poll(cx))
+            };
+            // The predicate future is dropped in place, as pinning permits.
+            this.pending_fut = None;
+            let item = this.pending_item.take().unwrap();
+            if keep {
+                return Poll::Ready(Some(item));
+            }
+        }
+    }
+}
+
+impl<St, Fut, F, S> FusedStream for Filter<St, Fut, F, S>
+    where St: Stream<S>,
+          S: Spawn + ?Sized
+{
+    fn is_terminated(&self) -> bool {
+        self.done && self.pending_fut.is_none()
+    }
+}
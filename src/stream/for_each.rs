@@ -0,0 +1,60 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use stream::Stream;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `StreamExt::for_each` method.
+#[derive(Debug)]
+pub struct ForEach<St, Fut, F> {
+    stream: St,
+    f: F,
+    future: Option<Fut>,
+    done: bool,
+}
+
+impl<St: Unpin, Fut: Unpin, F> Unpin for ForEach<St, Fut, F> {}
+
+impl<St, Fut, F> ForEach<St, Fut, F> {
+    pub(super) fn new(stream: St, f: F) -> ForEach<St, Fut, F> {
+        ForEach { stream, f, future: None, done: false }
+    }
+}
+
+impl<S, St, Fut, F> Future<S> for ForEach<St, Fut, F>
+    where S: Spawn + ?Sized,
+          St: Stream<S>,
+          F: FnMut(St::Item) -> Fut,
+          Fut: Future<S, Output = ()>
+{
+    type Output = ();
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<()> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if this.done {
+            panic!("ForEach polled after completion");
+        }
+        loop {
+            if let Some(future) = &mut this.future {
+                ready!(unsafe { PinMut::new_unchecked(future) }.poll(cx));
+            }
+            this.future = None;
+
+            let stream = unsafe { PinMut::new_unchecked(&mut this.stream) };
+            match ready!(stream.poll_next(cx)) {
+                Some(item) => this.future = Some((this.f)(item)),
+                None => {
+                    this.done = true;
+                    return Poll::Ready(());
+                }
+            }
+        }
+    }
+}
+
+impl<St, Fut, F> FusedFuture for ForEach<St, Fut, F> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
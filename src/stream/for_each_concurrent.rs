@@ -0,0 +1,103 @@
+use std::boxed::PinBox;
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use stream::Stream;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `StreamExt::for_each_concurrent` method.
+pub struct ForEachConcurrent<St, Fut, F> {
+    stream: St,
+    f: F,
+    futures: Vec<PinBox<Fut>>,
+    limit: Option<usize>,
+    stream_done: bool,
+    done: bool,
+}
+
+impl<St: Unpin, Fut, F> Unpin for ForEachConcurrent<St, Fut, F> {}
+
+impl<St: fmt::Debug, Fut, F> fmt::Debug for ForEachConcurrent<St, Fut, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ForEachConcurrent")
+            .field("stream", &self.stream)
+            .field("in_flight", &self.futures.len())
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+impl<St, Fut, F> ForEachConcurrent<St, Fut, F> {
+    pub(super) fn new(stream: St, limit: Option<usize>, f: F) -> ForEachConcurrent<St, Fut, F> {
+        ForEachConcurrent {
+            stream,
+            f,
+            futures: Vec::new(),
+            // A limit of zero could never make progress, so, as documented on
+            // `StreamExt::for_each_concurrent`, it means no limit.
+            limit: limit.and_then(|limit| if limit == 0 { None } else { Some(limit) }),
+            stream_done: false,
+            done: false,
+        }
+    }
+}
+
+impl<S, St, Fut, F> Future<S> for ForEachConcurrent<St, Fut, F>
+    where S: Spawn + ?Sized,
+          St: Stream<S>,
+          F: FnMut(St::Item) -> Fut,
+          Fut: Future<S, Output = ()>
+{
+    type Output = ();
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<()> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if this.done {
+            panic!("ForEachConcurrent polled after completion");
+        }
+        loop {
+            // Pull new items only while below the limit, so that a full set
+            // of in-flight futures exerts backpressure on the stream.
+            while !this.stream_done &&
+                this.limit.map_or(true, |limit| this.futures.len() < limit)
+            {
+                let stream = unsafe { PinMut::new_unchecked(&mut this.stream) };
+                match stream.poll_next(cx) {
+                    Poll::Ready(Some(item)) => {
+                        this.futures.push(PinBox::new((this.f)(item)));
+                    }
+                    Poll::Ready(None) => this.stream_done = true,
+                    Poll::Pending => break,
+                }
+            }
+
+            let mut completed_any = false;
+            let mut i = 0;
+            while i < this.futures.len() {
+                if let Poll::Ready(()) = this.futures[i].as_pin_mut().poll(cx) {
+                    this.futures.swap_remove(i);
+                    completed_any = true;
+                } else {
+                    i += 1;
+                }
+            }
+
+            if this.stream_done && this.futures.is_empty() {
+                this.done = true;
+                return Poll::Ready(());
+            }
+            // Only go around again if capacity was freed for more items.
+            if !completed_any || this.stream_done {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl<St, Fut, F> FusedFuture for ForEachConcurrent<St, Fut, F> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,131 @@
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use std::sync::{Arc, Mutex};
+use future::{Future, FusedFuture, FutureObj};
+use stream::Stream;
+use task::{Context, Poll, Waker};
+use spawn::{Spawn, SpawnErrorKind};
+
+struct Tracker {
+    running: usize,
+    waker: Option<Waker>,
+}
+
+/// A spawned item future which reports to its `ForEachSpawned` when it
+/// finishes or is dropped by the executor.
+struct Tracked<Fut> {
+    future: Fut,
+    tracker: Arc<Mutex<Tracker>>,
+}
+
+impl<Fut: Future<Output = ()>> Future for Tracked<Fut> {
+    type Output = ();
+
+    fn poll(self: PinMut<Self>, cx: &mut Context) -> Poll<()> {
+        unsafe { PinMut::map_unchecked(self, |x| &mut x.future) }.poll(cx)
+    }
+}
+
+impl<Fut> Drop for Tracked<Fut> {
+    fn drop(&mut self) {
+        let mut tracker = self.tracker.lock().unwrap();
+        tracker.running -= 1;
+        if tracker.running == 0 {
+            if let Some(waker) = tracker.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Future for the `StreamExt::for_each_spawned` method.
+pub struct ForEachSpawned<St, F> {
+    stream: St,
+    f: F,
+    tracker: Arc<Mutex<Tracker>>,
+    stream_done: bool,
+    error: Option<SpawnErrorKind>,
+    done: bool,
+}
+
+impl<St: Unpin, F> Unpin for ForEachSpawned<St, F> {}
+
+impl<St: fmt::Debug, F> fmt::Debug for ForEachSpawned<St, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ForEachSpawned")
+            .field("stream", &self.stream)
+            .field("stream_done", &self.stream_done)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+impl<St, F> ForEachSpawned<St, F> {
+    pub(super) fn new(stream: St, f: F) -> ForEachSpawned<St, F> {
+        ForEachSpawned {
+            stream,
+            f,
+            tracker: Arc::new(Mutex::new(Tracker { running: 0, waker: None })),
+            stream_done: false,
+            error: None,
+            done: false,
+        }
+    }
+}
+
+impl<S, St, Fut, F> Future<S> for ForEachSpawned<St, F>
+    where S: Spawn + ?Sized,
+          St: Stream<S>,
+          F: FnMut(St::Item) -> Fut,
+          Fut: Future<Output = ()> + Send + 'static
+{
+    type Output = Result<(), SpawnErrorKind>;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if this.done {
+            panic!("ForEachSpawned polled after completion");
+        }
+
+        while !this.stream_done && this.error.is_none() {
+            let item = {
+                let stream = unsafe { PinMut::new_unchecked(&mut this.stream) };
+                match stream.poll_next(cx) {
+                    Poll::Ready(Some(item)) => item,
+                    Poll::Ready(None) => {
+                        this.stream_done = true;
+                        break;
+                    }
+                    Poll::Pending => break,
+                }
+            };
+            this.tracker.lock().unwrap().running += 1;
+            let task = Tracked { future: (this.f)(item), tracker: this.tracker.clone() };
+            // On failure the task is dropped with the error, which releases
+            // its slot in the tracker.
+            if let Err(err) = cx.spawner().spawn_obj(FutureObj::new(Box::new(task))) {
+                this.error = Some(err.kind);
+            }
+        }
+
+        let mut tracker = this.tracker.lock().unwrap();
+        if tracker.running == 0 && (this.stream_done || this.error.is_some()) {
+            this.done = true;
+            return Poll::Ready(match this.error.take() {
+                Some(kind) => Err(kind),
+                None => Ok(()),
+            });
+        }
+        if tracker.running > 0 {
+            tracker.waker = Some(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl<St, F> FusedFuture for ForEachSpawned<St, F> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,121 @@
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use sink::Sink;
+use stream::{Stream, StreamExt};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `StreamExt::forward` method.
+pub struct Forward<St, Si, S: Spawn + ?Sized = dyn Spawn>
+    where St: Stream<S>
+{
+    stream: St,
+    sink: Si,
+    buffered: Option<St::Item>,
+    stream_done: bool,
+    done: bool,
+}
+
+impl<St, Si, S> Unpin for Forward<St, Si, S>
+    where St: Stream<S> + Unpin,
+          Si: Unpin,
+          S: Spawn + ?Sized
+{}
+
+impl<St, Si, S> fmt::Debug for Forward<St, Si, S>
+    where St: Stream<S> + fmt::Debug,
+          St::Item: fmt::Debug,
+          Si: fmt::Debug,
+          S: Spawn + ?Sized
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Forward")
+            .field("stream", &self.stream)
+            .field("sink", &self.sink)
+            .field("buffered", &self.buffered)
+            .finish()
+    }
+}
+
+impl<St, Si, S> Forward<St, Si, S>
+    where St: Stream<S> + Unpin,
+          Si: Sink<St::Item, S> + Unpin,
+          S: Spawn + ?Sized
+{
+    pub(super) fn new(stream: St, sink: Si) -> Forward<St, Si, S> {
+        Forward { stream, sink, buffered: None, stream_done: false, done: false }
+    }
+}
+
+impl<St, Si, S> Future<S> for Forward<St, Si, S>
+    where St: Stream<S> + Unpin,
+          Si: Sink<St::Item, S> + Unpin,
+          S: Spawn + ?Sized
+{
+    type Output = Result<(), Si::Error>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if this.done {
+            panic!("Forward polled after completion");
+        }
+        let result = ready!(this.poll_forward(cx));
+        this.done = true;
+        Poll::Ready(result)
+    }
+}
+
+impl<St, Si, S> Forward<St, Si, S>
+    where St: Stream<S> + Unpin,
+          Si: Sink<St::Item, S> + Unpin,
+          S: Spawn + ?Sized
+{
+    fn poll_forward(&mut self, cx: &mut Context<S>) -> Poll<Result<(), Si::Error>> {
+        loop {
+            // An item taken from the stream is only handed over once the sink
+            // is ready for it.
+            if let Some(item) = self.buffered.take() {
+                match PinMut::new(&mut self.sink).poll_ready(cx) {
+                    Poll::Ready(Ok(())) => {
+                        if let Err(e) = PinMut::new(&mut self.sink).start_send(item) {
+                            return Poll::Ready(Err(e));
+                        }
+                    }
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Pending => {
+                        self.buffered = Some(item);
+                        return Poll::Pending;
+                    }
+                }
+            }
+
+            if self.stream_done {
+                return PinMut::new(&mut self.sink).poll_close(cx);
+            }
+
+            match self.stream.poll_next_unpin(cx) {
+                Poll::Ready(Some(item)) => self.buffered = Some(item),
+                Poll::Ready(None) => self.stream_done = true,
+                Poll::Pending => {
+                    // Make progress on what was already sent while waiting
+                    // for the stream.
+                    return match PinMut::new(&mut self.sink).poll_flush(cx) {
+                        Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+                        _ => Poll::Pending,
+                    };
+                }
+            }
+        }
+    }
+}
+
+impl<St, Si, S> FusedFuture for Forward<St, Si, S>
+    where St: Stream<S>,
+          S: Spawn + ?Sized
+{
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
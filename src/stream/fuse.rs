@@ -0,0 +1,52 @@
+use core::mem::PinMut;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `fuse` combinator.
+///
+/// This is created by the `StreamExt::fuse` method.
+#[derive(Debug)]
+pub struct Fuse<St> {
+    stream: St,
+    done: bool,
+}
+
+impl<St> Fuse<St> {
+    pub(super) fn new(stream: St) -> Fuse<St> {
+        Fuse { stream, done: false }
+    }
+
+    /// Acquires a reference to the underlying stream.
+    pub fn get_ref(&self) -> &St {
+        &self.stream
+    }
+
+    /// Consumes this combinator, returning the underlying stream.
+    pub fn into_inner(self) -> St {
+        self.stream
+    }
+}
+
+impl<St: Stream<S>, S: Spawn + ?Sized> Stream<S> for Fuse<St> {
+    type Item = St::Item;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<St::Item>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if this.done {
+            return Poll::Ready(None);
+        }
+        let stream = unsafe { PinMut::new_unchecked(&mut this.stream) };
+        let item = ready!(stream.poll_next(cx));
+        if item.is_none() {
+            this.done = true;
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl<St> FusedStream for Fuse<St> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
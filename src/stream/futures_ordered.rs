@@ -0,0 +1,151 @@
+use core::cmp::Ordering;
+use std::collections::BinaryHeap;
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::Future;
+use stream::{Stream, StreamExt, FusedStream, FuturesUnordered};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A future tagged with its position in the input stream.
+#[derive(Debug)]
+struct OrderWrapper<T> {
+    data: T,
+    index: usize,
+}
+
+impl<T> PartialEq for OrderWrapper<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for OrderWrapper<T> {}
+
+impl<T> PartialOrd for OrderWrapper<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for OrderWrapper<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // `BinaryHeap` is a max-heap, so reverse the ordering to pop the
+        // lowest index first.
+        other.index.cmp(&self.index)
+    }
+}
+
+impl<S, T> Future<S> for OrderWrapper<T>
+    where S: Spawn + ?Sized,
+          T: Future<S>
+{
+    type Output = OrderWrapper<T::Output>;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        let index = self.index;
+        match unsafe { PinMut::map_unchecked(self, |x| &mut x.data) }.poll(cx) {
+            Poll::Ready(data) => Poll::Ready(OrderWrapper { data, index }),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// A set of futures which yields their outputs in the order in which the
+/// futures were pushed.
+///
+/// All the futures in the set are driven concurrently, but an output is only
+/// yielded once every future pushed before it has yielded its own. Outputs
+/// which complete early are buffered internally; since each buffered output
+/// belongs to a future that was pushed into the set, the buffer never holds
+/// more entries than `len()`.
+pub struct FuturesOrdered<Fut, S: Spawn + ?Sized = dyn Spawn>
+    where Fut: Future<S>
+{
+    in_progress: FuturesUnordered<OrderWrapper<Fut>>,
+    queued_outputs: BinaryHeap<OrderWrapper<Fut::Output>>,
+    next_incoming_index: usize,
+    next_outgoing_index: usize,
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> Unpin for FuturesOrdered<Fut, S> {}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> fmt::Debug for FuturesOrdered<Fut, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FuturesOrdered")
+            .field("in_progress", &self.in_progress)
+            .field("queued_outputs", &self.queued_outputs.len())
+            .finish()
+    }
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> FuturesOrdered<Fut, S> {
+    /// Constructs a new, empty `FuturesOrdered`.
+    pub fn new() -> FuturesOrdered<Fut, S> {
+        FuturesOrdered {
+            in_progress: FuturesUnordered::new(),
+            queued_outputs: BinaryHeap::new(),
+            next_incoming_index: 0,
+            next_outgoing_index: 0,
+        }
+    }
+
+    /// Returns the number of futures contained in the set, including those
+    /// whose outputs are buffered waiting for earlier futures.
+    pub fn len(&self) -> usize {
+        self.in_progress.len() + self.queued_outputs.len()
+    }
+
+    /// Returns `true` if the set contains no futures.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Push a future into the set.
+    ///
+    /// Its output will be yielded after the outputs of all the futures pushed
+    /// before it.
+    pub fn push(&mut self, future: Fut) {
+        let index = self.next_incoming_index;
+        self.next_incoming_index += 1;
+        self.in_progress.push(OrderWrapper { data: future, index });
+    }
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> Default for FuturesOrdered<Fut, S> {
+    fn default() -> FuturesOrdered<Fut, S> {
+        FuturesOrdered::new()
+    }
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> Stream<S> for FuturesOrdered<Fut, S> {
+    type Item = Fut::Output;
+
+    fn poll_next(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Fut::Output>> {
+        let this = &mut *self;
+        loop {
+            if this.queued_outputs.peek().map(|o| o.index) == Some(this.next_outgoing_index) {
+                this.next_outgoing_index += 1;
+                return Poll::Ready(Some(this.queued_outputs.pop().unwrap().data));
+            }
+
+            match ready!(this.in_progress.poll_next_unpin(cx)) {
+                Some(output) => {
+                    if output.index == this.next_outgoing_index {
+                        this.next_outgoing_index += 1;
+                        return Poll::Ready(Some(output.data));
+                    }
+                    this.queued_outputs.push(output);
+                }
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> FusedStream for FuturesOrdered<Fut, S> {
+    fn is_terminated(&self) -> bool {
+        self.in_progress.is_terminated() && self.queued_outputs.is_empty()
+    }
+}
@@ -0,0 +1,249 @@
+use std::boxed::PinBox;
+use std::collections::VecDeque;
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use std::sync::{Arc, Mutex};
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Wake, local_waker_from_nonlocal};
+use future::Future;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll, Waker};
+use spawn::Spawn;
+
+/// The queue of children which have been woken, shared with every child's
+/// waker.
+///
+/// Entries identify a child by its slot and the generation of that slot, as
+/// a waker may outlive its child, and the slot be reused by another.
+struct ReadyQueue {
+    ready: Mutex<VecDeque<(usize, usize)>>,
+    parent: Mutex<Option<Waker>>,
+}
+
+/// The waker handed to a single child future.
+struct ChildWaker {
+    index: usize,
+    generation: usize,
+    queued: AtomicBool,
+    queue: Arc<ReadyQueue>,
+}
+
+impl Wake for ChildWaker {
+    fn wake(arc_self: &Arc<ChildWaker>) {
+        if !arc_self.queued.swap(true, Ordering::AcqRel) {
+            let entry = (arc_self.index, arc_self.generation);
+            arc_self.queue.ready.lock().unwrap().push_back(entry);
+            if let Some(waker) = &*arc_self.queue.parent.lock().unwrap() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+struct Child<Fut> {
+    future: PinBox<Fut>,
+    waker: Arc<ChildWaker>,
+}
+
+struct Slot<Fut> {
+    /// Incremented whenever the slot is reused, so that wakeups for the
+    /// child which previously held it are told apart.
+    generation: usize,
+    child: Option<Child<Fut>>,
+}
+
+/// A set of futures which may complete in any order.
+///
+/// Futures can be pushed into the set at any time, and the set is polled as a
+/// `Stream` of their outputs, yielding each output as soon as its future
+/// completes. Each child is polled with its own waker, so polling the set
+/// only visits the children which have been woken since the last poll rather
+/// than every child.
+///
+/// When the set is empty, polling it yields `Poll::Ready(None)`. This is not
+/// permanent: futures pushed afterwards are driven as usual, and
+/// `is_terminated` only reports `true` between a `None` being yielded and the
+/// next `push`.
+///
+/// The set is `Send` and `Sync` whenever the futures it contains are, and
+/// dropping it drops every future still in it.
+pub struct FuturesUnordered<Fut> {
+    children: Vec<Slot<Fut>>,
+    free: Vec<usize>,
+    len: usize,
+    queue: Arc<ReadyQueue>,
+    is_terminated: bool,
+}
+
+impl<Fut> fmt::Debug for FuturesUnordered<Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FuturesUnordered")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<Fut> FuturesUnordered<Fut> {
+    /// Constructs a new, empty `FuturesUnordered`.
+    pub fn new() -> FuturesUnordered<Fut> {
+        FuturesUnordered {
+            children: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            is_terminated: false,
+            queue: Arc::new(ReadyQueue {
+                ready: Mutex::new(VecDeque::new()),
+                parent: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Returns the number of futures contained in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the set contains no futures.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of wakeups waiting to be handled, i.e. roughly how many
+    /// children are due to be polled. Wakeups for children which have since
+    /// completed are counted until the set is next polled.
+    pub(crate) fn ready_len(&self) -> usize {
+        self.queue.ready.lock().unwrap().len()
+    }
+
+    /// Push a future into the set.
+    ///
+    /// The future will be polled the next time the set is polled. The set
+    /// does not poll it from `push`, so the task driving the set must be
+    /// woken (or already be polling) for it to make progress.
+    pub fn push(&mut self, future: Fut) {
+        let index = self.vacant_slot();
+        let waker = self.child_waker(index, true);
+        let slot = &mut self.children[index];
+        slot.child = Some(Child { future: PinBox::new(future), waker });
+        self.queue.ready.lock().unwrap().push_back((index, slot.generation));
+        self.len += 1;
+        self.is_terminated = false;
+    }
+
+    /// Poll a future once with the waker it would have in the set, and push
+    /// it into the set only if it is still pending.
+    ///
+    /// Unlike `push`, the future isn't polled again until it is woken, and a
+    /// future which is ready straight away is never boxed.
+    pub(crate) fn push_and_poll<S>(&mut self, mut future: Fut, cx: &mut Context<S>) -> Poll<Fut::Output>
+        where Fut: Future<S> + Unpin,
+              S: Spawn + ?Sized
+    {
+        let index = self.vacant_slot();
+        let waker = self.child_waker(index, false);
+        let ret = {
+            let local_waker = local_waker_from_nonlocal(waker.clone());
+            let waker = Waker::from(waker.clone());
+            let mut cx = cx.with_waker(&local_waker, &waker);
+            PinMut::new(&mut future).poll(&mut cx)
+        };
+        if ret.is_ready() {
+            // A wakeup from that poll is stale once the slot is reused.
+            self.free.push(index);
+            return ret;
+        }
+        self.children[index].child = Some(Child { future: PinBox::new(future), waker });
+        self.len += 1;
+        self.is_terminated = false;
+        Poll::Pending
+    }
+
+    /// Find a slot for a new child, reusing a freed one if there is one.
+    fn vacant_slot(&mut self) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.children[index];
+                slot.generation = slot.generation.wrapping_add(1);
+                index
+            }
+            None => {
+                self.children.push(Slot { generation: 0, child: None });
+                self.children.len() - 1
+            }
+        }
+    }
+
+    fn child_waker(&self, index: usize, queued: bool) -> Arc<ChildWaker> {
+        Arc::new(ChildWaker {
+            index,
+            generation: self.children[index].generation,
+            queued: AtomicBool::new(queued),
+            queue: self.queue.clone(),
+        })
+    }
+}
+
+impl<Fut> Default for FuturesUnordered<Fut> {
+    fn default() -> FuturesUnordered<Fut> {
+        FuturesUnordered::new()
+    }
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> Stream<S> for FuturesUnordered<Fut> {
+    type Item = Fut::Output;
+
+    fn poll_next(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Fut::Output>> {
+        let this = &mut *self;
+        // Register before draining the queue, so that a child woken
+        // concurrently is never missed.
+        *this.queue.parent.lock().unwrap() = Some(cx.waker().clone());
+
+        // Bound the work done per call, so a child which keeps waking itself
+        // cannot starve the rest of the task.
+        let mut budget = this.len;
+        loop {
+            if this.len == 0 {
+                this.is_terminated = true;
+                return Poll::Ready(None);
+            }
+            if budget == 0 {
+                cx.local_waker().wake();
+                return Poll::Pending;
+            }
+            let (index, generation) = match this.queue.ready.lock().unwrap().pop_front() {
+                Some(entry) => entry,
+                None => return Poll::Pending,
+            };
+
+            let slot = &mut this.children[index];
+            let output = match &mut slot.child {
+                // A stale wakeup for a child which has since completed, and
+                // whose slot may since have been reused by another.
+                Some(_) if slot.generation != generation => continue,
+                None => continue,
+                Some(child) => {
+                    budget -= 1;
+                    child.waker.queued.store(false, Ordering::Release);
+                    let local_waker = local_waker_from_nonlocal(child.waker.clone());
+                    let waker = Waker::from(child.waker.clone());
+                    let mut cx = cx.with_waker(&local_waker, &waker);
+                    match child.future.as_pin_mut().poll(&mut cx) {
+                        Poll::Ready(output) => output,
+                        Poll::Pending => continue,
+                    }
+                }
+            };
+            slot.child = None;
+            this.free.push(index);
+            this.len -= 1;
+            return Poll::Ready(Some(output));
+        }
+    }
+}
+
+impl<Fut> FusedStream for FuturesUnordered<Fut> {
+    fn is_terminated(&self) -> bool {
+        self.is_terminated
+    }
+}
@@ -0,0 +1,44 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `iter` function.
+#[derive(Debug, Clone)]
+pub struct Iter<I> {
+    iter: I,
+    done: bool,
+}
+
+impl<I> Unpin for Iter<I> {}
+
+/// Converts an `Iterator` into a `Stream` which is always ready to yield the
+/// next value.
+pub fn iter<I>(i: I) -> Iter<I::IntoIter>
+    where I: IntoIterator
+{
+    Iter { iter: i.into_iter(), done: false }
+}
+
+impl<I: Iterator, S: Spawn + ?Sized> Stream<S> for Iter<I> {
+    type Item = I::Item;
+
+    fn poll_next(mut self: PinMut<Self>, _: &mut Context<S>) -> Poll<Option<I::Item>> {
+        // The iterator isn't necessarily fused, so don't call it again.
+        if self.done {
+            return Poll::Ready(None);
+        }
+        let next = self.iter.next();
+        if next.is_none() {
+            self.done = true;
+        }
+        Poll::Ready(next)
+    }
+}
+
+impl<I> FusedStream for Iter<I> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
@@ -0,0 +1,51 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `StreamExt::map` method.
+#[derive(Debug)]
+pub struct Map<St, F> {
+    stream: St,
+    f: F,
+    done: bool,
+}
+
+impl<St: Unpin, F> Unpin for Map<St, F> {}
+
+impl<St, F> Map<St, F> {
+    pub(super) fn new(stream: St, f: F) -> Map<St, F> {
+        Map { stream, f, done: false }
+    }
+}
+
+impl<S, St, F, T> Stream<S> for Map<St, F>
+    where S: Spawn + ?Sized,
+          St: Stream<S>,
+          F: FnMut(St::Item) -> T
+{
+    type Item = T;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<T>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if this.done {
+            return Poll::Ready(None);
+        }
+        let stream = unsafe { PinMut::new_unchecked(&mut this.stream) };
+        match stream.poll_next(cx) {
+            Poll::Ready(Some(item)) => Poll::Ready(Some((this.f)(item))),
+            Poll::Ready(None) => {
+                this.done = true;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<St, F> FusedStream for Map<St, F> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
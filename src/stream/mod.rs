@@ -0,0 +1,93 @@
+mod stream;
+pub use self::stream::{Stream, FusedStream};
+
+mod iter;
+pub use self::iter::{iter, Iter};
+
+mod empty;
+pub use self::empty::{empty, Empty};
+
+mod once;
+pub use self::once::{once, Once};
+
+mod unfold;
+pub use self::unfold::{unfold, Unfold};
+
+mod try_unfold;
+pub use self::try_unfold::{try_unfold, TryUnfold};
+
+mod stream_obj;
+pub use self::stream_obj::{StreamObj, LocalStreamObj, UnsafeStreamObj};
+
+mod ext;
+pub use self::ext::StreamExt;
+
+mod next;
+pub use self::next::Next;
+
+mod map;
+pub use self::map::Map;
+
+mod filter;
+pub use self::filter::Filter;
+
+mod zip;
+pub use self::zip::Zip;
+
+mod enumerate;
+pub use self::enumerate::Enumerate;
+
+mod fuse;
+pub use self::fuse::Fuse;
+
+mod select;
+pub use self::select::{select, Select};
+
+mod forward;
+pub use self::forward::Forward;
+
+#[cfg(feature = "alloc")]
+mod split;
+#[cfg(feature = "alloc")]
+pub use self::split::{SplitSink, SplitStream};
+
+#[cfg(feature = "alloc")]
+mod chunks;
+#[cfg(feature = "alloc")]
+pub use self::chunks::{Chunks, ReadyChunks};
+
+mod collect;
+pub use self::collect::Collect;
+
+mod for_each;
+pub use self::for_each::ForEach;
+
+#[cfg(feature = "std")]
+mod for_each_concurrent;
+#[cfg(feature = "std")]
+pub use self::for_each_concurrent::ForEachConcurrent;
+
+#[cfg(feature = "std")]
+mod for_each_spawned;
+#[cfg(feature = "std")]
+pub use self::for_each_spawned::ForEachSpawned;
+
+#[cfg(feature = "std")]
+mod futures_unordered;
+#[cfg(feature = "std")]
+pub use self::futures_unordered::FuturesUnordered;
+
+#[cfg(feature = "std")]
+mod futures_ordered;
+#[cfg(feature = "std")]
+pub use self::futures_ordered::FuturesOrdered;
+
+#[cfg(feature = "std")]
+mod buffer_unordered;
+#[cfg(feature = "std")]
+pub use self::buffer_unordered::BufferUnordered;
+
+#[cfg(feature = "std")]
+mod buffered;
+#[cfg(feature = "std")]
+pub use self::buffered::Buffered;
@@ -0,0 +1,31 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::Future;
+use stream::Stream;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `StreamExt::next` method.
+#[derive(Debug)]
+pub struct Next<'a, St: ?Sized + 'a> {
+    stream: &'a mut St,
+}
+
+impl<'a, St: ?Sized + Unpin> Unpin for Next<'a, St> {}
+
+impl<'a, St: ?Sized + Unpin> Next<'a, St> {
+    pub(super) fn new(stream: &'a mut St) -> Next<'a, St> {
+        Next { stream }
+    }
+}
+
+impl<'a, S, St> Future<S> for Next<'a, St>
+    where S: Spawn + ?Sized,
+          St: ?Sized + Stream<S> + Unpin
+{
+    type Output = Option<St::Item>;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        PinMut::new(&mut *self.stream).poll_next(cx)
+    }
+}
@@ -0,0 +1,42 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::Future;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `once` function.
+#[derive(Debug)]
+pub struct Once<Fut> {
+    future: Option<Fut>,
+}
+
+impl<Fut: Unpin> Unpin for Once<Fut> {}
+
+/// Creates a stream of a single element, the output of the given future.
+pub fn once<Fut>(future: Fut) -> Once<Fut> {
+    Once { future: Some(future) }
+}
+
+impl<Fut: Future<S>, S: Spawn + ?Sized> Stream<S> for Once<Fut> {
+    type Item = Fut::Output;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Fut::Output>> {
+        let mut future = unsafe { PinMut::map_unchecked(self, |x| &mut x.future) };
+        let output = {
+            let fut = match unsafe { PinMut::get_mut_unchecked(future.reborrow()) } {
+                Some(fut) => unsafe { PinMut::new_unchecked(fut) },
+                None => return Poll::Ready(None),
+            };
+            ready!(fut.poll(cx))
+        };
+        PinMut::set(future, None);
+        Poll::Ready(Some(output))
+    }
+}
+
+impl<Fut> FusedStream for Once<Fut> {
+    fn is_terminated(&self) -> bool {
+        self.future.is_none()
+    }
+}
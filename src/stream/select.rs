@@ -0,0 +1,83 @@
+use core::mem::PinMut;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `select` function.
+#[derive(Debug)]
+pub struct Select<St1, St2> {
+    stream1: St1,
+    stream2: St2,
+    done1: bool,
+    done2: bool,
+    first_next: bool,
+}
+
+/// Merges two streams of the same item type into one, yielding items from
+/// whichever is ready.
+///
+/// The two streams take turns being polled first, so a stream which is
+/// always ready can't starve the other. Each stream is not polled again
+/// once it has ended, and the merged stream ends when both have.
+pub fn select<St1, St2>(stream1: St1, stream2: St2) -> Select<St1, St2> {
+    Select { stream1, stream2, done1: false, done2: false, first_next: true }
+}
+
+impl<St1, St2, S> Stream<S> for Select<St1, St2>
+    where St1: Stream<S>,
+          St2: Stream<S, Item = St1::Item>,
+          S: Spawn + ?Sized
+{
+    type Item = St1::Item;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<St1::Item>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        let first_next = this.first_next;
+        this.first_next = !first_next;
+        let stream1 = unsafe { PinMut::new_unchecked(&mut this.stream1) };
+        let stream2 = unsafe { PinMut::new_unchecked(&mut this.stream2) };
+        if first_next {
+            poll_pair(stream1, &mut this.done1, stream2, &mut this.done2, cx)
+        } else {
+            poll_pair(stream2, &mut this.done2, stream1, &mut this.done1, cx)
+        }
+    }
+}
+
+fn poll_pair<S, T, A, B>(
+    a: PinMut<A>,
+    a_done: &mut bool,
+    b: PinMut<B>,
+    b_done: &mut bool,
+    cx: &mut Context<S>,
+) -> Poll<Option<T>>
+    where S: Spawn + ?Sized,
+          A: Stream<S, Item = T>,
+          B: Stream<S, Item = T>
+{
+    if !*a_done {
+        match a.poll_next(cx) {
+            Poll::Ready(Some(item)) => return Poll::Ready(Some(item)),
+            Poll::Ready(None) => *a_done = true,
+            Poll::Pending => {}
+        }
+    }
+    if !*b_done {
+        match b.poll_next(cx) {
+            Poll::Ready(Some(item)) => return Poll::Ready(Some(item)),
+            Poll::Ready(None) => *b_done = true,
+            Poll::Pending => {}
+        }
+    }
+    if *a_done && *b_done {
+        Poll::Ready(None)
+    } else {
+        Poll::Pending
+    }
+}
+
+impl<St1, St2> FusedStream for Select<St1, St2> {
+    fn is_terminated(&self) -> bool {
+        self.done1 && self.done2
+    }
+}
@@ -0,0 +1,127 @@
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use lock::{BiLock, ReuniteError};
+use sink::Sink;
+use stream::Stream;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// The `Stream` half of an object split by `StreamExt::split`.
+#[derive(Debug)]
+pub struct SplitStream<T>(BiLock<T>);
+
+/// The `Sink` half of an object split by `StreamExt::split`.
+pub struct SplitSink<T, Item> {
+    lock: BiLock<T>,
+    slot: Option<Item>,
+}
+
+impl<T, Item> Unpin for SplitSink<T, Item> {}
+
+impl<T: fmt::Debug, Item: fmt::Debug> fmt::Debug for SplitSink<T, Item> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SplitSink")
+            .field("lock", &self.lock)
+            .field("slot", &self.slot)
+            .finish()
+    }
+}
+
+pub(super) fn split<T, Item>(t: T) -> (SplitSink<T, Item>, SplitStream<T>) {
+    let (a, b) = BiLock::new(t);
+    (SplitSink { lock: a, slot: None }, SplitStream(b))
+}
+
+impl<T> SplitStream<T> {
+    /// Attempts to put the two halves back together, recovering the original
+    /// object. Succeeds only if both halves came from the same call to
+    /// `split`.
+    ///
+    /// An item given to the sink half but not yet delivered to the object is
+    /// dropped; flush the sink half first to avoid losing it.
+    pub fn reunite<Item>(self, other: SplitSink<T, Item>) -> Result<T, ReuniteError<T>> {
+        self.0.reunite(other.lock)
+    }
+}
+
+impl<T, Item> SplitSink<T, Item> {
+    /// Attempts to put the two halves back together, recovering the original
+    /// object. See `SplitStream::reunite`.
+    pub fn reunite(self, other: SplitStream<T>) -> Result<T, ReuniteError<T>> {
+        other.reunite(self)
+    }
+}
+
+impl<T: Stream<S> + Unpin, S: Spawn + ?Sized> Stream<S> for SplitStream<T> {
+    type Item = T::Item;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<T::Item>> {
+        let mut inner = ready!(self.0.poll_lock(cx));
+        PinMut::new(&mut *inner).poll_next(cx)
+    }
+}
+
+/// Deliver the item left by `start_send`, if any, to the locked object.
+fn poll_deliver<T, Item, S>(
+    slot: &mut Option<Item>,
+    inner: &mut T,
+    cx: &mut Context<S>,
+) -> Poll<Result<(), T::Error>>
+    where T: Sink<Item, S> + Unpin,
+          S: Spawn + ?Sized
+{
+    if slot.is_some() {
+        if let Err(e) = ready!(PinMut::new(&mut *inner).poll_ready(cx)) {
+            return Poll::Ready(Err(e));
+        }
+        let item = slot.take().unwrap();
+        if let Err(e) = PinMut::new(&mut *inner).start_send(item) {
+            return Poll::Ready(Err(e));
+        }
+    }
+    Poll::Ready(Ok(()))
+}
+
+// `start_send` can't take the lock, since it has no context to wait with, so
+// the item is kept in a slot and delivered by the next `poll_*` call. The
+// slot holds at most one item, which keeps the readiness contract intact.
+impl<T, Item, S> Sink<Item, S> for SplitSink<T, Item>
+    where T: Sink<Item, S> + Unpin,
+          S: Spawn + ?Sized
+{
+    type Error = T::Error;
+
+    fn poll_ready(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), T::Error>> {
+        let this = &mut *self;
+        if this.slot.is_none() {
+            return Poll::Ready(Ok(()));
+        }
+        let mut inner = ready!(this.lock.poll_lock(cx));
+        poll_deliver(&mut this.slot, &mut *inner, cx)
+    }
+
+    fn start_send(mut self: PinMut<Self>, item: Item) -> Result<(), T::Error> {
+        assert!(self.slot.is_none(), "SplitSink::start_send called without poll_ready");
+        self.slot = Some(item);
+        Ok(())
+    }
+
+    fn poll_flush(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), T::Error>> {
+        let this = &mut *self;
+        let mut inner = ready!(this.lock.poll_lock(cx));
+        if let Err(e) = ready!(poll_deliver(&mut this.slot, &mut *inner, cx)) {
+            return Poll::Ready(Err(e));
+        }
+        PinMut::new(&mut *inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), T::Error>> {
+        let this = &mut *self;
+        let mut inner = ready!(this.lock.poll_lock(cx));
+        if let Err(e) = ready!(poll_deliver(&mut this.slot, &mut *inner, cx)) {
+            return Poll::Ready(Err(e));
+        }
+        PinMut::new(&mut *inner).poll_close(cx)
+    }
+}
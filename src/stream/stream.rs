@@ -0,0 +1,50 @@
+use core::mem::PinMut;
+use core::marker::Unpin;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A stream of values produced asynchronously.
+///
+/// Once a stream has returned `Poll::Ready(None)` from `poll_next`, it has
+/// finished, and calling `poll_next` again is a logic error: the stream may
+/// panic, block forever, or start producing values again. Use `FusedStream`
+/// to find out whether a stream may still be polled.
+pub trait Stream<S: Spawn + ?Sized = dyn Spawn> {
+    type Item;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Self::Item>>;
+}
+
+impl<'a, S: Spawn + ?Sized, St: ?Sized + Stream<S> + Unpin> Stream<S> for &'a mut St {
+    type Item = St::Item;
+
+    fn poll_next(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Self::Item>> {
+        St::poll_next(PinMut::new(&mut **self), cx)
+    }
+}
+
+impl<'a, S: Spawn + ?Sized, St: ?Sized + Stream<S>> Stream<S> for PinMut<'a, St> {
+    type Item = St::Item;
+
+    fn poll_next(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Self::Item>> {
+        St::poll_next((*self).reborrow(), cx)
+    }
+}
+
+/// A `Stream` which tracks whether or not it has terminated.
+pub trait FusedStream {
+    /// Returns `true` if the stream should no longer be polled.
+    fn is_terminated(&self) -> bool;
+}
+
+impl<'a, St: ?Sized + FusedStream> FusedStream for &'a mut St {
+    fn is_terminated(&self) -> bool {
+        St::is_terminated(&**self)
+    }
+}
+
+impl<'a, St: ?Sized + FusedStream> FusedStream for PinMut<'a, St> {
+    fn is_terminated(&self) -> bool {
+        St::is_terminated(&**self)
+    }
+}
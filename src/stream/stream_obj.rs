@@ -0,0 +1,206 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::{Box, PinBox};
+use core::fmt;
+use core::marker::{PhantomData, Unpin};
+use core::mem::PinMut;
+use stream::Stream;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A custom trait object for polling streams, roughly akin to
+/// `Box<dyn Stream<Item = T> + 'a>`.
+///
+/// This custom trait object exists for the same reasons as `LocalFutureObj`:
+/// `Stream::poll_next` takes `self` through `PinMut`, which makes the trait
+/// not object safe with current compiler limitations.
+pub struct LocalStreamObj<'a, T, S: Spawn + ?Sized> {
+    ptr: *mut (),
+    poll_next_fn: unsafe fn(*mut (), &mut Context<S>) -> Poll<Option<T>>,
+    drop_fn: unsafe fn(*mut ()),
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a, T, S: Spawn + ?Sized> Unpin for LocalStreamObj<'a, T, S> {}
+
+impl<'a, T, S: Spawn + ?Sized> LocalStreamObj<'a, T, S> {
+    /// Create a `LocalStreamObj` from a custom trait object representation.
+    #[inline]
+    pub fn new<F: UnsafeStreamObj<'a, T, S> + 'a>(f: F) -> LocalStreamObj<'a, T, S> {
+        LocalStreamObj {
+            ptr: f.into_raw(),
+            poll_next_fn: F::poll_next,
+            drop_fn: F::drop,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Converts the `LocalStreamObj` into a `StreamObj`
+    /// To make this operation safe one has to ensure that the `UnsafeStreamObj`
+    /// instance from which this `LocalStreamObj` was created actually
+    /// implements `Send`.
+    #[inline]
+    pub unsafe fn into_stream_obj(self) -> StreamObj<'a, T, S> {
+        StreamObj(self)
+    }
+}
+
+impl<'a, T, S: Spawn + ?Sized> fmt::Debug for LocalStreamObj<'a, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LocalStreamObj")
+            .finish()
+    }
+}
+
+impl<'a, T, S: Spawn + ?Sized> From<StreamObj<'a, T, S>> for LocalStreamObj<'a, T, S> {
+    #[inline]
+    fn from(f: StreamObj<'a, T, S>) -> LocalStreamObj<'a, T, S> {
+        f.0
+    }
+}
+
+impl<'a, T, S: Spawn + ?Sized> Stream<S> for LocalStreamObj<'a, T, S> {
+    type Item = T;
+
+    #[inline]
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<T>> {
+        unsafe {
+            (self.poll_next_fn)(self.ptr, cx)
+        }
+    }
+}
+
+impl<'a, T, S: Spawn + ?Sized> Drop for LocalStreamObj<'a, T, S> {
+    fn drop(&mut self) {
+        unsafe {
+            (self.drop_fn)(self.ptr)
+        }
+    }
+}
+
+/// A custom trait object for polling streams, roughly akin to
+/// `Box<dyn Stream<Item = T> + Send + 'a>`.
+///
+/// This custom trait object exists for the same reasons as `FutureObj`:
+/// `Stream::poll_next` takes `self` through `PinMut`, which makes the trait
+/// not object safe with current compiler limitations.
+///
+/// Like `FutureObj`, a `StreamObj` is `Send` and `Sync` for any spawner and
+/// item types, since only the stream itself is stored.
+pub struct StreamObj<'a, T, S: Spawn + ?Sized>(LocalStreamObj<'a, T, S>);
+
+impl<'a, T, S: Spawn + ?Sized> Unpin for StreamObj<'a, T, S> {}
+// SAFETY: `StreamObj::new` requires the stream to be `Send`, and the obj
+// stores nothing else. As with `FutureObj`, the spawner is only borrowed
+// while polling and each item is returned to the thread which polled for
+// it, so neither `S` nor `T` needs to be `Send`.
+unsafe impl<'a, T, S: Spawn + ?Sized> Send for StreamObj<'a, T, S> {}
+// SAFETY: only `poll_next`, through `PinMut`, and drop reach the stream,
+// and `Debug` prints no fields, so a `&StreamObj` can't touch it.
+unsafe impl<'a, T, S: Spawn + ?Sized> Sync for StreamObj<'a, T, S> {}
+
+impl<'a, T, S: Spawn + ?Sized> StreamObj<'a, T, S> {
+    /// Create a `StreamObj` from a custom trait object representation.
+    #[inline]
+    pub fn new<F: UnsafeStreamObj<'a, T, S> + Send>(f: F) -> StreamObj<'a, T, S> {
+        StreamObj(LocalStreamObj::new(f))
+    }
+}
+
+impl<'a, T, S: Spawn + ?Sized> fmt::Debug for StreamObj<'a, T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StreamObj")
+            .finish()
+    }
+}
+
+impl<'a, T, S: Spawn + ?Sized> Stream<S> for StreamObj<'a, T, S> {
+    type Item = T;
+
+    #[inline]
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<T>> {
+        let pinned_field = unsafe { PinMut::map_unchecked(self, |x| &mut x.0) };
+        pinned_field.poll_next(cx)
+    }
+}
+
+/// A custom implementation of a stream trait object for `StreamObj`,
+/// providing a hand-rolled vtable.
+///
+/// The implementor must guarantee that it is safe to call `poll_next`
+/// repeatedly (in a non-concurrent fashion) with the result of `into_raw`
+/// until `drop` is called.
+pub unsafe trait UnsafeStreamObj<'a, T, S: Spawn + ?Sized>: 'a {
+    /// Convert an owned instance into a (conceptually owned) void pointer.
+    fn into_raw(self) -> *mut ();
+
+    /// Poll the stream represented by the given void pointer.
+    ///
+    /// # Safety
+    ///
+    /// The trait implementor must guarantee that it is safe to repeatedly call
+    /// `poll_next` with the result of `into_raw` until `drop` is called; such
+    /// calls are not, however, allowed to race with each other or with calls
+    /// to `drop`.
+    ///
+    /// This includes calls made after `poll_next` has returned
+    /// `Poll::Ready(None)`: those must remain memory safe, although, as for
+    /// any `Stream`, what they return is unspecified.
+    unsafe fn poll_next(ptr: *mut (), cx: &mut Context<S>) -> Poll<Option<T>>;
+
+    /// Drops the stream represented by the given void pointer.
+    ///
+    /// # Safety
+    ///
+    /// The trait implementor must guarantee that it is safe to call this
+    /// function once per `into_raw` invocation; that call cannot race with
+    /// other calls to `drop` or `poll_next`.
+    unsafe fn drop(ptr: *mut ());
+}
+
+unsafe impl<'a, T, F, S: Spawn + ?Sized> UnsafeStreamObj<'a, T, S> for &'a mut F
+    where F: Stream<S, Item = T> + Unpin + 'a
+{
+    fn into_raw(self) -> *mut () {
+        self as *mut F as *mut ()
+    }
+
+    unsafe fn poll_next(ptr: *mut (), cx: &mut Context<S>) -> Poll<Option<T>> {
+        PinMut::new_unchecked(&mut *(ptr as *mut F)).poll_next(cx)
+    }
+
+    unsafe fn drop(_ptr: *mut ()) {}
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<'a, T, F, S: Spawn + ?Sized> UnsafeStreamObj<'a, T, S> for Box<F>
+    where F: Stream<S, Item = T> + 'a
+{
+    fn into_raw(self) -> *mut () {
+        Box::into_raw(self) as *mut ()
+    }
+
+    unsafe fn poll_next(ptr: *mut (), cx: &mut Context<S>) -> Poll<Option<T>> {
+        PinMut::new_unchecked(&mut *(ptr as *mut F)).poll_next(cx)
+    }
+
+    unsafe fn drop(ptr: *mut ()) {
+        drop(Box::from_raw(ptr as *mut F))
+    }
+}
+
+#[cfg(feature = "alloc")]
+unsafe impl<'a, T, F, S: Spawn + ?Sized> UnsafeStreamObj<'a, T, S> for PinBox<F>
+    where F: Stream<S, Item = T> + 'a
+{
+    fn into_raw(self) -> *mut () {
+        PinBox::into_raw(self) as *mut ()
+    }
+
+    unsafe fn poll_next(ptr: *mut (), cx: &mut Context<S>) -> Poll<Option<T>> {
+        PinMut::new_unchecked(&mut *(ptr as *mut F)).poll_next(cx)
+    }
+
+    unsafe fn drop(ptr: *mut ()) {
+        drop(PinBox::from_raw(ptr as *mut F))
+    }
+}
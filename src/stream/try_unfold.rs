@@ -0,0 +1,70 @@
+use core::fmt;
+use core::mem::PinMut;
+use future::Future;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `try_unfold` function.
+pub struct TryUnfold<T, F, Fut> {
+    f: F,
+    state: Option<T>,
+    future: Option<Fut>,
+}
+
+/// Creates a fallible stream from a seed and a closure returning a future.
+///
+/// This is like `unfold`, but the future resolves to a `Result`. Each
+/// `Ok(Some((item, next_state)))` yields `Ok(item)` and `Ok(None)` ends the
+/// stream. An `Err(e)` is yielded once, after which the stream ends.
+pub fn try_unfold<T, F, Fut>(init: T, f: F) -> TryUnfold<T, F, Fut>
+    where F: FnMut(T) -> Fut
+{
+    TryUnfold { f, state: Some(init), future: None }
+}
+
+impl<T: fmt::Debug, F, Fut: fmt::Debug> fmt::Debug for TryUnfold<T, F, Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TryUnfold")
+            .field("state", &self.state)
+            .field("future", &self.future)
+            .finish()
+    }
+}
+
+impl<T, F, Fut, It, E, S> Stream<S> for TryUnfold<T, F, Fut>
+    where F: FnMut(T) -> Fut,
+          Fut: Future<S, Output = Result<Option<(It, T)>, E>>,
+          S: Spawn + ?Sized
+{
+    type Item = Result<It, E>;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Result<It, E>>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if let Some(state) = this.state.take() {
+            this.future = Some((this.f)(state));
+        }
+        let step = {
+            let future = match &mut this.future {
+                Some(future) => unsafe { PinMut::new_unchecked(future) },
+                None => return Poll::Ready(None),
+            };
+            ready!(future.poll(cx))
+        };
+        this.future = None;
+        Poll::Ready(match step {
+            Ok(Some((item, next))) => {
+                this.state = Some(next);
+                Some(Ok(item))
+            }
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+}
+
+impl<T, F, Fut> FusedStream for TryUnfold<T, F, Fut> {
+    fn is_terminated(&self) -> bool {
+        self.state.is_none() && self.future.is_none()
+    }
+}
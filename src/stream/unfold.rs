@@ -0,0 +1,69 @@
+use core::fmt;
+use core::mem::PinMut;
+use future::Future;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `unfold` function.
+pub struct Unfold<T, F, Fut> {
+    f: F,
+    state: Option<T>,
+    future: Option<Fut>,
+}
+
+/// Creates a stream from a seed and a closure returning a future.
+///
+/// The closure is called with the current state and returns a future which
+/// resolves to `Some((item, next_state))` to yield `item`, or to `None` to
+/// end the stream. The closure isn't called again until the future it
+/// returned has completed, and never again once a future resolves to
+/// `None`.
+pub fn unfold<T, F, Fut>(init: T, f: F) -> Unfold<T, F, Fut>
+    where F: FnMut(T) -> Fut
+{
+    Unfold { f, state: Some(init), future: None }
+}
+
+impl<T: fmt::Debug, F, Fut: fmt::Debug> fmt::Debug for Unfold<T, F, Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Unfold")
+            .field("state", &self.state)
+            .field("future", &self.future)
+            .finish()
+    }
+}
+
+impl<T, F, Fut, It, S> Stream<S> for Unfold<T, F, Fut>
+    where F: FnMut(T) -> Fut,
+          Fut: Future<S, Output = Option<(It, T)>>,
+          S: Spawn + ?Sized
+{
+    type Item = It;
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<It>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if let Some(state) = this.state.take() {
+            this.future = Some((this.f)(state));
+        }
+        let step = {
+            let future = match &mut this.future {
+                Some(future) => unsafe { PinMut::new_unchecked(future) },
+                None => return Poll::Ready(None),
+            };
+            ready!(future.poll(cx))
+        };
+        // The step future is dropped in place before the next one is made.
+        this.future = None;
+        Poll::Ready(step.map(|(item, next)| {
+            this.state = Some(next);
+            item
+        }))
+    }
+}
+
+impl<T, F, Fut> FusedStream for Unfold<T, F, Fut> {
+    fn is_terminated(&self) -> bool {
+        self.state.is_none() && self.future.is_none()
+    }
+}
@@ -0,0 +1,108 @@
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use stream::{Stream, FusedStream};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Stream for the `StreamExt::zip` method.
+pub struct Zip<St1, St2, S: Spawn + ?Sized = dyn Spawn>
+    where St1: Stream<S>,
+          St2: Stream<S>
+{
+    stream1: St1,
+    stream2: St2,
+    queued1: Option<St1::Item>,
+    queued2: Option<St2::Item>,
+    done: bool,
+}
+
+impl<St1, St2, S> Unpin for Zip<St1, St2, S>
+    where St1: Stream<S> + Unpin,
+          St2: Stream<S> + Unpin,
+          S: Spawn + ?Sized
+{}
+
+impl<St1, St2, S> fmt::Debug for Zip<St1, St2, S>
+    where St1: Stream<S> + fmt::Debug,
+          St2: Stream<S> + fmt::Debug,
+          St1::Item: fmt::Debug,
+          St2::Item: fmt::Debug,
+          S: Spawn + ?Sized
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Zip")
+            .field("stream1", &self.stream1)
+            .field("stream2", &self.stream2)
+            .field("queued1", &self.queued1)
+            .field("queued2", &self.queued2)
+            .finish()
+    }
+}
+
+impl<St1, St2, S> Zip<St1, St2, S>
+    where St1: Stream<S>,
+          St2: Stream<S>,
+          S: Spawn + ?Sized
+{
+    pub(super) fn new(stream1: St1, stream2: St2) -> Zip<St1, St2, S> {
+        Zip { stream1, stream2, queued1: None, queued2: None, done: false }
+    }
+}
+
+impl<St1, St2, S> Stream<S> for Zip<St1, St2, S>
+    where St1: Stream<S>,
+          St2: Stream<S>,
+          S: Spawn + ?Sized
+{
+    type Item = (St1::Item, St2::Item);
+
+    fn poll_next(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Self::Item>> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        // Both sides are polled on every call, so that each registers the
+        // task, but each buffers at most one item while waiting for the other.
+        if this.queued1.is_none() {
+            let stream1 = unsafe { PinMut::new_unchecked(&mut this.stream1) };
+            match stream1.poll_next(cx) {
+                Poll::Ready(Some(item)) => this.queued1 = Some(item),
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => {}
+            }
+        }
+        if this.queued2.is_none() && !this.done {
+            let stream2 = unsafe { PinMut::new_unchecked(&mut this.stream2) };
+            match stream2.poll_next(cx) {
+                Poll::Ready(Some(item)) => this.queued2 = Some(item),
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => {}
+            }
+        }
+
+        if this.done {
+            // An item buffered from the longer side is dropped.
+            this.queued1 = None;
+            this.queued2 = None;
+            return Poll::Ready(None);
+        }
+        if this.queued1.is_some() && this.queued2.is_some() {
+            let pair = (this.queued1.take().unwrap(), this.queued2.take().unwrap());
+            Poll::Ready(Some(pair))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<St1, St2, S> FusedStream for Zip<St1, St2, S>
+    where St1: Stream<S>,
+          St2: Stream<S>,
+          S: Spawn + ?Sized
+{
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
@@ -8,35 +8,51 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use std::fmt;
-use std::task::{Waker, LocalWaker};
-use spawn::Spawn;
+use core::fmt;
+use core::task::{Waker, LocalWaker};
+use spawn::{Spawn, SpawnHandle, SpawnShared};
+#[cfg(feature = "alloc")]
+use future::Future;
+#[cfg(feature = "alloc")]
+use spawn::{SpawnLocal, LocalSpawnExt, SpawnErrorKind};
 
 /// Information about the currently-running task.
 ///
 /// Contexts are always tied to the stack, since they are set up specifically
 /// when performing a single `poll` step on a task.
+///
+/// A context carries two handles for waking the task: a `LocalWaker`, which
+/// may only be used on the thread polling the task, and a `Waker`, which can
+/// be sent to other threads. The executor supplies both, so that handing out
+/// a `Waker` never requires reinterpreting a `LocalWaker`, whose wake object
+/// need not be thread-safe.
 pub struct Context<'a, S: Spawn + 'a + ?Sized = dyn Spawn> {
     local_waker: &'a LocalWaker,
+    waker: &'a Waker,
     spawner: &'a mut S,
 }
 
-impl<'a, S: Spawn + 'a> fmt::Debug for Context<'a, S> {
+impl<'a, S: Spawn + 'a + ?Sized> fmt::Debug for Context<'a, S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("Context")
             .finish()
     }
 }
 
-impl<'a, S: Spawn + 'a> Context<'a, S> {
+impl<'a, S: Spawn + 'a + ?Sized> Context<'a, S> {
     /// Create a new task `Context` with the provided `local_waker`, `waker`,
     /// and `spawner`.
+    ///
+    /// Both wakers must wake the same task. The `waker` may be woken from
+    /// any thread, so it must not share a non-thread-safe wake object with
+    /// `local_waker`.
     #[inline]
     pub fn new(
         local_waker: &'a LocalWaker,
+        waker: &'a Waker,
         spawner: &'a mut S,
     ) -> Context<'a, S> {
-        Context { local_waker, spawner }
+        Context { local_waker, waker, spawner }
     }
 
     /// Get the `LocalWaker` associated with the current task.
@@ -48,7 +64,7 @@ impl<'a, S: Spawn + 'a> Context<'a, S> {
     /// Get the `Waker` associated with the current task.
     #[inline]
     pub fn waker(&self) -> &'a Waker {
-        unsafe { &*(self.local_waker as *const LocalWaker as *const Waker) }
+        self.waker
     }
 
     /// Get the spawner associated with this task.
@@ -60,19 +76,37 @@ impl<'a, S: Spawn + 'a> Context<'a, S> {
         self.spawner
     }
 
-    /// Produce a context like the current one, but using the given waker
+    /// Reborrow this context, producing one with the same wakers and
+    /// spawner but a shorter lifetime.
+    ///
+    /// This is useful for passing a context by value to a helper while
+    /// keeping the original for later use; the reborrow can itself be
+    /// adapted with `with_waker` or `with_spawner`.
+    #[inline]
+    pub fn by_ref(&mut self) -> Context<S> {
+        Context {
+            local_waker: self.local_waker,
+            waker: self.waker,
+            spawner: self.spawner,
+        }
+    }
+
+    /// Produce a context like the current one, but using the given wakers
     /// instead.
     ///
     /// This advanced method is primarily used when building "internal
     /// schedulers" within a task, where you want to provide some customized
-    /// wakeup logic.
+    /// wakeup logic. As with `Context::new`, both wakers must wake the same
+    /// task.
     #[inline]
     pub fn with_waker<'b>(
         &'b mut self,
         local_waker: &'b LocalWaker,
+        waker: &'b Waker,
     ) -> Context<'b, S> {
         Context {
             local_waker,
+            waker,
             spawner: self.spawner,
         }
     }
@@ -89,7 +123,41 @@ impl<'a, S: Spawn + 'a> Context<'a, S> {
     ) -> Context<'b, Sp> {
         Context {
             local_waker: self.local_waker,
+            waker: self.waker,
             spawner,
         }
     }
-}
\ No newline at end of file
+}
+
+impl<'a, S: SpawnHandle + 'a> Context<'a, S> {
+    /// Get a handle to the spawner associated with this task, which can be
+    /// kept and used after this poll step has finished.
+    #[inline]
+    pub fn spawn_handle(&self) -> S {
+        self.spawner.clone()
+    }
+}
+
+impl<'a, S: SpawnShared + 'a + ?Sized> Context<'a, S> {
+    /// Get shared access to the spawner associated with this task, for
+    /// spawning where only a `&Context` is available.
+    #[inline]
+    pub fn spawner_shared(&self) -> &S {
+        self.spawner
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, S: SpawnLocal + 'a + ?Sized> Context<'a, S> {
+    /// Spawn a task on the current thread which polls the given future to
+    /// completion.
+    ///
+    /// The future need not be `Send`. This is shorthand for
+    /// `cx.spawner().spawn_local(future)`.
+    #[inline]
+    pub fn spawn_local<Fut>(&mut self, future: Fut) -> Result<(), SpawnErrorKind>
+        where Fut: Future<Output = ()> + 'static
+    {
+        self.spawner.spawn_local(future)
+    }
+}
@@ -1,4 +1,10 @@
-pub use std::task::{Poll, Waker, LocalWaker, UnsafeWake};
+pub use core::task::{Poll, Waker, LocalWaker, UnsafeWake};
 
 mod context;
-pub use self::context::Context;
\ No newline at end of file
+pub use self::context::Context;
+
+mod noop_waker;
+pub use self::noop_waker::{noop_local_waker, noop_waker};
+
+mod atomic_waker;
+pub use self::atomic_waker::AtomicWaker;
@@ -0,0 +1,35 @@
+use core::ptr::NonNull;
+use core::task::{LocalWaker, UnsafeWake, Waker};
+
+struct NoopWake;
+
+unsafe impl UnsafeWake for NoopWake {
+    unsafe fn clone_raw(&self) -> Waker {
+        noop_waker()
+    }
+
+    unsafe fn drop_raw(&self) {}
+
+    unsafe fn wake(&self) {}
+}
+
+static NOOP: NoopWake = NoopWake;
+
+fn noop_unsafe_wake() -> NonNull<dyn UnsafeWake> {
+    // `UnsafeWake` only ever takes `&self`, so the pointer is never written
+    // through, and `NoopWake` is zero-sized, so there is nothing to write.
+    NonNull::from(&NOOP as &dyn UnsafeWake)
+}
+
+/// Create a new `Waker` which does nothing when `wake()` is called on it.
+#[inline]
+pub fn noop_waker() -> Waker {
+    unsafe { Waker::new(noop_unsafe_wake()) }
+}
+
+/// Create a new `LocalWaker` which does nothing when `wake()` is called on
+/// it.
+#[inline]
+pub fn noop_local_waker() -> LocalWaker {
+    unsafe { LocalWaker::new(noop_unsafe_wake()) }
+}
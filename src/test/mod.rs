@@ -0,0 +1,247 @@
+//! Utilities for testing futures, streams and sinks.
+//!
+//! This module is only available with the `test-util` feature. It provides a
+//! `TestContext` which sets up a waker and spawner that record what happened
+//! to them, and assertion macros for the `Poll` values returned by a single
+//! poll step.
+
+use core::fmt;
+use std::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{LocalWaker, Waker, Wake, local_waker_from_nonlocal};
+use future::FutureObj;
+use spawn::{Spawn, SpawnObjError};
+use task::Context;
+
+mod poll_guard;
+pub use self::poll_guard::PollGuard;
+
+/// A waker which counts how many times it has been woken.
+#[derive(Debug, Default)]
+pub struct FlagWaker {
+    wakes: AtomicUsize,
+}
+
+impl FlagWaker {
+    /// Create a new `FlagWaker` which has not been woken.
+    pub fn new() -> Arc<FlagWaker> {
+        Arc::new(FlagWaker::default())
+    }
+
+    /// Whether the waker has been woken at least once.
+    pub fn woken(&self) -> bool {
+        self.wakes() > 0
+    }
+
+    /// The number of times the waker has been woken.
+    pub fn wakes(&self) -> usize {
+        self.wakes.load(Ordering::SeqCst)
+    }
+
+    /// Reset the wake count to zero, returning the previous count.
+    pub fn reset(&self) -> usize {
+        self.wakes.swap(0, Ordering::SeqCst)
+    }
+}
+
+impl Wake for FlagWaker {
+    fn wake(arc_self: &Arc<Self>) {
+        arc_self.wakes.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// A spawner which accepts every task, and keeps it around without ever
+/// running it.
+#[derive(Default)]
+pub struct RecordingSpawner {
+    spawned: Vec<FutureObj<'static, (), dyn Spawn>>,
+}
+
+impl fmt::Debug for RecordingSpawner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RecordingSpawner")
+            .field("spawned", &self.spawned.len())
+            .finish()
+    }
+}
+
+impl RecordingSpawner {
+    /// Create a new `RecordingSpawner` which has not spawned anything.
+    pub fn new() -> RecordingSpawner {
+        RecordingSpawner::default()
+    }
+
+    /// The number of tasks spawned so far.
+    pub fn spawned(&self) -> usize {
+        self.spawned.len()
+    }
+
+    /// Take the futures spawned so far, e.g. to run them by hand.
+    pub fn take_spawned(&mut self) -> Vec<FutureObj<'static, (), dyn Spawn>> {
+        self.spawned.drain(..).collect()
+    }
+}
+
+impl Spawn for RecordingSpawner {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawned.push(future);
+        Ok(())
+    }
+}
+
+/// Everything needed to poll a future by hand in a test.
+///
+/// A `TestContext` owns a `FlagWaker` and a spawner, `RecordingSpawner` by
+/// default, and lends out task contexts built from them. After polling, the
+/// `woken` and `spawned` accessors report what the future did with its
+/// context.
+///
+/// Futures specialized to a concrete spawner can be tested by substituting
+/// it with `with_spawner` and polling with `specialized_context`.
+///
+/// ```
+/// #![feature(pin, arbitrary_self_types, futures_api)]
+/// # #[macro_use] extern crate specialized_futures;
+/// use std::mem::PinMut;
+/// use specialized_futures::Future;
+/// use specialized_futures::future::{ready, pending};
+/// use specialized_futures::test::TestContext;
+///
+/// # fn main() {
+/// let mut tcx = TestContext::new();
+/// let mut fut = ready(5);
+/// assert_ready_eq!(PinMut::new(&mut fut).poll(&mut tcx.context()), 5);
+///
+/// let mut fut = pending::<()>();
+/// assert_pending!(PinMut::new(&mut fut).poll(&mut tcx.context()));
+/// assert!(!tcx.woken());
+/// assert_eq!(tcx.spawned(), 0);
+/// # }
+/// ```
+pub struct TestContext<Sp = RecordingSpawner> {
+    flag: Arc<FlagWaker>,
+    local_waker: LocalWaker,
+    waker: Waker,
+    spawner: Sp,
+}
+
+impl<Sp: fmt::Debug> fmt::Debug for TestContext<Sp> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TestContext")
+            .field("flag", &self.flag)
+            .field("spawner", &self.spawner)
+            .finish()
+    }
+}
+
+impl Default for TestContext {
+    fn default() -> TestContext {
+        TestContext::new()
+    }
+}
+
+impl TestContext {
+    /// Create a new `TestContext` with a fresh `FlagWaker` and an empty
+    /// `RecordingSpawner`.
+    pub fn new() -> TestContext {
+        TestContext::with_spawner(RecordingSpawner::new())
+    }
+
+    /// The number of tasks spawned through this context so far.
+    pub fn spawned(&self) -> usize {
+        self.spawner.spawned()
+    }
+}
+
+impl<Sp: Spawn> TestContext<Sp> {
+    /// Create a new `TestContext` with a fresh `FlagWaker` and the given
+    /// spawner.
+    pub fn with_spawner(spawner: Sp) -> TestContext<Sp> {
+        let flag = FlagWaker::new();
+        let local_waker = local_waker_from_nonlocal(flag.clone());
+        let waker = Waker::from(flag.clone());
+        TestContext { flag, local_waker, waker, spawner }
+    }
+
+    /// A task context for polling futures that work with any spawner.
+    pub fn context(&mut self) -> Context<dyn Spawn> {
+        Context::new(&self.local_waker, &self.waker, &mut self.spawner as &mut dyn Spawn)
+    }
+
+    /// A task context specialized to this context's spawner type.
+    pub fn specialized_context(&mut self) -> Context<Sp> {
+        Context::new(&self.local_waker, &self.waker, &mut self.spawner)
+    }
+
+    /// Whether the waker has been woken since it was last reset.
+    pub fn woken(&self) -> bool {
+        self.flag.woken()
+    }
+
+    /// The waker handed out by this context's task contexts.
+    pub fn flag(&self) -> &Arc<FlagWaker> {
+        &self.flag
+    }
+
+    /// The spawner handed out by this context's task contexts.
+    pub fn spawner(&mut self) -> &mut Sp {
+        &mut self.spawner
+    }
+}
+
+/// Asserts that a `Poll` is `Ready`, returning the value inside.
+///
+/// On failure, the panic message includes the expression that was polled.
+#[macro_export]
+macro_rules! assert_ready {
+    ($e:expr) => {
+        match $e {
+            $crate::task::Poll::Ready(t) => t,
+            $crate::task::Poll::Pending => {
+                panic!("assertion failed: `{}` is ready, but it was `Pending`", stringify!($e))
+            }
+        }
+    };
+}
+
+/// Asserts that a `Poll` is `Ready` with a value equal to the given one.
+///
+/// On failure, the panic message includes the expression that was polled
+/// and the unexpected value or variant.
+#[macro_export]
+macro_rules! assert_ready_eq {
+    ($e:expr, $expected:expr) => {
+        match ($e, $expected) {
+            ($crate::task::Poll::Ready(t), expected) => {
+                if t != expected {
+                    panic!("assertion failed: `{}` is ready with `{:?}`, but it was `Ready({:?})`",
+                           stringify!($e), expected, t)
+                }
+            }
+            ($crate::task::Poll::Pending, expected) => {
+                panic!("assertion failed: `{}` is ready with `{:?}`, but it was `Pending`",
+                       stringify!($e), expected)
+            }
+        }
+    };
+}
+
+/// Asserts that a `Poll` is `Pending`.
+///
+/// On failure, the panic message includes the expression that was polled
+/// and the value it was ready with.
+#[macro_export]
+macro_rules! assert_pending {
+    ($e:expr) => {
+        match $e {
+            $crate::task::Poll::Pending => {}
+            $crate::task::Poll::Ready(t) => {
+                panic!("assertion failed: `{}` is pending, but it was `Ready({:?})`",
+                       stringify!($e), t)
+            }
+        }
+    };
+}
@@ -0,0 +1,120 @@
+use core::fmt;
+use core::intrinsics::type_name;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use core::sync::atomic::{AtomicBool, Ordering};
+use future::{Future, FusedFuture};
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `guard_polls` combinator, which panics as soon as the
+/// wrapped future is misused.
+///
+/// This is created by the `FutureExt::guard_polls` method. Otherwise it
+/// behaves exactly like the wrapped future.
+///
+/// Besides polls after completion, the guard catches a poll which starts
+/// while another poll of the same future is still running. Polling takes a
+/// `PinMut`, so two threads can never poll the guard at once; this can only
+/// happen re-entrantly, when a buggy combinator polls its child again, e.g.
+/// through an aliased pointer, from inside that child's own `poll`.
+pub struct PollGuard<Fut> {
+    future: Fut,
+    polls: usize,
+    done: bool,
+    in_poll: AtomicBool,
+    on_unpolled_drop: Option<fn(&'static str)>,
+}
+
+impl<Fut: Unpin> Unpin for PollGuard<Fut> {}
+
+impl<Fut: fmt::Debug> fmt::Debug for PollGuard<Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PollGuard")
+            .field("future", &self.future)
+            .field("polls", &self.polls)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<Fut> PollGuard<Fut> {
+    pub(crate) fn new(future: Fut) -> PollGuard<Fut> {
+        PollGuard {
+            future,
+            polls: 0,
+            done: false,
+            in_poll: AtomicBool::new(false),
+            on_unpolled_drop: None,
+        }
+    }
+
+    /// Call `hook` with the wrapped future's type name if this guard is
+    /// dropped without ever having been polled.
+    ///
+    /// Such a future was most likely created and forgotten by mistake. The
+    /// hook can log a warning or panic, depending on how strict the test
+    /// should be.
+    pub fn on_unpolled_drop(mut self, hook: fn(&'static str)) -> PollGuard<Fut> {
+        self.on_unpolled_drop = Some(hook);
+        self
+    }
+
+    /// The number of times the wrapped future has been polled.
+    pub fn polls(&self) -> usize {
+        self.polls
+    }
+}
+
+/// Clears the re-entrancy flag when the poll returns or unwinds.
+struct InPoll<'a>(&'a AtomicBool);
+
+impl<'a> Drop for InPoll<'a> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+impl<S, Fut> Future<S> for PollGuard<Fut>
+    where S: Spawn + ?Sized,
+          Fut: Future<S>
+{
+    type Output = Fut::Output;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Fut::Output> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        let name = unsafe { type_name::<Fut>() };
+        if this.in_poll.swap(true, Ordering::SeqCst) {
+            panic!("`{}` polled re-entrantly while it was already being polled \
+                    (after {} polls)",
+                   name, this.polls);
+        }
+        let _in_poll = InPoll(&this.in_poll);
+        if this.done {
+            panic!("`{}` polled after completion (after {} polls)", name, this.polls);
+        }
+
+        this.polls += 1;
+        let res = unsafe { PinMut::new_unchecked(&mut this.future) }.poll(cx);
+        if res.is_ready() {
+            this.done = true;
+        }
+        res
+    }
+}
+
+impl<Fut> FusedFuture for PollGuard<Fut> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl<Fut> Drop for PollGuard<Fut> {
+    fn drop(&mut self) {
+        if self.polls == 0 {
+            if let Some(hook) = self.on_unpolled_drop {
+                hook(unsafe { type_name::<Fut>() });
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A source of the current time for a `Timer`.
+pub trait Clock: Send + Sync {
+    /// Returns the current instant according to this clock.
+    fn now(&self) -> Instant;
+}
+
+/// A `Clock` reading the system's monotonic clock.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A `Clock` which only moves forward when told to.
+///
+/// This is useful for deterministically testing code that depends on timers.
+/// Clones share the same underlying time.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl MockClock {
+    /// Create a new `MockClock` starting at the current system time.
+    pub fn new() -> MockClock {
+        MockClock { now: Arc::new(Mutex::new(Instant::now())) }
+    }
+
+    /// Move this clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> MockClock {
+        MockClock::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
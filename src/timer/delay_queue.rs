@@ -0,0 +1,215 @@
+use core::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use std::time::{Duration, Instant};
+use future::Future;
+use stream::Stream;
+use task::{Context, Poll, Waker};
+use spawn::Spawn;
+use timer::{TimerHandle, Sleep};
+
+/// A key identifying an entry in a `DelayQueue`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Key(u64);
+
+/// An entry whose deadline has passed, yielded by a `DelayQueue`.
+#[derive(Debug)]
+pub struct Expired<T> {
+    value: T,
+    key: Key,
+    deadline: Instant,
+}
+
+impl<T> Expired<T> {
+    /// The value which was inserted.
+    pub fn get_ref(&self) -> &T {
+        &self.value
+    }
+
+    /// Consume the entry, returning the value which was inserted.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// The key the entry was inserted under. It is no longer valid.
+    pub fn key(&self) -> Key {
+        self.key
+    }
+
+    /// The deadline at which the entry expired.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+}
+
+struct Slot<T> {
+    value: T,
+    deadline: Instant,
+}
+
+/// A queue of values which are each yielded, as a stream, once their delay
+/// has passed.
+///
+/// Entries are yielded in deadline order, whatever order they were inserted
+/// in. The queue keeps a single `Sleep` on its timer for the earliest
+/// deadline, so the consuming task is only woken when that deadline passes
+/// or an earlier one is inserted. Polling an empty queue returns `Pending`
+/// rather than ending the stream, since entries may still be inserted.
+pub struct DelayQueue<T> {
+    timer: TimerHandle,
+    entries: HashMap<u64, Slot<T>>,
+    // Entries are removed and reset lazily: a heap item is stale unless its
+    // entry still exists with the same deadline.
+    heap: BinaryHeap<Reverse<(Instant, u64)>>,
+    next_key: u64,
+    sleep: Option<Sleep>,
+    waker: Option<Waker>,
+}
+
+impl<T> Unpin for DelayQueue<T> {}
+
+impl<T> fmt::Debug for DelayQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DelayQueue")
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
+
+impl<T> DelayQueue<T> {
+    /// Create an empty queue, whose deadlines are kept by the given timer.
+    pub fn new(timer: TimerHandle) -> DelayQueue<T> {
+        DelayQueue {
+            timer,
+            entries: HashMap::new(),
+            heap: BinaryHeap::new(),
+            next_key: 0,
+            sleep: None,
+            waker: None,
+        }
+    }
+
+    /// The number of entries in the queue.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the queue has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Insert `value`, to be yielded once `delay` has passed.
+    pub fn insert(&mut self, value: T, delay: Duration) -> Key {
+        let key = self.next_key;
+        self.next_key += 1;
+        let deadline = self.timer.now() + delay;
+        self.entries.insert(key, Slot { value, deadline });
+        self.schedule(key, deadline);
+        Key(key)
+    }
+
+    /// Change the delay of an entry, so it is yielded once `delay` has
+    /// passed from now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key isn't in the queue.
+    pub fn reset(&mut self, key: Key, delay: Duration) {
+        let deadline = self.timer.now() + delay;
+        match self.entries.get_mut(&key.0) {
+            Some(slot) => slot.deadline = deadline,
+            None => panic!("invalid key passed to DelayQueue::reset"),
+        }
+        self.schedule(key.0, deadline);
+    }
+
+    /// Remove an entry before it expires, returning its value.
+    ///
+    /// Returns `None` if the key isn't in the queue, e.g. because the entry
+    /// has already been yielded.
+    pub fn remove(&mut self, key: Key) -> Option<T> {
+        self.entries.remove(&key.0).map(|slot| slot.value)
+    }
+
+    /// Remove every entry from the queue.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.heap.clear();
+        self.sleep = None;
+    }
+
+    fn schedule(&mut self, key: u64, deadline: Instant) {
+        self.heap.push(Reverse((deadline, key)));
+        // Only an entry which is now the earliest needs the task to re-arm
+        // its sleep.
+        let earlier = match &self.sleep {
+            Some(sleep) => deadline < sleep.deadline(),
+            None => true,
+        };
+        if earlier {
+            self.sleep = None;
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// The earliest live entry, discarding stale heap items.
+    fn peek(&mut self) -> Option<(Instant, u64)> {
+        while let Some(&Reverse((deadline, key))) = self.heap.peek() {
+            match self.entries.get(&key) {
+                Some(slot) if slot.deadline == deadline => return Some((deadline, key)),
+                _ => {
+                    self.heap.pop();
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<T, S: Spawn + ?Sized> Stream<S> for DelayQueue<T> {
+    type Item = Expired<T>;
+
+    fn poll_next(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<Expired<T>>> {
+        let this = &mut *self;
+        loop {
+            let (deadline, key) = match this.peek() {
+                Some(next) => next,
+                None => {
+                    this.sleep = None;
+                    this.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            };
+
+            if deadline <= this.timer.now() {
+                this.heap.pop();
+                let slot = this.entries.remove(&key).unwrap();
+                return Poll::Ready(Some(Expired {
+                    value: slot.value,
+                    key: Key(key),
+                    deadline,
+                }));
+            }
+
+            let rearm = match &this.sleep {
+                Some(sleep) => sleep.deadline() != deadline,
+                None => true,
+            };
+            if rearm {
+                this.sleep = Some(this.timer.sleep_until(deadline));
+            }
+            match PinMut::new(this.sleep.as_mut().unwrap()).poll(cx) {
+                Poll::Ready(()) => this.sleep = None,
+                Poll::Pending => {
+                    this.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::PinMut;
+use core::time::Duration;
+use future::Future;
+use stream::Stream;
+use task::{Context, Poll};
+use spawn::TimerSpawn;
+
+/// A periodic timer created by the `interval` function.
+///
+/// Ticks can be awaited one at a time with `next_tick`, or the interval can
+/// be used as a `Stream` which yields `()` at every tick and never ends.
+///
+/// Each tick is scheduled one period after the previous tick was observed,
+/// using the sleep provided by the polling task's `TimerSpawn` spawner. If the
+/// consumer falls behind, missed ticks are skipped rather than delivered in a
+/// burst: the next tick always comes a full period after the last one was
+/// taken.
+pub struct Interval<S: TimerSpawn + ?Sized> {
+    period: Duration,
+    sleep: Option<S::Sleep>,
+}
+
+impl<S: TimerSpawn + ?Sized> Unpin for Interval<S> {}
+
+impl<S: TimerSpawn + ?Sized> fmt::Debug for Interval<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Interval")
+            .field("period", &self.period)
+            .field("scheduled", &self.sleep.is_some())
+            .finish()
+    }
+}
+
+/// Creates a new `Interval` that ticks every `period`, starting one period
+/// after it is first polled.
+///
+/// # Panics
+///
+/// Panics if `period` is zero.
+pub fn interval<S: TimerSpawn + ?Sized>(period: Duration) -> Interval<S> {
+    assert!(period != Duration::new(0, 0), "`period` must be non-zero");
+    Interval { period, sleep: None }
+}
+
+impl<S: TimerSpawn + ?Sized> Interval<S> {
+    /// Returns a future which resolves at the next tick of this interval.
+    pub fn next_tick(&mut self) -> NextTick<S> {
+        NextTick { interval: self }
+    }
+
+    /// Polls for the next tick of this interval.
+    pub fn poll_tick(&mut self, cx: &mut Context<S>) -> Poll<()> {
+        if self.sleep.is_none() {
+            self.sleep = Some(cx.spawner().sleep(self.period));
+        }
+        match PinMut::new(self.sleep.as_mut().unwrap()).poll(cx) {
+            Poll::Ready(()) => {
+                self.sleep = None;
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    /// Restarts the current period, so that the next tick happens a full
+    /// period after the interval is next polled.
+    pub fn reset(&mut self) {
+        self.sleep = None;
+    }
+
+    /// Returns the period of this interval.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Changes the period of this interval.
+    ///
+    /// A tick which is already scheduled keeps its deadline; the new period
+    /// applies from the tick after it. Call `reset` to apply it immediately.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is zero.
+    pub fn set_period(&mut self, period: Duration) {
+        assert!(period != Duration::new(0, 0), "`period` must be non-zero");
+        self.period = period;
+    }
+}
+
+impl<S: TimerSpawn + ?Sized> Stream<S> for Interval<S> {
+    type Item = ();
+
+    fn poll_next(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<()>> {
+        self.poll_tick(cx).map(Some)
+    }
+}
+
+/// Future for the `Interval::next_tick` method.
+pub struct NextTick<'a, S: TimerSpawn + ?Sized + 'a> {
+    interval: &'a mut Interval<S>,
+}
+
+impl<'a, S: TimerSpawn + ?Sized> Unpin for NextTick<'a, S> {}
+
+impl<'a, S: TimerSpawn + ?Sized> fmt::Debug for NextTick<'a, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NextTick")
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
+
+impl<'a, S: TimerSpawn + ?Sized> Future<S> for NextTick<'a, S> {
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<()> {
+        self.interval.poll_tick(cx)
+    }
+}
@@ -0,0 +1,310 @@
+//! A reference timer implementation for executors providing `TimerSpawn`.
+//!
+//! A `Timer` keeps its pending deadlines in a binary heap. The executor owning
+//! it is responsible for calling `Timer::turn` from its run loop, which fires
+//! every expired timer and reports when the next one is due so the executor
+//! knows how long it may sleep. `LocalPool` and `ThreadPool` drive a timer of
+//! their own this way, and implement `TimerSpawn` with it.
+
+use core::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::{self, PinMut};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use future::{Future, FusedFuture, FutureObj};
+use task::{Context, Poll, Waker};
+use spawn::{Spawn, SpawnObjError, SpawnErrorKind, TimerSpawn};
+
+mod clock;
+pub use self::clock::{Clock, SystemClock, MockClock};
+
+mod interval;
+pub use self::interval::{interval, Interval, NextTick};
+
+mod delay_queue;
+pub use self::delay_queue::{DelayQueue, Expired, Key};
+
+struct Entry {
+    waker: Option<Waker>,
+    fired: bool,
+}
+
+struct State {
+    next_id: u64,
+    heap: BinaryHeap<Reverse<(Instant, u64)>>,
+    entries: HashMap<u64, Entry>,
+    /// Woken when a deadline earlier than every pending one is added.
+    driver: Option<Waker>,
+}
+
+impl State {
+    /// Drop the heap entries of dropped `Sleep`s, once they make up most of
+    /// the heap, so that timers which are repeatedly created and cancelled
+    /// don't grow it without bound.
+    fn compact(&mut self) {
+        if self.heap.len() <= 2 * self.entries.len() + 16 {
+            return;
+        }
+        let entries = &self.entries;
+        let mut heap = mem::replace(&mut self.heap, BinaryHeap::new()).into_vec();
+        heap.retain(|&Reverse((_, id))| entries.contains_key(&id));
+        self.heap = BinaryHeap::from(heap);
+    }
+}
+
+struct Inner {
+    clock: Box<dyn Clock>,
+    state: Mutex<State>,
+}
+
+/// A timer which fires `Sleep` futures once their deadline has passed.
+pub struct Timer {
+    inner: Arc<Inner>,
+}
+
+impl fmt::Debug for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Timer")
+            .finish()
+    }
+}
+
+impl Timer {
+    /// Create a new `Timer` driven by the system clock.
+    pub fn new() -> Timer {
+        Timer::with_clock(SystemClock)
+    }
+
+    /// Create a new `Timer` driven by the given clock.
+    pub fn with_clock<C: Clock + 'static>(clock: C) -> Timer {
+        Timer {
+            inner: Arc::new(Inner {
+                clock: Box::new(clock),
+                state: Mutex::new(State {
+                    next_id: 0,
+                    heap: BinaryHeap::new(),
+                    entries: HashMap::new(),
+                    driver: None,
+                }),
+            }),
+        }
+    }
+
+    /// Get a handle through which new timers can be created.
+    pub fn handle(&self) -> TimerHandle {
+        TimerHandle { inner: self.inner.clone() }
+    }
+
+    /// Wake `waker` whenever a timer is created with a deadline earlier than
+    /// every pending one.
+    ///
+    /// An executor sleeping until the deadline returned by `turn` uses this
+    /// to learn that it must wake up sooner. Only the most recent waker is
+    /// kept.
+    pub fn set_waker(&self, waker: &Waker) {
+        self.inner.state.lock().unwrap().driver = Some(waker.clone());
+    }
+
+    /// Fire every timer whose deadline has passed, waking the tasks waiting
+    /// on them.
+    ///
+    /// Returns the deadline of the earliest timer still pending, if any.
+    pub fn turn(&self) -> Option<Instant> {
+        let now = self.inner.clock.now();
+        let mut wakers = Vec::new();
+        let next = {
+            let mut state = self.inner.state.lock().unwrap();
+            let state = &mut *state;
+            let mut next = None;
+            while let Some(&Reverse((deadline, id))) = state.heap.peek() {
+                match state.entries.get_mut(&id) {
+                    // Entries of dropped `Sleep`s are removed lazily.
+                    None => {}
+                    Some(_) if deadline > now => {
+                        next = Some(deadline);
+                        break;
+                    }
+                    Some(entry) => {
+                        entry.fired = true;
+                        wakers.extend(entry.waker.take());
+                    }
+                }
+                state.heap.pop();
+            }
+            next
+        };
+        // Wake outside the lock, as a waker may poll the task inline, which
+        // would deadlock if it touched a `Sleep` of this timer.
+        for waker in wakers {
+            waker.wake();
+        }
+        next
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Timer {
+        Timer::new()
+    }
+}
+
+/// A cloneable handle to a `Timer`.
+#[derive(Clone)]
+pub struct TimerHandle {
+    inner: Arc<Inner>,
+}
+
+impl fmt::Debug for TimerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TimerHandle")
+            .finish()
+    }
+}
+
+impl TimerHandle {
+    /// The current time, according to the timer's clock.
+    pub fn now(&self) -> Instant {
+        self.inner.clock.now()
+    }
+
+    /// Create a future which resolves once `duration` has elapsed.
+    pub fn sleep(&self, duration: Duration) -> Sleep {
+        self.sleep_until(self.inner.clock.now() + duration)
+    }
+
+    /// Create a future which resolves once `deadline` has been reached.
+    pub fn sleep_until(&self, deadline: Instant) -> Sleep {
+        let (id, driver) = {
+            let mut state = self.inner.state.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            let earliest = state.heap.peek().map_or(true, |&Reverse((next, _))| deadline < next);
+            state.heap.push(Reverse((deadline, id)));
+            state.entries.insert(id, Entry { waker: None, fired: false });
+            (id, if earliest { state.driver.clone() } else { None })
+        };
+        if let Some(driver) = driver {
+            driver.wake();
+        }
+        Sleep { inner: self.inner.clone(), id, deadline, done: false }
+    }
+}
+
+/// A future which resolves once its deadline has passed.
+///
+/// Created by `TimerHandle::sleep` or a `TimerSpawn` spawner. Dropping a
+/// `Sleep` deregisters it from its timer.
+pub struct Sleep {
+    inner: Arc<Inner>,
+    id: u64,
+    deadline: Instant,
+    done: bool,
+}
+
+impl Unpin for Sleep {}
+
+impl fmt::Debug for Sleep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sleep")
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+impl Sleep {
+    /// The instant at which this future resolves.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+}
+
+impl<S: Spawn + ?Sized> Future<S> for Sleep {
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<()> {
+        if self.done {
+            panic!("Sleep polled after completion");
+        }
+        let now = self.inner.clock.now();
+        let fired = {
+            let mut state = self.inner.state.lock().unwrap();
+            let entry = state.entries.get_mut(&self.id).unwrap();
+            if entry.fired || self.deadline <= now {
+                true
+            } else {
+                entry.waker = Some(cx.waker().clone());
+                false
+            }
+        };
+        if fired {
+            self.done = true;
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl FusedFuture for Sleep {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.inner.state.lock() {
+            state.entries.remove(&self.id);
+            state.compact();
+        }
+    }
+}
+
+/// A spawner combining another spawner with a `Timer`, so that tasks spawned
+/// through it can use `TimerSpawn`.
+#[derive(Debug, Clone)]
+pub struct TimerSpawner<S> {
+    spawner: S,
+    timer: TimerHandle,
+}
+
+impl<S: Spawn> TimerSpawner<S> {
+    /// Create a new `TimerSpawner` spawning onto `spawner` and creating timers
+    /// on the timer behind `timer`.
+    pub fn new(spawner: S, timer: TimerHandle) -> TimerSpawner<S> {
+        TimerSpawner { spawner, timer }
+    }
+
+    /// Get a reference to the underlying spawner.
+    pub fn get_ref(&self) -> &S {
+        &self.spawner
+    }
+
+    /// Get a mutable reference to the underlying spawner.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.spawner
+    }
+}
+
+impl<S: Spawn> Spawn for TimerSpawner<S> {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawner.spawn_obj(future)
+    }
+
+    fn status(&self) -> Result<(), SpawnErrorKind> {
+        self.spawner.status()
+    }
+}
+
+impl<S: Spawn> TimerSpawn for TimerSpawner<S> {
+    type Sleep = Sleep;
+
+    fn sleep(&mut self, duration: Duration) -> Sleep {
+        self.timer.sleep(duration)
+    }
+}
@@ -0,0 +1,45 @@
+use core::marker::{PhantomData, Unpin};
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use try_future::TryFuture;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `err_into` combinator, changing the error type of a future.
+///
+/// This is created by the `TryFutureExt::err_into` method.
+#[derive(Debug)]
+pub struct ErrInto<Fut, E> {
+    future: Fut,
+    _marker: PhantomData<E>,
+}
+
+impl<Fut: Unpin, E> Unpin for ErrInto<Fut, E> {}
+
+impl<Fut, E> ErrInto<Fut, E> {
+    pub(super) fn new(future: Fut) -> ErrInto<Fut, E> {
+        ErrInto { future, _marker: PhantomData }
+    }
+}
+
+impl<Fut: FusedFuture, E> FusedFuture for ErrInto<Fut, E> {
+    fn is_terminated(&self) -> bool {
+        self.future.is_terminated()
+    }
+}
+
+impl<S, Fut, E> Future<S> for ErrInto<Fut, E>
+    where S: Spawn + ?Sized,
+          Fut: TryFuture<S>,
+          Fut::Error: Into<E>
+{
+    type Output = Result<Fut::Ok, E>;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        let future = unsafe { PinMut::map_unchecked(self, |x| &mut x.future) };
+        match future.try_poll(cx) {
+            Poll::Ready(output) => Poll::Ready(output.map_err(Into::into)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
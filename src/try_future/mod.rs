@@ -0,0 +1,93 @@
+use core::mem::PinMut;
+use future::Future;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+mod err_into;
+pub use self::err_into::ErrInto;
+
+mod ok_into;
+pub use self::ok_into::OkInto;
+
+mod unwrap_or_else;
+pub use self::unwrap_or_else::UnwrapOrElse;
+
+mod try_select;
+pub use self::try_select::{try_select, TrySelect};
+
+mod try_join;
+pub use self::try_join::{try_join, try_join3, try_join4, try_join5, try_join6, try_join7, try_join8};
+pub use self::try_join::{TryJoin, TryJoin3, TryJoin4, TryJoin5, TryJoin6, TryJoin7, TryJoin8};
+
+/// A convenience for futures that return `Result` values that includes
+/// a variety of adapters tailored to such futures.
+pub trait TryFuture<S: Spawn + ?Sized = dyn Spawn> {
+    /// The type of successful values yielded by this future
+    type Ok;
+
+    /// The type of failures yielded by this future
+    type Error;
+
+    /// Poll this `TryFuture` as if it were a `Future`.
+    ///
+    /// This method is a stopgap for a compiler limitation that prevents us from
+    /// directly inheriting from the `Future` trait; in the future it won't be
+    /// needed.
+    fn try_poll(
+        self: PinMut<Self>,
+        cx: &mut Context<S>,
+    ) -> Poll<Result<Self::Ok, Self::Error>>;
+}
+
+impl<S, F, T, E> TryFuture<S> for F
+    where S: Spawn + ?Sized,
+          F: Future<S, Output = Result<T, E>> + ?Sized
+{
+    type Ok = T;
+    type Error = E;
+
+    #[inline]
+    fn try_poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<F::Output> {
+        self.poll(cx)
+    }
+}
+
+/// Adapters specific to `Result`-returning futures
+pub trait TryFutureExt<S: Spawn + ?Sized = dyn Spawn>: TryFuture<S> {
+    /// Maps this future's `Error` to a new error type using the `Into` trait.
+    ///
+    /// This method does for futures what the `?`-operator does for `Result`:
+    /// it lets the compiler infer the type of the resulting error. The
+    /// returned combinator is a named type, so it can be stored in struct
+    /// fields without boxing.
+    fn err_into<E>(self) -> ErrInto<Self, E>
+        where Self: Sized,
+              Self::Error: Into<E>
+    {
+        ErrInto::new(self)
+    }
+
+    /// Maps this future's `Ok` to a new type using the `Into` trait.
+    fn ok_into<T>(self) -> OkInto<Self, T>
+        where Self: Sized,
+              Self::Ok: Into<T>
+    {
+        OkInto::new(self)
+    }
+
+    /// Unwraps this future's output, producing a future with this future's
+    /// `Ok` type as its `Output` type.
+    ///
+    /// If this future is resolved successfully, the returned future will
+    /// contain the original future's success value as output. Otherwise, the
+    /// closure `f` is called with the error value to produce an alternate
+    /// success value.
+    fn unwrap_or_else<F>(self, f: F) -> UnwrapOrElse<Self, F>
+        where Self: Sized,
+              F: FnOnce(Self::Error) -> Self::Ok
+    {
+        UnwrapOrElse::new(self, f)
+    }
+}
+
+impl<S: Spawn + ?Sized, F: TryFuture<S> + ?Sized> TryFutureExt<S> for F {}
@@ -0,0 +1,45 @@
+use core::marker::{PhantomData, Unpin};
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use try_future::TryFuture;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `ok_into` combinator, changing the success type of a future.
+///
+/// This is created by the `TryFutureExt::ok_into` method.
+#[derive(Debug)]
+pub struct OkInto<Fut, T> {
+    future: Fut,
+    _marker: PhantomData<T>,
+}
+
+impl<Fut: Unpin, T> Unpin for OkInto<Fut, T> {}
+
+impl<Fut, T> OkInto<Fut, T> {
+    pub(super) fn new(future: Fut) -> OkInto<Fut, T> {
+        OkInto { future, _marker: PhantomData }
+    }
+}
+
+impl<Fut: FusedFuture, T> FusedFuture for OkInto<Fut, T> {
+    fn is_terminated(&self) -> bool {
+        self.future.is_terminated()
+    }
+}
+
+impl<S, Fut, T> Future<S> for OkInto<Fut, T>
+    where S: Spawn + ?Sized,
+          Fut: TryFuture<S>,
+          Fut::Ok: Into<T>
+{
+    type Output = Result<T, Fut::Error>;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        let future = unsafe { PinMut::map_unchecked(self, |x| &mut x.future) };
+        match future.try_poll(cx) {
+            Poll::Ready(output) => Poll::Ready(output.map(Into::into)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,202 @@
+#![allow(non_snake_case)]
+
+use core::fmt;
+use core::marker::Unpin;
+use core::mem::{self, PinMut};
+use future::{Future, FusedFuture};
+use try_future::TryFuture;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// A `MaybeDone` for fallible futures, holding only the `Ok` value so that
+/// an error can be propagated as soon as it is seen.
+enum TryMaybeDone<Fut: TryFuture<S>, S: Spawn + ?Sized> {
+    Future(Fut),
+    Done(Fut::Ok),
+    Gone,
+}
+
+// Safe because we never generate `PinMut<Fut::Ok>`
+impl<Fut: TryFuture<S> + Unpin, S: Spawn + ?Sized> Unpin for TryMaybeDone<Fut, S> {}
+
+impl<Fut, S> fmt::Debug for TryMaybeDone<Fut, S>
+    where Fut: TryFuture<S> + fmt::Debug,
+          Fut::Ok: fmt::Debug,
+          S: Spawn + ?Sized
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TryMaybeDone::Future(fut) => f.debug_tuple("Future").field(fut).finish(),
+            TryMaybeDone::Done(ok) => f.debug_tuple("Done").field(ok).finish(),
+            TryMaybeDone::Gone => f.debug_tuple("Gone").finish(),
+        }
+    }
+}
+
+impl<Fut: TryFuture<S>, S: Spawn + ?Sized> TryMaybeDone<Fut, S> {
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Result<(), Fut::Error>> {
+        unsafe {
+            let this = PinMut::get_mut_unchecked(self);
+            let result = match this {
+                TryMaybeDone::Future(fut) => ready!(PinMut::new_unchecked(fut).try_poll(cx)),
+                TryMaybeDone::Done(_) => return Poll::Ready(Ok(())),
+                TryMaybeDone::Gone => panic!("TryJoin polled after completion"),
+            };
+            match result {
+                Ok(ok) => {
+                    *this = TryMaybeDone::Done(ok);
+                    Poll::Ready(Ok(()))
+                }
+                Err(e) => {
+                    *this = TryMaybeDone::Gone;
+                    Poll::Ready(Err(e))
+                }
+            }
+        }
+    }
+
+    fn take_ok(self: PinMut<Self>) -> Option<Fut::Ok> {
+        unsafe {
+            let this = PinMut::get_mut_unchecked(self);
+            match mem::replace(this, TryMaybeDone::Gone) {
+                TryMaybeDone::Done(ok) => Some(ok),
+                _ => None,
+            }
+        }
+    }
+
+    fn is_gone(&self) -> bool {
+        match self {
+            TryMaybeDone::Gone => true,
+            _ => false,
+        }
+    }
+}
+
+macro_rules! generate {
+    ($(
+        $(#[$doc:meta])*
+        ($TryJoin:ident, $try_join:ident, <$Fut1:ident, $($Fut:ident),*>),
+    )*) => ($(
+        $(#[$doc])*
+        pub struct $TryJoin<$Fut1, $($Fut,)* S: Spawn + ?Sized = dyn Spawn>
+            where $Fut1: TryFuture<S>,
+                  $($Fut: TryFuture<S, Error = $Fut1::Error>,)*
+        {
+            $Fut1: TryMaybeDone<$Fut1, S>,
+            $($Fut: TryMaybeDone<$Fut, S>,)*
+        }
+
+        impl<$Fut1, $($Fut,)* S> fmt::Debug for $TryJoin<$Fut1, $($Fut,)* S>
+            where $Fut1: TryFuture<S> + fmt::Debug,
+                  $Fut1::Ok: fmt::Debug,
+                  $($Fut: TryFuture<S, Error = $Fut1::Error> + fmt::Debug,
+                    $Fut::Ok: fmt::Debug,)*
+                  S: Spawn + ?Sized
+        {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($TryJoin))
+                    .field(stringify!($Fut1), &self.$Fut1)
+                    $(.field(stringify!($Fut), &self.$Fut))*
+                    .finish()
+            }
+        }
+
+        impl<$Fut1, $($Fut,)* S> Future<S> for $TryJoin<$Fut1, $($Fut,)* S>
+            where $Fut1: TryFuture<S>,
+                  $($Fut: TryFuture<S, Error = $Fut1::Error>,)*
+                  S: Spawn + ?Sized
+        {
+            type Output = Result<($Fut1::Ok, $($Fut::Ok),*), $Fut1::Error>;
+
+            fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+                let this = unsafe { PinMut::get_mut_unchecked(self) };
+                let mut all_done = true;
+                let mut error = None;
+                match unsafe { PinMut::new_unchecked(&mut this.$Fut1) }.poll(cx) {
+                    Poll::Ready(Ok(())) => {}
+                    Poll::Ready(Err(e)) => error = Some(e),
+                    Poll::Pending => all_done = false,
+                }
+                $(
+                    if error.is_none() {
+                        match unsafe { PinMut::new_unchecked(&mut this.$Fut) }.poll(cx) {
+                            Poll::Ready(Ok(())) => {}
+                            Poll::Ready(Err(e)) => error = Some(e),
+                            Poll::Pending => all_done = false,
+                        }
+                    }
+                )*
+
+                if let Some(e) = error {
+                    // Drop the remaining children in place so that the join
+                    // is terminated.
+                    unsafe { PinMut::new_unchecked(&mut this.$Fut1) }.take_ok();
+                    $(unsafe { PinMut::new_unchecked(&mut this.$Fut) }.take_ok();)*
+                    return Poll::Ready(Err(e))
+                }
+
+                if all_done {
+                    Poll::Ready(Ok((
+                        unsafe { PinMut::new_unchecked(&mut this.$Fut1) }.take_ok().unwrap(),
+                        $(unsafe { PinMut::new_unchecked(&mut this.$Fut) }.take_ok().unwrap()),*
+                    )))
+                } else {
+                    Poll::Pending
+                }
+            }
+        }
+
+        impl<$Fut1, $($Fut,)* S> FusedFuture for $TryJoin<$Fut1, $($Fut,)* S>
+            where $Fut1: TryFuture<S>,
+                  $($Fut: TryFuture<S, Error = $Fut1::Error>,)*
+                  S: Spawn + ?Sized
+        {
+            fn is_terminated(&self) -> bool {
+                self.$Fut1.is_gone() $(&& self.$Fut.is_gone())*
+            }
+        }
+
+        $(#[$doc])*
+        #[cfg_attr(feature = "cargo-clippy", allow(too_many_arguments))]
+        pub fn $try_join<$Fut1, $($Fut,)* S>($Fut1: $Fut1, $($Fut: $Fut),*)
+            -> $TryJoin<$Fut1, $($Fut,)* S>
+            where $Fut1: TryFuture<S>,
+                  $($Fut: TryFuture<S, Error = $Fut1::Error>,)*
+                  S: Spawn + ?Sized
+        {
+            $TryJoin {
+                $Fut1: TryMaybeDone::Future($Fut1),
+                $($Fut: TryMaybeDone::Future($Fut),)*
+            }
+        }
+    )*)
+}
+
+generate! {
+    /// Joins the result of two fallible futures, waiting for them both to
+    /// complete or for one of them to fail.
+    ///
+    /// The returned future resolves to a tuple of the `Ok` values once every
+    /// future has succeeded. As soon as any future produces an error, the
+    /// remaining futures are dropped and that error is returned.
+    (TryJoin, try_join, <Fut1, Fut2>),
+
+    /// Same as `try_join`, but with more futures.
+    (TryJoin3, try_join3, <Fut1, Fut2, Fut3>),
+
+    /// Same as `try_join`, but with more futures.
+    (TryJoin4, try_join4, <Fut1, Fut2, Fut3, Fut4>),
+
+    /// Same as `try_join`, but with more futures.
+    (TryJoin5, try_join5, <Fut1, Fut2, Fut3, Fut4, Fut5>),
+
+    /// Same as `try_join`, but with more futures.
+    (TryJoin6, try_join6, <Fut1, Fut2, Fut3, Fut4, Fut5, Fut6>),
+
+    /// Same as `try_join`, but with more futures.
+    (TryJoin7, try_join7, <Fut1, Fut2, Fut3, Fut4, Fut5, Fut6, Fut7>),
+
+    /// Same as `try_join`, but with more futures.
+    (TryJoin8, try_join8, <Fut1, Fut2, Fut3, Fut4, Fut5, Fut6, Fut7, Fut8>),
+}
@@ -0,0 +1,64 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::{Future, FusedFuture, Either};
+use try_future::TryFuture;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `try_select` function.
+#[derive(Debug)]
+pub struct TrySelect<A, B> {
+    inner: Option<(A, B)>,
+}
+
+impl<A: Unpin, B: Unpin> Unpin for TrySelect<A, B> {}
+
+/// Waits for either one of two differently-typed fallible futures to
+/// complete.
+///
+/// This is like `future::select`, except that the result tells apart which
+/// future finished and whether it succeeded: the returned future resolves to
+/// `Ok` if the first future to finish succeeded and `Err` if it failed,
+/// either way along with the other, still unfinished, future.
+///
+/// If both futures are ready on the same poll, the first one wins.
+pub fn try_select<A, B>(future1: A, future2: B) -> TrySelect<A, B>
+    where A: Unpin,
+          B: Unpin
+{
+    TrySelect { inner: Some((future1, future2)) }
+}
+
+#[cfg_attr(feature = "cargo-clippy", allow(type_complexity))]
+impl<S, A, B> Future<S> for TrySelect<A, B>
+    where S: Spawn + ?Sized,
+          A: TryFuture<S> + Unpin,
+          B: TryFuture<S> + Unpin
+{
+    type Output = Result<
+        Either<(A::Ok, B), (B::Ok, A)>,
+        Either<(A::Error, B), (B::Error, A)>,
+    >;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Self::Output> {
+        let (mut a, mut b) = self.inner.take().expect("cannot poll TrySelect twice");
+        match PinMut::new(&mut a).try_poll(cx) {
+            Poll::Ready(Ok(x)) => Poll::Ready(Ok(Either::Left((x, b)))),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(Either::Left((e, b)))),
+            Poll::Pending => match PinMut::new(&mut b).try_poll(cx) {
+                Poll::Ready(Ok(x)) => Poll::Ready(Ok(Either::Right((x, a)))),
+                Poll::Ready(Err(e)) => Poll::Ready(Err(Either::Right((e, a)))),
+                Poll::Pending => {
+                    self.inner = Some((a, b));
+                    Poll::Pending
+                }
+            }
+        }
+    }
+}
+
+impl<A, B> FusedFuture for TrySelect<A, B> {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_none()
+    }
+}
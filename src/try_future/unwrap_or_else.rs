@@ -0,0 +1,50 @@
+use core::marker::Unpin;
+use core::mem::PinMut;
+use future::{Future, FusedFuture};
+use try_future::TryFuture;
+use task::{Context, Poll};
+use spawn::Spawn;
+
+/// Future for the `unwrap_or_else` combinator.
+///
+/// This is created by the `TryFutureExt::unwrap_or_else` method.
+#[derive(Debug)]
+pub struct UnwrapOrElse<Fut, F> {
+    future: Fut,
+    f: Option<F>,
+}
+
+impl<Fut: Unpin, F> Unpin for UnwrapOrElse<Fut, F> {}
+
+impl<Fut, F> UnwrapOrElse<Fut, F> {
+    pub(super) fn new(future: Fut, f: F) -> UnwrapOrElse<Fut, F> {
+        UnwrapOrElse { future, f: Some(f) }
+    }
+}
+
+impl<Fut, F> FusedFuture for UnwrapOrElse<Fut, F> {
+    fn is_terminated(&self) -> bool {
+        self.f.is_none()
+    }
+}
+
+impl<S, Fut, F> Future<S> for UnwrapOrElse<Fut, F>
+    where S: Spawn + ?Sized,
+          Fut: TryFuture<S>,
+          F: FnOnce(Fut::Error) -> Fut::Ok
+{
+    type Output = Fut::Ok;
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Fut::Ok> {
+        let this = unsafe { PinMut::get_mut_unchecked(self) };
+        let future = unsafe { PinMut::new_unchecked(&mut this.future) };
+        match future.try_poll(cx) {
+            Poll::Ready(result) => {
+                let op = this.f.take()
+                    .expect("UnwrapOrElse polled after completion");
+                Poll::Ready(result.unwrap_or_else(op))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
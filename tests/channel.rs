@@ -0,0 +1,217 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+#![cfg(feature = "std")]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::cell::RefCell;
+use std::mem::PinMut;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, mpsc as mpsc_std};
+use std::task::{Wake, local_waker_from_nonlocal};
+use std::thread;
+use std::time::Duration;
+use specialized_futures::{Context, Future, LocalSpawnExt, SpawnExt, Stream};
+use specialized_futures::channel::{mpsc, oneshot};
+use specialized_futures::executor::{LocalPool, ThreadPool, block_on};
+use specialized_futures::future::{FusedFuture, poll_fn};
+use specialized_futures::stream::FusedStream;
+use specialized_futures::spawn::NoSpawn;
+use specialized_futures::task::{Poll, Waker};
+
+use support::{with_counting_context, with_noop_context};
+
+#[test]
+fn oneshot_delivers_across_threads() {
+    let (tx, rx) = oneshot::channel();
+    let sender = thread::spawn(move || tx.send(String::from("hello")).unwrap());
+    assert_eq!(block_on(rx), Ok(String::from("hello")));
+    sender.join().unwrap();
+}
+
+#[test]
+fn oneshot_receiver_dropped_first() {
+    let (tx, rx) = oneshot::channel();
+    assert!(!tx.is_canceled());
+    drop(rx);
+    assert!(tx.is_canceled());
+    assert_eq!(tx.send(5), Err(5));
+}
+
+#[test]
+fn oneshot_sender_dropped_first() {
+    let (tx, rx) = oneshot::channel::<i32>();
+    pin_mut!(rx);
+    let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| rx.reborrow().poll(cx));
+    assert_eq!(ret, Poll::Pending);
+    drop(tx);
+    assert_eq!(wakes.get(), 1);
+    assert_eq!(with_noop_context(|cx| rx.reborrow().poll(cx)), Poll::Ready(Err(oneshot::Canceled)));
+    assert!(rx.is_terminated());
+}
+
+#[test]
+fn oneshot_poll_cancel_wakes_sender() {
+    let (mut tx, rx) = oneshot::channel::<i32>();
+    let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| tx.poll_cancel(cx));
+    assert_eq!(ret, Poll::Pending);
+    assert_eq!(wakes.get(), 0);
+    drop(rx);
+    assert_eq!(wakes.get(), 1);
+    assert_eq!(with_noop_context(|cx| tx.poll_cancel(cx)), Poll::Ready(()));
+}
+
+#[test]
+fn oneshot_halves_are_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<oneshot::Sender<i32>>();
+    assert_send_sync::<oneshot::Receiver<i32>>();
+}
+
+/// A task which sends every value in `values` on `tx`, waiting for capacity
+/// before each one.
+fn send_each(
+    mut tx: mpsc::Sender<u32>,
+    values: ::std::ops::Range<u32>,
+) -> impl Future<Output = ()> + Send {
+    let mut values = values.peekable();
+    poll_fn(move |cx: &mut Context| {
+        loop {
+            if values.peek().is_none() {
+                return Poll::Ready(());
+            }
+            match tx.poll_ready(cx) {
+                Poll::Ready(Ok(())) => tx.try_send(values.next().unwrap()).unwrap(),
+                Poll::Ready(Err(e)) => panic!("send failed: {}", e),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    })
+}
+
+#[test]
+fn mpsc_ping_pong_on_local_pool() {
+    let mut pool = LocalPool::new();
+    let (tx, mut rx) = mpsc::channel(1);
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let received2 = received.clone();
+    pool.spawner().spawn_local(send_each(tx, 0..100)).unwrap();
+    pool.spawner().spawn_local(poll_fn(move |cx: &mut Context| {
+        loop {
+            match PinMut::new(&mut rx).poll_next(cx) {
+                Poll::Ready(Some(n)) => received2.borrow_mut().push(n),
+                Poll::Ready(None) => return Poll::Ready(()),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    })).unwrap();
+    pool.run();
+    assert_eq!(*received.borrow(), (0..100).collect::<Vec<_>>());
+}
+
+#[test]
+fn mpsc_many_producers_on_thread_pool() {
+    const PRODUCERS: u32 = 8;
+    const PER_PRODUCER: u32 = 1000;
+    let mut pool = ThreadPool::builder().pool_size(4).create().unwrap();
+    let (tx, mut rx) = mpsc::channel(4);
+    for i in 0..PRODUCERS {
+        let start = i * PER_PRODUCER;
+        pool.spawn(send_each(tx.clone(), start..start + PER_PRODUCER)).unwrap();
+    }
+    drop(tx);
+    let mut received = Vec::new();
+    block_on(poll_fn(|cx: &mut Context<_>| {
+        loop {
+            match PinMut::new(&mut rx).poll_next(cx) {
+                Poll::Ready(Some(n)) => received.push(n),
+                Poll::Ready(None) => return Poll::Ready(()),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }));
+    received.sort();
+    assert_eq!(received, (0..PRODUCERS * PER_PRODUCER).collect::<Vec<_>>());
+}
+
+#[test]
+fn mpsc_receiver_dropped_mid_stream() {
+    let (mut tx, mut rx) = mpsc::channel(1);
+    let mut tx2 = tx.clone();
+    tx.try_send(1).unwrap();
+    let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| tx2.poll_ready(cx));
+    assert_eq!(ret, Poll::Pending);
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut rx).poll_next(cx)), Poll::Ready(Some(1)));
+    assert_eq!(wakes.get(), 1);
+    tx.try_send(2).unwrap();
+    drop(rx);
+    assert!(tx.is_closed());
+    match with_noop_context(|cx| tx2.poll_ready(cx)) {
+        Poll::Ready(Err(e)) => assert!(e.is_disconnected()),
+        ret => panic!("expected a disconnected error, got {:?}", ret),
+    }
+    assert!(tx2.try_send(3).unwrap_err().is_disconnected());
+}
+
+#[test]
+fn mpsc_receiver_terminates_once_senders_drop() {
+    let (tx, mut rx) = mpsc::unbounded();
+    let tx2 = tx.clone();
+    assert!(!rx.is_terminated());
+    tx.unbounded_send(1).unwrap();
+    drop(tx);
+    // Another sender is still alive.
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut rx).poll_next(cx)), Poll::Ready(Some(1)));
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut rx).poll_next(cx)), Poll::Pending);
+    assert!(!rx.is_terminated());
+
+    tx2.unbounded_send(2).unwrap();
+    drop(tx2);
+    // Buffered items are still delivered after the last sender is gone.
+    assert!(!rx.is_terminated());
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut rx).poll_next(cx)), Poll::Ready(Some(2)));
+    assert!(!rx.is_terminated());
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut rx).poll_next(cx)), Poll::Ready(None));
+    assert!(rx.is_terminated());
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut rx).poll_next(cx)), Poll::Ready(None));
+}
+
+/// A waker which checks, from another thread, that the channel's lock is
+/// free when it is woken.
+struct CheckUnlocked {
+    tx: Mutex<mpsc::Sender<u32>>,
+    unlocked: Mutex<Option<bool>>,
+}
+
+impl Wake for CheckUnlocked {
+    fn wake(arc_self: &Arc<Self>) {
+        let (done_tx, done_rx) = mpsc_std::channel();
+        let this = arc_self.clone();
+        thread::spawn(move || {
+            let closed = this.tx.lock().unwrap().is_closed();
+            done_tx.send(closed).ok();
+        });
+        let unlocked = done_rx.recv_timeout(Duration::from_secs(1)).is_ok();
+        *arc_self.unlocked.lock().unwrap() = Some(unlocked);
+    }
+}
+
+#[test]
+fn mpsc_wakes_parked_sender_outside_lock() {
+    let (mut tx, mut rx) = mpsc::channel(1);
+    let mut parked = tx.clone();
+    tx.try_send(1).unwrap();
+    let check = Arc::new(CheckUnlocked {
+        tx: Mutex::new(tx),
+        unlocked: Mutex::new(None),
+    });
+    let lw = local_waker_from_nonlocal(check.clone());
+    let w = Waker::from(check.clone());
+    let mut spawner = NoSpawn;
+    let mut cx = Context::new(&lw, &w, &mut spawner);
+    assert!(parked.poll_ready(&mut cx).is_pending());
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut rx).poll_next(cx)), Poll::Ready(Some(1)));
+    assert_eq!(*check.unlocked.lock().unwrap(), Some(true));
+}
@@ -0,0 +1,16 @@
+// `AssertSendFuture` only wraps `Send` futures.
+
+#![feature(pin, arbitrary_self_types, futures_api)]
+extern crate specialized_futures;
+
+use std::rc::Rc;
+use specialized_futures::Context;
+use specialized_futures::future::poll_fn;
+use specialized_futures::guarantees::AssertSendFuture;
+use specialized_futures::task::Poll;
+
+pub fn wrap_rc_future() {
+    let rc = Rc::new(1);
+    let future = poll_fn(move |_: &mut Context| Poll::Ready(*rc));
+    let _ = AssertSendFuture::new(future); //~ ERROR `std::rc::Rc<i32>` cannot be sent between threads safely
+}
@@ -0,0 +1,28 @@
+// Combinators are only `Unpin` when what they wrap is.
+
+#![feature(pin, arbitrary_self_types, futures_api)]
+extern crate specialized_futures;
+
+use std::marker::{Pinned, Unpin};
+use std::mem::PinMut;
+use specialized_futures::{Context, Future, Spawn};
+use specialized_futures::future::{Race, Ready};
+use specialized_futures::guarantees::AssertSendFuture;
+use specialized_futures::task::Poll;
+
+struct Immovable(Pinned);
+
+impl<S: Spawn + ?Sized> Future<S> for Immovable {
+    type Output = u32;
+
+    fn poll(self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<u32> {
+        Poll::Ready(0)
+    }
+}
+
+fn assert_unpin<T: Unpin>() {}
+
+pub fn wrappers_of_immovable_futures() {
+    assert_unpin::<AssertSendFuture<Immovable>>(); //~ ERROR required because of the requirements on the impl of `std::marker::Unpin` for `specialized_futures::guarantees::AssertSendFuture<Immovable>`
+    assert_unpin::<Race<Immovable, Ready<u32>>>(); //~ ERROR required because it appears within the type `specialized_futures::future::Race<Immovable, specialized_futures::future::Ready<u32>>`
+}
@@ -0,0 +1,15 @@
+// A `Context` borrows the task's `LocalWaker`, so it can't leave the
+// thread polling the task, whatever its spawner.
+
+#![feature(pin, arbitrary_self_types, futures_api)]
+extern crate specialized_futures;
+
+use specialized_futures::{Context, Spawn};
+use specialized_futures::spawn::NoSpawn;
+
+fn assert_send<T: Send>() {}
+
+pub fn contexts_are_not_send() {
+    assert_send::<Context<'static, dyn Spawn>>(); //~ ERROR cannot be sent between threads safely
+    assert_send::<Context<'static, NoSpawn>>(); //~ ERROR required because it appears within the type `specialized_futures::Context<'static, specialized_futures::spawn::NoSpawn>`
+}
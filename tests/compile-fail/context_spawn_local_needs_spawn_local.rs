@@ -0,0 +1,12 @@
+// `Context::spawn_local` is only there when the spawner is `SpawnLocal`.
+// features: alloc
+
+#![feature(pin, arbitrary_self_types, futures_api)]
+extern crate specialized_futures;
+
+use specialized_futures::{Context, Spawn};
+use specialized_futures::future::ready;
+
+pub fn spawn_from_dyn_spawn(cx: &mut Context<dyn Spawn>) {
+    let _ = cx.spawn_local(ready(())); //~ ERROR no method named `spawn_local` found
+}
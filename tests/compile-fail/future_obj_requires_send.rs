@@ -0,0 +1,15 @@
+// Only a `Send` future can be put behind a `FutureObj`, even though the
+// obj's output needn't be `Send`.
+// features: alloc
+
+#![feature(pin, arbitrary_self_types, futures_api)]
+extern crate specialized_futures;
+
+use std::cell::Cell;
+use specialized_futures::{FutureObj, Spawn};
+use specialized_futures::future::poll_fn;
+use specialized_futures::task::Poll;
+
+pub fn not_send_future(cell: &'static Cell<u8>) -> FutureObj<'static, u8, dyn Spawn> {
+    FutureObj::new(Box::new(poll_fn(move |_| Poll::Ready(cell.get())))) //~ ERROR cannot be shared between threads safely
+}
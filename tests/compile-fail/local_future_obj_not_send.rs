@@ -0,0 +1,16 @@
+// A `LocalFutureObj` may hold a non-`Send` future, so it isn't `Send`
+// itself, whatever its spawner.
+// features: alloc
+
+#![feature(pin, arbitrary_self_types, futures_api)]
+extern crate specialized_futures;
+
+use specialized_futures::{LocalFutureObj, Spawn};
+use specialized_futures::stream::LocalStreamObj;
+
+fn assert_send<T: Send>() {}
+
+pub fn local_objs_are_not_send() {
+    assert_send::<LocalFutureObj<'static, (), dyn Spawn>>(); //~ ERROR `*mut ()` cannot be sent between threads safely
+    assert_send::<LocalStreamObj<'static, (), dyn Spawn>>(); //~ ERROR required because it appears within the type `specialized_futures::stream::LocalStreamObj
+}
@@ -0,0 +1,18 @@
+// A handle for a non-`Send` output mustn't leave the pool's thread.
+// features: std
+
+#![feature(pin, arbitrary_self_types, futures_api)]
+extern crate specialized_futures;
+
+use std::rc::Rc;
+use specialized_futures::LocalSpawnExt;
+use specialized_futures::executor::LocalPool;
+use specialized_futures::future::ready;
+
+fn assert_send<T: Send>(_: T) {}
+
+pub fn handle_is_not_send() {
+    let pool = LocalPool::new();
+    let handle = pool.spawner().spawn_local_with_handle(ready(Rc::new(1))).unwrap();
+    assert_send(handle); //~ ERROR `std::rc::Rc<{integer}>` cannot be sent between threads safely
+}
@@ -0,0 +1,12 @@
+// The original binding is shadowed by `pin_mut!`, so the pinned value can't
+// be moved out from behind the pin.
+
+#![feature(pin, arbitrary_self_types, futures_api)]
+#[macro_use] extern crate specialized_futures;
+
+use specialized_futures::future::Ready;
+
+pub fn moved_after_pinning(fut: Ready<i32>) -> Ready<i32> {
+    pin_mut!(fut);
+    fut //~ ERROR mismatched types
+}
@@ -0,0 +1,14 @@
+// The hidden local which owns the pinned value can't be named, so the value
+// can't be moved out of it either.
+
+#![feature(pin, arbitrary_self_types, futures_api)]
+#[macro_use] extern crate specialized_futures;
+
+use std::mem::PinMut;
+use specialized_futures::future::Ready;
+
+pub fn moved_out_of_pin(fut: Ready<i32>) -> Ready<i32> {
+    pin_mut!(fut);
+    let pinned: PinMut<Ready<i32>> = fut;
+    *pinned //~ ERROR cannot move out of borrowed content
+}
@@ -0,0 +1,26 @@
+// `select!` only accepts fused futures, since it skips branches which have
+// already completed instead of polling them again.
+
+#![feature(pin, arbitrary_self_types, futures_api)]
+#[macro_use] extern crate specialized_futures;
+
+use specialized_futures::{Context, Future, Spawn};
+use specialized_futures::task::Poll;
+use std::mem::PinMut;
+
+struct NotFused;
+
+impl<S: Spawn + ?Sized> Future<S> for NotFused {
+    type Output = ();
+
+    fn poll(self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<()> {
+        Poll::Ready(())
+    }
+}
+
+pub fn select_not_fused(cx: &mut Context) -> Poll<()> {
+    let mut fut = NotFused;
+    select! { cx; //~ ERROR the trait bound `NotFused: specialized_futures::future::FusedFuture` is not satisfied
+        () = fut => Poll::Ready(()),
+    }
+}
@@ -0,0 +1,34 @@
+// `select!` polls its branches in place through `PinMut::new`, so their
+// futures must be `Unpin`.
+
+#![feature(pin, arbitrary_self_types, futures_api)]
+#[macro_use] extern crate specialized_futures;
+
+use specialized_futures::{Context, Future, Spawn};
+use specialized_futures::future::FusedFuture;
+use specialized_futures::task::Poll;
+use std::marker::Pinned;
+use std::mem::PinMut;
+
+struct Immovable(Pinned);
+
+impl<S: Spawn + ?Sized> Future<S> for Immovable {
+    type Output = ();
+
+    fn poll(self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<()> {
+        Poll::Ready(())
+    }
+}
+
+impl FusedFuture for Immovable {
+    fn is_terminated(&self) -> bool {
+        false
+    }
+}
+
+pub fn select_not_unpin(cx: &mut Context) -> Poll<()> {
+    let mut fut = Immovable(Pinned);
+    select! { cx; //~ ERROR the trait bound `std::marker::Pinned: std::marker::Unpin` is not satisfied
+        () = fut => Poll::Ready(()),
+    }
+}
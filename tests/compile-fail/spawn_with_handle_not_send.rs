@@ -0,0 +1,18 @@
+// Only `spawn_local_with_handle` accepts futures which aren't `Send`.
+// features: std
+
+#![feature(pin, arbitrary_self_types, futures_api)]
+extern crate specialized_futures;
+
+use std::rc::Rc;
+use specialized_futures::{Context, SpawnExt};
+use specialized_futures::executor::LocalPool;
+use specialized_futures::future::poll_fn;
+use specialized_futures::task::Poll;
+
+pub fn spawn_rc_future() {
+    let pool = LocalPool::new();
+    let rc = Rc::new(1);
+    let future = poll_fn(move |_: &mut Context| Poll::Ready(*rc));
+    pool.spawner().spawn_with_handle(future); //~ ERROR `std::rc::Rc<i32>` cannot be sent between threads safely
+}
@@ -0,0 +1,129 @@
+//! Checks that every program in `tests/compile-fail` is rejected by the
+//! compiler with the errors it expects.
+//!
+//! Each program lists the errors it expects in `//~ ERROR <text>` comments,
+//! where `<text>` must appear somewhere in the compiler's output, not
+//! counting the source lines it quotes. A `// features: <names>` comment
+//! skips the program unless every named feature of this crate is enabled.
+//!
+//! The crate is compiled once into a temporary directory with the same
+//! features as this test, and each program is compiled against it.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn enabled_features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "alloc") { features.push("alloc"); }
+    if cfg!(feature = "std") { features.push("std"); }
+    if cfg!(feature = "reactor") { features.push("reactor"); }
+    if cfg!(feature = "test-util") { features.push("test-util"); }
+    features
+}
+
+fn rustc() -> Command {
+    Command::new(env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+}
+
+fn build_crate(root: &Path, out_dir: &Path) -> PathBuf {
+    let mut cmd = rustc();
+    cmd.arg("--crate-name").arg("specialized_futures")
+        .arg("--crate-type").arg("lib")
+        .arg("--out-dir").arg(out_dir)
+        .arg(root.join("src/lib.rs"));
+    for feature in enabled_features() {
+        cmd.arg("--cfg").arg(format!("feature=\"{}\"", feature));
+    }
+    let output = cmd.output().expect("failed to run rustc");
+    assert!(
+        output.status.success(),
+        "failed to build the crate:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    out_dir.join("libspecialized_futures.rlib")
+}
+
+struct Expectations {
+    features: Vec<String>,
+    errors: Vec<String>,
+}
+
+fn parse(source: &str) -> Expectations {
+    let mut features = Vec::new();
+    let mut errors = Vec::new();
+    for line in source.lines() {
+        let line = line.trim();
+        if line.starts_with("// features:") {
+            features.extend(line["// features:".len()..].split_whitespace().map(String::from));
+        } else if let Some(i) = line.find("//~ ERROR ") {
+            errors.push(line[i + "//~ ERROR ".len()..].trim().to_string());
+        }
+    }
+    Expectations { features, errors }
+}
+
+/// Strip the source lines which rustc quotes under each diagnostic, since
+/// they include the `//~ ERROR` comments themselves.
+fn without_source(stderr: &str) -> String {
+    stderr.lines()
+        .filter(|line| {
+            let line = line.trim_left();
+            let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+            digits == 0 || !line[digits..].starts_with(" |")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn compile_fail() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let out_dir = env::temp_dir().join(format!("specialized-futures-compile-fail-{}", std::process::id()));
+    fs::create_dir_all(&out_dir).unwrap();
+    let rlib = build_crate(&root, &out_dir);
+
+    let mut programs: Vec<PathBuf> = fs::read_dir(root.join("tests/compile-fail")).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "rs"))
+        .collect();
+    programs.sort();
+
+    let enabled = enabled_features();
+    let mut failures = Vec::new();
+    for program in &programs {
+        let source = fs::read_to_string(program).unwrap();
+        let expected = parse(&source);
+        assert!(!expected.errors.is_empty(), "{} expects no errors", program.display());
+        if !expected.features.iter().all(|f| enabled.contains(&&**f)) {
+            continue;
+        }
+
+        let output = rustc()
+            .arg("--crate-type").arg("lib")
+            .arg("--emit").arg("metadata")
+            .arg("--out-dir").arg(&out_dir)
+            .arg("--extern").arg(format!("specialized_futures={}", rlib.display()))
+            .arg(program)
+            .output()
+            .expect("failed to run rustc");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let diagnostics = without_source(&stderr);
+        if output.status.success() {
+            failures.push(format!("{} compiled successfully", program.display()));
+            continue;
+        }
+        for error in &expected.errors {
+            if !diagnostics.contains(&**error) {
+                failures.push(format!(
+                    "{} did not report `{}`:\n{}",
+                    program.display(), error, stderr
+                ));
+            }
+        }
+    }
+
+    let _ = fs::remove_dir_all(&out_dir);
+    assert!(failures.is_empty(), "\n{}", failures.join("\n\n"));
+}
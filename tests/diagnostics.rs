@@ -0,0 +1,115 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+#![cfg(feature = "std")]
+
+extern crate specialized_futures;
+
+mod support;
+
+use std::marker::Unpin;
+use std::mem::PinMut;
+use std::thread;
+use std::time::Duration;
+use specialized_futures::{Context, Future, FutureExt, Spawn};
+use specialized_futures::diagnostics::SlowPoll;
+use specialized_futures::future::ready;
+use specialized_futures::task::Poll;
+
+use support::with_noop_context;
+
+/// Pends once, then blocks the thread for `delay` in its second poll
+/// before resolving.
+struct Sleepy {
+    delay: Duration,
+    polled: bool,
+}
+
+impl Unpin for Sleepy {}
+
+impl Future<dyn Spawn> for Sleepy {
+    type Output = &'static str;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<&'static str> {
+        if !self.polled {
+            self.polled = true;
+            cx.waker().wake();
+            return Poll::Pending;
+        }
+        thread::sleep(self.delay);
+        Poll::Ready("slept")
+    }
+}
+
+fn run<F: Future<dyn Spawn> + Unpin>(mut future: F) -> F::Output {
+    loop {
+        if let Poll::Ready(output) = with_noop_context(|cx| PinMut::new(&mut future).poll(cx)) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn slow_poll_reported_once() {
+    let mut reports = Vec::new();
+    let stats = {
+        let sleepy = Sleepy { delay: Duration::from_millis(50), polled: false };
+        let future = FutureExt::<dyn Spawn>::instrument_polls(
+            sleepy,
+            Some(Duration::from_millis(20)),
+            |report: SlowPoll| reports.push(report),
+        );
+        let stats = future.stats();
+        assert_eq!(run(future), "slept");
+        stats
+    };
+    assert_eq!(reports.len(), 1);
+    let report = reports[0];
+    assert_eq!(report.poll_index, 1);
+    assert!(report.duration >= Duration::from_millis(50));
+    assert!(report.duration < Duration::from_secs(5), "implausible duration {:?}", report.duration);
+    assert!(report.type_name.ends_with("Sleepy"), "unexpected type name {}", report.type_name);
+
+    // The stats outlive the future.
+    assert_eq!((stats.polls(), stats.slow_polls()), (2, 1));
+    assert_eq!(stats.max_time(), report.duration);
+    assert!(stats.total_time() >= report.duration);
+}
+
+#[test]
+fn fast_future_reports_nothing() {
+    let mut reports = 0;
+    let stats = {
+        let future = FutureExt::<dyn Spawn>::instrument_polls(
+            ready(1),
+            Some(Duration::from_secs(1)),
+            |_| reports += 1,
+        );
+        let stats = future.stats();
+        assert_eq!(run(future), 1);
+        stats
+    };
+    assert_eq!(reports, 0);
+    assert_eq!((stats.polls(), stats.slow_polls()), (1, 0));
+}
+
+#[test]
+fn no_threshold_only_counts_polls() {
+    let mut reports = 0;
+    let stats = {
+        let sleepy = Sleepy { delay: Duration::from_millis(10), polled: false };
+        let future = FutureExt::<dyn Spawn>::instrument_polls(sleepy, None, |_| reports += 1);
+        let stats = future.stats();
+        run(future);
+        stats
+    };
+    assert_eq!(reports, 0);
+    assert_eq!(stats.polls(), 2);
+    assert_eq!(stats.total_time(), Duration::from_secs(0));
+}
+
+#[test]
+fn instrumented_preserves_auto_traits() {
+    fn assert_send_unpin<T: Send + Unpin>(_: &T) {}
+    let future = FutureExt::<dyn Spawn>::instrument_polls(ready(()), None, |_| {});
+    assert_send_unpin(&future);
+    assert!(FutureExt::<dyn Spawn>::now_or_never(future).is_some());
+}
@@ -0,0 +1,1168 @@
+#![feature(pin, arbitrary_self_types, futures_api, set_stdio)]
+#![cfg(feature = "std")]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::io;
+use std::mem::PinMut;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Barrier, Mutex, mpsc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use specialized_futures::{Context, Future, FutureExt, FutureObj, LocalFutureObj, LocalSpawnExt, Spawn, SpawnExt};
+use specialized_futures::executor::{Bounded, LocalPool, LocalSpawner, ThreadPool, block_on};
+use specialized_futures::future::{FusedFuture, poll_fn, ready, yield_now, YieldNow};
+use specialized_futures::spawn::{JoinError, NoSpawn, ShutdownSpawn, SpawnBlocking};
+use specialized_futures::spawn::{Priority, SpawnPriority};
+use specialized_futures::task::Poll;
+
+use support::{with_counting_context, with_noop_context};
+
+#[test]
+fn block_on_ready() {
+    assert_eq!(block_on(ready(7)), 7);
+}
+
+#[test]
+fn run_until_returns_before_tasks_finish() {
+    let mut pool = LocalPool::new();
+    let ran = Rc::new(Cell::new(false));
+    let ran2 = ran.clone();
+    pool.spawner().spawn_local(poll_fn(move |_| {
+        ran2.set(true);
+        Poll::Pending::<()>
+    })).unwrap();
+    assert_eq!(pool.run_until(ready(1)), 1);
+    assert!(!pool.run_until_stalled());
+    assert!(ran.get());
+}
+
+#[test]
+fn run_completes_tasks_spawned_while_running() {
+    let mut pool = LocalPool::new();
+    let count = Arc::new(AtomicUsize::new(0));
+    let count2 = count.clone();
+    pool.spawner().spawn_local(poll_fn(move |cx: &mut Context| {
+        for _ in 0..3 {
+            let count = count2.clone();
+            cx.spawner().spawn(poll_fn(move |_| {
+                count.fetch_add(1, Ordering::SeqCst);
+                Poll::Ready(())
+            })).unwrap();
+        }
+        Poll::Ready(())
+    })).unwrap();
+    pool.run();
+    assert_eq!(count.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn self_waking_task_makes_progress() {
+    let mut pool = LocalPool::new();
+    let polls = Rc::new(Cell::new(0));
+    let polls2 = polls.clone();
+    pool.spawner().spawn_local(poll_fn(move |cx| {
+        polls2.set(polls2.get() + 1);
+        if polls2.get() == 3 {
+            return Poll::Ready(());
+        }
+        cx.waker().wake();
+        Poll::Pending
+    })).unwrap();
+    assert!(pool.run_until_stalled());
+    assert_eq!(polls.get(), 3);
+}
+
+/// A task which logs its id three times, yielding between each.
+fn yielding_task(id: u8, log: Rc<RefCell<Vec<u8>>>) -> impl Future<Output = ()> {
+    let mut logged = 0;
+    let mut yielder: Option<YieldNow> = None;
+    poll_fn(move |cx| {
+        if let Some(ref mut fut) = yielder {
+            if PinMut::new(fut).poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+        log.borrow_mut().push(id);
+        logged += 1;
+        if logged == 3 {
+            return Poll::Ready(());
+        }
+        let mut fut = yield_now();
+        assert!(PinMut::new(&mut fut).poll(cx).is_pending());
+        yielder = Some(fut);
+        Poll::Pending
+    })
+}
+
+#[test]
+fn yield_now_interleaves_tasks() {
+    let mut pool = LocalPool::new();
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut spawner = pool.spawner();
+    spawner.spawn_local(yielding_task(0, log.clone())).unwrap();
+    spawner.spawn_local(yielding_task(1, log.clone())).unwrap();
+    pool.run();
+    let log = log.borrow();
+    assert_eq!(log.len(), 6);
+    assert!(log.windows(2).all(|w| w[0] != w[1]), "tasks didn't interleave: {:?}", *log);
+}
+
+#[test]
+fn spawner_outliving_pool_is_shut_down() {
+    let pool = LocalPool::new();
+    let mut spawner = pool.spawner();
+    assert!(spawner.status().is_ok());
+    drop(pool);
+    assert!(spawner.status().unwrap_err().is_shutdown());
+    assert!(spawner.spawn(ready(())).unwrap_err().is_shutdown());
+}
+
+#[test]
+fn thread_pool_zero_workers_is_an_error() {
+    let err = ThreadPool::builder().pool_size(0).create().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn thread_pool_hooks_run_once_per_worker_in_order() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let (stopped_tx, stopped_rx) = mpsc::channel();
+    let stopped_tx = Mutex::new(stopped_tx);
+    let (start_events, stop_events) = (events.clone(), events.clone());
+    let pool = ThreadPool::builder()
+        .pool_size(3)
+        .after_start(move |i| start_events.lock().unwrap().push(("start", i)))
+        .before_stop(move |i| {
+            stop_events.lock().unwrap().push(("stop", i));
+            stopped_tx.lock().unwrap().send(()).unwrap();
+        })
+        .create()
+        .unwrap();
+    drop(pool);
+    for _ in 0..3 {
+        stopped_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    }
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 6);
+    for i in 0..3 {
+        let start = events.iter().position(|&e| e == ("start", i)).unwrap();
+        let stop = events.iter().position(|&e| e == ("stop", i)).unwrap();
+        assert!(start < stop);
+    }
+}
+
+#[test]
+fn thread_pool_names_its_threads() {
+    let mut pool = ThreadPool::builder()
+        .pool_size(1)
+        .name_prefix("pool-worker-")
+        .create()
+        .unwrap();
+    let (tx, rx) = mpsc::channel();
+    pool.spawn(poll_fn(move |_| {
+        tx.send(thread::current().name().map(String::from)).unwrap();
+        Poll::Ready(())
+    })).unwrap();
+    let name = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(name.as_ref().map(|s| &**s), Some("pool-worker-0"));
+}
+
+#[test]
+fn thread_pool_runs_tasks_on_distinct_threads() {
+    let mut pool = ThreadPool::builder().pool_size(2).create().unwrap();
+    // Each task waits for the other, which only works if they are polled on
+    // different workers at the same time.
+    let barrier = Arc::new(Barrier::new(2));
+    let (tx, rx) = mpsc::channel();
+    for _ in 0..2 {
+        let (barrier, tx) = (barrier.clone(), tx.clone());
+        pool.spawn(poll_fn(move |_| {
+            barrier.wait();
+            tx.send(thread::current().id()).unwrap();
+            Poll::Ready(())
+        })).unwrap();
+    }
+    let a = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    let b = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_ne!(a, b);
+}
+
+#[test]
+fn thread_pool_wakes_tasks_from_other_threads() {
+    let mut pool = ThreadPool::builder().pool_size(2).create().unwrap();
+    let (tx, rx) = mpsc::channel();
+    let mut polls = 0;
+    pool.spawn(poll_fn(move |cx| {
+        polls += 1;
+        if polls == 1 {
+            let waker = cx.waker().clone();
+            thread::spawn(move || waker.wake());
+            return Poll::Pending;
+        }
+        tx.send(polls).unwrap();
+        Poll::Ready(())
+    })).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(10)).unwrap(), 2);
+}
+
+/// A task which tries to spawn `n` children which never complete, and
+/// records which attempts were rejected for being over the limit.
+fn spawn_children<S: Spawn + ?Sized>(n: usize, rejected: Rc<Cell<Vec<bool>>>)
+    -> impl FnMut(&mut Context<S>) -> Poll<()>
+{
+    move |cx| {
+        let results = (0..n)
+            .map(|_| cx.spawner().spawn(poll_fn(|_| Poll::Pending::<()>)))
+            .map(|res| res.err().map_or(false, |err| err.is_queue_full()))
+            .collect();
+        rejected.set(results);
+        Poll::Ready(())
+    }
+}
+
+#[test]
+fn local_pool_injects_per_task_spawners() {
+    let mut pool = LocalPool::new();
+    let mut spawner = pool.spawner();
+    let (small, large) = (Rc::new(Cell::new(Vec::new())), Rc::new(Cell::new(Vec::new())));
+    spawner.spawn_obj_with_spawner(
+        LocalFutureObj::new(Box::new(poll_fn(spawn_children(2, small.clone())))),
+        |pool| Bounded::new(pool, 1),
+    ).unwrap();
+    spawner.spawn_obj_with_spawner(
+        LocalFutureObj::new(Box::new(poll_fn(spawn_children(2, large.clone())))),
+        |pool| Bounded::new(pool, 2),
+    ).unwrap();
+    assert!(!pool.run_until_stalled());
+    assert_eq!(small.take(), vec![false, true]);
+    assert_eq!(large.take(), vec![false, false]);
+}
+
+#[test]
+fn local_pool_default_spawner_is_unbounded() {
+    let mut pool = LocalPool::new();
+    let rejected = Rc::new(Cell::new(Vec::new()));
+    pool.spawner().spawn_local(poll_fn(spawn_children::<dyn Spawn>(3, rejected.clone()))).unwrap();
+    assert!(!pool.run_until_stalled());
+    assert_eq!(rejected.take(), vec![false, false, false]);
+}
+
+#[test]
+fn thread_pool_injects_per_task_spawners() {
+    let pool = ThreadPool::builder().pool_size(2).create().unwrap();
+    let (tx, rx) = mpsc::channel();
+    for &limit in &[1, 2] {
+        let tx = tx.clone();
+        let task = poll_fn(move |cx: &mut Context<Bounded<ThreadPool>>| {
+            let full = (0..2)
+                .map(|_| cx.spawner().spawn(poll_fn(|_| Poll::Pending::<()>)))
+                .filter(|res| res.as_ref().err().map_or(false, |err| err.is_queue_full()))
+                .count();
+            tx.send((limit, full)).unwrap();
+            Poll::Ready(())
+        });
+        pool.spawn_obj_with_spawner(
+            specialized_futures::FutureObj::new(Box::new(task)),
+            move |pool| Bounded::new(pool, limit),
+        ).unwrap();
+    }
+    let mut results = vec![
+        rx.recv_timeout(Duration::from_secs(10)).unwrap(),
+        rx.recv_timeout(Duration::from_secs(10)).unwrap(),
+    ];
+    results.sort();
+    assert_eq!(results, vec![(1, 1), (2, 0)]);
+}
+
+#[test]
+fn thread_pool_hands_back_future_with_own_spawner_when_shut_down() {
+    let mut pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    pool.begin_shutdown();
+    let ran = Arc::new(AtomicUsize::new(0));
+    let task = {
+        let ran = ran.clone();
+        poll_fn(move |cx: &mut Context<Bounded<ThreadPool>>| {
+            ran.fetch_add(cx.spawner().limit(), Ordering::SeqCst);
+            Poll::Ready(())
+        })
+    };
+    let err = pool.spawn_obj_with_spawner(
+        specialized_futures::FutureObj::new(Box::new(task)),
+        |pool| Bounded::new(pool, 1),
+    ).unwrap_err();
+    assert!(err.kind.is_shutdown());
+    // The error carries the caller's future, which can still be polled with
+    // a spawner of its own type.
+    let mut future = err.future;
+    let mut spawner = Bounded::new(pool.clone(), 3);
+    let (_, ret) = with_counting_context(&mut spawner, |cx| PinMut::new(&mut future).poll(cx));
+    assert_eq!(ret, Poll::Ready(()));
+    assert_eq!(ran.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn local_pool_hands_back_future_with_own_spawner_when_shut_down() {
+    let pool = LocalPool::new();
+    let mut spawner = pool.spawner();
+    drop(pool);
+    let ran = Rc::new(Cell::new(false));
+    let task = {
+        let ran = ran.clone();
+        poll_fn(move |_: &mut Context<Bounded<LocalSpawner>>| {
+            ran.set(true);
+            Poll::Ready(())
+        })
+    };
+    let err = spawner.spawn_obj_with_spawner(
+        LocalFutureObj::new(Box::new(task)),
+        |pool| Bounded::new(pool, 1),
+    ).unwrap_err();
+    assert!(err.kind.is_shutdown());
+    let mut future = err.future;
+    let mut bounded = Bounded::new(spawner.clone(), 1);
+    let (_, ret) = with_counting_context(&mut bounded, |cx| PinMut::new(&mut future).poll(cx));
+    assert_eq!(ret, Poll::Ready(()));
+    assert!(ran.get());
+}
+
+#[test]
+fn local_spawner_factory_sees_the_pool() {
+    let mut pool = LocalPool::new();
+    let seen = Rc::new(Cell::new(false));
+    let seen2 = seen.clone();
+    pool.spawner().spawn_obj_with_spawner(
+        LocalFutureObj::new(Box::new(poll_fn(|_: &mut Context<LocalSpawner>| Poll::Ready(())))),
+        move |pool| {
+            seen2.set(pool.status().is_ok());
+            pool
+        },
+    ).unwrap();
+    assert!(pool.run_until_stalled());
+    assert!(seen.get());
+}
+
+#[test]
+fn local_pool_survives_panicking_task() {
+    let mut pool = LocalPool::new();
+    let ran = Rc::new(Cell::new(false));
+    let ran2 = ran.clone();
+    pool.spawner().spawn_local(poll_fn(|_| -> Poll<()> { panic!("task panicked") })).unwrap();
+    pool.spawner().spawn_local(poll_fn(move |_| {
+        ran2.set(true);
+        Poll::Ready(())
+    })).unwrap();
+    pool.run();
+    assert!(ran.get());
+}
+
+#[test]
+fn local_pool_polls_new_tasks_only_when_woken() {
+    let mut pool = LocalPool::new();
+    let polls = Rc::new(Cell::new(0));
+    let waker = Rc::new(RefCell::new(None));
+    let (polls2, waker2) = (polls.clone(), waker.clone());
+    pool.spawner().spawn_local(poll_fn(move |cx: &mut Context| {
+        polls2.set(polls2.get() + 1);
+        if polls2.get() == 3 {
+            return Poll::Ready(());
+        }
+        *waker2.borrow_mut() = Some(cx.waker().clone());
+        Poll::Pending
+    })).unwrap();
+    assert!(!pool.run_until_stalled());
+    assert_eq!(polls.get(), 1);
+
+    // The waker from the first poll reaches the task in the pool.
+    waker.borrow_mut().take().unwrap().wake();
+    assert!(!pool.run_until_stalled());
+    assert_eq!(polls.get(), 2);
+    waker.borrow_mut().take().unwrap().wake();
+    assert!(pool.run_until_stalled());
+    assert_eq!(polls.get(), 3);
+}
+
+#[test]
+fn local_pool_runs_tasks_spawned_by_new_tasks() {
+    let mut pool = LocalPool::new();
+    let ran = Arc::new(AtomicUsize::new(0));
+    for _ in 0..3 {
+        let ran = ran.clone();
+        pool.spawner().spawn_local(poll_fn(move |cx: &mut Context| {
+            let ran = ran.clone();
+            // Spawned during the new task's first poll.
+            cx.spawner().spawn(poll_fn(move |_: &mut Context| {
+                ran.fetch_add(1, Ordering::SeqCst);
+                Poll::Ready(())
+            })).unwrap();
+            Poll::Ready(())
+        })).unwrap();
+    }
+    assert!(pool.run_until_stalled());
+    assert_eq!(ran.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn thread_pool_worker_survives_panicking_task() {
+    let mut pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    let (tx, rx) = mpsc::channel();
+    pool.spawn(poll_fn(|_| -> Poll<()> { panic!("task panicked") })).unwrap();
+    pool.spawn(poll_fn(move |_| {
+        tx.send(thread::current().id()).unwrap();
+        Poll::Ready(())
+    })).unwrap();
+    rx.recv_timeout(Duration::from_secs(10)).unwrap();
+}
+
+/// A sink for stderr which keeps what is written to it, to read panic
+/// reports back.
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Captured {
+    fn contents(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn local_pool_names_panicking_task_in_report() {
+    let captured = Captured::default();
+    let stderr = io::set_panic(Some(Box::new(captured.clone())));
+    let mut pool = LocalPool::new();
+    let mut spawner = pool.spawner();
+    spawner.spawn_named("exploder", poll_fn(|_| -> Poll<()> { panic!("task panicked") })).unwrap();
+    spawner.spawn(poll_fn(|_| -> Poll<()> { panic!("anonymous task panicked") })).unwrap();
+    pool.run();
+    io::set_panic(stderr);
+
+    let report = captured.contents();
+    assert!(report.contains("task 'exploder' panicked at 'task panicked'"), "{}", report);
+    // Unnamed tasks are only reported by the panic hook.
+    assert_eq!(report.matches("task '").count(), 1, "{}", report);
+}
+
+#[test]
+fn thread_pool_names_panicking_task_in_report() {
+    let captured = Captured::default();
+    let sink = captured.clone();
+    let mut pool = ThreadPool::builder()
+        .pool_size(1)
+        .after_start(move |_| { io::set_panic(Some(Box::new(sink.clone()))); })
+        .create()
+        .unwrap();
+    let (tx, rx) = mpsc::channel();
+    pool.spawn_named(format!("worker-{}", 7), poll_fn(|_| -> Poll<()> {
+        panic!("{} panicked", "named task")
+    })).unwrap();
+    pool.spawn(poll_fn(move |_| {
+        tx.send(()).unwrap();
+        Poll::Ready(())
+    })).unwrap();
+    rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    let report = captured.contents();
+    assert!(report.contains("task 'worker-7' panicked at 'named task panicked'"), "{}", report);
+}
+
+#[cfg(feature = "debug")]
+#[test]
+fn local_pool_lists_tasks_until_they_complete() {
+    use specialized_futures::executor::{TaskInfo, TaskState};
+
+    let mut pool = LocalPool::new();
+    let mut spawner = pool.spawner();
+    let (gate, waiter) = gated();
+    spawner.spawn_named("waiter", waiter).unwrap();
+    spawner.spawn(ready(())).unwrap();
+    assert_eq!(pool.tasks(), vec![
+        TaskInfo { name: Some("waiter".into()), state: TaskState::Queued, polls: 0 },
+        TaskInfo { name: None, state: TaskState::Queued, polls: 0 },
+    ]);
+
+    assert!(!pool.run_until_stalled());
+    assert_eq!(pool.tasks(), vec![
+        TaskInfo { name: Some("waiter".into()), state: TaskState::Idle, polls: 1 },
+    ]);
+
+    gate.send(()).unwrap();
+    pool.run();
+    assert_eq!(pool.tasks(), vec![]);
+}
+
+#[cfg(feature = "debug")]
+#[test]
+fn thread_pool_lists_tasks_until_they_complete() {
+    use specialized_futures::executor::{TaskInfo, TaskState};
+
+    let mut pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    let (gate, waiter) = gated();
+    pool.spawn_named("waiter", waiter).unwrap();
+    let wait_for = |expected: Vec<TaskInfo>| {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while pool.tasks() != expected {
+            assert!(Instant::now() < deadline, "{:?}", pool.tasks());
+            thread::yield_now();
+        }
+    };
+    wait_for(vec![TaskInfo { name: Some("waiter".into()), state: TaskState::Idle, polls: 1 }]);
+    gate.send(()).unwrap();
+    wait_for(vec![]);
+}
+
+#[test]
+fn thread_pool_zero_blocking_threads_is_an_error() {
+    let err = ThreadPool::builder().max_blocking_threads(0).create().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn thread_pool_blocking_results_round_trip() {
+    let mut pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    let sum = pool.spawn_blocking(|| (1..=10).sum::<u32>());
+    assert_eq!(block_on(sum).unwrap(), 55);
+
+    let panicked = pool.spawn_blocking(|| -> u32 { panic!("blocking closure panicked") });
+    match block_on(panicked) {
+        Err(JoinError::Panicked(_)) => {}
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    // The pool keeps running closures after one panics.
+    assert_eq!(block_on(pool.spawn_blocking(|| 1)).unwrap(), 1);
+}
+
+#[test]
+fn thread_pool_blocking_runs_off_the_workers() {
+    let mut pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    let (worker_tx, worker_rx) = mpsc::channel();
+    pool.spawn(poll_fn(move |_| {
+        worker_tx.send(thread::current().id()).unwrap();
+        Poll::Ready(())
+    })).unwrap();
+    let worker = worker_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let blocked = pool.spawn_blocking(move || {
+        release_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        thread::current().id()
+    });
+
+    // The only worker is free to run other tasks while the closure blocks.
+    let start = Instant::now();
+    let (tx, rx) = mpsc::channel();
+    pool.spawn(poll_fn(move |_| {
+        tx.send(()).unwrap();
+        Poll::Ready(())
+    })).unwrap();
+    rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    release_tx.send(()).unwrap();
+    assert_ne!(block_on(blocked).unwrap(), worker);
+}
+
+#[test]
+fn thread_pool_blocking_threads_respect_the_cap() {
+    let mut pool = ThreadPool::builder()
+        .pool_size(1)
+        .max_blocking_threads(3)
+        .create()
+        .unwrap();
+    let running = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let threads = Arc::new(Mutex::new(Vec::new()));
+    let handles: Vec<_> = (0..20).map(|_| {
+        let (running, peak, threads) = (running.clone(), peak.clone(), threads.clone());
+        pool.spawn_blocking(move || {
+            let id = thread::current().id();
+            {
+                let mut threads = threads.lock().unwrap();
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                if now > peak.load(Ordering::SeqCst) {
+                    peak.store(now, Ordering::SeqCst);
+                }
+                if !threads.contains(&id) {
+                    threads.push(id);
+                }
+            }
+            thread::sleep(Duration::from_millis(10));
+            running.fetch_sub(1, Ordering::SeqCst);
+        })
+    }).collect();
+    for handle in handles {
+        block_on(handle).unwrap();
+    }
+    assert!(peak.load(Ordering::SeqCst) <= 3);
+    assert!(threads.lock().unwrap().len() <= 3);
+}
+
+#[test]
+fn spawn_remote_runs_on_another_worker() {
+    let mut pool = ThreadPool::builder().pool_size(2).create().unwrap();
+    let (work_tx, work_rx) = mpsc::channel();
+    let (tx, rx) = mpsc::channel();
+    let work = poll_fn(move |_: &mut Context| {
+        work_tx.send(thread::current().id()).unwrap();
+        Poll::Ready(42)
+    });
+    let mut remote = FutureExt::<dyn Spawn>::spawn_remote(work);
+    let mut work_rx = Some(work_rx);
+    let mut worker = None;
+    pool.spawn(poll_fn(move |cx: &mut Context| {
+        let ret = PinMut::new(&mut remote).poll(cx);
+        if let Some(work_rx) = work_rx.take() {
+            // Keep this worker busy until the work has run, so that it must
+            // have run on the other one.
+            worker = Some(work_rx.recv().unwrap());
+        }
+        match ret {
+            Poll::Ready(value) => {
+                tx.send((value, worker != Some(thread::current().id()))).unwrap();
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    })).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(10)).unwrap(), (42, true));
+}
+
+#[test]
+fn spawn_remote_runs_inline_when_spawning_fails() {
+    let mut pool = ThreadPool::builder().pool_size(2).create().unwrap();
+    pool.begin_shutdown();
+    let work = poll_fn(|_: &mut Context| Poll::Ready(thread::current().id()));
+    let mut remote = FutureExt::<dyn Spawn>::spawn_remote(work);
+    // Polled with the shut-down pool as its spawner, which refuses the task.
+    let (_, ret) = with_counting_context(&mut pool, |cx| PinMut::new(&mut remote).poll(cx));
+    assert_eq!(ret, Poll::Ready(thread::current().id()));
+}
+
+#[test]
+fn spawn_remote_panics_when_executor_drops_the_task() {
+    let mut pool = LocalPool::new();
+    let (_gate, work) = gated();
+    let mut remote = FutureExt::<dyn Spawn>::spawn_remote(work);
+    let mut spawner = pool.spawner();
+    let (_, ret) = with_counting_context(&mut spawner, |cx| PinMut::new(&mut remote).poll(cx));
+    assert_eq!(ret, Poll::Pending);
+    assert!(!pool.run_until_stalled());
+
+    // Dropping the pool drops the task, which wakes the waiting future.
+    let (wakes, _) = with_counting_context(&mut spawner, |cx| PinMut::new(&mut remote).poll(cx));
+    drop(pool);
+    assert_eq!(wakes.get(), 1);
+    let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+        with_counting_context(&mut spawner, |cx| PinMut::new(&mut remote).poll(cx))
+    }));
+    assert!(ret.is_err());
+    assert!(remote.is_terminated());
+}
+
+#[test]
+fn spawn_remote_propagates_panics() {
+    let mut pool = ThreadPool::builder().pool_size(2).create().unwrap();
+    let (tx, rx) = mpsc::channel();
+    let work = poll_fn(|_: &mut Context| -> Poll<()> { panic!("remote work panicked") });
+    let mut remote = FutureExt::<dyn Spawn>::spawn_remote(work);
+    pool.spawn(poll_fn(move |cx: &mut Context| {
+        let ret = panic::catch_unwind(AssertUnwindSafe(|| PinMut::new(&mut remote).poll(cx)));
+        match ret {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(())) => {
+                tx.send(false).unwrap();
+                Poll::Ready(())
+            }
+            Err(_) => {
+                tx.send(true).unwrap();
+                Poll::Ready(())
+            }
+        }
+    })).unwrap();
+    assert!(rx.recv_timeout(Duration::from_secs(10)).unwrap());
+}
+
+/// A `Send` task which stays pending until the returned sender is used or
+/// dropped.
+fn gated() -> (specialized_futures::channel::oneshot::Sender<()>, impl Future<Output = ()> + Send) {
+    let (tx, mut rx) = specialized_futures::channel::oneshot::channel();
+    let task = poll_fn(move |cx: &mut Context| PinMut::new(&mut rx).poll(cx).map(drop));
+    (tx, task)
+}
+
+#[test]
+fn bounded_rejects_spawns_beyond_the_limit() {
+    let mut pool = LocalPool::new();
+    let mut spawner = Bounded::new(pool.spawner(), 2);
+    let (_tx1, task1) = gated();
+    let (_tx2, task2) = gated();
+    spawner.spawn(task1).unwrap();
+    assert!(spawner.status().is_ok());
+    spawner.spawn(task2).unwrap();
+    assert_eq!(spawner.running(), 2);
+    assert!(spawner.status().unwrap_err().is_queue_full());
+    // `SpawnExt::spawn` drops the future and keeps only the reason.
+    let (_tx3, task3) = gated();
+    assert!(spawner.spawn(task3).unwrap_err().is_queue_full());
+    assert_eq!(spawner.running(), 2);
+    assert!(!pool.run_until_stalled());
+}
+
+#[test]
+fn bounded_rejected_spawn_returns_future_for_retry() {
+    let mut pool = LocalPool::new();
+    let mut spawner = Bounded::new(pool.spawner(), 1);
+    let (tx, blocker) = gated();
+    spawner.spawn(blocker).unwrap();
+
+    let ran = Arc::new(AtomicUsize::new(0));
+    let ran2 = ran.clone();
+    let retried = specialized_futures::FutureObj::new(Box::new(poll_fn(move |_: &mut Context| {
+        ran2.fetch_add(1, Ordering::SeqCst);
+        Poll::Ready(())
+    })));
+    let err = spawner.spawn_obj(retried).unwrap_err();
+    assert!(err.kind.is_queue_full());
+    assert!(!pool.run_until_stalled());
+    assert_eq!(ran.load(Ordering::SeqCst), 0);
+
+    tx.send(()).unwrap();
+    assert!(pool.run_until_stalled());
+    spawner.spawn_obj(err.future).unwrap();
+    assert!(pool.run_until_stalled());
+    assert_eq!(ran.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn bounded_frees_capacity_when_tasks_finish() {
+    let mut pool = LocalPool::new();
+    let mut spawner = Bounded::new(pool.spawner(), 2);
+    let (tx1, task1) = gated();
+    let (tx2, task2) = gated();
+    spawner.spawn(task1).unwrap();
+    spawner.spawn(task2).unwrap();
+    assert!(!pool.run_until_stalled());
+
+    tx1.send(()).unwrap();
+    assert!(!pool.run_until_stalled());
+    assert_eq!(spawner.running(), 1);
+    assert!(spawner.status().is_ok());
+
+    // Dropping the sender cancels the task, which completes it all the same.
+    drop(tx2);
+    assert!(pool.run_until_stalled());
+    assert_eq!(spawner.running(), 0);
+    // Clones share the limit.
+    let mut clone = spawner.clone();
+    for _ in 0..2 {
+        clone.spawn(poll_fn(|_: &mut Context| Poll::Ready(()))).unwrap();
+    }
+    assert!(spawner.spawn(poll_fn(|_: &mut Context| Poll::Ready(()))).unwrap_err().is_queue_full());
+    assert!(pool.run_until_stalled());
+    assert_eq!(spawner.running(), 0);
+}
+
+#[test]
+fn bounded_releases_slot_when_inner_spawner_rejects() {
+    let mut spawner = Bounded::new(specialized_futures::spawn::NoSpawn, 1);
+    assert!(spawner.status().unwrap_err().is_not_supported());
+    let (tx, task) = gated();
+    let err = spawner.spawn_obj(specialized_futures::FutureObj::new(Box::new(task))).unwrap_err();
+    assert!(err.kind.is_not_supported());
+    // The slot is released straight away, and the error hands back the
+    // caller's own future rather than the tracked task.
+    assert_eq!(spawner.running(), 0);
+    let mut pool = LocalPool::new();
+    pool.spawner().spawn_obj(err.future).unwrap();
+    assert!(!pool.run_until_stalled());
+    tx.send(()).unwrap();
+    assert!(pool.run_until_stalled());
+}
+
+#[test]
+fn bounded_hands_back_original_future_when_inner_pool_is_gone() {
+    let pool = LocalPool::new();
+    let mut spawner = Bounded::new(pool.spawner(), 1);
+    drop(pool);
+    let ran = Arc::new(AtomicUsize::new(0));
+    let task = {
+        let ran = ran.clone();
+        specialized_futures::FutureObj::new(Box::new(poll_fn(move |_: &mut Context| {
+            ran.fetch_add(1, Ordering::SeqCst);
+            Poll::Ready(())
+        })))
+    };
+    let err = spawner.spawn_obj(task).unwrap_err();
+    assert!(err.kind.is_shutdown());
+    assert_eq!(spawner.running(), 0);
+    // The future can be retried elsewhere, and runs outside the `Bounded`
+    // limit since it no longer holds a slot.
+    let mut pool = LocalPool::new();
+    pool.spawner().spawn_obj(err.future).unwrap();
+    pool.run();
+    assert_eq!(ran.load(Ordering::SeqCst), 1);
+    assert_eq!(spawner.running(), 0);
+}
+
+#[test]
+fn shutdown_runs_queued_work() {
+    let mut pool = LocalPool::new();
+    let mut spawner = Bounded::new(pool.spawner(), 8);
+    let ran = Arc::new(AtomicUsize::new(0));
+    for _ in 0..3 {
+        let ran = ran.clone();
+        spawner.spawn(poll_fn(move |_: &mut Context| {
+            ran.fetch_add(1, Ordering::SeqCst);
+            Poll::Ready(())
+        })).unwrap();
+    }
+    spawner.begin_shutdown();
+    assert!(spawner.status().unwrap_err().is_shutdown());
+    assert!(spawner.drain().now_or_never().is_none());
+    assert!(pool.run_until_stalled());
+    assert_eq!(ran.load(Ordering::SeqCst), 3);
+    assert_eq!(spawner.drain().now_or_never(), Some(()));
+}
+
+#[test]
+fn shutdown_rejects_spawns_from_running_tasks() {
+    let pool = ThreadPool::builder().pool_size(2).create().unwrap();
+    let mut spawner = Bounded::new(pool, 8);
+    let (tx, rx) = mpsc::channel();
+    let (gate, blocker) = gated();
+    let mut blocker = Some(blocker);
+    let mut inner = spawner.clone();
+    spawner.spawn(poll_fn(move |cx: &mut Context| {
+        ready!(PinMut::new(blocker.as_mut().unwrap()).poll(cx));
+        // Spawned during the drain, after shutdown began.
+        tx.send(inner.spawn(poll_fn(|_: &mut Context| Poll::Ready(())))).unwrap();
+        Poll::Ready(())
+    })).unwrap();
+
+    spawner.begin_shutdown();
+    let err = spawner.spawn_obj(specialized_futures::FutureObj::new(Box::new(ready(())))).unwrap_err();
+    assert!(err.kind.is_shutdown());
+    gate.send(()).unwrap();
+    let spawned = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(spawned.unwrap_err().is_shutdown());
+    // `Drain` is polled with a `dyn Spawn` context, which the obj adapts
+    // `block_on`'s `LocalSpawner` context to.
+    block_on(LocalFutureObj::<(), dyn Spawn>::new(Box::new(spawner.drain())));
+    assert_eq!(spawner.running(), 0);
+}
+
+#[test]
+fn drain_is_woken_by_last_task_and_resolves_once() {
+    let mut pool = LocalPool::new();
+    let mut spawner = Bounded::new(pool.spawner(), 8);
+    let (gate1, task1) = gated();
+    let (gate2, task2) = gated();
+    spawner.spawn(task1).unwrap();
+    spawner.spawn(task2).unwrap();
+    assert!(!pool.run_until_stalled());
+    spawner.begin_shutdown();
+
+    // The drain runs as a task on a second pool, which only polls it again
+    // once it has been woken.
+    let polls = Rc::new(Cell::new(0));
+    let resolved = Rc::new(Cell::new(0));
+    let mut drain_pool = LocalPool::new();
+    {
+        let (polls, resolved, spawner) = (polls.clone(), resolved.clone(), spawner.clone());
+        drain_pool.spawner().spawn_local(poll_fn(move |cx: &mut Context| {
+            polls.set(polls.get() + 1);
+            ready!(spawner.poll_drained(cx));
+            resolved.set(resolved.get() + 1);
+            Poll::Ready(())
+        })).unwrap();
+    }
+    assert!(!drain_pool.run_until_stalled());
+    assert_eq!(polls.get(), 1);
+
+    // The first task finishing doesn't wake the drain.
+    gate1.send(()).unwrap();
+    assert!(!pool.run_until_stalled());
+    assert!(!drain_pool.run_until_stalled());
+    assert_eq!(polls.get(), 1);
+
+    gate2.send(()).unwrap();
+    assert!(pool.run_until_stalled());
+    assert!(drain_pool.run_until_stalled());
+    assert_eq!(polls.get(), 2);
+    assert_eq!(resolved.get(), 1);
+    let mut drain = spawner.drain();
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut drain).poll(cx)), Poll::Ready(()));
+    assert!(drain.is_terminated());
+}
+
+#[test]
+fn local_pool_shutdown_runs_accepted_tasks_and_drains() {
+    let mut pool = LocalPool::new();
+    let mut spawner = pool.spawner();
+    let (gate, task) = gated();
+    spawner.spawn(task).unwrap();
+    let rejected = Rc::new(Cell::new(None));
+    {
+        let rejected = rejected.clone();
+        spawner.spawn_local(poll_fn(move |cx: &mut Context| {
+            // Spawned by an accepted task after shutdown began.
+            rejected.set(Some(cx.spawner().spawn(ready(())).unwrap_err().is_shutdown()));
+            Poll::Ready(())
+        })).unwrap();
+    }
+
+    spawner.begin_shutdown();
+    assert!(spawner.status().unwrap_err().is_shutdown());
+    assert!(pool.spawner().spawn(ready(())).unwrap_err().is_shutdown());
+    assert!(spawner.drain().now_or_never().is_none());
+
+    assert!(!pool.run_until_stalled());
+    assert_eq!(rejected.get(), Some(true));
+    assert!(spawner.drain().now_or_never().is_none());
+    gate.send(()).unwrap();
+    assert!(pool.run_until_stalled());
+    assert_eq!(spawner.drain().now_or_never(), Some(()));
+}
+
+#[test]
+fn local_pool_drain_is_woken_by_last_task() {
+    let mut pool = LocalPool::new();
+    let mut spawner = pool.spawner();
+    let (gate, task) = gated();
+    spawner.spawn(task).unwrap();
+    assert!(!pool.run_until_stalled());
+    spawner.begin_shutdown();
+
+    let mut drain = spawner.drain();
+    let mut no_spawn = NoSpawn;
+    let (wakes, ret) = with_counting_context(&mut no_spawn as &mut dyn Spawn, |cx| {
+        PinMut::new(&mut drain).poll(cx)
+    });
+    assert_eq!(ret, Poll::Pending);
+    gate.send(()).unwrap();
+    assert!(pool.run_until_stalled());
+    assert_eq!(wakes.get(), 1);
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut drain).poll(cx)), Poll::Ready(()));
+}
+
+#[test]
+fn local_pool_drain_resolves_once_pool_is_dropped() {
+    let pool = LocalPool::new();
+    let mut spawner = pool.spawner();
+    spawner.spawn(gated().1).unwrap();
+    assert!(spawner.drain().now_or_never().is_none());
+    drop(pool);
+    assert_eq!(spawner.drain().now_or_never(), Some(()));
+}
+
+#[test]
+fn thread_pool_shutdown_runs_accepted_tasks_and_drains() {
+    let mut pool = ThreadPool::builder().pool_size(2).create().unwrap();
+    let (tx, rx) = mpsc::channel();
+    let (gate, blocker) = gated();
+    let mut blocker = Some(blocker);
+    pool.spawn(poll_fn(move |cx: &mut Context| {
+        ready!(PinMut::new(blocker.as_mut().unwrap()).poll(cx));
+        // Spawned by an accepted task after shutdown began.
+        tx.send(cx.spawner().spawn(ready(())).unwrap_err().is_shutdown()).unwrap();
+        Poll::Ready(())
+    })).unwrap();
+
+    pool.begin_shutdown();
+    assert!(pool.clone().spawn(ready(())).unwrap_err().is_shutdown());
+    assert!(pool.drain().now_or_never().is_none());
+    gate.send(()).unwrap();
+    assert!(rx.recv_timeout(Duration::from_secs(10)).unwrap());
+    block_on(LocalFutureObj::<(), dyn Spawn>::new(Box::new(pool.drain())));
+}
+
+#[test]
+fn thread_pool_rejects_spawns_after_shutdown() {
+    let mut pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    assert!(pool.status().is_ok());
+    pool.begin_shutdown();
+    assert!(pool.status().unwrap_err().is_shutdown());
+    let ran = Arc::new(AtomicUsize::new(0));
+    let task = {
+        let ran = ran.clone();
+        specialized_futures::FutureObj::new(Box::new(poll_fn(move |_: &mut Context| {
+            ran.fetch_add(1, Ordering::SeqCst);
+            Poll::Ready(())
+        })))
+    };
+    let err = pool.spawn_obj(task).unwrap_err();
+    assert!(err.kind.is_shutdown());
+    // The future is handed back intact.
+    let mut other = LocalPool::new();
+    other.spawner().spawn_obj(err.future).unwrap();
+    other.run();
+    assert_eq!(ran.load(Ordering::SeqCst), 1);
+}
+
+/// A high priority task which respawns itself until `remaining` runs out,
+/// logging each step.
+fn high_priority_chain(mut pool: ThreadPool, remaining: usize, log: Arc<Mutex<Vec<(Priority, usize)>>>)
+    -> FutureObj<'static, (), dyn Spawn>
+{
+    FutureObj::new(Box::new(poll_fn(move |_: &mut Context| {
+        log.lock().unwrap().push((Priority::High, remaining));
+        if remaining > 1 {
+            let next = high_priority_chain(pool.clone(), remaining - 1, log.clone());
+            pool.spawn_obj_with_priority(next, Priority::High).unwrap();
+        }
+        Poll::Ready(())
+    })))
+}
+
+#[test]
+fn thread_pool_runs_high_priority_first_without_starving_low() {
+    let mut pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    // Hold the only worker until everything is queued.
+    let (release, blocked) = mpsc::channel::<()>();
+    let blocked = Mutex::new(blocked);
+    pool.spawn(poll_fn(move |_: &mut Context| {
+        blocked.lock().unwrap().recv().unwrap();
+        Poll::Ready(())
+    })).unwrap();
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let (done_tx, done) = mpsc::channel();
+    for i in 0..20 {
+        let log = log.clone();
+        let done_tx = done_tx.clone();
+        let task = FutureObj::new(Box::new(poll_fn(move |_: &mut Context| {
+            log.lock().unwrap().push((Priority::Low, i));
+            done_tx.send(()).unwrap();
+            Poll::Ready(())
+        })));
+        pool.spawn_obj_with_priority(task, Priority::Low).unwrap();
+    }
+    // A steady stream of high priority work, queued after the low flood.
+    let chain = high_priority_chain(pool.clone(), 50, log.clone());
+    pool.spawn_obj_with_priority(chain, Priority::High).unwrap();
+    release.send(()).unwrap();
+    for _ in 0..20 {
+        done.recv_timeout(Duration::from_secs(10)).unwrap();
+    }
+    // The chain's last step may still be running once the flood is done.
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while log.lock().unwrap().len() < 70 {
+        assert!(Instant::now() < deadline);
+        thread::yield_now();
+    }
+
+    let log = log.lock().unwrap();
+    let position = |entry| log.iter().position(|&e| e == entry).unwrap();
+    // High priority work jumps the queue...
+    assert_eq!(log[0], (Priority::High, 50));
+    assert!(position((Priority::High, 1)) < position((Priority::Low, 19)));
+    // ...but the flood still makes progress while it keeps arriving, in
+    // the order it was spawned.
+    assert!(position((Priority::Low, 0)) < position((Priority::High, 1)));
+    let lows: Vec<usize> = log.iter().filter(|e| e.0 == Priority::Low).map(|e| e.1).collect();
+    assert_eq!(lows, (0..20).collect::<Vec<_>>());
+}
+
+#[test]
+fn thread_pool_rejects_spawns_after_last_handle_drops() {
+    let mut pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    let (tx, rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    // The first task holds the only worker until the last handle is gone.
+    pool.spawn(poll_fn(move |_: &mut Context| {
+        release_rx.recv().unwrap();
+        Poll::Ready(())
+    })).unwrap();
+    // The second task is already queued when the pool closes, so it still
+    // runs, but can't spawn anything more.
+    pool.spawn(poll_fn(move |cx: &mut Context| {
+        let status = cx.spawner().status();
+        let spawned = cx.spawner().spawn(ready(()));
+        tx.send((status.unwrap_err().is_shutdown(), spawned.unwrap_err().is_shutdown())).unwrap();
+        Poll::Ready(())
+    })).unwrap();
+    drop(pool);
+    release_tx.send(()).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(10)).unwrap(), (true, true));
+}
+
+#[test]
+fn thread_pool_weak_spawner_fails_after_pool_is_dropped() {
+    let pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    let mut weak = pool.weak_spawner();
+    let mut strong = pool.strong_spawner();
+    let (tx, rx) = mpsc::channel();
+    {
+        let tx = tx.clone();
+        weak.spawn(poll_fn(move |_: &mut Context| {
+            tx.send(1).unwrap();
+            Poll::Ready(())
+        })).unwrap();
+    }
+    strong.spawn(poll_fn(move |_: &mut Context| {
+        tx.send(2).unwrap();
+        Poll::Ready(())
+    })).unwrap();
+    let mut received = vec![
+        rx.recv_timeout(Duration::from_secs(10)).unwrap(),
+        rx.recv_timeout(Duration::from_secs(10)).unwrap(),
+    ];
+    received.sort();
+    assert_eq!(received, vec![1, 2]);
+
+    drop(pool);
+    // The worker may still hold the handle it polled the last task with.
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while strong.status().is_ok() && Instant::now() < deadline {
+        thread::yield_now();
+    }
+    // A strong handle keeps the spawner, but not the pool, alive.
+    assert!(strong.status().unwrap_err().is_shutdown());
+    assert!(strong.spawn(ready(())).unwrap_err().is_shutdown());
+    drop(strong);
+    assert!(weak.upgrade().is_none());
+    assert!(weak.status().unwrap_err().is_shutdown());
+    assert!(weak.spawn(ready(())).unwrap_err().is_shutdown());
+}
+
+#[test]
+fn local_pool_weak_spawner_fails_after_pool_is_dropped() {
+    let mut pool = LocalPool::new();
+    let mut weak = pool.weak_spawner();
+    let ran = Arc::new(AtomicUsize::new(0));
+    {
+        let ran = ran.clone();
+        weak.spawn(poll_fn(move |_: &mut Context| {
+            ran.fetch_add(1, Ordering::SeqCst);
+            Poll::Ready(())
+        })).unwrap();
+    }
+    pool.run();
+    assert_eq!(ran.load(Ordering::SeqCst), 1);
+
+    let mut strong = pool.strong_spawner();
+    drop(pool);
+    // The strong handle keeps the spawner alive, but the pool is gone.
+    assert!(weak.upgrade().is_some());
+    assert!(weak.spawn(ready(())).unwrap_err().is_shutdown());
+    assert!(strong.spawn(ready(())).unwrap_err().is_shutdown());
+    drop(strong);
+    assert!(weak.upgrade().is_none());
+    assert!(weak.status().unwrap_err().is_shutdown());
+}
@@ -0,0 +1,583 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::cell::{Cell, RefCell};
+use std::marker::{Pinned, Unpin};
+use std::mem::PinMut;
+use std::rc::Rc;
+use specialized_futures::{Context, Future, FutureExt, FutureObj, Spawn};
+use specialized_futures::future::{Either, FusedFuture, lazy, maybe_done, pending, poll_fn, race, ready, select, MaybeDone, Ready};
+use specialized_futures::spawn::{NoSpawn, SpawnObjError};
+use specialized_futures::task::Poll;
+
+use support::{with_counting_context, with_noop_context};
+
+/// A spawner with an inherent method, which only futures specialized to it
+/// can call.
+#[derive(Default)]
+struct CountingSpawner {
+    spawned: usize,
+}
+
+impl CountingSpawner {
+    fn spawned(&self) -> usize {
+        self.spawned
+    }
+}
+
+impl Spawn for CountingSpawner {
+    fn spawn_obj(
+        &mut self,
+        _future: FutureObj<'static, (), dyn Spawn>
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawned += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn poll_fn_pending_then_ready() {
+    let mut polls = 0;
+    let fut = poll_fn(move |cx: &mut Context| {
+        polls += 1;
+        if polls < 3 {
+            cx.waker().wake();
+            Poll::Pending
+        } else {
+            Poll::Ready(polls)
+        }
+    });
+    pin_mut!(fut);
+    let mut spawner = CountingSpawner::default();
+    let (wakes, ()) = with_counting_context(&mut spawner as &mut dyn Spawn, |cx| {
+        assert_eq!(fut.reborrow().poll(cx), Poll::Pending);
+        assert_eq!(fut.reborrow().poll(cx), Poll::Pending);
+        assert_eq!(fut.reborrow().poll(cx), Poll::Ready(3));
+    });
+    assert_eq!(wakes.get(), 2);
+    assert_eq!(spawner.spawned(), 0);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn poll_fn_spawns_through_concrete_spawner() {
+    use specialized_futures::SpawnExt;
+    use specialized_futures::future::ready;
+
+    let fut = poll_fn(|cx: &mut Context<CountingSpawner>| {
+        cx.spawner().spawn(ready(())).unwrap();
+        Poll::Ready(cx.spawner().spawned())
+    });
+    pin_mut!(fut);
+    let mut spawner = CountingSpawner::default();
+    let (_, res) = with_counting_context(&mut spawner, |cx| fut.reborrow().poll(cx));
+    assert_eq!(res, Poll::Ready(1));
+    assert_eq!(spawner.spawned(), 1);
+}
+
+#[test]
+fn ready_resolves_on_first_poll() {
+    let fut = ready(1);
+    pin_mut!(fut);
+    assert!(!fut.is_terminated());
+    with_noop_context(|cx| assert_eq!(fut.reborrow().poll(cx), Poll::Ready(1)));
+    assert!(fut.is_terminated());
+}
+
+#[test]
+#[should_panic(expected = "Ready polled after completion")]
+fn ready_polled_twice() {
+    let fut = ready(1);
+    pin_mut!(fut);
+    with_noop_context(|cx| {
+        let _ = fut.reborrow().poll(cx);
+        let _ = fut.reborrow().poll(cx);
+    });
+}
+
+#[test]
+fn ready_into_inner() {
+    assert_eq!(ready("value").into_inner(), "value");
+}
+
+#[test]
+#[should_panic(expected = "Ready::into_inner called after the future completed")]
+fn ready_into_inner_after_completion() {
+    let mut fut = ready(1);
+    with_noop_context(|cx| {
+        let _ = PinMut::new(&mut fut).poll(cx);
+    });
+    fut.into_inner();
+}
+
+#[test]
+fn pending_never_resolves_or_wakes() {
+    let fut = pending::<()>();
+    pin_mut!(fut);
+    let (wakes, ()) = with_counting_context(&mut CountingSpawner::default(), |cx| {
+        for _ in 0..3 {
+            assert_eq!(fut.reborrow().poll(cx), Poll::Pending);
+        }
+    });
+    assert_eq!(wakes.get(), 0);
+    assert!(!fut.is_terminated());
+}
+
+#[test]
+fn pending_never_wins_select() {
+    let fut = select(pending::<i32>(), ready(2));
+    pin_mut!(fut);
+    match with_noop_context(|cx| fut.reborrow().poll(cx)) {
+        Poll::Ready(Either::Right((2, _))) => {}
+        _ => panic!("the ready branch should win"),
+    }
+    let fut = select(ready(1), pending::<i32>());
+    pin_mut!(fut);
+    match with_noop_context(|cx| fut.reborrow().poll(cx)) {
+        Poll::Ready(Either::Left((1, _))) => {}
+        _ => panic!("the ready branch should win"),
+    }
+}
+
+/// A `!Unpin` future which becomes ready on its second poll.
+struct Immovable {
+    polled: bool,
+    _pinned: Pinned,
+}
+
+impl Immovable {
+    fn new() -> Immovable {
+        Immovable { polled: false, _pinned: Pinned }
+    }
+}
+
+impl<S: Spawn + ?Sized> Future<S> for Immovable {
+    type Output = i32;
+
+    fn poll(mut self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<i32> {
+        // Safe: `polled` is never pinned structurally.
+        let this = unsafe { PinMut::get_mut_unchecked(self.reborrow()) };
+        if this.polled {
+            Poll::Ready(3)
+        } else {
+            this.polled = true;
+            Poll::Pending
+        }
+    }
+}
+
+fn either_future(left: bool) -> Either<Ready<i32>, Immovable> {
+    if left {
+        FutureExt::<dyn Spawn>::left_future(ready(1))
+    } else {
+        FutureExt::<dyn Spawn>::right_future(Immovable::new())
+    }
+}
+
+#[test]
+fn either_polls_active_arm() {
+    let fut = either_future(true);
+    pin_mut!(fut);
+    with_noop_context(|cx| assert_eq!(fut.reborrow().poll(cx), Poll::Ready(1)));
+
+    let fut = either_future(false);
+    pin_mut!(fut);
+    with_noop_context(|cx| {
+        assert_eq!(fut.reborrow().poll(cx), Poll::Pending);
+        assert_eq!(fut.reborrow().poll(cx), Poll::Ready(3));
+    });
+}
+
+#[test]
+fn either_is_terminated_delegates() {
+    let fut: Either<Ready<i32>, Ready<i32>> = Either::Right(ready(2));
+    pin_mut!(fut);
+    assert!(!fut.is_terminated());
+    with_noop_context(|cx| assert_eq!(fut.reborrow().poll(cx), Poll::Ready(2)));
+    assert!(fut.is_terminated());
+}
+
+#[test]
+fn either_from_select_drives_loser() {
+    let fut = select(ready(1), pending::<i32>());
+    pin_mut!(fut);
+    let (won, lost) = match with_noop_context(|cx| fut.reborrow().poll(cx)) {
+        Poll::Ready(Either::Left((won, lost))) => (won, lost),
+        _ => panic!("the ready branch should win"),
+    };
+    assert_eq!(won, 1);
+    assert!(!lost.is_terminated());
+
+    // The loser can be put back into an `Either` alongside other futures.
+    let rest: Either<_, Ready<i32>> = Either::Left(lost);
+    pin_mut!(rest);
+    with_noop_context(|cx| assert_eq!(rest.reborrow().poll(cx), Poll::Pending));
+}
+
+#[test]
+fn maybe_done_state_transitions() {
+    let fut: MaybeDone<Immovable> = maybe_done(Immovable::new());
+    pin_mut!(fut);
+    assert!(!fut.is_terminated());
+    assert_eq!(fut.reborrow().output_mut(), None);
+    assert_eq!(fut.reborrow().take_output(), None);
+
+    // Future -> Future
+    with_noop_context(|cx| assert_eq!(fut.reborrow().poll(cx), Poll::Pending));
+    assert!(!fut.is_terminated());
+    assert_eq!(fut.reborrow().take_output(), None);
+
+    // Future -> Done
+    with_noop_context(|cx| assert_eq!(fut.reborrow().poll(cx), Poll::Ready(())));
+    assert!(fut.is_terminated());
+    *fut.reborrow().output_mut().unwrap() += 1;
+
+    // Done stays Done when polled again
+    with_noop_context(|cx| assert_eq!(fut.reborrow().poll(cx), Poll::Ready(())));
+
+    // Done -> Gone
+    assert_eq!(fut.reborrow().take_output(), Some(4));
+    assert!(fut.is_terminated());
+    assert_eq!(fut.reborrow().output_mut(), None);
+    assert_eq!(fut.reborrow().take_output(), None);
+}
+
+#[test]
+#[should_panic(expected = "MaybeDone polled after value taken")]
+fn maybe_done_polled_after_take() {
+    let fut: MaybeDone<Ready<i32>> = maybe_done(ready(1));
+    pin_mut!(fut);
+    with_noop_context(|cx| {
+        let _ = fut.reborrow().poll(cx);
+        assert_eq!(fut.reborrow().take_output(), Some(1));
+        let _ = fut.reborrow().poll(cx);
+    });
+}
+
+#[test]
+fn now_or_never_ready() {
+    assert_eq!(FutureExt::<dyn Spawn>::now_or_never(ready(1)), Some(1));
+}
+
+#[test]
+fn now_or_never_pending_not_unpin() {
+    assert_eq!(FutureExt::<dyn Spawn>::now_or_never(Immovable::new()), None);
+}
+
+#[test]
+fn now_or_never_drops_pending_future() {
+    struct DropFlag<'a>(&'a Cell<bool>);
+
+    impl<'a> Drop for DropFlag<'a> {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    let dropped = Cell::new(false);
+    let flag = DropFlag(&dropped);
+    let fut = poll_fn(move |_: &mut Context| {
+        let _ = &flag;
+        Poll::Pending::<()>
+    });
+    assert_eq!(fut.now_or_never(), None);
+    assert!(dropped.get());
+}
+
+#[test]
+fn lazy_runs_once_on_first_poll() {
+    let calls = Cell::new(0);
+    let fut = lazy(|_: &mut Context| {
+        calls.set(calls.get() + 1);
+        calls.get()
+    });
+    assert_eq!(calls.get(), 0);
+    pin_mut!(fut);
+    with_noop_context(|cx| assert_eq!(fut.reborrow().poll(cx), Poll::Ready(1)));
+    assert_eq!(calls.get(), 1);
+    assert!(fut.is_terminated());
+}
+
+#[test]
+#[should_panic(expected = "Lazy polled after completion")]
+fn lazy_polled_after_completion() {
+    let fut = lazy(|_: &mut Context| ());
+    pin_mut!(fut);
+    with_noop_context(|cx| {
+        let _ = fut.reborrow().poll(cx);
+        let _ = fut.reborrow().poll(cx);
+    });
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn lazy_spawns_through_its_context() {
+    use specialized_futures::SpawnExt;
+
+    let fut = lazy(|cx: &mut Context<CountingSpawner>| {
+        cx.spawner().spawn(ready(())).unwrap();
+    });
+    pin_mut!(fut);
+    let mut spawner = CountingSpawner::default();
+    with_counting_context(&mut spawner, |cx| assert_eq!(fut.reborrow().poll(cx), Poll::Ready(())));
+    assert_eq!(spawner.spawned(), 1);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn ready_under_block_on() {
+    assert_eq!(specialized_futures::executor::block_on(ready(4)), 4);
+}
+
+#[cfg(feature = "std")]
+mod catch_unwind {
+    use std::cell::Cell;
+    use std::panic::{AssertUnwindSafe, UnwindSafe};
+    use specialized_futures::{Future, FutureExt};
+    use specialized_futures::future::{CatchUnwind, FusedFuture, poll_fn, ready};
+    use specialized_futures::task::Poll;
+
+    use support::with_noop_context;
+
+    fn catch_unwind<F>(future: F) -> CatchUnwind<F>
+        where F: Future + UnwindSafe
+    {
+        future.catch_unwind()
+    }
+
+    #[test]
+    fn catch_unwind_completes_normally() {
+        let fut = catch_unwind(ready(3));
+        pin_mut!(fut);
+        with_noop_context(|cx| match fut.reborrow().poll(cx) {
+            Poll::Ready(Ok(3)) => {}
+            _ => panic!("expected Ready(Ok(3))"),
+        });
+    }
+
+    #[test]
+    fn catch_unwind_first_poll_panics() {
+        let fut = catch_unwind(poll_fn(|_| -> Poll<()> { panic!("boom") }));
+        pin_mut!(fut);
+        with_noop_context(|cx| match fut.reborrow().poll(cx) {
+            Poll::Ready(Err(payload)) => assert_eq!(*payload.downcast::<&str>().unwrap(), "boom"),
+            _ => panic!("expected the panic to be caught"),
+        });
+        assert!(fut.is_terminated());
+    }
+
+    #[test]
+    fn catch_unwind_later_poll_panics() {
+        let mut polls = 0;
+        let fut = catch_unwind(poll_fn(move |_| -> Poll<()> {
+            polls += 1;
+            if polls < 3 { Poll::Pending } else { panic!("third poll") }
+        }));
+        pin_mut!(fut);
+        with_noop_context(|cx| {
+            assert!(fut.reborrow().poll(cx).is_pending());
+            assert!(fut.reborrow().poll(cx).is_pending());
+            match fut.reborrow().poll(cx) {
+                Poll::Ready(Err(payload)) => assert_eq!(*payload.downcast::<&str>().unwrap(), "third poll"),
+                _ => panic!("expected the panic to be caught"),
+            }
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "CatchUnwind polled after completion")]
+    fn catch_unwind_polled_after_panic() {
+        let fut = catch_unwind(poll_fn(|_| -> Poll<()> { panic!("boom") }));
+        pin_mut!(fut);
+        with_noop_context(|cx| {
+            let _ = fut.reborrow().poll(cx);
+            let _ = fut.reborrow().poll(cx);
+        });
+    }
+
+    #[test]
+    fn catch_unwind_assert_unwind_safe() {
+        let cell = Cell::new(0);
+        let fut = catch_unwind(AssertUnwindSafe(poll_fn(|_| {
+            cell.set(1);
+            Poll::Ready(())
+        })));
+        pin_mut!(fut);
+        with_noop_context(|cx| assert!(fut.reborrow().poll(cx).is_ready()));
+        assert_eq!(cell.get(), 1);
+    }
+}
+
+/// A manual combinator over three `Unpin` children, summing their outputs.
+/// Its `poll` needs no `unsafe` and builds no `PinMut` of its own.
+struct Sum3<A, B, C> {
+    a: A,
+    b: B,
+    c: C,
+    outputs: [Option<i32>; 3],
+}
+
+impl<A: Unpin, B: Unpin, C: Unpin> Unpin for Sum3<A, B, C> {}
+
+impl<A, B, C> Future for Sum3<A, B, C>
+    where A: Future<Output = i32> + Unpin,
+          B: Future<Output = i32> + Unpin,
+          C: Future<Output = i32> + Unpin
+{
+    type Output = i32;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<i32> {
+        let this = &mut *self;
+        if this.outputs[0].is_none() {
+            this.outputs[0] = ready_value(this.a.poll_unpin(cx));
+        }
+        if this.outputs[1].is_none() {
+            this.outputs[1] = ready_value(this.b.poll_unpin(cx));
+        }
+        if this.outputs[2].is_none() {
+            this.outputs[2] = ready_value(this.c.poll_unpin(cx));
+        }
+        match this.outputs {
+            [Some(a), Some(b), Some(c)] => Poll::Ready(a + b + c),
+            _ => Poll::Pending,
+        }
+    }
+}
+
+fn ready_value<T>(poll: Poll<T>) -> Option<T> {
+    match poll {
+        Poll::Ready(value) => Some(value),
+        Poll::Pending => None,
+    }
+}
+
+#[test]
+fn poll_unpin_in_manual_combinator() {
+    let mut remaining = 1;
+    let slow = poll_fn(move |cx: &mut Context| {
+        if remaining == 0 {
+            return Poll::Ready(3);
+        }
+        remaining -= 1;
+        cx.waker().wake();
+        Poll::Pending
+    });
+    let mut sum = Sum3 { a: ready(1), b: slow, c: ready(2), outputs: [None; 3] };
+    let (wakes, ret) = with_counting_context(&mut NoSpawn as &mut dyn Spawn, |cx| sum.poll_unpin(cx));
+    assert_eq!((ret, wakes.get()), (Poll::Pending, 1));
+    assert_eq!(with_noop_context(|cx| sum.poll_unpin(cx)), Poll::Ready(6));
+}
+
+/// A `!Unpin` future which becomes ready with its name after `polls_left`
+/// pending polls, logging each poll and its drop.
+struct Runner {
+    name: &'static str,
+    polls_left: Option<u32>,
+    log: Rc<RefCell<Vec<String>>>,
+    _pinned: Pinned,
+}
+
+impl Runner {
+    fn new(name: &'static str, polls_left: Option<u32>, log: &Rc<RefCell<Vec<String>>>) -> Runner {
+        Runner { name, polls_left, log: log.clone(), _pinned: Pinned }
+    }
+}
+
+impl<S: Spawn + ?Sized> Future<S> for Runner {
+    type Output = &'static str;
+
+    fn poll(mut self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<&'static str> {
+        // Safe: no field is pinned structurally.
+        let this = unsafe { PinMut::get_mut_unchecked(self.reborrow()) };
+        this.log.borrow_mut().push(format!("poll {}", this.name));
+        match this.polls_left {
+            Some(0) => Poll::Ready(this.name),
+            Some(ref mut n) => {
+                *n -= 1;
+                Poll::Pending
+            }
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for Runner {
+    fn drop(&mut self) {
+        self.log.borrow_mut().push(format!("drop {}", self.name));
+    }
+}
+
+fn take_log(log: &Rc<RefCell<Vec<String>>>) -> Vec<String> {
+    log.replace(Vec::new())
+}
+
+#[test]
+fn race_left_wins() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let fut = race(Runner::new("a", Some(0), &log), Runner::new("b", None, &log));
+    pin_mut!(fut);
+    assert!(!fut.is_terminated());
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready("a"));
+    assert!(fut.is_terminated());
+    assert_eq!(take_log(&log), ["poll a", "drop a", "drop b"]);
+}
+
+#[test]
+fn race_right_wins() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let fut = FutureExt::<dyn Spawn>::race(Runner::new("a", None, &log), Runner::new("b", Some(0), &log));
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready("b"));
+    assert_eq!(take_log(&log), ["poll a", "poll b", "drop a", "drop b"]);
+}
+
+#[test]
+fn race_both_ready_on_first_poll() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let fut = race(Runner::new("a", Some(0), &log), Runner::new("b", Some(0), &log));
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready("a"));
+    // `b` is dropped without ever being polled.
+    assert_eq!(take_log(&log), ["poll a", "drop a", "drop b"]);
+}
+
+#[test]
+fn race_alternates_poll_order() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let fut = race(Runner::new("a", Some(1), &log), Runner::new("b", Some(1), &log));
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Pending);
+    assert_eq!(take_log(&log), ["poll a", "poll b"]);
+    // Both are ready now, and `b` goes first this time.
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready("b"));
+    assert_eq!(take_log(&log), ["poll b", "drop a", "drop b"]);
+}
+
+#[test]
+fn race_drops_loser_before_resolving() {
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let fut = race(Runner::new("a", None, &log), Runner::new("b", Some(2), &log));
+    pin_mut!(fut);
+    for _ in 0..2 {
+        assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Pending);
+    }
+    take_log(&log);
+    // The race itself is still alive here, so the drops below happened
+    // inside the poll which returned the winner's value.
+    let out = with_noop_context(|cx| fut.reborrow().poll(cx));
+    assert_eq!(take_log(&log), ["poll a", "poll b", "drop a", "drop b"]);
+    assert_eq!(out, Poll::Ready("b"));
+}
+
+#[test]
+#[should_panic(expected = "Race polled after completion")]
+fn race_polled_after_completion() {
+    let fut = race(ready(1), ready(2));
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready(1));
+    let _ = with_noop_context(|cx| fut.reborrow().poll(cx));
+}
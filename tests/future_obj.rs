@@ -0,0 +1,561 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+
+extern crate specialized_futures;
+
+mod support;
+
+use std::cell::Cell;
+use std::mem::PinMut;
+use std::rc::Rc;
+use specialized_futures::{Context, Future, FutureObj, LocalFutureObj, Spawn};
+use specialized_futures::future::poll_fn;
+use specialized_futures::spawn::SpawnObjError;
+use specialized_futures::stream::StreamObj;
+use specialized_futures::task::Poll;
+
+use support::with_counting_context;
+
+/// A spawner which counts the tasks spawned through it and drops them.
+#[derive(Default)]
+struct Recorder {
+    spawned: usize,
+}
+
+impl Spawn for Recorder {
+    fn spawn_obj(
+        &mut self,
+        _future: FutureObj<'static, (), dyn Spawn>,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawned += 1;
+        Ok(())
+    }
+}
+
+/// Spawns a task through whatever spawner it is polled with, then pends
+/// once before resolving to `value`.
+struct SpawnThenYield {
+    polls: usize,
+    value: i32,
+}
+
+impl Future<dyn Spawn> for SpawnThenYield {
+    type Output = i32;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<i32> {
+        self.polls += 1;
+        if self.polls == 1 {
+            // `Box::leak` of a zero-sized future allocates nothing, and
+            // works without the crate's `alloc` feature.
+            let task = FutureObj::new(Box::leak(Box::new(Nothing)));
+            assert!(cx.spawner().spawn_obj(task).is_ok());
+            cx.waker().wake();
+            Poll::Pending
+        } else {
+            Poll::Ready(self.value)
+        }
+    }
+}
+
+struct Nothing;
+
+impl Future<dyn Spawn> for Nothing {
+    type Output = ();
+
+    fn poll(self: PinMut<Self>, _cx: &mut Context) -> Poll<()> {
+        Poll::Ready(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod specialize {
+    use std::mem::PinMut;
+    use ::specialized_futures::{Future, FutureObj, LocalFutureObj, Spawn};
+    use ::specialized_futures::task::Poll;
+    use support::with_counting_context;
+    use super::{Recorder, SpawnThenYield};
+
+    #[test]
+    fn local_obj_pending_then_ready() {
+        let obj: LocalFutureObj<i32, dyn Spawn> =
+            LocalFutureObj::new(Box::new(SpawnThenYield { polls: 0, value: 5 }));
+        let mut obj: LocalFutureObj<i32, Recorder> = obj.specialize();
+        let mut spawner = Recorder::default();
+        let (wakes, ret) = with_counting_context(&mut spawner, |cx| PinMut::new(&mut obj).poll(cx));
+        assert_eq!(ret, Poll::Pending);
+        assert_eq!(wakes.get(), 1);
+        // The inner future spawned through the concrete spawner.
+        assert_eq!(spawner.spawned, 1);
+        let (_, ret) = with_counting_context(&mut spawner, |cx| PinMut::new(&mut obj).poll(cx));
+        assert_eq!(ret, Poll::Ready(5));
+    }
+
+    #[test]
+    fn future_obj_pending_then_ready() {
+        let obj: FutureObj<i32, dyn Spawn> =
+            FutureObj::new(Box::new(SpawnThenYield { polls: 0, value: 6 }));
+        let mut obj: FutureObj<i32, Recorder> = obj.specialize();
+        let mut spawner = Recorder::default();
+        let (_, ret) = with_counting_context(&mut spawner, |cx| PinMut::new(&mut obj).poll(cx));
+        assert_eq!(ret, Poll::Pending);
+        let (_, ret) = with_counting_context(&mut spawner, |cx| PinMut::new(&mut obj).poll(cx));
+        assert_eq!(ret, Poll::Ready(6));
+        assert_eq!(spawner.spawned, 1);
+    }
+
+    #[test]
+    fn specialized_obj_is_sendable() {
+        fn assert_send<T: Send>(_: &T) {}
+        let obj: FutureObj<i32, dyn Spawn> =
+            FutureObj::new(Box::new(SpawnThenYield { polls: 0, value: 0 }));
+        assert_send(&obj.specialize::<Recorder>());
+    }
+}
+
+#[test]
+fn dyn_obj_polled_with_concrete_spawner_by_borrowing() {
+    let mut inner = SpawnThenYield { polls: 0, value: 7 };
+    let mut obj: LocalFutureObj<i32, dyn Spawn> = LocalFutureObj::new(&mut inner);
+    let mut spawner = Recorder::default();
+    {
+        // No allocation: the specialized obj only borrows the `dyn Spawn` one.
+        let mut specialized: LocalFutureObj<i32, Recorder> = LocalFutureObj::new(&mut obj);
+        let (wakes, ret) = with_counting_context(&mut spawner, |cx| PinMut::new(&mut specialized).poll(cx));
+        assert_eq!((ret, wakes.get()), (Poll::Pending, 1));
+        let (_, ret) = with_counting_context(&mut spawner, |cx| PinMut::new(&mut specialized).poll(cx));
+        assert_eq!(ret, Poll::Ready(7));
+    }
+    assert_eq!(spawner.spawned, 1);
+}
+
+#[test]
+fn borrowed_poll_fn_obj_polled_manually() {
+    let mut remaining = 2;
+    let mut total_wakes = 0;
+    {
+        let mut f = poll_fn(|cx: &mut Context| {
+            if remaining == 0 {
+                return Poll::Ready("done");
+            }
+            remaining -= 1;
+            cx.waker().wake();
+            Poll::Pending
+        });
+        let mut obj: LocalFutureObj<&str, dyn Spawn> = LocalFutureObj::new(&mut f);
+        loop {
+            let (wakes, ret) = with_counting_context(&mut Recorder::default(), |cx| {
+                PinMut::new(&mut obj).poll(cx)
+            });
+            match ret {
+                Poll::Ready(ret) => {
+                    assert_eq!(ret, "done");
+                    break;
+                }
+                Poll::Pending => total_wakes += wakes.get(),
+            }
+        }
+    }
+    assert_eq!((remaining, total_wakes), (0, 2));
+}
+
+#[cfg(feature = "alloc")]
+mod from_fn {
+    use std::cell::Cell;
+    use std::mem::PinMut;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use ::specialized_futures::{Context, Future, Spawn};
+    use ::specialized_futures::future::{from_fn_local_obj, from_fn_obj};
+    use ::specialized_futures::task::Poll;
+    use support::with_noop_context;
+
+    /// Bumps the counter when dropped.
+    struct DropCount(Arc<AtomicUsize>);
+
+    impl Drop for DropCount {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn closure_dropped_exactly_once() {
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let guard = DropCount(dropped.clone());
+        let mut obj = from_fn_obj(move |_: &mut Context| {
+            let _guard = &guard;
+            Poll::Ready(())
+        });
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut obj).poll(cx)), Poll::Ready(()));
+        assert_eq!(dropped.load(Ordering::SeqCst), 0);
+        drop(obj);
+        assert_eq!(dropped.load(Ordering::SeqCst), 1);
+
+        // Also when the obj is dropped without being polled.
+        let guard = DropCount(dropped.clone());
+        drop(from_fn_obj::<(), dyn Spawn, _>(move |_| {
+            let _guard = &guard;
+            Poll::Ready(())
+        }));
+        assert_eq!(dropped.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn local_obj_from_non_send_closure() {
+        let polls = Rc::new(Cell::new(0));
+        let mut obj = {
+            let polls = polls.clone();
+            from_fn_local_obj(move |_: &mut Context| {
+                polls.set(polls.get() + 1);
+                if polls.get() < 2 { Poll::Pending } else { Poll::Ready(polls.get()) }
+            })
+        };
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut obj).poll(cx)), Poll::Pending);
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut obj).poll(cx)), Poll::Ready(2));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn fn_obj_spawned_on_local_pool() {
+        use ::specialized_futures::executor::LocalPool;
+
+        let ran = Arc::new(AtomicUsize::new(0));
+        let mut pool = LocalPool::new();
+        let task = {
+            let ran = ran.clone();
+            from_fn_obj(move |_: &mut Context| {
+                ran.fetch_add(1, Ordering::SeqCst);
+                Poll::Ready(())
+            })
+        };
+        pool.spawner().spawn_obj(task).unwrap();
+        pool.run();
+        assert_eq!(ran.load(Ordering::SeqCst), 1);
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod reuse_allocation {
+    use std::boxed::PinBox;
+    use std::marker::Unpin;
+    use std::mem::PinMut;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use ::specialized_futures::{Context, Future, FutureExt, FutureObj, LocalFutureObj, Spawn};
+    use ::specialized_futures::task::Poll;
+    use support::with_noop_context;
+
+    /// Records the address it is polled at, and counts its drops.
+    struct Located {
+        polled_at: Arc<AtomicUsize>,
+        drops: Arc<AtomicUsize>,
+    }
+
+    impl Future<dyn Spawn> for Located {
+        type Output = ();
+
+        fn poll(self: PinMut<Self>, _cx: &mut Context) -> Poll<()> {
+            self.polled_at.store(&*self as *const Located as usize, Ordering::SeqCst);
+            Poll::Ready(())
+        }
+    }
+
+    impl Drop for Located {
+        fn drop(&mut self) {
+            self.drops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn located() -> (Located, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+        let (polled_at, drops) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        (Located { polled_at: polled_at.clone(), drops: drops.clone() }, polled_at, drops)
+    }
+
+    fn poll_and_drop<O: Future<dyn Spawn, Output = ()> + Unpin>(mut obj: O) {
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut obj).poll(cx)), Poll::Ready(()));
+    }
+
+    #[test]
+    fn box_into_obj_keeps_allocation() {
+        let (future, polled_at, drops) = located();
+        let boxed = Box::new(future);
+        let address = &*boxed as *const Located as usize;
+        let obj: FutureObj<(), dyn Spawn> = FutureObj::from(boxed);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        poll_and_drop(obj);
+        assert_eq!(polled_at.load(Ordering::SeqCst), address);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        let (future, polled_at, drops) = located();
+        let boxed = Box::new(future);
+        let address = &*boxed as *const Located as usize;
+        let obj: LocalFutureObj<(), dyn Spawn> = boxed.into();
+        poll_and_drop(obj);
+        assert_eq!(polled_at.load(Ordering::SeqCst), address);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn pin_box_into_obj_keeps_allocation() {
+        let (future, polled_at, drops) = located();
+        let mut boxed = PinBox::new(future);
+        let address = &*boxed.as_pin_mut() as *const Located as usize;
+        let obj: FutureObj<(), dyn Spawn> = boxed.into();
+        poll_and_drop(obj);
+        assert_eq!(polled_at.load(Ordering::SeqCst), address);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn unpolled_obj_drops_future_once() {
+        let (future, _, drops) = located();
+        drop(FutureObj::<(), dyn Spawn>::from(Box::new(future)));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        let (future, _, drops) = located();
+        drop(LocalFutureObj::<(), dyn Spawn>::from(PinBox::new(future)));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn boxed_and_boxed_local_drop_once() {
+        let (future, _, drops) = located();
+        poll_and_drop(FutureExt::<dyn Spawn>::boxed(future));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        let (future, _, drops) = located();
+        drop(FutureExt::<dyn Spawn>::boxed_local(future));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod map_output {
+    use std::mem::PinMut;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use ::specialized_futures::{Context, Future, FutureObj, LocalFutureObj, Spawn};
+    use ::specialized_futures::task::Poll;
+    use support::with_noop_context;
+
+    /// Pends once, then completes with `value`. Records the address it is
+    /// polled at, and counts its drops.
+    struct Twice {
+        value: u32,
+        polled: bool,
+        polled_at: Arc<AtomicUsize>,
+        drops: Arc<AtomicUsize>,
+    }
+
+    impl Future<dyn Spawn> for Twice {
+        type Output = u32;
+
+        fn poll(mut self: PinMut<Self>, cx: &mut Context) -> Poll<u32> {
+            self.polled_at.store(&*self as *const Twice as usize, Ordering::SeqCst);
+            if self.polled {
+                return Poll::Ready(self.value);
+            }
+            self.polled = true;
+            cx.waker().wake();
+            Poll::Pending
+        }
+    }
+
+    impl Drop for Twice {
+        fn drop(&mut self) {
+            self.drops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Bumps the counter when dropped.
+    struct DropCount(Arc<AtomicUsize>);
+
+    impl Drop for DropCount {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn twice(value: u32) -> (Box<Twice>, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+        let (polled_at, drops) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let future = Twice { value, polled: false, polled_at: polled_at.clone(), drops: drops.clone() };
+        (Box::new(future), polled_at, drops)
+    }
+
+    #[test]
+    fn mapping_runs_once_and_nothing_drops_twice() {
+        let (future, polled_at, drops) = twice(4);
+        let address = &*future as *const Twice as usize;
+        let (calls, closure_drops) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let mut obj = {
+            let (calls, guard) = (calls.clone(), DropCount(closure_drops.clone()));
+            LocalFutureObj::<u32, dyn Spawn>::from(future).map_output(move |n| {
+                let _guard = guard;
+                calls.fetch_add(1, Ordering::SeqCst);
+                n * 10
+            })
+        };
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut obj).poll(cx)), Poll::Pending);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut obj).poll(cx)), Poll::Ready(40));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        // The closure went when it was called; the future stays in its
+        // original allocation until the obj is dropped.
+        assert_eq!(closure_drops.load(Ordering::SeqCst), 1);
+        assert_eq!(polled_at.load(Ordering::SeqCst), address);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        drop(obj);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert_eq!(closure_drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn unpolled_mapped_obj_drops_everything_once() {
+        let (future, _, drops) = twice(1);
+        let (calls, closure_drops) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let obj = {
+            let (calls, guard) = (calls.clone(), DropCount(closure_drops.clone()));
+            FutureObj::<u32, dyn Spawn>::from(future).map_output(move |_| {
+                let _guard = guard;
+                calls.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        drop(obj);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert_eq!(closure_drops.load(Ordering::SeqCst), 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn output_obj_mapped_to_unit_and_spawned() {
+        use ::specialized_futures::executor::LocalPool;
+
+        let (future, _, drops) = twice(7);
+        let received = Arc::new(AtomicUsize::new(0));
+        let task: FutureObj<'static, (), dyn Spawn> = {
+            let received = received.clone();
+            FutureObj::from(future).map_output(move |n| received.store(n as usize, Ordering::SeqCst))
+        };
+        let mut pool = LocalPool::new();
+        pool.spawner().spawn_obj(task).unwrap();
+        pool.run();
+        assert_eq!(received.load(Ordering::SeqCst), 7);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod downcast {
+    use std::mem::PinMut;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use ::specialized_futures::{Context, Future, LocalFutureObj, Spawn};
+    use ::specialized_futures::task::Poll;
+    use support::with_noop_context;
+
+    /// Pends until it has been polled `target` times, and counts its drops.
+    #[derive(Debug)]
+    struct Countdown {
+        polls: usize,
+        target: usize,
+        drops: Arc<AtomicUsize>,
+    }
+
+    impl Future<dyn Spawn> for Countdown {
+        type Output = usize;
+
+        fn poll(mut self: PinMut<Self>, _cx: &mut Context) -> Poll<usize> {
+            self.polls += 1;
+            if self.polls < self.target {
+                Poll::Pending
+            } else {
+                Poll::Ready(self.polls)
+            }
+        }
+    }
+
+    impl Drop for Countdown {
+        fn drop(&mut self) {
+            self.drops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn countdown(target: usize) -> (Box<Countdown>, Arc<AtomicUsize>) {
+        let drops = Arc::new(AtomicUsize::new(0));
+        (Box::new(Countdown { polls: 0, target, drops: drops.clone() }), drops)
+    }
+
+    fn poll(obj: &mut LocalFutureObj<usize, dyn Spawn>) -> Poll<usize> {
+        with_noop_context(|cx| PinMut::new(obj).poll(cx))
+    }
+
+    #[test]
+    fn round_trip_keeps_state() {
+        let (future, drops) = countdown(3);
+        let mut obj = LocalFutureObj::<usize, dyn Spawn>::new_downcastable(future);
+        assert_eq!(poll(&mut obj), Poll::Pending);
+
+        let future = obj.downcast::<Countdown>().unwrap();
+        assert_eq!(future.polls, 1);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+
+        let mut obj = LocalFutureObj::<usize, dyn Spawn>::new_downcastable(future);
+        assert_eq!(poll(&mut obj), Poll::Pending);
+        assert_eq!(poll(&mut obj), Poll::Ready(3));
+        drop(obj);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn round_trip_hands_back_the_original_box() {
+        let (future, drops) = countdown(2);
+        let addr = &*future as *const Countdown;
+        let mut obj = LocalFutureObj::<usize, dyn Spawn>::new_downcastable(future);
+        assert_eq!(poll(&mut obj), Poll::Pending);
+
+        // The future was neither moved into a new allocation when the obj
+        // was made nor when it was recovered.
+        let future = obj.downcast::<Countdown>().unwrap();
+        assert_eq!(&*future as *const Countdown, addr);
+        drop(future);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn type_mismatch_returns_obj_intact() {
+        let (future, drops) = countdown(2);
+        let mut obj = LocalFutureObj::<usize, dyn Spawn>::new_downcastable(future);
+        assert_eq!(poll(&mut obj), Poll::Pending);
+
+        let mut obj = obj.downcast::<u32>().unwrap_err();
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        assert_eq!(poll(&mut obj), Poll::Ready(2));
+        drop(obj);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn plain_obj_is_not_downcastable() {
+        let (future, drops) = countdown(1);
+        let obj = LocalFutureObj::<usize, dyn Spawn>::new(future);
+        let mut obj = obj.downcast::<Countdown>().unwrap_err();
+        assert_eq!(poll(&mut obj), Poll::Ready(1));
+        drop(obj);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+}
+
+#[test]
+fn objs_are_send_and_sync_for_any_spawner_and_output() {
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+    // What `Spawn::spawn_obj` takes, and so what executors queue.
+    assert_send::<FutureObj<'static, (), dyn Spawn>>();
+    assert_sync::<FutureObj<'static, (), dyn Spawn>>();
+    assert_send::<FutureObj<'static, (), dyn Spawn + Send>>();
+    assert_send::<FutureObj<'static, (), Recorder>>();
+    // Outputs and items never cross threads inside an obj.
+    assert_send::<FutureObj<'static, Rc<u8>, dyn Spawn>>();
+    assert_sync::<FutureObj<'static, Cell<u8>, dyn Spawn>>();
+    assert_send::<StreamObj<'static, Rc<u8>, dyn Spawn>>();
+    assert_sync::<StreamObj<'static, Cell<u8>, dyn Spawn>>();
+}
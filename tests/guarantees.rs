@@ -0,0 +1,194 @@
+//! The auto-trait guarantees documented in `specialized_futures::guarantees`,
+//! and the behavior of `AssertSendFuture`.
+//!
+//! The positive guarantees are checked by the functions below type-checking;
+//! the negative ones are checked by the programs in `tests/compile-fail`.
+#![feature(pin, arbitrary_self_types, futures_api)]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::marker::Unpin;
+use std::mem::PinMut;
+use specialized_futures::{Context, Future, Spawn};
+use specialized_futures::future::{Either, FusedFuture, Pending, Race, Ready, Select, poll_fn, ready};
+use specialized_futures::guarantees::AssertSendFuture;
+use specialized_futures::spawn::{NoSpawn, SpawnErrorKind};
+use specialized_futures::stream::{Empty, Enumerate, Fuse, Iter, Zip};
+use specialized_futures::task::Poll;
+use specialized_futures::try_future::TrySelect;
+
+use support::{with_counting_context, with_noop_context};
+
+fn assert_send<T: ?Sized + Send>() {}
+fn assert_sync<T: ?Sized + Sync>() {}
+fn assert_unpin<T: ?Sized + Unpin>() {}
+
+#[test]
+fn spawn_error_kind_is_send_and_sync() {
+    assert_send::<SpawnErrorKind>();
+    assert_sync::<SpawnErrorKind>();
+}
+
+#[test]
+fn combinators_preserve_unpin() {
+    type Fut = Ready<u32>;
+    type St = Iter<std::vec::IntoIter<u32>>;
+    assert_unpin::<Fut>();
+    assert_unpin::<Pending<u32>>();
+    assert_unpin::<Either<Fut, Pending<u32>>>();
+    assert_unpin::<Race<Fut, Fut>>();
+    assert_unpin::<Select<Fut, Fut>>();
+    assert_unpin::<TrySelect<Ready<Result<u32, ()>>, Ready<Result<u32, ()>>>>();
+    assert_unpin::<AssertSendFuture<Fut>>();
+
+    assert_unpin::<St>();
+    assert_unpin::<Empty<u32>>();
+    assert_unpin::<Fuse<St>>();
+    assert_unpin::<Enumerate<St>>();
+    assert_unpin::<Zip<St, St>>();
+    assert_unpin::<Zip<St, St, NoSpawn>>();
+}
+
+#[test]
+fn combinators_preserve_send_and_sync() {
+    type Fut = Ready<u32>;
+    type St = Iter<std::vec::IntoIter<u32>>;
+    assert_send::<Race<Fut, Fut>>();
+    assert_sync::<Race<Fut, Fut>>();
+    assert_send::<Select<Fut, Fut>>();
+    assert_send::<AssertSendFuture<Fut>>();
+    assert_sync::<AssertSendFuture<Fut>>();
+    assert_send::<Zip<St, St>>();
+    assert_sync::<Zip<St, St>>();
+    assert_send::<Enumerate<St>>();
+}
+
+#[cfg(feature = "alloc")]
+mod objs {
+    use specialized_futures::{FutureObj, LocalFutureObj, Spawn};
+    use specialized_futures::spawn::NoSpawn;
+    use specialized_futures::stream::{Chunks, LocalStreamObj, ReadyChunks, StreamObj};
+    use super::{assert_send, assert_sync, assert_unpin};
+
+    #[test]
+    fn future_objs_are_send_and_sync() {
+        // What executors queue, for any spawner they poll tasks with.
+        assert_send::<FutureObj<'static, (), dyn Spawn>>();
+        assert_sync::<FutureObj<'static, (), dyn Spawn>>();
+        assert_send::<FutureObj<'static, (), NoSpawn>>();
+        assert_sync::<FutureObj<'static, (), NoSpawn>>();
+        assert_send::<StreamObj<'static, (), dyn Spawn>>();
+        assert_sync::<StreamObj<'static, (), dyn Spawn>>();
+    }
+
+    #[test]
+    fn objs_are_unpin() {
+        assert_unpin::<FutureObj<'static, (), dyn Spawn>>();
+        assert_unpin::<LocalFutureObj<'static, (), dyn Spawn>>();
+        assert_unpin::<StreamObj<'static, (), dyn Spawn>>();
+        assert_unpin::<LocalStreamObj<'static, (), dyn Spawn>>();
+    }
+
+    #[test]
+    fn chunk_combinators_preserve_unpin_and_send() {
+        type St = StreamObj<'static, u32, dyn Spawn>;
+        assert_unpin::<Chunks<St, u32>>();
+        assert_unpin::<ReadyChunks<St>>();
+        assert_send::<Chunks<St, u32>>();
+        assert_send::<ReadyChunks<St>>();
+    }
+}
+
+#[test]
+fn assert_send_future_is_transparent() {
+    let fut = AssertSendFuture::new(ready(5));
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready(5));
+
+    // `into_inner` hands back the future itself, unpolled.
+    let mut polls = 0;
+    let fut = AssertSendFuture::new(poll_fn(move |_: &mut Context| {
+        polls += 1;
+        Poll::Ready(polls)
+    }));
+    let mut inner = fut.into_inner();
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut inner).poll(cx)), Poll::Ready(1));
+}
+
+#[test]
+fn assert_send_future_passes_wakeups_and_spawner_through() {
+    let mut woke = false;
+    let mut fut = AssertSendFuture::new(poll_fn(move |cx: &mut Context<NoSpawn>| {
+        // Polled with the caller's concrete spawner.
+        let _: &mut NoSpawn = cx.spawner();
+        if woke {
+            return Poll::Ready(());
+        }
+        woke = true;
+        cx.waker().wake();
+        Poll::Pending
+    }));
+    let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| PinMut::new(&mut fut).poll(cx));
+    assert_eq!(ret, Poll::Pending);
+    assert_eq!(wakes.get(), 1);
+    let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| PinMut::new(&mut fut).poll(cx));
+    assert_eq!(ret, Poll::Ready(()));
+    assert_eq!(wakes.get(), 0);
+}
+
+/// A fused future which completes on its first poll.
+#[derive(Debug, Default)]
+struct Once {
+    done: bool,
+}
+
+impl<S: Spawn + ?Sized> Future<S> for Once {
+    type Output = ();
+
+    fn poll(mut self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<()> {
+        self.done = true;
+        Poll::Ready(())
+    }
+}
+
+impl FusedFuture for Once {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+#[test]
+fn assert_send_future_forwards_is_terminated() {
+    let mut fut = AssertSendFuture::new(Once::default());
+    assert!(!fut.is_terminated());
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut fut).poll(cx)), Poll::Ready(()));
+    assert!(fut.is_terminated());
+    assert_eq!(format!("{:?}", fut), "AssertSendFuture(Once { done: true })");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn assert_send_future_spawns_on_thread_pool() {
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use specialized_futures::SpawnExt;
+    use specialized_futures::executor::ThreadPool;
+
+    let mut pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    let (tx, rx) = mpsc::channel();
+    let mut yielded = false;
+    let fut = AssertSendFuture::new(poll_fn(move |cx: &mut Context| {
+        if !yielded {
+            yielded = true;
+            cx.waker().wake();
+            return Poll::Pending;
+        }
+        tx.send(7).unwrap();
+        Poll::Ready(())
+    }));
+    pool.spawn(fut).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_secs(10)).unwrap(), 7);
+}
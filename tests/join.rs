@@ -0,0 +1,278 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+#![cfg(feature = "std")]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::marker::Unpin;
+use std::mem::PinMut;
+use std::sync::{Arc, Mutex, mpsc};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::task::{Wake, local_waker_from_nonlocal};
+use std::thread;
+use std::time::Duration;
+use specialized_futures::{Context, Future, FutureObj, Spawn, SpawnExt};
+use specialized_futures::executor::{LocalPool, ThreadPool, block_on};
+use specialized_futures::future::{pending, poll_fn};
+use specialized_futures::spawn::{JoinError, NoSpawn, SpawnObjError, TimerSpawn};
+use specialized_futures::task::{Poll, Waker};
+
+use support::with_noop_context;
+
+/// Sets the flag when dropped.
+struct SetOnDrop(Arc<AtomicBool>);
+
+impl Drop for SetOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn panic_payload_round_trips_and_worker_survives() {
+    let mut pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    let (tx, rx) = mpsc::channel();
+    let handle = pool.spawn_with_handle(poll_fn(move |_: &mut Context| -> Poll<()> {
+        tx.send(thread::current().id()).unwrap();
+        panic!("task panicked")
+    })).unwrap();
+    let err = block_on(handle).unwrap_err();
+    assert!(err.is_panic() && !err.is_cancelled());
+    assert_eq!(err.to_string(), "task panicked");
+    assert_eq!(err.into_panic().downcast_ref::<&str>(), Some(&"task panicked"));
+
+    // The same worker is still alive to run the next task.
+    let handle = pool.spawn_with_handle(poll_fn(|_: &mut Context| {
+        Poll::Ready(thread::current().id())
+    })).unwrap();
+    assert_eq!(block_on(handle).unwrap(), rx.recv().unwrap());
+}
+
+#[test]
+fn normal_completion_yields_output() {
+    let mut pool = ThreadPool::builder().pool_size(2).create().unwrap();
+    let handles = (0..4)
+        .map(|i| pool.spawn_with_handle(poll_fn(move |_: &mut Context| Poll::Ready(i * 2))).unwrap())
+        .collect::<Vec<_>>();
+    let outputs = handles.into_iter().map(|handle| block_on(handle).unwrap()).collect::<Vec<_>>();
+    assert_eq!(outputs, vec![0, 2, 4, 6]);
+}
+
+#[test]
+fn task_dropped_by_executor_is_cancelled() {
+    let pool = LocalPool::new();
+    let dropped = Arc::new(AtomicBool::new(false));
+    let mut handle = {
+        let guard = SetOnDrop(dropped.clone());
+        let mut never = pending::<()>();
+        pool.spawner().spawn_with_handle(poll_fn(move |cx: &mut Context| {
+            let _guard = &guard;
+            PinMut::new(&mut never).poll(cx)
+        })).unwrap()
+    };
+    assert!(with_noop_context(|cx| PinMut::new(&mut handle).poll(cx)).is_pending());
+    drop(pool);
+    assert!(dropped.load(Ordering::SeqCst));
+    match with_noop_context(|cx| PinMut::new(&mut handle).poll(cx)) {
+        Poll::Ready(Err(err)) => {
+            assert!(err.is_cancelled() && !err.is_panic());
+            assert_eq!(err.to_string(), "task was cancelled");
+        }
+        other => panic!("expected cancellation, got {:?}", other.map(|res| res.is_ok())),
+    }
+}
+
+#[test]
+#[should_panic(expected = "called `JoinError::into_panic` on a cancelled task")]
+fn into_panic_on_cancelled() {
+    JoinError::Cancelled.into_panic();
+}
+
+#[test]
+fn dropped_handle_detaches_task() {
+    let mut pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    let (gate_tx, mut gate_rx) = specialized_futures::channel::oneshot::channel::<()>();
+    let (done_tx, done_rx) = mpsc::channel();
+    let handle = pool.spawn_with_handle(poll_fn(move |cx: &mut Context| {
+        match PinMut::new(&mut gate_rx).poll(cx) {
+            Poll::Ready(_) => {
+                done_tx.send(()).unwrap();
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    })).unwrap();
+    drop(handle);
+    gate_tx.send(()).unwrap();
+    done_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+}
+
+#[test]
+fn cancel_on_drop_stops_task_before_sleep_finishes() {
+    let mut pool = LocalPool::new();
+    let mut spawner = pool.spawner();
+    let (dropped, finished) = (Arc::new(AtomicBool::new(false)), Arc::new(AtomicBool::new(false)));
+    let handle = {
+        let guard = SetOnDrop(dropped.clone());
+        let finished = finished.clone();
+        let mut sleep = spawner.sleep(Duration::from_secs(60));
+        spawner.spawn_with_handle(poll_fn(move |cx: &mut Context| {
+            let _guard = &guard;
+            ready!(PinMut::new(&mut sleep).poll(cx));
+            finished.store(true, Ordering::SeqCst);
+            Poll::Ready(())
+        })).unwrap().cancel_on_drop()
+    };
+    assert!(!pool.run_until_stalled());
+    drop(handle);
+    // The task notices the abort at its next poll, long before the sleep.
+    assert!(pool.run_until_stalled());
+    assert!(dropped.load(Ordering::SeqCst));
+    assert!(!finished.load(Ordering::SeqCst));
+}
+
+#[test]
+fn abort_pending_task() {
+    let mut pool = LocalPool::new();
+    let dropped = Arc::new(AtomicBool::new(false));
+    let mut handle = {
+        let guard = SetOnDrop(dropped.clone());
+        let mut never = pending::<u32>();
+        pool.spawner().spawn_with_handle(poll_fn(move |cx: &mut Context| {
+            let _guard = &guard;
+            PinMut::new(&mut never).poll(cx)
+        })).unwrap()
+    };
+    assert!(!pool.run_until_stalled());
+    handle.abort();
+    // Aborting twice is harmless.
+    handle.abort();
+    assert!(pool.run_until_stalled());
+    assert!(dropped.load(Ordering::SeqCst));
+    match with_noop_context(|cx| PinMut::new(&mut handle).poll(cx)) {
+        Poll::Ready(Err(err)) => assert!(err.is_cancelled()),
+        other => panic!("expected cancellation, got {:?}", other.map(|res| res.is_ok())),
+    }
+}
+
+#[test]
+fn abort_after_completion_keeps_output() {
+    let mut pool = LocalPool::new();
+    let handle = pool.spawner().spawn_with_handle(poll_fn(|_: &mut Context| Poll::Ready(7))).unwrap();
+    pool.run();
+    handle.abort();
+    assert_eq!(block_on(handle).unwrap(), 7);
+}
+
+#[test]
+fn abort_races_with_completion() {
+    let mut pool = ThreadPool::builder().pool_size(2).create().unwrap();
+    let (mut completed, mut cancelled) = (0, 0);
+    for i in 0..500 {
+        let ran = Arc::new(AtomicUsize::new(0));
+        let handle = {
+            let ran = ran.clone();
+            pool.spawn_with_handle(poll_fn(move |_: &mut Context| {
+                ran.fetch_add(1, Ordering::SeqCst);
+                Poll::Ready(i)
+            })).unwrap()
+        };
+        handle.abort();
+        // Whichever side wins, the handle resolves exactly once and the
+        // future never runs twice.
+        match block_on(handle) {
+            Ok(output) => {
+                assert_eq!(output, i);
+                assert_eq!(ran.load(Ordering::SeqCst), 1);
+                completed += 1;
+            }
+            Err(err) => {
+                assert!(err.is_cancelled());
+                assert!(ran.load(Ordering::SeqCst) <= 1);
+                cancelled += 1;
+            }
+        }
+    }
+    assert_eq!(completed + cancelled, 500);
+}
+
+/// A waker which, when woken, polls the future it holds from within the
+/// wakeup, as an executor which runs tasks inline would.
+struct PollOnWake<F> {
+    future: Mutex<Option<F>>,
+    polled: AtomicBool,
+}
+
+impl<F> PollOnWake<F> {
+    fn new() -> Arc<PollOnWake<F>> {
+        Arc::new(PollOnWake { future: Mutex::new(None), polled: AtomicBool::new(false) })
+    }
+}
+
+impl<F: Future + Unpin + Send> Wake for PollOnWake<F> {
+    fn wake(arc_self: &Arc<Self>) {
+        if let Some(mut future) = arc_self.future.lock().unwrap().take() {
+            let _ = with_noop_context(|cx| PinMut::new(&mut future).poll(cx));
+            arc_self.polled.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Polls `future` once with wakers which poll whatever `wake` holds.
+fn poll_with<F, W>(future: &mut F, wake: &Arc<PollOnWake<W>>) -> Poll<F::Output>
+    where F: Future + Unpin,
+          W: Future + Unpin + Send + 'static
+{
+    let lw = local_waker_from_nonlocal(wake.clone());
+    let w = Waker::from(wake.clone());
+    let mut spawner = NoSpawn;
+    let mut cx = Context::new(&lw, &w, &mut spawner as &mut dyn Spawn);
+    PinMut::new(future).poll(&mut cx)
+}
+
+/// A spawner which keeps the last task spawned on it.
+#[derive(Default)]
+struct Capture(Option<FutureObj<'static, (), dyn Spawn>>);
+
+impl Spawn for Capture {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.0 = Some(future);
+        Ok(())
+    }
+}
+
+#[test]
+fn completion_wakes_handle_outside_the_slot_lock() {
+    let mut pool = LocalPool::new();
+    let mut handle = pool.spawner().spawn_with_handle(poll_fn(|_: &mut Context| Poll::Ready(7))).unwrap();
+    let wake = PollOnWake::new();
+    assert!(poll_with(&mut handle, &wake).is_pending());
+    *wake.future.lock().unwrap() = Some(handle);
+    // Completing the task wakes the handle, which polls itself again from
+    // within the wakeup. That would deadlock if the slot were still locked.
+    pool.run();
+    assert!(wake.polled.load(Ordering::SeqCst));
+}
+
+#[test]
+fn abort_wakes_task_outside_the_slot_lock() {
+    let mut spawner = Capture::default();
+    let mut handle = spawner.spawn_with_handle(pending::<u32>()).unwrap();
+    let mut task = spawner.0.take().unwrap();
+    let wake = PollOnWake::new();
+    assert!(poll_with(&mut task, &wake).is_pending());
+    *wake.future.lock().unwrap() = Some(task);
+    // The task is polled from within the wakeup, and has to lock the slot to
+    // observe the abort.
+    handle.abort();
+    assert!(wake.polled.load(Ordering::SeqCst));
+    match with_noop_context(|cx| PinMut::new(&mut handle).poll(cx)) {
+        Poll::Ready(Err(err)) => assert!(err.is_cancelled()),
+        other => panic!("expected cancellation, got {:?}", other.map(|res| res.is_ok())),
+    }
+}
@@ -0,0 +1,493 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+
+extern crate specialized_futures;
+
+mod support;
+
+#[cfg(feature = "std")]
+mod mutex {
+    use std::cell::RefCell;
+    use std::mem::PinMut;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use ::specialized_futures::{Context, Future, LocalSpawnExt, SpawnExt};
+    use ::specialized_futures::executor::{LocalPool, ThreadPool};
+    use ::specialized_futures::future::{FusedFuture, poll_fn};
+    use ::specialized_futures::lock::{LockFuture, Mutex, MutexGuard};
+    use ::specialized_futures::spawn::NoSpawn;
+    use ::specialized_futures::task::Poll;
+    use support::{with_counting_context, with_noop_context};
+
+    /// Two counters which are only ever unequal while the lock is held.
+    #[derive(Default)]
+    struct Pair {
+        a: usize,
+        b: usize,
+    }
+
+    enum Step {
+        Idle,
+        Locking(LockFuture<'static, Pair>),
+        Holding(MutexGuard<'static, Pair>),
+    }
+
+    /// A task which takes the lock `times` times, pending once while holding
+    /// it so that other tasks get a chance to contend.
+    fn increment(mutex: &'static Mutex<Pair>, times: usize) -> impl Future<Output = ()> + Send {
+        let mut step = Step::Idle;
+        let mut done = 0;
+        poll_fn(move |cx: &mut Context| {
+            loop {
+                step = match ::std::mem::replace(&mut step, Step::Idle) {
+                    Step::Idle if done == times => return Poll::Ready(()),
+                    Step::Idle => Step::Locking(mutex.lock()),
+                    Step::Locking(mut fut) => match PinMut::new(&mut fut).poll(cx) {
+                        Poll::Ready(mut guard) => {
+                            assert_eq!(guard.a, guard.b, "lock held by two tasks at once");
+                            guard.a += 1;
+                            step = Step::Holding(guard);
+                            cx.waker().wake();
+                            return Poll::Pending;
+                        }
+                        Poll::Pending => {
+                            step = Step::Locking(fut);
+                            return Poll::Pending;
+                        }
+                    },
+                    Step::Holding(mut guard) => {
+                        guard.b += 1;
+                        done += 1;
+                        Step::Idle
+                    }
+                };
+            }
+        })
+    }
+
+    #[test]
+    fn contended_on_thread_pool() {
+        const TASKS: usize = 16;
+        const TIMES: usize = 100;
+        let mutex: &'static Mutex<Pair> = Box::leak(Box::new(Mutex::new(Pair::default())));
+        let mut pool = ThreadPool::builder().pool_size(4).create().unwrap();
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..TASKS {
+            let tx = tx.clone();
+            let mut task = increment(mutex, TIMES);
+            pool.spawn(poll_fn(move |cx: &mut Context| {
+                let ret = PinMut::new(&mut task).poll(cx);
+                if ret.is_ready() {
+                    tx.send(()).unwrap();
+                }
+                ret
+            })).unwrap();
+        }
+        for _ in 0..TASKS {
+            rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        }
+        let guard = mutex.try_lock().unwrap();
+        assert_eq!((guard.a, guard.b), (TASKS * TIMES, TASKS * TIMES));
+    }
+
+    #[test]
+    fn waiters_acquire_in_fifo_order() {
+        let mutex: &'static Mutex<()> = Box::leak(Box::new(Mutex::new(())));
+        let mut pool = LocalPool::new();
+        let queued = Rc::new(RefCell::new(Vec::new()));
+        let acquired = Rc::new(RefCell::new(Vec::new()));
+        let guard = mutex.try_lock().unwrap();
+        for i in 0..5 {
+            let (queued, acquired) = (queued.clone(), acquired.clone());
+            let mut fut = mutex.lock();
+            pool.spawner().spawn_local(poll_fn(move |cx: &mut Context| {
+                match PinMut::new(&mut fut).poll(cx) {
+                    Poll::Ready(_guard) => {
+                        acquired.borrow_mut().push(i);
+                        Poll::Ready(())
+                    }
+                    Poll::Pending => {
+                        queued.borrow_mut().push(i);
+                        Poll::Pending
+                    }
+                }
+            })).unwrap();
+        }
+        assert!(!pool.run_until_stalled());
+        assert_eq!(queued.borrow().len(), 5);
+        assert!(acquired.borrow().is_empty());
+        drop(guard);
+        pool.run();
+        assert_eq!(*acquired.borrow(), *queued.borrow());
+    }
+
+    #[test]
+    fn cancel_while_queued() {
+        let mutex = Mutex::new(0);
+        let guard = mutex.try_lock().unwrap();
+        let mut first = mutex.lock();
+        let mut second = mutex.lock();
+        assert!(with_noop_context(|cx| PinMut::new(&mut first).poll(cx)).is_pending());
+        let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| PinMut::new(&mut second).poll(cx));
+        assert!(ret.is_pending());
+        drop(first);
+        assert_eq!(wakes.get(), 0);
+        drop(guard);
+        assert_eq!(wakes.get(), 1);
+        match with_noop_context(|cx| PinMut::new(&mut second).poll(cx)) {
+            Poll::Ready(mut guard) => *guard += 1,
+            Poll::Pending => panic!("lock not handed to the remaining waiter"),
+        }
+        assert!(second.is_terminated());
+        assert_eq!(*mutex.try_lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn cancel_after_being_granted() {
+        let mutex = Mutex::new(());
+        let guard = mutex.try_lock().unwrap();
+        let mut first = mutex.lock();
+        let mut second = mutex.lock();
+        assert!(with_noop_context(|cx| PinMut::new(&mut first).poll(cx)).is_pending());
+        let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| PinMut::new(&mut second).poll(cx));
+        assert!(ret.is_pending());
+        // The lock goes to `first`, which is dropped without taking it.
+        drop(guard);
+        assert!(mutex.try_lock().is_none());
+        drop(first);
+        assert_eq!(wakes.get(), 1);
+        assert!(with_noop_context(|cx| PinMut::new(&mut second).poll(cx)).is_ready());
+        drop(second);
+        assert!(mutex.try_lock().is_some());
+    }
+
+    #[test]
+    fn try_lock_does_not_jump_the_queue() {
+        let mutex = Mutex::new(());
+        let guard = mutex.try_lock().unwrap();
+        let mut waiter = mutex.lock();
+        assert!(with_noop_context(|cx| PinMut::new(&mut waiter).poll(cx)).is_pending());
+        drop(guard);
+        assert!(mutex.try_lock().is_none());
+    }
+
+    #[test]
+    fn mutex_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Mutex<Vec<u8>>>();
+        assert_send_sync::<Mutex<::std::cell::Cell<u8>>>();
+    }
+}
+
+#[cfg(feature = "alloc")]
+mod bilock {
+    use std::mem::PinMut;
+    use ::specialized_futures::Future;
+    use ::specialized_futures::lock::BiLock;
+    use ::specialized_futures::spawn::NoSpawn;
+    use ::specialized_futures::task::Poll;
+    use support::{with_counting_context, with_noop_context};
+
+    fn acquired<T>(poll: Poll<T>) -> T {
+        match poll {
+            Poll::Ready(guard) => guard,
+            Poll::Pending => panic!("lock not acquired"),
+        }
+    }
+
+    #[test]
+    fn halves_alternate() {
+        let (a, b) = BiLock::new(Vec::new());
+        for round in 0..3 {
+            let mut guard = acquired(with_noop_context(|cx| a.poll_lock(cx)));
+            guard.push(('a', round));
+            let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| b.poll_lock(cx).is_ready());
+            assert!(!ret);
+            drop(guard);
+            assert_eq!(wakes.get(), 1);
+
+            let mut fut = b.lock();
+            let mut guard = acquired(with_noop_context(|cx| PinMut::new(&mut fut).poll(cx)));
+            guard.push(('b', round));
+            let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| a.poll_lock(cx).is_ready());
+            assert!(!ret);
+            drop(guard);
+            assert_eq!(wakes.get(), 1);
+        }
+        let value = a.reunite(b).unwrap();
+        assert_eq!(value, vec![('a', 0), ('b', 0), ('a', 1), ('b', 1), ('a', 2), ('b', 2)]);
+    }
+
+    #[test]
+    fn guard_held_across_polls_wakes_latest_waiter() {
+        let (a, b) = BiLock::new(0);
+        let guard = acquired(with_noop_context(|cx| a.poll_lock(cx)));
+        let (first, ret) = with_counting_context(&mut NoSpawn, |cx| b.poll_lock(cx).is_ready());
+        assert!(!ret);
+        let (second, ret) = with_counting_context(&mut NoSpawn, |cx| b.poll_lock(cx).is_ready());
+        assert!(!ret);
+        drop(guard);
+        assert_eq!((first.get(), second.get()), (0, 1));
+        assert!(with_noop_context(|cx| b.poll_lock(cx)).is_ready());
+    }
+
+    #[test]
+    fn reunite_pair() {
+        let (a, b) = BiLock::new(String::from("value"));
+        assert_eq!(b.reunite(a).unwrap(), "value");
+    }
+
+    #[test]
+    fn reunite_mismatched_halves() {
+        let (a1, b1) = BiLock::new(1);
+        let (a2, b2) = BiLock::new(2);
+        let err = a1.reunite(b2).unwrap_err();
+        let (a1, b2) = (err.0, err.1);
+        assert_eq!(a1.reunite(b1).unwrap(), 1);
+        assert_eq!(a2.reunite(b2).unwrap(), 2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn halves_on_separate_threads() {
+        use std::thread;
+        use ::specialized_futures::executor::block_on;
+
+        let (a, b) = BiLock::new(0);
+        let other = thread::spawn(move || {
+            for _ in 0..1000 {
+                *block_on(b.lock()) += 1;
+            }
+            b
+        });
+        for _ in 0..1000 {
+            *block_on(a.lock()) += 1;
+        }
+        let b = other.join().unwrap();
+        assert_eq!(a.reunite(b).unwrap(), 2000);
+    }
+}
+
+#[cfg(feature = "std")]
+mod once_cell {
+    use std::mem::PinMut;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use ::specialized_futures::{Context, Future, SpawnExt};
+    use ::specialized_futures::executor::ThreadPool;
+    use ::specialized_futures::future::{FusedFuture, pending, poll_fn, ready};
+    use ::specialized_futures::lock::OnceCell;
+    use ::specialized_futures::spawn::NoSpawn;
+    use ::specialized_futures::task::Poll;
+    use support::{with_counting_context, with_noop_context};
+
+    #[test]
+    fn one_of_many_initializers_runs_on_thread_pool() {
+        const TASKS: usize = 16;
+        let cell: &'static OnceCell<usize> = Box::leak(Box::new(OnceCell::new()));
+        let inits = Arc::new(AtomicUsize::new(0));
+        let mut pool = ThreadPool::builder().pool_size(4).create().unwrap();
+        let (tx, rx) = mpsc::channel();
+        for i in 0..TASKS {
+            let (tx, inits) = (tx.clone(), inits.clone());
+            let mut task = cell.get_or_init(move || {
+                inits.fetch_add(1, Ordering::SeqCst);
+                // Pend once, so that the other tasks find it initializing.
+                let mut yielded = false;
+                poll_fn(move |cx: &mut Context| {
+                    if yielded {
+                        return Poll::Ready(i);
+                    }
+                    yielded = true;
+                    cx.waker().wake();
+                    Poll::Pending
+                })
+            });
+            pool.spawn(poll_fn(move |cx: &mut Context| {
+                PinMut::new(&mut task).poll(cx).map(|value| tx.send(*value).unwrap())
+            })).unwrap();
+        }
+        let values: Vec<usize> = (0..TASKS)
+            .map(|_| rx.recv_timeout(Duration::from_secs(10)).unwrap())
+            .collect();
+        assert!(values.iter().all(|&v| v == values[0]));
+        assert_eq!(cell.get(), Some(&values[0]));
+        assert_eq!(inits.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn waiter_takes_over_from_cancelled_initializer() {
+        let cell = OnceCell::new();
+        let mut first = cell.get_or_init(pending::<u32>);
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut first).poll(cx)), Poll::Pending);
+
+        let mut second = cell.get_or_init(|| ready(2));
+        let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| PinMut::new(&mut second).poll(cx));
+        assert_eq!(ret, Poll::Pending);
+        assert_eq!(wakes.get(), 0);
+
+        // Dropping the initializer mid-flight wakes the waiter, which then
+        // runs its own initializer.
+        drop(first);
+        assert_eq!(wakes.get(), 1);
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut second).poll(cx)), Poll::Ready(&2));
+        assert!(second.is_terminated());
+        assert_eq!(cell.get(), Some(&2));
+    }
+
+    #[test]
+    fn cancelled_waiter_is_not_woken() {
+        let cell = OnceCell::new();
+        let mut first = cell.get_or_init(|| poll_fn(|_: &mut Context| Poll::Pending::<u32>));
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut first).poll(cx)), Poll::Pending);
+        let mut second = cell.get_or_init(|| ready(2));
+        let (wakes, _) = with_counting_context(&mut NoSpawn, |cx| PinMut::new(&mut second).poll(cx));
+        drop(second);
+        drop(first);
+        assert_eq!(wakes.get(), 0);
+        assert_eq!(cell.get(), None);
+    }
+
+    #[test]
+    fn reads_after_initialization_skip_the_initializer() {
+        let cell = OnceCell::new();
+        assert_eq!(cell.get(), None);
+        assert_eq!(cell.set(5), Ok(()));
+        assert_eq!(cell.set(6), Err(6));
+        assert_eq!(cell.get(), Some(&5));
+        {
+            let mut read = cell.get_or_init(|| -> ::specialized_futures::future::Ready<u32> {
+                panic!("the cell is already initialized")
+            });
+            assert_eq!(with_noop_context(|cx| PinMut::new(&mut read).poll(cx)), Poll::Ready(&5));
+        }
+        assert_eq!(cell.into_inner(), Some(5));
+    }
+
+    #[test]
+    fn set_fails_while_initializing() {
+        let cell = OnceCell::new();
+        let mut init = cell.get_or_init(|| poll_fn(|_: &mut Context| Poll::Pending::<u32>));
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut init).poll(cx)), Poll::Pending);
+        assert_eq!(cell.set(1), Err(1));
+        drop(init);
+        assert_eq!(cell.set(1), Ok(()));
+    }
+}
+
+#[cfg(feature = "std")]
+mod cancellation {
+    use std::mem::PinMut;
+    use std::sync::Arc;
+    use std::sync::mpsc;
+    use std::time::Duration;
+    use ::specialized_futures::{Context, Future, FutureExt, Spawn, SpawnExt};
+    use ::specialized_futures::channel::oneshot;
+    use ::specialized_futures::executor::ThreadPool;
+    use ::specialized_futures::future::{FusedFuture, pending, poll_fn};
+    use ::specialized_futures::lock::CancellationToken;
+    use ::specialized_futures::spawn::NoSpawn;
+    use ::specialized_futures::task::Poll;
+    use support::{with_counting_context, with_noop_context};
+
+    #[test]
+    fn cancelling_the_root_wakes_every_level() {
+        let root = CancellationToken::new();
+        let child = root.child_token();
+        let grandchild = child.child_token();
+        let sibling = root.child_token();
+
+        let mut waiters = vec![root.cancelled(), child.cancelled(), grandchild.cancelled(), sibling.cancelled()];
+        let counters: Vec<_> = waiters.iter_mut().map(|waiter| {
+            let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| PinMut::new(waiter).poll(cx));
+            assert_eq!(ret, Poll::Pending);
+            wakes
+        }).collect();
+        assert!(counters.iter().all(|wakes| wakes.get() == 0));
+
+        root.cancel();
+        assert!(counters.iter().all(|wakes| wakes.get() == 1));
+        for token in &[&root, &child, &grandchild, &sibling] {
+            assert!(token.is_cancelled());
+        }
+        for waiter in &mut waiters {
+            assert!(waiter.is_terminated());
+            assert_eq!(with_noop_context(|cx| PinMut::new(waiter).poll(cx)), Poll::Ready(()));
+        }
+
+        // Tokens made after cancellation start out cancelled.
+        assert!(grandchild.child_token().is_cancelled());
+    }
+
+    #[test]
+    fn cancelling_a_child_leaves_its_parent() {
+        let root = CancellationToken::new();
+        let child = root.child_token();
+        let grandchild = child.child_token();
+        let mut waiter = root.cancelled();
+        let (wakes, _) = with_counting_context(&mut NoSpawn, |cx| PinMut::new(&mut waiter).poll(cx));
+
+        child.cancel();
+        assert!(child.is_cancelled());
+        assert!(grandchild.is_cancelled());
+        assert!(!root.is_cancelled());
+        assert_eq!(wakes.get(), 0);
+
+        // Clones share their state.
+        root.clone().cancel();
+        assert!(root.is_cancelled());
+        assert_eq!(wakes.get(), 1);
+    }
+
+    #[test]
+    fn dropped_waiters_are_removed() {
+        let token = CancellationToken::new();
+        let child = token.child_token();
+        let (wakes, ret) = {
+            let mut waiter = child.cancelled();
+            let mut repoll = child.cancelled();
+            let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| PinMut::new(&mut waiter).poll(cx));
+            // Polling again replaces the registered waker rather than adding
+            // another entry.
+            with_counting_context(&mut NoSpawn, |cx| PinMut::new(&mut repoll).poll(cx));
+            let (repoll_wakes, _) = with_counting_context(&mut NoSpawn, |cx| PinMut::new(&mut repoll).poll(cx));
+            drop(repoll);
+            assert_eq!(Arc::strong_count(&repoll_wakes), 1);
+            (wakes, ret)
+        };
+        assert_eq!(ret, Poll::Pending);
+        // The token held the only other references to the waker, in the
+        // dropped waiter's list entry.
+        assert_eq!(Arc::strong_count(&wakes), 1);
+
+        token.cancel();
+        assert!(child.is_cancelled());
+        assert_eq!(wakes.get(), 0);
+    }
+
+    #[test]
+    fn with_cancellation_races_on_thread_pool() {
+        let mut pool = ThreadPool::builder().pool_size(2).create().unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        let token = CancellationToken::new();
+        let (done_tx, done_rx) = oneshot::channel::<u32>();
+        let mut cancelled = FutureExt::<dyn Spawn>::with_cancellation(pending::<u32>(), token.child_token());
+        let mut finished = FutureExt::<dyn Spawn>::with_cancellation(done_rx, token.clone());
+        let first = tx.clone();
+        pool.spawn(poll_fn(move |cx: &mut Context| {
+            PinMut::new(&mut cancelled).poll(cx).map(|output| first.send(output).unwrap())
+        })).unwrap();
+        pool.spawn(poll_fn(move |cx: &mut Context| {
+            PinMut::new(&mut finished).poll(cx).map(|output| tx.send(output.map(Result::unwrap)).unwrap())
+        })).unwrap();
+
+        done_tx.send(7).unwrap();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(10)).unwrap(), Some(7));
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+        token.cancel();
+        assert_eq!(rx.recv_timeout(Duration::from_secs(10)).unwrap(), None);
+    }
+}
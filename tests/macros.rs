@@ -0,0 +1,277 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::cell::Cell;
+use std::marker::{Pinned, Unpin};
+use std::mem::PinMut;
+use std::rc::Rc;
+use specialized_futures::{Context, Future, Spawn};
+use specialized_futures::task::Poll;
+use specialized_futures::future::{FusedFuture, ready};
+use specialized_futures::spawn::NoSpawn;
+
+use support::{with_counting_context, with_noop_context};
+
+/// A future which is `!Unpin` and counts how often it was polled.
+struct Immovable {
+    polls: usize,
+    _pinned: Pinned,
+}
+
+impl<S: Spawn + ?Sized> Future<S> for Immovable {
+    type Output = usize;
+
+    fn poll(mut self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<usize> {
+        // Safe: `polls` is never pinned structurally.
+        let this = unsafe { PinMut::get_mut_unchecked(self.reborrow()) };
+        this.polls += 1;
+        if this.polls < 2 { Poll::Pending } else { Poll::Ready(this.polls) }
+    }
+}
+
+#[test]
+fn pin_mut_unpin_future() {
+    let fut = ready(5);
+    pin_mut!(fut);
+    with_noop_context(|cx| assert_eq!(fut.reborrow().poll(cx), Poll::Ready(5)));
+}
+
+#[test]
+fn pin_mut_immovable_future() {
+    let fut = Immovable { polls: 0, _pinned: Pinned };
+    pin_mut!(fut);
+    with_noop_context(|cx| {
+        assert_eq!(fut.reborrow().poll(cx), Poll::Pending);
+        assert_eq!(fut.reborrow().poll(cx), Poll::Ready(2));
+    });
+}
+
+#[test]
+fn pin_mut_several() {
+    let a = ready(1);
+    let b = ready(2);
+    pin_mut!(a, b,);
+    with_noop_context(|cx| {
+        assert_eq!(b.reborrow().poll(cx), Poll::Ready(2));
+        assert_eq!(a.reborrow().poll(cx), Poll::Ready(1));
+    });
+}
+
+/// A future which is pending `remaining` times before resolving to `value`,
+/// and records that it was dropped.
+struct Countdown<T> {
+    remaining: usize,
+    value: Option<T>,
+    dropped: Rc<Cell<bool>>,
+}
+
+fn countdown<T>(remaining: usize, value: T) -> Countdown<T> {
+    Countdown { remaining, value: Some(value), dropped: Rc::new(Cell::new(false)) }
+}
+
+impl<T> Unpin for Countdown<T> {}
+
+impl<T, S: Spawn + ?Sized> Future<S> for Countdown<T> {
+    type Output = T;
+
+    fn poll(mut self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<T> {
+        if self.remaining == 0 {
+            Poll::Ready(self.value.take().expect("Countdown polled after completion"))
+        } else {
+            self.remaining -= 1;
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> Drop for Countdown<T> {
+    fn drop(&mut self) {
+        self.dropped.set(true);
+    }
+}
+
+/// Polls `fut` with a no-op context until it completes, returning its output
+/// and how many polls it took.
+fn drive<F: Future<Output = T>, T>(fut: F) -> (T, usize) {
+    pin_mut!(fut);
+    let mut polls = 0;
+    loop {
+        polls += 1;
+        if let Poll::Ready(out) = with_noop_context(|cx| fut.reborrow().poll(cx)) {
+            return (out, polls);
+        }
+    }
+}
+
+#[test]
+fn join_two() {
+    assert_eq!(drive(join!(ready(1), ready("two"))), ((1, "two"), 1));
+}
+
+#[test]
+fn join_mixed_ready_and_pending() {
+    let immovable = Immovable { polls: 0, _pinned: Pinned };
+    let (out, polls) = drive(join!(countdown(3, 'a'), ready(1), immovable));
+    assert_eq!(out, ('a', 1, 2));
+    // Every incomplete child is polled each time, so the slowest one decides.
+    assert_eq!(polls, 4);
+}
+
+#[test]
+fn join_eight() {
+    let fut = join!(
+        countdown(0, 0), countdown(1, 1), countdown(2, 2), countdown(3, 3),
+        countdown(4, 4), countdown(5, 5), countdown(6, 6), countdown(7, 7),
+    );
+    assert_eq!(drive(fut), ((0, 1, 2, 3, 4, 5, 6, 7), 8));
+}
+
+#[test]
+fn join_terminates() {
+    let fut = join!(ready(()), countdown(1, ()));
+    pin_mut!(fut);
+    assert!(with_noop_context(|cx| fut.reborrow().poll(cx)).is_pending());
+    assert!(!fut.is_terminated());
+    assert!(with_noop_context(|cx| fut.reborrow().poll(cx)).is_ready());
+    assert!(fut.is_terminated());
+}
+
+#[test]
+fn join_does_not_capture_caller_names() {
+    // Names a hand-written expansion might use internally.
+    let a = ready(1);
+    let fut = ready(2);
+    let cx = ready(3);
+    assert_eq!(drive(join!(a, fut, cx)).0, (1, 2, 3));
+}
+
+#[test]
+fn try_join_all_ok() {
+    let fut = try_join!(
+        countdown(2, Ok::<_, ()>(1)),
+        ready(Ok(2)),
+        countdown(1, Ok("three")),
+    );
+    assert_eq!(drive(fut), (Ok((1, 2, "three")), 3));
+}
+
+#[test]
+fn try_join_returns_first_error() {
+    let slow = countdown(5, Ok::<i32, &str>(1));
+    let dropped = slow.dropped.clone();
+    let fut = try_join!(slow, countdown(1, Err::<i32, _>("failed")), ready(Ok(3)));
+    pin_mut!(fut);
+    assert!(with_noop_context(|cx| fut.reborrow().poll(cx)).is_pending());
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready(Err("failed")));
+    // The error ends the join at once, dropping the futures still running.
+    assert!(dropped.get());
+}
+
+#[test]
+fn try_join_eight_with_error() {
+    let fut = try_join!(
+        ready(Ok::<_, usize>(0)), ready(Ok(1)), ready(Ok(2)), ready(Ok(3)),
+        ready(Ok(4)), ready(Ok(5)), ready(Ok(6)), ready(Err::<i32, _>(7)),
+    );
+    assert_eq!(drive(fut), (Err(7), 1));
+}
+
+/// Adds one to the output of `inner`, written with `poll!` and `ready!` for
+/// any spawner.
+struct AddOne<F> {
+    inner: F,
+}
+
+impl<F: Unpin> Unpin for AddOne<F> {}
+
+impl<F, S> Future<S> for AddOne<F>
+    where F: Future<S, Output = i32> + Unpin,
+          S: Spawn + ?Sized,
+{
+    type Output = i32;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<i32> {
+        let n = ready!(poll!(self.inner, cx));
+        Poll::Ready(n + 1)
+    }
+}
+
+/// Pends once, waking itself through `pending!`.
+struct YieldOnce {
+    yielded: bool,
+}
+
+impl<S: Spawn + ?Sized> Future<S> for YieldOnce {
+    type Output = i32;
+
+    fn poll(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<i32> {
+        if !self.yielded {
+            self.yielded = true;
+            pending!(cx.waker().wake());
+        }
+        Poll::Ready(1)
+    }
+}
+
+#[test]
+fn ready_and_poll_in_generic_context() {
+    let fut = AddOne { inner: YieldOnce { yielded: false } };
+    pin_mut!(fut);
+    let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| fut.reborrow().poll(cx));
+    assert_eq!(ret, Poll::Pending);
+    assert_eq!(wakes.get(), 1);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready(2));
+}
+
+#[test]
+fn poll_borrows_in_place() {
+    let mut inner = YieldOnce { yielded: false };
+    with_noop_context(|cx| assert_eq!(poll!(inner, cx), Poll::Pending));
+    // The future was polled where it is, not moved into the macro.
+    assert!(inner.yielded);
+    with_noop_context(|cx| assert_eq!(poll!(inner, cx), Poll::Ready(1)));
+}
+
+#[test]
+fn pending_without_registration() {
+    fn poll_once<S: Spawn + ?Sized>(_cx: &mut Context<S>) -> Poll<()> {
+        pending!()
+    }
+    let (wakes, ret) = with_counting_context(&mut NoSpawn, poll_once);
+    assert_eq!(ret, Poll::Pending);
+    assert_eq!(wakes.get(), 0);
+}
+
+#[cfg(feature = "std")]
+mod concrete_spawner {
+    use std::mem::PinMut;
+    use ::specialized_futures::{Context, Future};
+    use ::specialized_futures::executor::{LocalSpawner, block_on};
+    use ::specialized_futures::task::Poll;
+    use super::{AddOne, YieldOnce};
+
+    /// Only implemented for `LocalSpawner`, so the macros expand against a
+    /// concrete spawner type.
+    struct OnLocalPool {
+        inner: AddOne<YieldOnce>,
+    }
+
+    impl Future<LocalSpawner> for OnLocalPool {
+        type Output = i32;
+
+        fn poll(mut self: PinMut<Self>, cx: &mut Context<LocalSpawner>) -> Poll<i32> {
+            let n = ready!(poll!(self.inner, cx));
+            Poll::Ready(n * 10)
+        }
+    }
+
+    #[test]
+    fn macros_in_concrete_context() {
+        let fut = OnLocalPool { inner: AddOne { inner: YieldOnce { yielded: false } } };
+        assert_eq!(block_on(fut), 20);
+    }
+}
@@ -0,0 +1,69 @@
+//! Uses the crate from a `no_std` crate, linking only `core`, to check that
+//! the core traits and the obj types need neither `std` nor `alloc`.
+//!
+//! The test harness itself still links `std`, but nothing in this file can
+//! name it.
+#![feature(pin, arbitrary_self_types, futures_api)]
+#![no_std]
+
+extern crate specialized_futures;
+
+use core::marker::Unpin;
+use core::mem::PinMut;
+use specialized_futures::{Context, Future, LocalFutureObj, Spawn};
+use specialized_futures::future::{FutureObj, ready};
+use specialized_futures::spawn::NoSpawn;
+use specialized_futures::task::{Poll, noop_local_waker, noop_waker};
+
+/// Counts down to zero, pending once per remaining step.
+struct Countdown(u32);
+
+impl Unpin for Countdown {}
+
+impl<S: Spawn + ?Sized> Future<S> for Countdown {
+    type Output = u32;
+
+    fn poll(mut self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<u32> {
+        if self.0 == 0 {
+            Poll::Ready(42)
+        } else {
+            self.0 -= 1;
+            Poll::Pending
+        }
+    }
+}
+
+fn poll<F: Future<Output = T>, T>(fut: PinMut<F>) -> Poll<T> {
+    let (lw, w) = (noop_local_waker(), noop_waker());
+    let mut spawner = NoSpawn;
+    let mut cx = Context::new(&lw, &w, &mut spawner as &mut dyn Spawn);
+    fut.poll(&mut cx)
+}
+
+#[test]
+fn local_future_obj_from_mut_ref() {
+    let mut countdown = Countdown(2);
+    {
+        let mut obj: LocalFutureObj<u32, dyn Spawn> = LocalFutureObj::new(&mut countdown);
+        assert_eq!(poll(PinMut::new(&mut obj)), Poll::Pending);
+        assert_eq!(poll(PinMut::new(&mut obj)), Poll::Pending);
+        assert_eq!(poll(PinMut::new(&mut obj)), Poll::Ready(42));
+    }
+    // The obj only borrowed the future, which is still here.
+    assert_eq!(countdown.0, 0);
+}
+
+#[test]
+fn future_obj_from_mut_ref() {
+    let mut fut = ready(7);
+    let mut obj: FutureObj<i32, dyn Spawn> = FutureObj::new(&mut fut);
+    assert_eq!(poll(PinMut::new(&mut obj)), Poll::Ready(7));
+}
+
+#[test]
+fn future_obj_into_local() {
+    let mut countdown = Countdown(0);
+    let obj: FutureObj<u32, dyn Spawn> = FutureObj::new(&mut countdown);
+    let mut local: LocalFutureObj<u32, dyn Spawn> = obj.into();
+    assert_eq!(poll(PinMut::new(&mut local)), Poll::Ready(42));
+}
@@ -0,0 +1,172 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+#![cfg(all(unix, feature = "reactor"))]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::mem::PinMut;
+use std::os::raw::c_int;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use specialized_futures::{Context, Future, ReactorSpawn};
+use specialized_futures::executor::{LocalPool, LocalSpawner};
+use specialized_futures::future::poll_fn;
+use specialized_futures::reactor::{Reactor, ReactorSpawner, Readiness};
+use specialized_futures::spawn::{NoSpawn, Ready};
+use specialized_futures::task::Poll;
+
+use support::with_counting_context;
+
+type Spawner = ReactorSpawner<NoSpawn>;
+
+/// Register `fd` through the context's spawner, and turn the reactor until
+/// the registration resolves.
+fn wait_for<F: AsRawFd>(reactor: &Reactor, spawner: &mut Spawner, source: &F, interest: Ready) -> Ready {
+    let (_, mut readiness) = with_counting_context(spawner, |cx| {
+        cx.spawner().register(source.as_raw_fd(), interest)
+    });
+    loop {
+        let (_, ret) = with_counting_context(spawner, |cx| PinMut::new(&mut readiness).poll(cx));
+        match ret {
+            Poll::Ready(ready) => return ready.unwrap(),
+            Poll::Pending => {
+                reactor.turn(Some(Duration::from_secs(10))).unwrap();
+            }
+        }
+    }
+}
+
+#[test]
+fn echo_through_registered_readiness() {
+    let reactor = Reactor::new().unwrap();
+    let mut spawner = ReactorSpawner::new(NoSpawn, reactor.handle());
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    client.set_nonblocking(true).unwrap();
+    server.set_nonblocking(true).unwrap();
+
+    let mut buf = [0; 5];
+    assert!(wait_for(&reactor, &mut spawner, &client, Ready::writable()).is_writable());
+    client.write_all(b"hello").unwrap();
+
+    assert!(wait_for(&reactor, &mut spawner, &server, Ready::readable()).is_readable());
+    server.read_exact(&mut buf).unwrap();
+    assert!(wait_for(&reactor, &mut spawner, &server, Ready::writable()).is_writable());
+    server.write_all(&buf).unwrap();
+
+    buf = [0; 5];
+    assert!(wait_for(&reactor, &mut spawner, &client, Ready::readable()).is_readable());
+    client.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hello");
+}
+
+#[test]
+fn turn_without_registrations_returns_immediately() {
+    let reactor = Reactor::new().unwrap();
+    assert_eq!(reactor.turn(None).unwrap(), 0);
+}
+
+#[test]
+fn waker_interrupts_blocked_turn() {
+    let reactor = Reactor::new().unwrap();
+    let (idle, _other) = UnixStream::pair().unwrap();
+    let _readiness = reactor.handle().register(idle.as_raw_fd(), Ready::readable());
+    let waker = reactor.waker();
+    let start = Instant::now();
+    let waking = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        waker.wake();
+    });
+    assert_eq!(reactor.turn(Some(Duration::from_secs(10))).unwrap(), 0);
+    assert!(start.elapsed() < Duration::from_secs(10));
+    waking.join().unwrap();
+}
+
+#[test]
+fn register_interrupts_blocked_turn() {
+    let reactor = Reactor::new().unwrap();
+    let (idle, _other) = UnixStream::pair().unwrap();
+    let _idle_readiness = reactor.handle().register(idle.as_raw_fd(), Ready::readable());
+    // Drain the wakeup written by that registration.
+    assert_eq!(reactor.turn(Some(Duration::from_millis(0))).unwrap(), 0);
+
+    let handle = reactor.handle();
+    let (writable, _peer) = UnixStream::pair().unwrap();
+    let (tx, rx) = mpsc::channel::<Readiness>();
+    let registering = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        tx.send(handle.register(writable.as_raw_fd(), Ready::writable())).unwrap();
+        writable
+    });
+    let start = Instant::now();
+    let mut resolved = reactor.turn(Some(Duration::from_secs(10))).unwrap();
+    if resolved == 0 {
+        // The first turn was interrupted before the new source was polled.
+        resolved = reactor.turn(Some(Duration::from_secs(10))).unwrap();
+    }
+    assert_eq!(resolved, 1);
+    assert!(start.elapsed() < Duration::from_secs(10));
+    let _writable = registering.join().unwrap();
+    drop(rx.recv().unwrap());
+}
+
+extern "C" {
+    fn pipe(fds: *mut c_int) -> c_int;
+}
+
+/// Create a pipe, returning its read and write ends.
+fn pipe_pair() -> (File, File) {
+    let mut fds = [0; 2];
+    assert_eq!(unsafe { pipe(fds.as_mut_ptr()) }, 0);
+    unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+}
+
+/// Read `source` to the end, waiting for it to become readable through the
+/// reactor of the spawner it is polled with before each read.
+fn read_to_end<S: ReactorSpawn>(mut source: File) -> impl Future<S, Output = Vec<u8>> {
+    let mut readiness = None;
+    let mut read = Vec::new();
+    poll_fn(move |cx: &mut Context<S>| {
+        loop {
+            if readiness.is_none() {
+                readiness = Some(cx.spawner().register(source.as_raw_fd(), Ready::readable()));
+            }
+            let ready = ready!(PinMut::new(readiness.as_mut().unwrap()).poll(cx)).unwrap();
+            assert!(ready.is_readable());
+            readiness = None;
+            // The pipe is readable, so this doesn't block.
+            let mut buf = [0; 16];
+            match source.read(&mut buf).unwrap() {
+                0 => return Poll::Ready(::std::mem::replace(&mut read, Vec::new())),
+                n => read.extend_from_slice(&buf[..n]),
+            }
+        }
+    })
+}
+
+#[test]
+fn local_pool_sleeps_on_its_reactor_until_a_pipe_is_readable() {
+    let (read_end, mut write_end) = pipe_pair();
+    let (tx, rx) = mpsc::channel();
+    // Run on a thread of its own, so that a pool which never wakes fails
+    // the test rather than hanging it.
+    thread::spawn(move || {
+        let mut pool = LocalPool::new();
+        let read: Vec<u8> = pool.run_until(read_to_end::<LocalSpawner>(read_end));
+        tx.send(read).unwrap();
+    });
+    for chunk in &[&b"hello"[..], &b", "[..], &b"world"[..]] {
+        // Give the pool time to go to sleep on the reactor.
+        thread::sleep(Duration::from_millis(20));
+        write_end.write_all(chunk).unwrap();
+    }
+    drop(write_end);
+    assert_eq!(rx.recv_timeout(Duration::from_secs(10)).unwrap(), b"hello, world");
+}
+
@@ -0,0 +1,340 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::marker::Unpin;
+use std::mem::PinMut;
+use specialized_futures::{Context, Future, Spawn};
+use specialized_futures::future::{FusedFuture, pending, ready};
+use specialized_futures::task::Poll;
+
+use support::with_noop_context;
+
+/// A fused future which is pending `remaining` times before resolving to
+/// `value`.
+struct Delayed<T> {
+    remaining: usize,
+    value: Option<T>,
+}
+
+fn delayed<T>(remaining: usize, value: T) -> Delayed<T> {
+    Delayed { remaining, value: Some(value) }
+}
+
+impl<T> Unpin for Delayed<T> {}
+
+impl<T, S: Spawn + ?Sized> Future<S> for Delayed<T> {
+    type Output = T;
+
+    fn poll(mut self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<T> {
+        if self.remaining == 0 {
+            Poll::Ready(self.value.take().expect("Delayed polled after completion"))
+        } else {
+            self.remaining -= 1;
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> FusedFuture for Delayed<T> {
+    fn is_terminated(&self) -> bool {
+        self.value.is_none()
+    }
+}
+
+#[test]
+fn select_runs_ready_branch() {
+    let mut a = pending::<i32>();
+    let mut b = ready(2);
+    let ret = with_noop_context(|cx| {
+        Poll::Ready(select! { cx;
+            x = a => x,
+            y = b => y * 10,
+        })
+    });
+    assert_eq!(ret, Poll::Ready(20));
+    assert!(b.is_terminated());
+}
+
+#[test]
+fn select_without_default_returns_pending() {
+    let mut a = delayed(1, 1);
+    let mut b = pending::<i32>();
+    let mut poll = |cx: &mut Context| -> Poll<i32> {
+        Poll::Ready(select! { cx;
+            x = a => x,
+            y = b => y,
+        })
+    };
+    assert_eq!(with_noop_context(|cx| poll(cx)), Poll::Pending);
+    assert_eq!(with_noop_context(|cx| poll(cx)), Poll::Ready(1));
+}
+
+#[test]
+fn select_default_arm() {
+    let mut a = delayed(1, 'a');
+    let mut b = pending::<char>();
+    let mut select = || with_noop_context(|cx| -> Poll<Option<char>> {
+        Poll::Ready(select! { cx;
+            x = a => Some(x),
+            y = b => Some(y),
+            default => None,
+        })
+    });
+    assert_eq!(select(), Poll::Ready(None));
+    assert_eq!(select(), Poll::Ready(Some('a')));
+}
+
+#[test]
+fn select_complete_arm() {
+    let mut a = delayed(0, 1);
+    let mut b = delayed(0, 2);
+    let mut total = 0;
+    let mut polls = 0;
+    let ret = with_noop_context(|cx| -> Poll<i32> {
+        loop {
+            polls += 1;
+            select! { cx;
+                x = a => total += x,
+                y = b => total += y,
+                complete => return Poll::Ready(total),
+            }
+        }
+    });
+    assert_eq!(ret, Poll::Ready(3));
+    // One selection per branch, then one which finds them all terminated.
+    assert_eq!(polls, 3);
+}
+
+#[test]
+fn select_complete_takes_precedence_over_default() {
+    let mut a = ready(());
+    with_noop_context(|cx| -> Poll<()> {
+        select! { cx; () = a => {}, complete => panic!("a was not selected yet") }
+        Poll::Ready(())
+    });
+    let ret = with_noop_context(|cx| -> Poll<&str> {
+        Poll::Ready(select! { cx;
+            () = a => "a",
+            default => "default",
+            complete => "complete",
+        })
+    });
+    assert_eq!(ret, Poll::Ready("complete"));
+}
+
+#[test]
+#[should_panic(expected = "all futures in select! were completed")]
+fn select_without_complete_panics_when_all_terminated() {
+    let mut a = ready(());
+    with_noop_context(|cx| -> Poll<()> {
+        loop {
+            select! { cx; () = a => {} }
+        }
+    });
+}
+
+#[test]
+fn select_loop_over_fused_futures() {
+    let mut a = delayed(3, "a");
+    let mut b = delayed(1, "b");
+    let mut c = delayed(0, "c");
+    let mut order = Vec::new();
+    let mut done = false;
+    while !done {
+        with_noop_context(|cx| -> Poll<()> {
+            loop {
+                select! { cx;
+                    x = a => order.push(x),
+                    x = b => order.push(x),
+                    x = c => order.push(x),
+                    complete => {
+                        done = true;
+                        return Poll::Ready(());
+                    },
+                }
+            }
+        });
+    }
+    assert_eq!(order, vec!["c", "b", "a"]);
+}
+
+#[test]
+fn select_discards_unmatched_output() {
+    let mut a = ready(None);
+    let mut b = delayed(1, Some(2));
+    let ret = with_noop_context(|cx| -> Poll<i32> {
+        Poll::Ready(select! { cx;
+            Some(x) = a => x,
+            Some(y) = b => y * 10,
+            complete => 0,
+        })
+    });
+    // `a`'s `None` is thrown away, leaving `b` to be selected once ready.
+    assert_eq!(ret, Poll::Pending);
+    assert!(a.is_terminated());
+    let ret = with_noop_context(|cx| -> Poll<i32> {
+        Poll::Ready(select! { cx;
+            Some(x) = a => x,
+            Some(y) = b => y * 10,
+        })
+    });
+    assert_eq!(ret, Poll::Ready(20));
+}
+
+#[test]
+fn select_allows_control_flow_in_arms() {
+    let mut a = delayed(0, 1);
+    let mut b = delayed(0, 2);
+    let mut seen = 0;
+    loop {
+        let ret = with_noop_context(|cx| -> Poll<bool> {
+            Poll::Ready(select! { cx;
+                x = a => { seen += x; false },
+                y = b => { seen += y; false },
+                complete => true,
+            })
+        });
+        if ret == Poll::Ready(true) {
+            break;
+        }
+    }
+    assert_eq!(seen, 3);
+}
+
+/// A fused future which is always ready with the same value and never
+/// terminates.
+struct Always<T>(T);
+
+impl<T> Unpin for Always<T> {}
+
+impl<T: Clone, S: Spawn + ?Sized> Future<S> for Always<T> {
+    type Output = T;
+
+    fn poll(self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<T> {
+        Poll::Ready(self.0.clone())
+    }
+}
+
+impl<T> FusedFuture for Always<T> {
+    fn is_terminated(&self) -> bool {
+        false
+    }
+}
+
+fn collect_picks<F: FnMut() -> Poll<char>>(n: usize, mut select: F) -> String {
+    (0..n).map(|_| match select() {
+        Poll::Ready(x) => x,
+        Poll::Pending => panic!("every branch is ready"),
+    }).collect()
+}
+
+#[test]
+fn select_rotates_from_first_branch() {
+    let mut a = Always('a');
+    let mut b = Always('b');
+    let mut c = Always('c');
+    let picks = collect_picks(6, || with_noop_context(|cx| -> Poll<char> {
+        Poll::Ready(select! { cx;
+            x = a => x,
+            x = b => x,
+            x = c => x,
+        })
+    }));
+    assert_eq!(picks, "abcabc");
+}
+
+#[test]
+fn select_rotation_is_kept_per_site() {
+    let mut a = Always('a');
+    let mut b = Always('b');
+    let mut first = || with_noop_context(|cx| -> Poll<char> {
+        Poll::Ready(select! { cx;
+            x = a => x,
+            x = b => x,
+        })
+    });
+    assert_eq!(first(), Poll::Ready('a'));
+
+    // Another `select!` over the same futures starts its own rotation,
+    // whatever the first one has done.
+    let mut c = Always('c');
+    let mut d = Always('d');
+    let mut second = || with_noop_context(|cx| -> Poll<char> {
+        Poll::Ready(select! { cx;
+            x = c => x,
+            x = d => x,
+        })
+    });
+    assert_eq!(collect_picks(3, &mut second), "cdc");
+    assert_eq!(collect_picks(3, &mut first), "bab");
+}
+
+#[test]
+fn select_rotation_skips_terminated_branches() {
+    let mut done = ready('x');
+    with_noop_context(|cx| PinMut::new(&mut done).poll(cx));
+    let mut d = Always('d');
+    let picks = collect_picks(2, || with_noop_context(|cx| -> Poll<char> {
+        Poll::Ready(select! { cx;
+            x = done => x,
+            x = d => x,
+        })
+    }));
+    assert_eq!(picks, "dd");
+}
+
+#[test]
+fn select_biased_prefers_first_branch() {
+    let mut control = Always("control");
+    let mut data = Always("data");
+    for _ in 0..10 {
+        let ret = with_noop_context(|cx| -> Poll<&str> {
+            Poll::Ready(select_biased! { cx;
+                x = control => x,
+                x = data => x,
+            })
+        });
+        assert_eq!(ret, Poll::Ready("control"));
+    }
+}
+
+#[test]
+fn select_biased_skips_terminated_branches() {
+    let mut a = ready(1);
+    let mut b = delayed(1, 2);
+    let mut c = Always(3);
+    {
+        let mut select = || with_noop_context(|cx| -> Poll<i32> {
+            Poll::Ready(select_biased! { cx;
+                x = a => x,
+                x = b => x,
+                x = c => x,
+            })
+        });
+        assert_eq!(select(), Poll::Ready(1));
+        // `b` is polled, and found pending, before `c` is selected.
+        assert_eq!(select(), Poll::Ready(3));
+        assert_eq!(select(), Poll::Ready(2));
+        assert_eq!(select(), Poll::Ready(3));
+    }
+    assert!(a.is_terminated() && b.is_terminated());
+}
+
+#[test]
+fn select_biased_complete_and_default_arms() {
+    let mut a = delayed(1, ());
+    let mut select = || with_noop_context(|cx| -> Poll<&str> {
+        Poll::Ready(select_biased! { cx;
+            () = a => "a",
+            default => "default",
+            complete => "complete",
+        })
+    });
+    assert_eq!(select(), Poll::Ready("default"));
+    assert_eq!(select(), Poll::Ready("a"));
+    assert_eq!(select(), Poll::Ready("complete"));
+}
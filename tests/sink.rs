@@ -0,0 +1,171 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::mem::{self, PinMut};
+use specialized_futures::{Context, Future, Sink, SinkExt, Spawn, Stream};
+use specialized_futures::future::poll_fn;
+use specialized_futures::stream::iter;
+use specialized_futures::task::Poll;
+
+use support::with_noop_context;
+
+/// A sink which holds at most `limit` unflushed items, and which is only
+/// ready again after reporting `Pending` once when full.
+#[derive(Default)]
+struct VecSink {
+    buffer: Vec<i32>,
+    flushed: Vec<i32>,
+    limit: usize,
+    ready: bool,
+    not_ready: usize,
+    closed: bool,
+    reject: Option<i32>,
+}
+
+impl VecSink {
+    fn new(limit: usize) -> VecSink {
+        VecSink { limit, ..VecSink::default() }
+    }
+}
+
+impl Sink<i32> for VecSink {
+    type Error = ();
+
+    fn poll_ready(mut self: PinMut<Self>, cx: &mut Context) -> Poll<Result<(), ()>> {
+        if self.buffer.len() >= self.limit {
+            // Make progress in the background, and ask to be polled again.
+            let buffer = mem::replace(&mut self.buffer, Vec::new());
+            self.flushed.extend(buffer);
+            self.not_ready += 1;
+            cx.waker().wake();
+            return Poll::Pending;
+        }
+        self.ready = true;
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(mut self: PinMut<Self>, item: i32) -> Result<(), ()> {
+        assert!(self.ready, "start_send without a successful poll_ready");
+        assert!(!self.closed, "start_send after poll_close");
+        self.ready = false;
+        if self.reject == Some(item) {
+            return Err(());
+        }
+        self.buffer.push(item);
+        Ok(())
+    }
+
+    fn poll_flush(mut self: PinMut<Self>, _cx: &mut Context) -> Poll<Result<(), ()>> {
+        let buffer = mem::replace(&mut self.buffer, Vec::new());
+        self.flushed.extend(buffer);
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: PinMut<Self>, cx: &mut Context) -> Poll<Result<(), ()>> {
+        self.closed = true;
+        self.poll_flush(cx)
+    }
+}
+
+/// Poll `future` until it completes, as its sink always wakes it.
+fn run<F: Future>(future: F) -> F::Output {
+    pin_mut!(future);
+    loop {
+        if let Poll::Ready(output) = with_noop_context(|cx| future.reborrow().poll(cx)) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn send_all_respects_readiness() {
+    let mut sink = VecSink::new(3);
+    let mut stream = iter(0..10);
+    assert_eq!(run(SinkExt::<i32, dyn Spawn>::send_all(&mut sink, &mut stream)), Ok(()));
+    assert_eq!(sink.flushed, (0..10).collect::<Vec<_>>());
+    assert!(sink.buffer.is_empty());
+    assert_eq!(sink.not_ready, 3);
+    assert!(!sink.closed);
+}
+
+#[test]
+fn send_flushes_item() {
+    let mut sink = VecSink::new(1);
+    assert_eq!(run(SinkExt::<i32, dyn Spawn>::send(&mut sink, 1)), Ok(()));
+    assert_eq!(sink.flushed, vec![1]);
+    assert_eq!(run(SinkExt::<i32, dyn Spawn>::send(&mut sink, 2)), Ok(()));
+    assert_eq!(sink.flushed, vec![1, 2]);
+}
+
+#[test]
+fn forwarding_impls() {
+    let mut sink = VecSink::new(2);
+    {
+        let mut by_ref = &mut sink;
+        assert_eq!(run(SinkExt::<i32, dyn Spawn>::send(&mut by_ref, 1)), Ok(()));
+    }
+    {
+        let mut pinned = PinMut::new(&mut sink);
+        assert_eq!(run(SinkExt::<i32, dyn Spawn>::send(&mut pinned, 2)), Ok(()));
+    }
+    assert_eq!(sink.flushed, vec![1, 2]);
+}
+
+#[test]
+fn send_all_into_capacity_one_sink() {
+    let mut sink = VecSink::new(1);
+    let mut stream = iter(0..5);
+    assert_eq!(run(SinkExt::<i32, dyn Spawn>::send_all(&mut sink, &mut stream)), Ok(()));
+    assert_eq!(sink.flushed, vec![0, 1, 2, 3, 4]);
+    // Every item after the first found the sink full.
+    assert_eq!(sink.not_ready, 4);
+}
+
+#[test]
+fn send_all_stops_at_sink_error() {
+    let mut sink = VecSink { reject: Some(2), ..VecSink::new(10) };
+    let mut stream = iter(0..5);
+    assert_eq!(run(SinkExt::<i32, dyn Spawn>::send_all(&mut sink, &mut stream)), Err(()));
+    assert_eq!(sink.buffer, vec![0, 1]);
+    // Nothing past the rejected item was taken from the stream.
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut stream).poll_next(cx)), Poll::Ready(Some(3)));
+}
+
+#[test]
+fn with_waits_for_a_pending_transformation() {
+    let mut sink = SinkExt::<i32, dyn Spawn>::with(VecSink::new(10), |x: i32| {
+        let mut polled = false;
+        poll_fn(move |cx: &mut Context| {
+            if !polled {
+                polled = true;
+                cx.waker().wake();
+                return Poll::Pending;
+            }
+            Poll::Ready(Ok::<i32, ()>(x * 10))
+        })
+    });
+    let mut stream = iter(1..4);
+    assert_eq!(run(SinkExt::<i32, dyn Spawn>::send_all(&mut sink, &mut stream)), Ok(()));
+    assert_eq!(sink.get_ref().flushed, vec![10, 20, 30]);
+}
+
+#[test]
+fn with_reports_errors_from_either_side() {
+    let mut sink = SinkExt::<i32, dyn Spawn>::with(VecSink::new(10), |x: i32| {
+        poll_fn(move |_: &mut Context| Poll::Ready(if x < 0 { Err(()) } else { Ok(x) }))
+    });
+    let mut stream = iter(vec![1, -1, 2]);
+    assert_eq!(run(SinkExt::<i32, dyn Spawn>::send_all(&mut sink, &mut stream)), Err(()));
+    assert_eq!(sink.get_ref().buffer, vec![1]);
+
+    let mut sink = SinkExt::<i32, dyn Spawn>::with(VecSink { reject: Some(2), ..VecSink::new(10) }, |x: i32| {
+        poll_fn(move |_: &mut Context| Poll::Ready(Ok::<i32, ()>(x)))
+    });
+    let mut stream = iter(vec![1, 2, 3]);
+    assert_eq!(run(SinkExt::<i32, dyn Spawn>::send_all(&mut sink, &mut stream)), Err(()));
+    assert_eq!(sink.get_ref().buffer, vec![1]);
+}
@@ -0,0 +1,872 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+#![cfg(feature = "std")]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::panic::{self, AssertUnwindSafe};
+use std::mem::PinMut;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, mpsc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use specialized_futures::{Context, Future, FutureExt, FutureObj, LocalFutureObj, LocalSpawnExt};
+use specialized_futures::{Spawn, SpawnExt, SpawnLocal};
+use specialized_futures::executor::{LocalPool, LocalSpawner, ThreadPool};
+use specialized_futures::future::{poll_fn, ready};
+use specialized_futures::spawn::{NoSpawn, Priority, SpawnNamed, SpawnObjError, SpawnPriority, WeakSpawn};
+use specialized_futures::spawn::{Layered, Metrics, MetricsSnapshot, SpawnLayer, SpawnShared, StrongSpawn, TaskEvent};
+use specialized_futures::spawn::{catch_panics, hooks, scope};
+use specialized_futures::task::{Poll, noop_local_waker, noop_waker};
+
+use support::with_noop_context;
+
+#[test]
+fn spawn_local_with_handle_rc() {
+    let mut pool = LocalPool::new();
+    let mut spawner = pool.spawner();
+    let cell = Rc::new(Cell::new(1));
+    let cell2 = cell.clone();
+    let mut handle = spawner.spawn_local_with_handle(poll_fn(move |_| {
+        cell2.set(cell2.get() * 10);
+        Poll::Ready(cell2.clone())
+    })).unwrap();
+
+    // Another local task awaits the non-`Send` output.
+    let seen = Rc::new(Cell::new(0));
+    let seen2 = seen.clone();
+    spawner.spawn_local(poll_fn(move |cx| {
+        let rc = match PinMut::new(&mut handle).poll(cx) {
+            Poll::Ready(result) => result.unwrap(),
+            Poll::Pending => return Poll::Pending,
+        };
+        seen2.set(rc.get());
+        Poll::Ready(())
+    })).unwrap();
+
+    pool.run();
+    assert_eq!(cell.get(), 10);
+    assert_eq!(seen.get(), 10);
+}
+
+#[test]
+fn context_spawn_local_rc_subtask() {
+    let mut pool = LocalPool::new();
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let parent_log = log.clone();
+    let mut spawned = false;
+    pool.run_until(poll_fn(move |cx: &mut Context<LocalSpawner>| {
+        if !spawned {
+            spawned = true;
+            let child_log = parent_log.clone();
+            cx.spawn_local(poll_fn(move |_: &mut Context| {
+                child_log.borrow_mut().push("child");
+                Poll::Ready(())
+            })).unwrap();
+            let obj_log = parent_log.clone();
+            cx.spawner().spawn_local_obj(LocalFutureObj::new(Box::new(poll_fn(move |_: &mut Context| {
+                obj_log.borrow_mut().push("obj");
+                Poll::Ready(())
+            })))).unwrap();
+            parent_log.borrow_mut().push("parent");
+            cx.waker().wake();
+            return Poll::Pending;
+        }
+        if parent_log.borrow().len() < 3 {
+            cx.waker().wake();
+            return Poll::Pending;
+        }
+        Poll::Ready(())
+    }));
+    assert_eq!(*log.borrow(), vec!["parent", "child", "obj"]);
+    assert_eq!(Rc::strong_count(&log), 1);
+}
+
+#[test]
+fn status_local_defaults_to_status() {
+    let pool = LocalPool::new();
+    let spawner = pool.spawner();
+    assert!(spawner.status_local().is_ok());
+    drop(pool);
+    assert!(spawner.status_local().unwrap_err().is_shutdown());
+}
+
+/// Bumps the counter when polled.
+struct Work(Arc<AtomicUsize>);
+
+impl Future<dyn Spawn> for Work {
+    type Output = ();
+
+    fn poll(self: PinMut<Self>, _cx: &mut Context) -> Poll<()> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Poll::Ready(())
+    }
+}
+
+#[test]
+fn no_spawn_rejects_with_not_supported() {
+    let mut spawner = NoSpawn;
+    assert!(spawner.status().unwrap_err().is_not_supported());
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let err = spawner.spawn_obj(FutureObj::new(Box::new(Work(count.clone())))).unwrap_err();
+    assert!(err.kind.is_not_supported());
+    assert!(!err.kind.is_shutdown());
+    let err = spawner.spawn_obj_local(LocalFutureObj::new(Box::new(Work(count.clone())))).unwrap_err();
+    assert!(err.kind.is_not_supported());
+    assert!(spawner.spawn(Work(count.clone())).unwrap_err().is_not_supported());
+    assert!(spawner.spawn_local(Work(count.clone())).unwrap_err().is_not_supported());
+    // Nothing was polled.
+    assert_eq!(count.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn no_spawn_lets_futures_run_work_inline() {
+    let count = Arc::new(AtomicUsize::new(0));
+    let ret = with_noop_context(|cx| {
+        let work = FutureObj::new(Box::new(Work(count.clone())));
+        match cx.spawner().spawn_obj(work) {
+            Ok(()) => panic!("NoSpawn accepted a task"),
+            Err(ref err) if !err.kind.is_not_supported() => panic!("unexpected error: {:?}", err.kind),
+            // Degrade by running the rejected task on the current one.
+            Err(err) => {
+                let mut work = err.future;
+                PinMut::new(&mut work).poll(cx)
+            }
+        }
+    });
+    assert_eq!(ret, Poll::Ready(()));
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn no_spawn_in_generic_code() {
+    fn status_of<S: Spawn>(spawner: &mut S) -> bool {
+        let (lw, w) = (noop_local_waker(), noop_waker());
+        let mut cx = Context::new(&lw, &w, spawner);
+        cx.spawner().status().is_ok()
+    }
+    fn assert_copy_default<T: Copy + Default>() {}
+
+    assert_copy_default::<NoSpawn>();
+    assert!(!status_of(&mut NoSpawn));
+    assert!(!status_of(&mut NoSpawn::default()));
+}
+
+#[test]
+fn now_or_never_polls_with_no_spawn() {
+    let ret = poll_fn(|cx: &mut Context| Poll::Ready(cx.spawner().status().unwrap_err()))
+        .now_or_never()
+        .unwrap();
+    assert!(ret.is_not_supported());
+}
+
+/// A spawner which counts its spawns and records when it is dropped.
+struct Tracker {
+    spawned: Arc<AtomicUsize>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl Spawn for Tracker {
+    fn spawn_obj(
+        &mut self,
+        _future: FutureObj<'static, (), dyn Spawn>,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawned.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl Drop for Tracker {
+    fn drop(&mut self) {
+        self.dropped.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn tracker() -> (Tracker, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+    let (spawned, dropped) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+    (Tracker { spawned: spawned.clone(), dropped: dropped.clone() }, spawned, dropped)
+}
+
+fn noop_task() -> FutureObj<'static, (), dyn Spawn> {
+    FutureObj::new(Box::new(poll_fn(|_: &mut Context| Poll::Ready(()))))
+}
+
+#[test]
+fn weak_spawn_spawns_through_upgraded_handle() {
+    let (spawner, spawned, _) = tracker();
+    let strong = StrongSpawn::new(spawner);
+    let mut weak = strong.downgrade();
+    assert!(weak.status().is_ok());
+    weak.spawn_obj(noop_task()).unwrap();
+    let mut upgraded = weak.upgrade().unwrap();
+    upgraded.spawn_obj(noop_task()).unwrap();
+    // Clones of either handle refer to the same spawner.
+    weak.clone().spawn_obj(noop_task()).unwrap();
+    assert_eq!(spawned.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn weak_spawn_fails_after_spawner_dropped() {
+    let (spawner, spawned, dropped) = tracker();
+    let strong = StrongSpawn::new(spawner);
+    let mut weak = strong.downgrade();
+    let other = strong.clone();
+    drop(strong);
+    assert_eq!(dropped.load(Ordering::SeqCst), 0);
+    drop(other);
+    // Only weak handles remain, which don't keep the spawner alive.
+    assert_eq!(dropped.load(Ordering::SeqCst), 1);
+
+    assert!(weak.upgrade().is_none());
+    assert!(weak.status().unwrap_err().is_shutdown());
+    let err = weak.spawn_obj(noop_task()).unwrap_err();
+    assert!(err.kind.is_shutdown());
+    assert_eq!(spawned.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn empty_weak_spawn_is_shut_down() {
+    let mut weak = WeakSpawn::<Tracker>::new();
+    assert!(weak.upgrade().is_none());
+    assert!(weak.status().unwrap_err().is_shutdown());
+    assert!(weak.spawn_obj(noop_task()).unwrap_err().kind.is_shutdown());
+}
+
+#[test]
+fn weak_spawn_handle_outlives_poll() {
+    let (spawner, spawned, _) = tracker();
+    let strong = StrongSpawn::new(spawner);
+    let mut weak = strong.downgrade();
+    let mut handle = {
+        let (lw, w) = (noop_local_waker(), noop_waker());
+        let cx = Context::new(&lw, &w, &mut weak);
+        cx.spawn_handle()
+    };
+    // The handle taken from the context is usable once the poll is over,
+    // including from another thread.
+    thread::spawn(move || handle.spawn_obj(noop_task()).unwrap()).join().unwrap();
+    assert_eq!(spawned.load(Ordering::SeqCst), 1);
+    drop(strong);
+    assert!(weak.spawn_obj(noop_task()).unwrap_err().kind.is_shutdown());
+}
+
+#[test]
+fn weak_spawn_to_thread_pool() {
+    let pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    let strong = StrongSpawn::new(pool);
+    let mut weak = strong.downgrade();
+    let (tx, rx) = mpsc::channel();
+    weak.spawn(poll_fn(move |_: &mut Context| {
+        tx.send(()).unwrap();
+        Poll::Ready(())
+    })).unwrap();
+    rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    drop(strong);
+    assert!(weak.spawn(poll_fn(|_: &mut Context| Poll::Ready(()))).unwrap_err().is_shutdown());
+}
+
+/// Pends `remaining` times, waking itself each time, then resolves.
+fn yield_times(mut remaining: usize) -> impl Future<Output = ()> {
+    poll_fn(move |cx: &mut Context| {
+        if remaining == 0 {
+            Poll::Ready(())
+        } else {
+            remaining -= 1;
+            cx.waker().wake();
+            Poll::Pending
+        }
+    })
+}
+
+/// Records its drop in the shared counter.
+struct DropCount(Rc<Cell<usize>>);
+
+impl Drop for DropCount {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[test]
+fn scope_children_borrow_stack_local_slice() {
+    let data = [1, 2, 3, 4, 5, 6];
+    let mut sums = [0; 3];
+    let order = RefCell::new(Vec::new());
+    let ret = {
+        let mut fut = scope(|s| {
+            for (i, (chunk, sum)) in data.chunks(2).zip(sums.iter_mut()).enumerate() {
+                let order = &order;
+                let mut yields = yield_times(2 - i);
+                s.spawn(poll_fn(move |cx: &mut Context| {
+                    ready!(PinMut::new(&mut yields).poll(cx));
+                    *sum = chunk.iter().sum();
+                    order.borrow_mut().push(i);
+                    Poll::Ready(())
+                }));
+            }
+            "done"
+        });
+        // Every child wakes itself, so polling in a loop drives them all.
+        loop {
+            if let Poll::Ready(ret) = with_noop_context(|cx| PinMut::new(&mut fut).poll(cx)) {
+                break ret;
+            }
+        }
+    };
+    assert_eq!(ret, "done");
+    assert_eq!(sums, [3, 7, 11]);
+    // The children ran concurrently: the one which yields least finished
+    // first.
+    assert_eq!(*order.borrow(), vec![2, 1, 0]);
+}
+
+#[test]
+fn scope_propagates_child_panic_and_drops_siblings() {
+    let dropped = Rc::new(Cell::new(0));
+    let mut fut = {
+        let dropped = dropped.clone();
+        scope(move |s| {
+            let guard = DropCount(dropped.clone());
+            let mut yields = yield_times(5);
+            s.spawn(poll_fn(move |cx: &mut Context| {
+                let _guard = &guard;
+                PinMut::new(&mut yields).poll(cx)
+            }));
+            s.spawn(poll_fn(|_: &mut Context| -> Poll<()> { panic!("child panicked") }));
+        })
+    };
+    let ret = panic::catch_unwind(AssertUnwindSafe(|| {
+        with_noop_context(|cx| PinMut::new(&mut fut).poll(cx))
+    }));
+    let err = ret.unwrap_err();
+    assert_eq!(err.downcast_ref::<&str>(), Some(&"child panicked"));
+    // The sibling is only dropped along with the scope.
+    drop(fut);
+    assert_eq!(dropped.get(), 1);
+}
+
+#[test]
+fn scope_dropped_early_drops_children_and_ends_borrows() {
+    let dropped = Rc::new(Cell::new(0));
+    let mut data = vec![1, 2, 3];
+    let polled = Cell::new(0);
+    {
+        let (data, polled) = (&data, &polled);
+        let mut fut = scope(|s| {
+            for _ in 0..2 {
+                let guard = DropCount(dropped.clone());
+                let mut yields = yield_times(10);
+                s.spawn(poll_fn(move |cx: &mut Context| {
+                    let _guard = &guard;
+                    polled.set(polled.get() + data.len());
+                    PinMut::new(&mut yields).poll(cx)
+                }));
+            }
+        });
+        assert!(with_noop_context(|cx| PinMut::new(&mut fut).poll(cx)).is_pending());
+        assert_eq!(dropped.get(), 0);
+        drop(fut);
+    }
+    // Neither child can run again, and `data` is no longer borrowed.
+    assert_eq!(dropped.get(), 2);
+    assert_eq!(polled.get(), 6);
+    data.push(4);
+    assert_eq!(data.len(), 4);
+}
+
+/// A spawner which records the priority of each task spawned through it.
+#[derive(Default)]
+struct ByPriority {
+    spawned: Vec<Priority>,
+}
+
+impl Spawn for ByPriority {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawn_obj_with_priority(future, Priority::default())
+    }
+}
+
+impl SpawnPriority for ByPriority {
+    fn spawn_obj_with_priority(
+        &mut self,
+        _future: FutureObj<'static, (), dyn Spawn>,
+        priority: Priority,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawned.push(priority);
+        Ok(())
+    }
+}
+
+#[test]
+fn priority_order_and_default() {
+    assert!(Priority::Low < Priority::Normal && Priority::Normal < Priority::High);
+    assert_eq!(Priority::default(), Priority::Normal);
+}
+
+#[test]
+fn priority_default_impl_forwards_to_spawn_obj() {
+    let (mut spawner, spawned, _) = tracker();
+    spawner.spawn_obj_with_priority(noop_task(), Priority::High).unwrap();
+    spawner.spawn_with_priority(ready(()), Priority::Low).unwrap();
+    assert_eq!(spawned.load(Ordering::SeqCst), 2);
+    let err = NoSpawn.spawn_obj_with_priority(noop_task(), Priority::High).unwrap_err();
+    assert!(err.kind.is_not_supported());
+    assert!(NoSpawn.spawn_with_priority(ready(()), Priority::Low).unwrap_err().is_not_supported());
+}
+
+#[test]
+fn priority_reaches_overriding_spawner() {
+    let mut spawner = ByPriority::default();
+    spawner.spawn_with_priority(ready(()), Priority::High).unwrap();
+    spawner.spawn(ready(())).unwrap();
+    spawner.spawn_with_priority(ready(()), Priority::Low).unwrap();
+    assert_eq!(spawner.spawned, vec![Priority::High, Priority::Normal, Priority::Low]);
+}
+
+impl SpawnPriority for Tracker {}
+impl SpawnNamed for Tracker {}
+
+/// A spawner which records the name of each task spawned through it.
+#[derive(Default)]
+struct ByName {
+    spawned: Vec<Option<Cow<'static, str>>>,
+}
+
+impl Spawn for ByName {
+    fn spawn_obj(
+        &mut self,
+        _future: FutureObj<'static, (), dyn Spawn>,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawned.push(None);
+        Ok(())
+    }
+}
+
+impl SpawnNamed for ByName {
+    fn spawn_obj_named(
+        &mut self,
+        name: Cow<'static, str>,
+        _future: FutureObj<'static, (), dyn Spawn>,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        self.spawned.push(Some(name));
+        Ok(())
+    }
+}
+
+#[test]
+fn named_default_impl_drops_name() {
+    let (mut spawner, spawned, _) = tracker();
+    spawner.spawn_obj_named("first".into(), noop_task()).unwrap();
+    spawner.spawn_named("second", ready(())).unwrap();
+    assert_eq!(spawned.load(Ordering::SeqCst), 2);
+    assert!(NoSpawn.spawn_named("rejected", ready(())).unwrap_err().is_not_supported());
+}
+
+#[test]
+fn named_reaches_overriding_spawner() {
+    let mut spawner = ByName::default();
+    spawner.spawn_named("static", ready(())).unwrap();
+    spawner.spawn_named(format!("worker-{}", 1), ready(())).unwrap();
+    spawner.spawn(ready(())).unwrap();
+    assert_eq!(spawner.spawned, vec![
+        Some(Cow::Borrowed("static")),
+        Some(Cow::Owned(String::from("worker-1"))),
+        None,
+    ]);
+}
+
+/// A layer which logs its label when a task it wrapped is first polled.
+struct Label(&'static str, Arc<Mutex<Vec<&'static str>>>);
+
+impl SpawnLayer for Label {
+    fn wrap(&mut self, mut future: FutureObj<'static, (), dyn Spawn>) -> FutureObj<'static, (), dyn Spawn> {
+        let (label, log) = (self.0, self.1.clone());
+        let mut logged = false;
+        FutureObj::new(Box::new(poll_fn(move |cx: &mut Context| {
+            if !logged {
+                logged = true;
+                log.lock().unwrap().push(label);
+            }
+            PinMut::new(&mut future).poll(cx)
+        })))
+    }
+
+    fn wrap_local(&mut self, future: LocalFutureObj<'static, (), dyn Spawn>) -> LocalFutureObj<'static, (), dyn Spawn> {
+        self.1.lock().unwrap().push("local");
+        future
+    }
+}
+
+#[test]
+fn hooks_layer_observes_tasks_on_local_pool() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut pool = LocalPool::new();
+    let mut spawner = {
+        let events = events.clone();
+        Layered::new(pool.spawner(), hooks(move |event| events.lock().unwrap().push(event)))
+    };
+    for i in 0..3 {
+        spawner.spawn_local(yield_times(i)).unwrap();
+    }
+    spawner.spawn(yield_times(1)).unwrap();
+    assert!(events.lock().unwrap().is_empty());
+    pool.run();
+    let events = events.lock().unwrap();
+    let count = |kind| events.iter().filter(|&&event| event == kind).count();
+    assert_eq!((count(TaskEvent::Started), count(TaskEvent::Finished)), (4, 4));
+}
+
+#[test]
+fn hooks_layer_reports_tasks_dropped_unfinished() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut pool = LocalPool::new();
+    {
+        let events = events.clone();
+        let mut spawner = Layered::new(pool.spawner(), hooks(move |event| events.lock().unwrap().push(event)));
+        spawner.spawn_local(poll_fn(|_: &mut Context| Poll::Pending::<()>)).unwrap();
+        spawner.spawn_local(poll_fn(|_: &mut Context| Poll::Pending::<()>)).unwrap();
+    }
+    assert!(!pool.run_until_stalled());
+    assert_eq!(*events.lock().unwrap(), vec![TaskEvent::Started; 2]);
+    drop(pool);
+    assert_eq!(events.lock().unwrap()[2..], [TaskEvent::Finished; 2]);
+}
+
+/// Waits for `done` to hold of the metrics' snapshot, which other threads
+/// update.
+fn wait_for_metrics<F>(metrics: &Metrics, done: F)
+    where F: Fn(&MetricsSnapshot) -> bool
+{
+    let start = Instant::now();
+    while !done(&metrics.snapshot()) {
+        assert!(start.elapsed() < Duration::from_secs(10), "stuck at {:?}", metrics.snapshot());
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn metrics_count_tasks_on_thread_pool() {
+    const TASKS: usize = 20;
+    let metrics = Metrics::new();
+    let pool = ThreadPool::builder().pool_size(4).create().unwrap();
+    let mut spawner = Layered::new(pool, metrics.clone());
+    for i in 0..TASKS {
+        spawner.spawn(yield_times(i % 3)).unwrap();
+    }
+    wait_for_metrics(&metrics, |snapshot| snapshot.alive == 0);
+    let snapshot = metrics.snapshot();
+    assert_eq!((snapshot.spawned, snapshot.completed, snapshot.queued), (TASKS, TASKS, 0));
+    // Tasks yield 0, 1 and 2 times in turn.
+    assert_eq!(snapshot.polls, TASKS + (0..TASKS).map(|i| i % 3).sum::<usize>());
+    assert_eq!(snapshot.poll_buckets.iter().sum::<usize>(), snapshot.polls);
+}
+
+#[test]
+fn metrics_queue_depth_shows_a_backlog() {
+    let metrics = Metrics::new();
+    let pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    let mut spawner = Layered::new(pool, metrics.clone());
+    let (started_tx, started_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let release_rx = Mutex::new(release_rx);
+    // Stall the only worker.
+    spawner.spawn(poll_fn(move |_: &mut Context| {
+        started_tx.send(()).unwrap();
+        release_rx.lock().unwrap().recv().unwrap();
+        Poll::Ready(())
+    })).unwrap();
+    started_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    for _ in 0..5 {
+        spawner.spawn(ready(())).unwrap();
+    }
+    let snapshot = metrics.snapshot();
+    assert_eq!((snapshot.spawned, snapshot.alive, snapshot.queued), (6, 6, 5));
+
+    release_tx.send(()).unwrap();
+    wait_for_metrics(&metrics, |snapshot| snapshot.alive == 0);
+    assert_eq!(metrics.snapshot().queued, 0);
+}
+
+#[test]
+fn metrics_report_each_completed_task() {
+    let completed = Arc::new(Mutex::new(Vec::new()));
+    let metrics = {
+        let completed = completed.clone();
+        Metrics::on_task_completed(move |task| completed.lock().unwrap().push(task.polls))
+    };
+    let mut pool = LocalPool::new();
+    let mut spawner = Layered::new(pool.spawner(), metrics.clone());
+    for i in 0..3 {
+        spawner.spawn_local(yield_times(i)).unwrap();
+    }
+    spawner.spawn_local(poll_fn(|_: &mut Context| Poll::Pending::<()>)).unwrap();
+    assert_eq!(metrics.snapshot().queued, 4);
+    assert!(!pool.run_until_stalled());
+    let mut polls = completed.lock().unwrap().clone();
+    polls.sort();
+    assert_eq!(polls, vec![1, 2, 3]);
+    let snapshot = metrics.snapshot();
+    assert_eq!((snapshot.completed, snapshot.alive, snapshot.queued), (3, 1, 0));
+
+    // A task dropped without completing leaves the counts consistent.
+    drop(pool);
+    let snapshot = metrics.snapshot();
+    assert_eq!((snapshot.spawned, snapshot.completed, snapshot.alive), (4, 3, 0));
+}
+
+/// A task which waits for the returned sender to fire.
+fn gated() -> (specialized_futures::channel::oneshot::Sender<()>, impl Future<Output = ()> + Send) {
+    let (tx, mut rx) = specialized_futures::channel::oneshot::channel();
+    let task = poll_fn(move |cx: &mut Context| PinMut::new(&mut rx).poll(cx).map(drop));
+    (tx, task)
+}
+
+#[test]
+fn thread_pool_metrics_count_tasks() {
+    const TASKS: usize = 20;
+    let completed = Arc::new(AtomicUsize::new(0));
+    let metrics = {
+        let completed = completed.clone();
+        Metrics::on_task_completed(move |_| { completed.fetch_add(1, Ordering::SeqCst); })
+    };
+    let mut pool = ThreadPool::builder().pool_size(4).metrics(metrics.clone()).create().unwrap();
+    for i in 0..TASKS {
+        pool.spawn(yield_times(i % 3)).unwrap();
+    }
+    wait_for_metrics(&metrics, |snapshot| snapshot.alive == 0);
+    let snapshot = pool.metrics();
+    assert_eq!(snapshot, metrics.snapshot());
+    assert_eq!((snapshot.spawned, snapshot.completed, snapshot.queued), (TASKS, TASKS, 0));
+    assert_eq!(snapshot.polls, TASKS + (0..TASKS).map(|i| i % 3).sum::<usize>());
+    assert_eq!(completed.load(Ordering::SeqCst), TASKS);
+}
+
+#[test]
+fn thread_pool_metrics_queue_depth_counts_woken_tasks() {
+    let metrics = Metrics::new();
+    let mut pool = ThreadPool::builder().pool_size(1).metrics(metrics.clone()).create().unwrap();
+    let (gate, waiter) = gated();
+    pool.spawn(waiter).unwrap();
+    wait_for_metrics(&metrics, |snapshot| snapshot.polls == 1);
+
+    let (started_tx, started_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let release_rx = Mutex::new(release_rx);
+    // Stall the only worker.
+    pool.spawn(poll_fn(move |_: &mut Context| {
+        started_tx.send(()).unwrap();
+        release_rx.lock().unwrap().recv().unwrap();
+        Poll::Ready(())
+    })).unwrap();
+    started_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(pool.metrics().queued, 0);
+    // The woken task waits behind the stalled one, as do new ones.
+    gate.send(()).unwrap();
+    assert_eq!(pool.metrics().queued, 1);
+    for _ in 0..2 {
+        pool.spawn(ready(())).unwrap();
+    }
+    let snapshot = pool.metrics();
+    assert_eq!((snapshot.spawned, snapshot.alive, snapshot.queued), (4, 4, 3));
+
+    release_tx.send(()).unwrap();
+    wait_for_metrics(&metrics, |snapshot| snapshot.alive == 0);
+    assert_eq!(pool.metrics().queued, 0);
+}
+
+#[test]
+fn local_pool_metrics_count_tasks_and_woken_backlog() {
+    let completed = Arc::new(Mutex::new(Vec::new()));
+    let metrics = {
+        let completed = completed.clone();
+        Metrics::on_task_completed(move |task| completed.lock().unwrap().push(task.polls))
+    };
+    let mut pool = LocalPool::with_metrics(metrics.clone());
+    let mut spawner = pool.spawner();
+    let mut gates = Vec::new();
+    for _ in 0..3 {
+        let (gate, waiter) = gated();
+        spawner.spawn(waiter).unwrap();
+        gates.push(gate);
+    }
+    spawner.spawn(yield_times(2)).unwrap();
+    assert_eq!(pool.metrics().queued, 4);
+
+    assert!(!pool.run_until_stalled());
+    let snapshot = pool.metrics();
+    assert_eq!((snapshot.spawned, snapshot.completed, snapshot.alive, snapshot.queued), (4, 1, 3, 0));
+    // Woken tasks wait for the pool to be run again.
+    gates.pop().unwrap().send(()).unwrap();
+    gates.pop().unwrap().send(()).unwrap();
+    assert_eq!(pool.metrics().queued, 2);
+    // The layer-style count on the handle only sees unpolled tasks.
+    assert_eq!(metrics.snapshot().queued, 0);
+
+    gates.pop().unwrap().send(()).unwrap();
+    pool.run();
+    let snapshot = pool.metrics();
+    assert_eq!((snapshot.spawned, snapshot.completed, snapshot.alive, snapshot.queued), (4, 4, 0, 0));
+    assert_eq!(snapshot.polls, 3 * 2 + 3);
+    let mut polls = completed.lock().unwrap().clone();
+    polls.sort();
+    assert_eq!(polls, vec![2, 2, 2, 3]);
+}
+
+#[test]
+fn catch_panics_layer_keeps_pool_alive() {
+    let panics = Arc::new(Mutex::new(Vec::new()));
+    let mut pool = LocalPool::new();
+    let mut spawner = {
+        let panics = panics.clone();
+        Layered::new(pool.spawner(), catch_panics(move |payload| {
+            let message = payload.downcast_ref::<&str>().cloned().unwrap_or("<non-string payload>");
+            panics.lock().unwrap().push(message);
+        }))
+    };
+    let ran = Rc::new(Cell::new(0));
+    spawner.spawn_local(poll_fn(|_: &mut Context| -> Poll<()> { panic!("first") })).unwrap();
+    {
+        let ran = ran.clone();
+        spawner.spawn_local(poll_fn(move |_: &mut Context| {
+            ran.set(ran.get() + 1);
+            Poll::Ready(())
+        })).unwrap();
+    }
+    spawner.spawn(poll_fn(|_: &mut Context| -> Poll<()> { panic!("second") })).unwrap();
+    pool.run();
+    assert_eq!(ran.get(), 1);
+
+    // The pool is still usable afterwards.
+    {
+        let ran = ran.clone();
+        spawner.spawn_local(poll_fn(move |_: &mut Context| {
+            ran.set(ran.get() + 1);
+            Poll::Ready(())
+        })).unwrap();
+    }
+    pool.run();
+    assert_eq!(ran.get(), 2);
+    let mut panics = panics.lock().unwrap().clone();
+    panics.sort();
+    assert_eq!(panics, vec!["first", "second"]);
+}
+
+#[test]
+fn layers_compose_with_later_layers_outermost() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let layer = Label("inner", log.clone()).and_then(Label("outer", log.clone()));
+    let mut pool = LocalPool::new();
+    let mut spawner = Layered::new(pool.spawner(), layer);
+    spawner.spawn(ready(())).unwrap();
+    spawner.spawn_local(ready(())).unwrap();
+    pool.run();
+    assert_eq!(*log.lock().unwrap(), vec!["local", "local", "outer", "inner"]);
+}
+
+#[test]
+fn layered_rejection_returns_wrapped_task() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut spawner = Layered::new(NoSpawn, Label("layer", log.clone()));
+    assert!(spawner.status().unwrap_err().is_not_supported());
+    let err = spawner.spawn_obj(noop_task()).unwrap_err();
+    assert!(err.kind.is_not_supported());
+    let mut task = err.future;
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut task).poll(cx)), Poll::Ready(()));
+    assert_eq!(*log.lock().unwrap(), vec!["layer"]);
+}
+
+/// Spawns `n` counting tasks through a shared reference only.
+fn spawn_counting<S: SpawnShared + ?Sized>(spawner: &S, n: usize, count: &Arc<AtomicUsize>) {
+    for _ in 0..n {
+        let count = count.clone();
+        let task = poll_fn(move |_: &mut Context| {
+            count.fetch_add(1, Ordering::SeqCst);
+            Poll::Ready(())
+        });
+        spawner.spawn_obj_shared(FutureObj::new(Box::new(task))).unwrap();
+    }
+}
+
+#[test]
+fn shared_thread_pool_spawned_from_two_threads() {
+    const PER_THREAD: usize = 500;
+    let pool = Arc::new(ThreadPool::builder().pool_size(2).create().unwrap());
+    let count = Arc::new(AtomicUsize::new(0));
+    let threads = (0..2).map(|_| {
+        let (pool, count) = (pool.clone(), count.clone());
+        // No lock: every spawn goes through `&ThreadPool`.
+        thread::spawn(move || spawn_counting(&*pool, PER_THREAD, &count))
+    }).collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while count.load(Ordering::SeqCst) < 2 * PER_THREAD {
+        assert!(Instant::now() < deadline, "only {} tasks ran", count.load(Ordering::SeqCst));
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn shared_reference_is_a_spawner() {
+    let mut pool = LocalPool::new();
+    let local = pool.spawner();
+    let count = Arc::new(AtomicUsize::new(0));
+    spawn_counting(&local, 2, &count);
+    // `&LocalSpawner` is a `Spawn` of its own.
+    let mut by_ref = &local;
+    by_ref.spawn(ready(())).unwrap();
+    assert!(by_ref.status().is_ok());
+    pool.run();
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+
+    drop(pool);
+    assert!((&local).spawn(ready(())).unwrap_err().is_shutdown());
+}
+
+#[test]
+fn spawner_shared_through_shared_context() {
+    fn spawn_from(cx: &Context<LocalSpawner>, count: &Arc<AtomicUsize>) {
+        spawn_counting(cx.spawner_shared(), 1, count);
+    }
+
+    let mut pool = LocalPool::new();
+    let count = Arc::new(AtomicUsize::new(0));
+    pool.run_until(poll_fn(|cx: &mut Context<LocalSpawner>| {
+        spawn_from(cx, &count);
+        Poll::Ready(())
+    }));
+    pool.run();
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn local_handle_is_send_when_output_is() {
+    fn assert_send<T: Send>(_: &T) {}
+    let mut pool = LocalPool::new();
+    // The future holds an `Rc`, but only its `Send` output crosses into the
+    // handle.
+    let rc = Rc::new(5);
+    let handle = pool.spawner().spawn_local_with_handle(poll_fn(move |_: &mut Context| {
+        Poll::Ready(*rc)
+    })).unwrap();
+    assert_send(&handle);
+    pool.run();
+    let output = thread::spawn(move || {
+        let mut handle = handle;
+        match with_noop_context(|cx| PinMut::new(&mut handle).poll(cx)) {
+            Poll::Ready(result) => result.unwrap(),
+            Poll::Pending => panic!("task already ran"),
+        }
+    }).join().unwrap();
+    assert_eq!(output, 5);
+}
@@ -0,0 +1,232 @@
+#![feature(proc_macro, pin, arbitrary_self_types, futures_api)]
+#![cfg(feature = "macros")]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::cell::Cell;
+use std::rc::Rc;
+use specialized_futures::{Context, Future, LocalSpawnExt, Spawn, specialized};
+use specialized_futures::channel::oneshot;
+use specialized_futures::executor::{LocalPool, LocalSpawner};
+use specialized_futures::future::{poll_fn, ready, yield_now};
+use specialized_futures::task::Poll;
+
+use support::{with_counting_context, with_noop_context};
+
+#[specialized]
+fn no_awaits(log: Rc<Cell<u32>>) -> u32 {
+    log.set(log.get() + 1);
+    7
+}
+
+#[test]
+fn body_runs_when_first_polled() {
+    let log = Rc::new(Cell::new(0));
+    let fut = no_awaits(log.clone());
+    assert_eq!(log.get(), 0);
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready(7));
+    assert_eq!(log.get(), 1);
+}
+
+#[specialized(spawner = LocalSpawner)]
+fn sum_received(a: oneshot::Receiver<u32>, b: oneshot::Receiver<u32>) -> u32 {
+    let scale = 10;
+    let first = await_!(a).unwrap();
+    let partial = first * scale;
+    await_!(yield_now());
+    let second: u32 = await_!(b).unwrap();
+    partial + second * scale
+}
+
+#[test]
+fn locals_live_across_awaits() {
+    let mut pool = LocalPool::new();
+    let (a_tx, a_rx) = oneshot::channel();
+    let (b_tx, b_rx) = oneshot::channel();
+    let (mut a_tx, mut b_tx) = (Some(a_tx), Some(b_tx));
+    pool.spawner().spawn_local(poll_fn(move |cx: &mut Context| {
+        // Send one value per poll, so that each await pends first.
+        if let Some(tx) = a_tx.take() {
+            tx.send(1).unwrap();
+        } else if let Some(tx) = b_tx.take() {
+            tx.send(2).unwrap();
+            return Poll::Ready(());
+        }
+        cx.waker().wake();
+        Poll::Pending
+    })).unwrap();
+    assert_eq!(pool.run_until(sum_received(a_rx, b_rx)), 30);
+}
+
+#[specialized(spawner = LocalSpawner)]
+fn pending_then_sum(rx: oneshot::Receiver<u32>) -> u32 {
+    let value = await_!(rx).unwrap();
+    value + 1
+}
+
+#[test]
+fn await_pends_until_woken() {
+    let (tx, rx) = oneshot::channel();
+    let fut = pending_then_sum(rx);
+    pin_mut!(fut);
+    let pool = LocalPool::new();
+    let mut spawner = pool.spawner();
+    let (wakes, ret) = with_counting_context(&mut spawner, |cx| fut.reborrow().poll(cx));
+    assert_eq!(ret, Poll::Pending);
+    assert_eq!(wakes.get(), 0);
+    tx.send(4).unwrap();
+    assert_eq!(wakes.get(), 1);
+    let (_, ret) = with_counting_context(&mut spawner, |cx| fut.reborrow().poll(cx));
+    assert_eq!(ret, Poll::Ready(5));
+}
+
+#[specialized]
+fn early_return(skip: bool, log: Rc<Cell<u32>>) -> &'static str {
+    if skip {
+        return "skipped";
+    }
+    await_!(yield_now());
+    log.set(1);
+    "finished"
+}
+
+#[test]
+fn early_return_before_an_await() {
+    let log = Rc::new(Cell::new(0));
+    let fut = early_return(true, log.clone());
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready("skipped"));
+    assert_eq!(log.get(), 0);
+
+    let fut = early_return(false, log.clone());
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Pending);
+    assert_eq!(log.get(), 0);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready("finished"));
+    assert_eq!(log.get(), 1);
+}
+
+#[derive(Debug, PartialEq)]
+enum Error {
+    Canceled,
+    Parse,
+}
+
+impl From<oneshot::Canceled> for Error {
+    fn from(_: oneshot::Canceled) -> Error {
+        Error::Canceled
+    }
+}
+
+fn parse(s: &str) -> Result<u32, Error> {
+    s.parse().map_err(|_| Error::Parse)
+}
+
+#[specialized(spawner = LocalSpawner)]
+fn receive_and_parse(rx: oneshot::Receiver<&'static str>, next: &'static str) -> Result<u32, Error> {
+    let received = await_!(rx)?;
+    // A `?` between two awaits returns early too.
+    let first = parse(received)?;
+    await_!(yield_now());
+    let second = await_!(ready(parse(next)))?;
+    Ok(first + second)
+}
+
+#[test]
+fn question_mark_on_try_future_awaits() {
+    let mut pool = LocalPool::new();
+
+    let (tx, rx) = oneshot::channel();
+    tx.send("1").unwrap();
+    assert_eq!(pool.run_until(receive_and_parse(rx, "2")), Ok(3));
+
+    let (tx, rx) = oneshot::channel();
+    drop(tx);
+    assert_eq!(pool.run_until(receive_and_parse(rx, "2")), Err(Error::Canceled));
+
+    let (tx, rx) = oneshot::channel();
+    tx.send("x").unwrap();
+    assert_eq!(pool.run_until(receive_and_parse(rx, "2")), Err(Error::Parse));
+
+    let (tx, rx) = oneshot::channel();
+    tx.send("1").unwrap();
+    assert_eq!(pool.run_until(receive_and_parse(rx, "y")), Err(Error::Parse));
+}
+
+#[specialized]
+fn accumulate(values: Vec<u32>) -> Result<u32, Error> {
+    let mut total = 0;
+    total += await_!(ready(values[0]));
+    await_!(ready(parse("1")))?;
+    total = await_!(ready(total * 2)) + values[1];
+    Ok(total)
+}
+
+#[test]
+fn await_starts_assignments_and_statements() {
+    let fut = accumulate(vec![3, 4]);
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready(Ok(10)));
+}
+
+/// Only pollable with a `LocalSpawner`, which it uses to spawn a task.
+fn spawn_on_pool(ran: Rc<Cell<bool>>) -> impl Future<LocalSpawner, Output = ()> {
+    let mut ran = Some(ran);
+    poll_fn(move |cx: &mut Context<LocalSpawner>| {
+        let ran = ran.take().unwrap();
+        cx.spawner().spawn_local(poll_fn(move |_: &mut Context| {
+            ran.set(true);
+            Poll::Ready(())
+        })).unwrap();
+        Poll::Ready(())
+    })
+}
+
+#[specialized(spawner = LocalSpawner)]
+fn threads_the_context(ran: Rc<Cell<bool>>) {
+    await_!(spawn_on_pool(ran.clone()));
+    await_!(yield_now());
+    assert!(ran.get());
+}
+
+#[test]
+fn awaited_futures_see_the_spawner() {
+    let ran = Rc::new(Cell::new(false));
+    let mut pool = LocalPool::new();
+    pool.run_until(threads_the_context(ran.clone()));
+    assert!(ran.get());
+}
+
+#[specialized(spawner = S)]
+fn generic<S: Spawn + ?Sized, T: Clone>(value: T) -> (T, T) {
+    let copy = value.clone();
+    await_!(yield_now());
+    (value, copy)
+}
+
+#[specialized]
+fn tail_await(value: u32) -> u32 {
+    await_!(ready(value * 2))
+}
+
+#[test]
+fn generic_and_tail_awaits() {
+    let mut pool = LocalPool::new();
+    assert_eq!(pool.run_until(generic::<LocalSpawner, _>("a")), ("a", "a"));
+    let fut = tail_await(21);
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready(42));
+}
+
+#[test]
+#[should_panic(expected = "`tail_await` polled after completion")]
+fn polling_after_completion_panics() {
+    let fut = tail_await(1);
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready(2));
+    let _ = with_noop_context(|cx| fut.reborrow().poll(cx));
+}
@@ -0,0 +1,1272 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::cell::Cell;
+use std::mem::PinMut;
+use std::rc::Rc;
+use specialized_futures::{Context, Future, FutureExt, Spawn, Stream, StreamExt};
+use specialized_futures::future::{pending, poll_fn, ready};
+use specialized_futures::spawn::NoSpawn;
+use specialized_futures::stream::{Collect, FusedStream, LocalStreamObj, empty, iter, once, select, try_unfold, unfold};
+use specialized_futures::task::Poll;
+
+use support::{with_counting_context, with_noop_context};
+
+/// Poll `stream` to exhaustion through `cx`, which must not return
+/// `Pending`.
+fn drain<S, St>(mut stream: PinMut<St>, cx: &mut Context<S>) -> Vec<St::Item>
+    where S: Spawn + ?Sized,
+          St: Stream<S> + ?Sized
+{
+    let mut items = Vec::new();
+    loop {
+        match stream.reborrow().poll_next(cx) {
+            Poll::Ready(Some(item)) => items.push(item),
+            Poll::Ready(None) => return items,
+            Poll::Pending => panic!("stream was not ready"),
+        }
+    }
+}
+
+#[test]
+fn iter_through_dyn_spawn_context() {
+    let stream = iter(vec![1, 2, 3]);
+    pin_mut!(stream);
+    assert!(!stream.is_terminated());
+    assert_eq!(with_noop_context(|cx| drain(stream.reborrow(), cx)), vec![1, 2, 3]);
+    assert!(stream.is_terminated());
+    // Fused: polling again keeps returning `None`.
+    assert_eq!(with_noop_context(|cx| stream.reborrow().poll_next(cx)), Poll::Ready(None));
+}
+
+#[test]
+fn iter_through_concrete_spawner_context() {
+    let stream = iter(0..3);
+    pin_mut!(stream);
+    let (_, items) = with_counting_context(&mut NoSpawn, |cx| drain(stream.reborrow(), cx));
+    assert_eq!(items, vec![0, 1, 2]);
+}
+
+#[test]
+fn empty_is_terminated() {
+    let stream = empty::<i32>();
+    pin_mut!(stream);
+    assert!(stream.is_terminated());
+    assert_eq!(with_noop_context(|cx| stream.reborrow().poll_next(cx)), Poll::Ready(None));
+    let (_, ret) = with_counting_context(&mut NoSpawn, |cx| stream.reborrow().poll_next(cx));
+    assert_eq!(ret, Poll::Ready(None));
+}
+
+#[test]
+fn once_yields_future_output() {
+    let stream = once(ready(5));
+    pin_mut!(stream);
+    assert!(!stream.is_terminated());
+    let (_, items) = with_counting_context(&mut NoSpawn, |cx| drain(stream.reborrow(), cx));
+    assert_eq!(items, vec![5]);
+    assert!(stream.is_terminated());
+}
+
+#[test]
+fn once_pending_future() {
+    let stream = once(pending::<i32>());
+    pin_mut!(stream);
+    assert_eq!(with_noop_context(|cx| stream.reborrow().poll_next(cx)), Poll::Pending);
+    assert!(!stream.is_terminated());
+}
+
+#[test]
+fn forwarding_impls() {
+    let mut stream = iter(vec!['a', 'b']);
+    {
+        let mut by_ref = &mut stream;
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut by_ref).poll_next(cx)), Poll::Ready(Some('a')));
+    }
+    let mut pinned = PinMut::new(&mut stream);
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut pinned).poll_next(cx)), Poll::Ready(Some('b')));
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut pinned).poll_next(cx)), Poll::Ready(None));
+}
+
+#[test]
+fn map_filter_collect() {
+    let stream = StreamExt::<dyn Spawn>::map(iter(1..=10), |x| x * 2);
+    let stream = StreamExt::<dyn Spawn>::filter(stream, |x| ready(x % 3 == 0));
+    let fut: Collect<_, Vec<i32>> = StreamExt::<dyn Spawn>::collect(stream);
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready(vec![6, 12, 18]));
+}
+
+#[test]
+fn unfold_counts_down() {
+    let calls = Cell::new(0);
+    let stream = unfold(3u32, |n| {
+        calls.set(calls.get() + 1);
+        ready(if n == 0 { None } else { Some((n, n - 1)) })
+    });
+    pin_mut!(stream);
+    assert!(!stream.is_terminated());
+    assert_eq!(with_noop_context(|cx| drain(stream.reborrow(), cx)), vec![3, 2, 1]);
+    assert!(stream.is_terminated());
+    assert_eq!(calls.get(), 4);
+    // The closure isn't called again once the stream has ended.
+    assert_eq!(with_noop_context(|cx| stream.reborrow().poll_next(cx)), Poll::Ready(None));
+    assert_eq!(calls.get(), 4);
+}
+
+#[test]
+fn unfold_step_pends_between_items() {
+    let stream = unfold(0u32, |n| {
+        let mut polled = false;
+        poll_fn(move |cx: &mut Context| {
+            if !polled {
+                polled = true;
+                cx.waker().wake();
+                return Poll::Pending;
+            }
+            Poll::Ready(if n < 2 { Some((n, n + 1)) } else { None })
+        })
+    });
+    pin_mut!(stream);
+    let mut next = || with_counting_context(&mut NoSpawn as &mut dyn Spawn, |cx| stream.reborrow().poll_next(cx));
+    for &expected in &[Some(0), Some(1), None] {
+        let (wakes, first) = next();
+        assert_eq!(first, Poll::Pending);
+        assert_eq!(wakes.get(), 1);
+        let (_, second) = next();
+        assert_eq!(second, Poll::Ready(expected));
+    }
+}
+
+#[test]
+fn try_unfold_ends_after_an_error() {
+    let calls = Cell::new(0);
+    let stream = try_unfold(0u32, |n| {
+        calls.set(calls.get() + 1);
+        ready(if n < 2 { Ok(Some((n, n + 1))) } else { Err("failed") })
+    });
+    pin_mut!(stream);
+    assert_eq!(with_noop_context(|cx| drain(stream.reborrow(), cx)), vec![Ok(0), Ok(1), Err("failed")]);
+    assert!(stream.is_terminated());
+    assert_eq!(with_noop_context(|cx| stream.reborrow().poll_next(cx)), Poll::Ready(None));
+    assert_eq!(calls.get(), 3);
+
+    let stream = try_unfold((), |()| ready(Ok::<Option<((), ())>, ()>(None)));
+    pin_mut!(stream);
+    assert_eq!(with_noop_context(|cx| drain(stream.reborrow(), cx)), Vec::new());
+    assert!(stream.is_terminated());
+}
+
+/// A stream which yields one item, then ends, then panics if polled again.
+struct OneThenPanic {
+    polls: usize,
+}
+
+impl Stream for OneThenPanic {
+    type Item = i32;
+
+    fn poll_next(mut self: PinMut<Self>, _cx: &mut Context) -> Poll<Option<i32>> {
+        self.polls += 1;
+        match self.polls {
+            1 => Poll::Ready(Some(1)),
+            2 => Poll::Ready(None),
+            _ => panic!("polled after the stream ended"),
+        }
+    }
+}
+
+#[test]
+fn into_stream_collects_one_item() {
+    let stream = FutureExt::<dyn Spawn>::into_stream(ready(7));
+    let fut: Collect<_, Vec<i32>> = StreamExt::<dyn Spawn>::collect(stream);
+    pin_mut!(fut);
+    assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready(vec![7]));
+}
+
+#[test]
+fn into_stream_is_terminated_after_its_item() {
+    let stream = FutureExt::<dyn Spawn>::into_stream(ready('x'));
+    pin_mut!(stream);
+    assert!(!stream.is_terminated());
+    assert_eq!(with_noop_context(|cx| stream.reborrow().poll_next(cx)), Poll::Ready(Some('x')));
+    assert!(stream.is_terminated());
+    assert_eq!(with_noop_context(|cx| stream.reborrow().poll_next(cx)), Poll::Ready(None));
+    assert_eq!(with_noop_context(|cx| stream.reborrow().poll_next(cx)), Poll::Ready(None));
+}
+
+#[test]
+fn flatten_stream_waits_for_the_future() {
+    let mut polls = 0;
+    let connect = poll_fn(move |cx: &mut Context| {
+        polls += 1;
+        if polls == 1 {
+            cx.waker().wake();
+            return Poll::Pending;
+        }
+        Poll::Ready(iter(vec![1, 2, 3]))
+    });
+    let stream = FutureExt::<dyn Spawn>::flatten_stream(connect);
+    pin_mut!(stream);
+    let (wakes, first) = with_counting_context(&mut NoSpawn as &mut dyn Spawn, |cx| {
+        stream.reborrow().poll_next(cx)
+    });
+    assert_eq!(first, Poll::Pending);
+    assert_eq!(wakes.get(), 1);
+    assert!(!stream.is_terminated());
+
+    assert_eq!(with_noop_context(|cx| drain(stream.reborrow(), cx)), vec![1, 2, 3]);
+    assert!(stream.is_terminated());
+    // The exhausted inner stream is not polled again.
+    assert_eq!(with_noop_context(|cx| stream.reborrow().poll_next(cx)), Poll::Ready(None));
+}
+
+#[test]
+fn flatten_stream_stops_polling_after_end() {
+    let stream = FutureExt::<dyn Spawn>::flatten_stream(ready(OneThenPanic { polls: 0 }));
+    pin_mut!(stream);
+    assert_eq!(with_noop_context(|cx| drain(stream.reborrow(), cx)), vec![1]);
+    assert!(stream.is_terminated());
+    assert_eq!(with_noop_context(|cx| stream.reborrow().poll_next(cx)), Poll::Ready(None));
+}
+
+#[test]
+fn adapters_stop_polling_after_end() {
+    let map = StreamExt::<dyn Spawn>::map(OneThenPanic { polls: 0 }, |x| x + 1);
+    pin_mut!(map);
+    assert_eq!(with_noop_context(|cx| drain(map.reborrow(), cx)), vec![2]);
+    assert!(map.is_terminated());
+    assert_eq!(with_noop_context(|cx| map.reborrow().poll_next(cx)), Poll::Ready(None));
+
+    let filter = StreamExt::<dyn Spawn>::filter(OneThenPanic { polls: 0 }, |_| ready(true));
+    pin_mut!(filter);
+    assert_eq!(with_noop_context(|cx| drain(filter.reborrow(), cx)), vec![1]);
+    assert!(filter.is_terminated());
+    assert_eq!(with_noop_context(|cx| filter.reborrow().poll_next(cx)), Poll::Ready(None));
+}
+
+/// A stream which yields `Some` and `None` in turn forever, counting its
+/// polls.
+struct Flickering {
+    polls: usize,
+}
+
+impl Stream for Flickering {
+    type Item = usize;
+
+    fn poll_next(mut self: PinMut<Self>, _cx: &mut Context) -> Poll<Option<usize>> {
+        self.polls += 1;
+        Poll::Ready(if self.polls % 2 == 1 { Some(self.polls) } else { None })
+    }
+}
+
+#[test]
+fn fuse_stays_ended_well_past_the_end() {
+    let stream = StreamExt::<dyn Spawn>::fuse(Flickering { polls: 0 });
+    pin_mut!(stream);
+    assert!(!stream.is_terminated());
+    assert_eq!(with_noop_context(|cx| drain(stream.reborrow(), cx)), vec![1]);
+    assert!(stream.is_terminated());
+    for _ in 0..10 {
+        assert_eq!(with_noop_context(|cx| stream.reborrow().poll_next(cx)), Poll::Ready(None));
+    }
+    assert!(stream.is_terminated());
+    assert_eq!(stream.get_ref().polls, 2);
+}
+
+#[test]
+fn fuse_passes_pending_through() {
+    let mut polls = 0;
+    let stream = StreamExt::<dyn Spawn>::fuse(once(poll_fn(move |_: &mut Context| {
+        polls += 1;
+        if polls == 1 { Poll::Pending } else { Poll::Ready(polls) }
+    })));
+    pin_mut!(stream);
+    with_noop_context(|cx| {
+        assert_eq!(stream.reborrow().poll_next(cx), Poll::Pending);
+        assert!(!stream.is_terminated());
+        assert_eq!(stream.reborrow().poll_next(cx), Poll::Ready(Some(2)));
+        assert_eq!(stream.reborrow().poll_next(cx), Poll::Ready(None));
+    });
+    assert!(stream.is_terminated());
+}
+
+/// An iterator which resumes after returning `None`, counting its calls.
+struct Resuming<'a> {
+    calls: &'a Cell<usize>,
+}
+
+impl<'a> Iterator for Resuming<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.calls.set(self.calls.get() + 1);
+        if self.calls.get() % 2 == 1 { Some(self.calls.get()) } else { None }
+    }
+}
+
+#[test]
+fn iter_does_not_resume_its_iterator() {
+    let calls = Cell::new(0);
+    let stream = iter(Resuming { calls: &calls });
+    pin_mut!(stream);
+    assert_eq!(with_noop_context(|cx| drain(stream.reborrow(), cx)), vec![1]);
+    assert_eq!(with_noop_context(|cx| stream.reborrow().poll_next(cx)), Poll::Ready(None));
+    assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn select_interleaves_ready_streams() {
+    let stream = select(iter(vec![1, 3, 5]), iter(vec![2, 4, 6]));
+    pin_mut!(stream);
+    assert_eq!(with_noop_context(|cx| drain(stream.reborrow(), cx)), vec![1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn select_continues_after_one_side_ends() {
+    let stream = StreamExt::<dyn Spawn>::select(OneThenPanic { polls: 0 }, iter(vec![2, 4, 6]));
+    pin_mut!(stream);
+    // `OneThenPanic` ends on its second poll and must not be polled again.
+    assert_eq!(with_noop_context(|cx| drain(stream.reborrow(), cx)), vec![1, 2, 4, 6]);
+
+    let stream = select(iter(vec![1, 3, 5]), empty());
+    pin_mut!(stream);
+    assert_eq!(with_noop_context(|cx| drain(stream.reborrow(), cx)), vec![1, 3, 5]);
+}
+
+#[test]
+fn select_pends_while_one_side_does() {
+    let mut polls = 0;
+    let slow = StreamExt::<dyn Spawn>::map(once(poll_fn(move |_: &mut Context| {
+        polls += 1;
+        if polls < 2 { Poll::Pending } else { Poll::Ready(()) }
+    })), |()| 0);
+    let stream = select(iter(vec![1]), slow);
+    pin_mut!(stream);
+    with_noop_context(|cx| {
+        assert_eq!(stream.reborrow().poll_next(cx), Poll::Ready(Some(1)));
+        assert_eq!(stream.reborrow().poll_next(cx), Poll::Pending);
+        assert!(!stream.is_terminated());
+        assert_eq!(stream.reborrow().poll_next(cx), Poll::Ready(Some(0)));
+        assert_eq!(stream.reborrow().poll_next(cx), Poll::Ready(None));
+    });
+}
+
+#[test]
+fn select_terminates_when_both_end() {
+    let stream = select(OneThenPanic { polls: 0 }, OneThenPanic { polls: 0 });
+    pin_mut!(stream);
+    with_noop_context(|cx| {
+        assert_eq!(stream.reborrow().poll_next(cx), Poll::Ready(Some(1)));
+        assert_eq!(stream.reborrow().poll_next(cx), Poll::Ready(Some(1)));
+        assert!(!stream.is_terminated());
+        assert_eq!(stream.reborrow().poll_next(cx), Poll::Ready(None));
+        assert!(stream.is_terminated());
+        // Neither side is polled again.
+        assert_eq!(stream.reborrow().poll_next(cx), Poll::Ready(None));
+    });
+}
+
+#[test]
+fn zip_ends_with_the_shorter_stream() {
+    let zip = StreamExt::<dyn Spawn>::zip(iter(1..=3), iter(vec!["a", "b"]));
+    pin_mut!(zip);
+    assert_eq!(with_noop_context(|cx| drain(zip.reborrow(), cx)), vec![(1, "a"), (2, "b")]);
+    assert!(zip.is_terminated());
+
+    let zip = StreamExt::<dyn Spawn>::zip(iter(vec!["a"]), iter(1..=3));
+    pin_mut!(zip);
+    assert_eq!(with_noop_context(|cx| drain(zip.reborrow(), cx)), vec![("a", 1)]);
+    assert!(zip.is_terminated());
+}
+
+#[test]
+fn zip_drops_the_unpaired_item() {
+    let item = Rc::new(());
+    let zip = StreamExt::<dyn Spawn>::zip(iter(vec![item.clone(), item.clone(), item.clone()]), iter(0..2));
+    pin_mut!(zip);
+    let pairs = with_noop_context(|cx| drain(zip.reborrow(), cx));
+    assert_eq!(pairs.len(), 2);
+    // The third item was taken from the longer side before the shorter one
+    // ended, and isn't kept around.
+    assert_eq!(Rc::strong_count(&item), 3);
+    drop(pairs);
+    assert_eq!(Rc::strong_count(&item), 1);
+}
+
+#[test]
+fn enumerate_counts_filtered_items() {
+    let evens = StreamExt::<dyn Spawn>::filter(iter(1..=10), |x| ready(x % 2 == 0));
+    let enumerated = StreamExt::<dyn Spawn>::enumerate(evens);
+    pin_mut!(enumerated);
+    assert_eq!(
+        with_noop_context(|cx| drain(enumerated.reborrow(), cx)),
+        vec![(0, 2), (1, 4), (2, 6), (3, 8), (4, 10)]
+    );
+}
+
+#[cfg(feature = "std")]
+mod with_std {
+    use std::cell::{Cell, RefCell};
+    use std::collections::VecDeque;
+    use std::mem::{self, PinMut};
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc as mpsc_std;
+    use std::time::Duration;
+    use specialized_futures::{Context, Future, LocalFutureObj, LocalSpawnExt, Sink, Spawn, SpawnExt, Stream, StreamExt};
+    use specialized_futures::channel::{mpsc, oneshot};
+    use specialized_futures::executor::{LocalPool, LocalSpawner, ThreadPool};
+    use specialized_futures::future::{FusedFuture, poll_fn, ready};
+    use specialized_futures::stream::{FusedStream, FuturesOrdered, FuturesUnordered, iter};
+    use specialized_futures::stream::{SplitSink, SplitStream};
+    use specialized_futures::task::{Poll, Waker};
+    use support::with_noop_context;
+
+    /// A future which pends once, waking itself, while counting how many
+    /// such futures are in flight.
+    fn tracked(in_flight: Rc<Cell<usize>>, max: Rc<Cell<usize>>) -> impl Future<Output = ()> {
+        let mut started = false;
+        poll_fn(move |cx: &mut Context| {
+            if started {
+                in_flight.set(in_flight.get() - 1);
+                return Poll::Ready(());
+            }
+            started = true;
+            in_flight.set(in_flight.get() + 1);
+            max.set(max.get().max(in_flight.get()));
+            cx.waker().wake();
+            Poll::Pending
+        })
+    }
+
+    #[test]
+    fn for_each_concurrent_respects_limit() {
+        let mut pool = LocalPool::new();
+        let (in_flight, max) = (Rc::new(Cell::new(0)), Rc::new(Cell::new(0)));
+        let done = Rc::new(Cell::new(false));
+        let (in_flight2, max2, done2) = (in_flight.clone(), max.clone(), done.clone());
+        let mut fut = StreamExt::<dyn Spawn>::for_each_concurrent(iter(0..6), Some(2), move |_| {
+            tracked(in_flight2.clone(), max2.clone())
+        });
+        pool.spawner().spawn_local(poll_fn(move |cx: &mut Context| {
+            let ret = PinMut::new(&mut fut).poll(cx);
+            done2.set(ret.is_ready());
+            ret
+        })).unwrap();
+        pool.run();
+        assert!(done.get());
+        assert_eq!(max.get(), 2);
+        assert_eq!(in_flight.get(), 0);
+    }
+
+    #[test]
+    fn for_each_concurrent_zero_limit_is_unlimited() {
+        let count = Rc::new(Cell::new(0));
+        let count2 = count.clone();
+        let fut = StreamExt::<dyn Spawn>::for_each_concurrent(iter(0..3), Some(0), move |_| {
+            count2.set(count2.get() + 1);
+            ready(())
+        });
+        pin_mut!(fut);
+        assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready(()));
+        assert_eq!(count.get(), 3);
+    }
+
+    #[test]
+    fn for_each_concurrent_drains_after_stream_ends() {
+        let finished = Rc::new(Cell::new(0));
+        let finished2 = finished.clone();
+        let fut = StreamExt::<dyn Spawn>::for_each_concurrent(iter(0..3), None, move |_| {
+            let finished = finished2.clone();
+            let mut polled = false;
+            poll_fn(move |_: &mut Context| {
+                if polled {
+                    finished.set(finished.get() + 1);
+                    Poll::Ready(())
+                } else {
+                    polled = true;
+                    Poll::Pending
+                }
+            })
+        });
+        pin_mut!(fut);
+        // The stream is exhausted on the first poll, but its items are not.
+        assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Pending);
+        assert_eq!(finished.get(), 0);
+        assert_eq!(with_noop_context(|cx| fut.reborrow().poll(cx)), Poll::Ready(()));
+        assert_eq!(finished.get(), 3);
+        assert!(fut.is_terminated());
+    }
+
+    #[test]
+    fn for_each_spawned_on_thread_pool() {
+        let mut pool = ThreadPool::builder().pool_size(2).create().unwrap();
+        let count = Arc::new(AtomicUsize::new(0));
+        let count2 = count.clone();
+        let (tx, rx) = mpsc_std::channel();
+        let mut fut = StreamExt::<dyn Spawn>::for_each_spawned(iter(0..8), move |_| {
+            let count = count2.clone();
+            poll_fn(move |_: &mut Context| {
+                count.fetch_add(1, Ordering::SeqCst);
+                Poll::Ready(())
+            })
+        });
+        pool.spawn(poll_fn(move |cx: &mut Context| {
+            let ret = PinMut::new(&mut fut).poll(cx);
+            match ret {
+                Poll::Ready(res) => {
+                    tx.send(res).unwrap();
+                    Poll::Ready(())
+                }
+                Poll::Pending => Poll::Pending,
+            }
+        })).unwrap();
+        assert!(rx.recv_timeout(Duration::from_secs(10)).unwrap().is_ok());
+        assert_eq!(count.load(Ordering::SeqCst), 8);
+    }
+
+    type Receivers = Vec<oneshot::Receiver<i32>>;
+
+    fn receivers(n: usize) -> (Vec<oneshot::Sender<i32>>, Receivers) {
+        (0..n).map(|_| oneshot::channel()).unzip()
+    }
+
+    fn poll_buffer<St: Stream>(stream: PinMut<St>) -> Poll<Option<St::Item>> {
+        with_noop_context(|cx| stream.poll_next(cx))
+    }
+
+    #[test]
+    fn buffered_yields_in_input_order() {
+        let (mut senders, rxs) = receivers(3);
+        let stream = StreamExt::<dyn Spawn>::buffered(iter(rxs), 3);
+        pin_mut!(stream);
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Pending);
+        senders.pop().unwrap().send(2).unwrap();
+        senders.pop().unwrap().send(1).unwrap();
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Pending);
+        senders.pop().unwrap().send(0).unwrap();
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Ready(Some(Ok(0))));
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Ready(Some(Ok(1))));
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Ready(Some(Ok(2))));
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Ready(None));
+    }
+
+    #[test]
+    fn buffer_unordered_yields_in_completion_order() {
+        let (mut senders, rxs) = receivers(3);
+        let stream = StreamExt::<dyn Spawn>::buffer_unordered(iter(rxs), 3);
+        pin_mut!(stream);
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Pending);
+        senders.pop().unwrap().send(2).unwrap();
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Ready(Some(Ok(2))));
+        senders.pop().unwrap().send(1).unwrap();
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Ready(Some(Ok(1))));
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Pending);
+        senders.pop().unwrap().send(0).unwrap();
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Ready(Some(Ok(0))));
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Ready(None));
+    }
+
+    /// Checks that a buffer of one future doesn't pull the next one from
+    /// the source, whose pulls are counted by `pulled`, until the first is
+    /// done.
+    fn check_sequential<St>(stream: PinMut<St>, mut senders: Vec<oneshot::Sender<i32>>, pulled: &Cell<usize>)
+        where St: Stream<Item = Result<i32, oneshot::Canceled>>
+    {
+        let mut stream = stream;
+        // The second future is already complete, but it isn't pulled from
+        // the source while the first is in flight.
+        senders.pop().unwrap().send(1).unwrap();
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Pending);
+        assert_eq!(pulled.get(), 1);
+        senders.pop().unwrap().send(0).unwrap();
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Ready(Some(Ok(0))));
+        assert_eq!(poll_buffer(stream.reborrow()), Poll::Ready(Some(Ok(1))));
+        assert_eq!(pulled.get(), 2);
+    }
+
+    #[test]
+    fn buffer_of_one_is_sequential() {
+        let (senders, rxs) = receivers(2);
+        let pulled = Rc::new(Cell::new(0));
+        let pulled2 = pulled.clone();
+        let source = StreamExt::<dyn Spawn>::map(iter(rxs), move |rx| {
+            pulled2.set(pulled2.get() + 1);
+            rx
+        });
+        let stream = StreamExt::<dyn Spawn>::buffered(source, 1);
+        pin_mut!(stream);
+        check_sequential(stream, senders, &pulled);
+
+        let (senders, rxs) = receivers(2);
+        let pulled = Rc::new(Cell::new(0));
+        let pulled2 = pulled.clone();
+        let source = StreamExt::<dyn Spawn>::map(iter(rxs), move |rx| {
+            pulled2.set(pulled2.get() + 1);
+            rx
+        });
+        let stream = StreamExt::<dyn Spawn>::buffer_unordered(source, 1);
+        pin_mut!(stream);
+        check_sequential(stream, senders, &pulled);
+    }
+
+    #[test]
+    fn dropping_buffer_drops_in_flight_futures() {
+        let (senders, rxs) = receivers(3);
+        {
+            let stream = StreamExt::<dyn Spawn>::buffered(iter(rxs), 2);
+            pin_mut!(stream);
+            assert_eq!(poll_buffer(stream.reborrow()), Poll::Pending);
+        }
+        assert!(senders.iter().all(|tx| tx.is_canceled()));
+
+        let (senders, rxs) = receivers(3);
+        {
+            let stream = StreamExt::<dyn Spawn>::buffer_unordered(iter(rxs), 2);
+            pin_mut!(stream);
+            assert_eq!(poll_buffer(stream.reborrow()), Poll::Pending);
+        }
+        assert!(senders.iter().all(|tx| tx.is_canceled()));
+    }
+
+    #[test]
+    #[should_panic(expected = "buffered requires a buffer size of at least 1")]
+    fn buffered_zero() {
+        let _ = StreamExt::<dyn Spawn>::buffered(iter(Vec::<oneshot::Receiver<i32>>::new()), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer_unordered requires a buffer size of at least 1")]
+    fn buffer_unordered_zero() {
+        let _ = StreamExt::<dyn Spawn>::buffer_unordered(iter(Vec::<oneshot::Receiver<i32>>::new()), 0);
+    }
+
+    type Task = LocalFutureObj<'static, usize, dyn Spawn>;
+
+    fn poll_set(set: &mut FuturesUnordered<Task>) -> Poll<Option<usize>> {
+        poll_buffer(PinMut::new(set))
+    }
+
+    #[test]
+    fn futures_unordered_empty() {
+        let mut set = FuturesUnordered::<Task>::new();
+        assert!(set.is_empty());
+        assert!(!set.is_terminated());
+        assert_eq!(poll_set(&mut set), Poll::Ready(None));
+        assert!(set.is_terminated());
+
+        set.push(LocalFutureObj::new(Box::new(ready(1))));
+        assert!(!set.is_terminated());
+        assert_eq!(set.len(), 1);
+        assert_eq!(poll_set(&mut set), Poll::Ready(Some(1)));
+        assert_eq!(poll_set(&mut set), Poll::Ready(None));
+    }
+
+    #[test]
+    fn futures_unordered_terminates_only_once_drained() {
+        let (tx, mut rx) = oneshot::channel();
+        let mut set = FuturesUnordered::<Task>::new();
+        set.push(LocalFutureObj::new(Box::new(poll_fn(move |cx: &mut Context| {
+            PinMut::new(&mut rx).poll(cx).map(Result::unwrap)
+        }))));
+        assert_eq!(poll_set(&mut set), Poll::Pending);
+        assert!(!set.is_terminated());
+        tx.send(4).unwrap();
+        assert_eq!(poll_set(&mut set), Poll::Ready(Some(4)));
+        // Empty, but the `None` which ends it hasn't been yielded yet.
+        assert!(set.is_empty());
+        assert!(!set.is_terminated());
+        assert_eq!(poll_set(&mut set), Poll::Ready(None));
+        assert!(set.is_terminated());
+    }
+
+    #[test]
+    fn futures_unordered_work_is_linear() {
+        const N: usize = 2000;
+        let polls = Rc::new(Cell::new(0));
+        let wakers = Rc::new(RefCell::new(Vec::new()));
+        let mut set = FuturesUnordered::<Task>::new();
+        for i in 0..N {
+            let (polls, wakers) = (polls.clone(), wakers.clone());
+            let mut parked = false;
+            set.push(LocalFutureObj::new(Box::new(poll_fn(move |cx: &mut Context| {
+                polls.set(polls.get() + 1);
+                if parked {
+                    return Poll::Ready(i);
+                }
+                parked = true;
+                wakers.borrow_mut().push(cx.waker().clone());
+                Poll::Pending
+            }))));
+        }
+        assert_eq!(poll_set(&mut set), Poll::Pending);
+        assert_eq!(polls.get(), N);
+
+        // Wake the children in a scrambled order.
+        let mut wakers = mem::replace(&mut *wakers.borrow_mut(), Vec::new());
+        let mut seed = 12345usize;
+        while !wakers.is_empty() {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let index = (seed >> 8) % wakers.len();
+            wakers.swap_remove(index).wake();
+        }
+
+        let mut outputs = Vec::new();
+        while let Poll::Ready(Some(output)) = poll_set(&mut set) {
+            outputs.push(output);
+        }
+        outputs.sort();
+        assert_eq!(outputs, (0..N).collect::<Vec<_>>());
+        assert_eq!(polls.get(), 2 * N);
+    }
+
+    #[test]
+    fn futures_unordered_push_while_polling() {
+        let mut set = FuturesUnordered::<Task>::new();
+        set.push(LocalFutureObj::new(Box::new(ready(0))));
+        let mut outputs = Vec::new();
+        while let Poll::Ready(Some(output)) = poll_set(&mut set) {
+            outputs.push(output);
+            if output < 5 {
+                set.push(LocalFutureObj::new(Box::new(ready(output + 1))));
+            }
+        }
+        assert_eq!(outputs, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn futures_unordered_ignores_stale_wakers() {
+        let mut set = FuturesUnordered::<Task>::new();
+        let stale = Rc::new(RefCell::new(None));
+        let stale2 = stale.clone();
+        set.push(LocalFutureObj::new(Box::new(poll_fn(move |cx: &mut Context| {
+            *stale2.borrow_mut() = Some(cx.waker().clone());
+            Poll::Ready(0)
+        }))));
+        assert_eq!(poll_set(&mut set), Poll::Ready(Some(0)));
+
+        // This child reuses the slot of the completed one.
+        let polls = Rc::new(Cell::new(0));
+        let polls2 = polls.clone();
+        set.push(LocalFutureObj::new(Box::new(poll_fn(move |_: &mut Context| {
+            polls2.set(polls2.get() + 1);
+            Poll::Pending
+        }))));
+        assert_eq!(poll_set(&mut set), Poll::Pending);
+        assert_eq!(polls.get(), 1);
+
+        stale.borrow_mut().take().unwrap().wake();
+        assert_eq!(poll_set(&mut set), Poll::Pending);
+        assert_eq!(polls.get(), 1);
+    }
+
+    #[test]
+    fn futures_unordered_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<FuturesUnordered<::specialized_futures::future::Ready<i32>>>();
+    }
+
+    type Ordered = FuturesOrdered<oneshot::Receiver<i32>>;
+
+    fn drain_ready(set: &mut Ordered) -> Vec<i32> {
+        let mut outputs = Vec::new();
+        while let Poll::Ready(Some(output)) = poll_buffer(PinMut::new(set)) {
+            outputs.push(output.unwrap());
+        }
+        outputs
+    }
+
+    #[test]
+    fn futures_ordered_head_of_line() {
+        let (mut senders, rxs) = receivers(50);
+        let mut set = Ordered::new();
+        for rx in rxs {
+            set.push(rx);
+        }
+        // Every successor completes before the head.
+        let head = senders.remove(0);
+        for (i, tx) in senders.into_iter().enumerate() {
+            tx.send(i as i32 + 1).unwrap();
+        }
+        assert_eq!(poll_buffer(PinMut::new(&mut set)), Poll::Pending);
+        assert_eq!(set.len(), 50);
+        head.send(0).unwrap();
+        assert_eq!(drain_ready(&mut set), (0..50).collect::<Vec<_>>());
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn futures_ordered_scrambled_completion() {
+        for seed in 0..8usize {
+            let (senders, rxs) = receivers(20);
+            let mut set = Ordered::new();
+            for rx in rxs {
+                set.push(rx);
+            }
+            let mut senders: Vec<_> = senders.into_iter().enumerate().collect();
+            let mut seed = seed;
+            let mut outputs = Vec::new();
+            while !senders.is_empty() {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                let index = (seed >> 8) % senders.len();
+                let (i, tx) = senders.swap_remove(index);
+                tx.send(i as i32).unwrap();
+                outputs.extend(drain_ready(&mut set));
+            }
+            assert_eq!(outputs, (0..20).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn futures_ordered_push_while_draining() {
+        let (mut senders, rxs) = receivers(4);
+        let mut set = Ordered::new();
+        let mut rxs = rxs.into_iter();
+        set.push(rxs.next().unwrap());
+        set.push(rxs.next().unwrap());
+        senders.remove(0).send(0).unwrap();
+        assert_eq!(poll_buffer(PinMut::new(&mut set)), Poll::Ready(Some(Ok(0))));
+
+        // Pushed behind the still-pending second future.
+        set.push(rxs.next().unwrap());
+        set.push(rxs.next().unwrap());
+        senders.pop().unwrap().send(3).unwrap();
+        senders.pop().unwrap().send(2).unwrap();
+        assert_eq!(poll_buffer(PinMut::new(&mut set)), Poll::Pending);
+        senders.pop().unwrap().send(1).unwrap();
+        assert_eq!(drain_ready(&mut set), vec![1, 2, 3]);
+        assert_eq!(poll_buffer(PinMut::new(&mut set)), Poll::Ready(None));
+    }
+
+    #[test]
+    fn select_is_fair_on_local_pool() {
+        let mut pool = LocalPool::new();
+        let mut stream = StreamExt::<LocalSpawner>::select(
+            iter(::std::iter::repeat('a')),
+            iter(::std::iter::repeat('b')),
+        );
+        // Both sides are always ready, yet each gets every other turn.
+        let items = pool.run_until(poll_fn(move |cx: &mut Context<LocalSpawner>| {
+            let mut items = Vec::new();
+            while items.len() < 10 {
+                match PinMut::new(&mut stream).poll_next(cx) {
+                    Poll::Ready(Some(item)) => items.push(item),
+                    _ => panic!("both sides are endless and always ready"),
+                }
+            }
+            Poll::Ready(items)
+        }));
+        assert_eq!(items.iter().collect::<String>(), "ababababab");
+    }
+
+    /// A loopback object: items sent into it come back out of its stream,
+    /// which ends once it is closed.
+    #[derive(Debug, Default)]
+    struct Pipe {
+        queue: VecDeque<i32>,
+        closed: bool,
+        reader: Option<Waker>,
+        reject: Option<i32>,
+    }
+
+    impl Pipe {
+        fn wake_reader(&mut self) {
+            if let Some(waker) = self.reader.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    impl<S: Spawn + ?Sized> Stream<S> for Pipe {
+        type Item = i32;
+
+        fn poll_next(mut self: PinMut<Self>, cx: &mut Context<S>) -> Poll<Option<i32>> {
+            match self.queue.pop_front() {
+                Some(item) => Poll::Ready(Some(item)),
+                None if self.closed => Poll::Ready(None),
+                None => {
+                    self.reader = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    impl<S: Spawn + ?Sized> Sink<i32, S> for Pipe {
+        type Error = i32;
+
+        fn poll_ready(self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<Result<(), i32>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(mut self: PinMut<Self>, item: i32) -> Result<(), i32> {
+            if self.reject == Some(item) {
+                return Err(item);
+            }
+            self.queue.push_back(item);
+            self.wake_reader();
+            Ok(())
+        }
+
+        fn poll_flush(self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<Result<(), i32>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(mut self: PinMut<Self>, _cx: &mut Context<S>) -> Poll<Result<(), i32>> {
+            self.closed = true;
+            self.wake_reader();
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    type Reader = Rc<RefCell<Option<(SplitStream<Pipe>, Vec<i32>)>>>;
+
+    /// Spawn a task reading `stream` to its end, which hands the stream
+    /// back along with what it read.
+    fn spawn_reader(pool: &LocalPool, stream: SplitStream<Pipe>) -> Reader {
+        let out = Rc::new(RefCell::new(None));
+        let out2 = out.clone();
+        let mut items = Vec::new();
+        let mut stream = Some(stream);
+        pool.spawner().spawn_local(poll_fn(move |cx: &mut Context| {
+            loop {
+                match PinMut::new(stream.as_mut().unwrap()).poll_next(cx) {
+                    Poll::Ready(Some(item)) => items.push(item),
+                    Poll::Ready(None) => {
+                        let done = (stream.take().unwrap(), mem::replace(&mut items, Vec::new()));
+                        *out2.borrow_mut() = Some(done);
+                        return Poll::Ready(());
+                    }
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        })).unwrap();
+        out
+    }
+
+    #[test]
+    fn forward_through_split_halves_then_reunite() {
+        let mut pool = LocalPool::new();
+        let (mut sink, stream): (SplitSink<Pipe, i32>, _) = StreamExt::<dyn Spawn>::split(Pipe::default());
+        let reader = spawn_reader(&pool, stream);
+        let forwarded = pool.run_until(StreamExt::<LocalSpawner>::forward(iter(1..4), &mut sink));
+        assert_eq!(forwarded, Ok(()));
+        pool.run();
+        let (stream, items) = reader.borrow_mut().take().unwrap();
+        assert_eq!(items, vec![1, 2, 3]);
+        let pipe = sink.reunite(stream).unwrap();
+        assert!(pipe.closed);
+        assert!(pipe.queue.is_empty());
+    }
+
+    #[test]
+    fn forward_stops_at_an_error_from_the_sink_half() {
+        let mut pool = LocalPool::new();
+        let pipe = Pipe { reject: Some(2), ..Pipe::default() };
+        let (mut sink, mut stream): (SplitSink<Pipe, i32>, _) = StreamExt::<dyn Spawn>::split(pipe);
+        let forwarded = pool.run_until(StreamExt::<LocalSpawner>::forward(iter(1..4), &mut sink));
+        assert_eq!(forwarded, Err(2));
+        // Only the item before the error went through, and the pipe wasn't
+        // closed, so the stream half is left waiting.
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut stream).poll_next(cx)), Poll::Ready(Some(1)));
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut stream).poll_next(cx)), Poll::Pending);
+        assert!(!stream.reunite(sink).unwrap().closed);
+    }
+
+    #[test]
+    fn reunite_rejects_halves_of_different_objects() {
+        let (sink, _): (SplitSink<Pipe, i32>, _) = StreamExt::<dyn Spawn>::split(Pipe::default());
+        let (_, stream): (SplitSink<Pipe, i32>, _) = StreamExt::<dyn Spawn>::split(Pipe::default());
+        assert!(sink.reunite(stream).is_err());
+    }
+
+    #[test]
+    fn next_loop_over_channel() {
+        let mut pool = LocalPool::new();
+        let (tx, rx) = mpsc::unbounded();
+        let mut sent = 0;
+        pool.spawner().spawn_local(poll_fn(move |cx: &mut Context| {
+            sent += 1;
+            tx.unbounded_send(sent).unwrap();
+            if sent == 3 {
+                return Poll::Ready(());
+            }
+            // Give the consumer a turn between items, so that it finds the
+            // channel empty.
+            cx.waker().wake();
+            Poll::Pending
+        })).unwrap();
+
+        // The spawner parameter flows through `map`, so the consumer is
+        // polled with the pool's own `LocalSpawner`.
+        let mut rx = StreamExt::<LocalSpawner>::map(rx, |x| x * 10);
+        let pendings = Rc::new(Cell::new(0));
+        let pendings2 = pendings.clone();
+        let mut received = Vec::new();
+        let items = pool.run_until(poll_fn(move |cx: &mut Context<LocalSpawner>| {
+            loop {
+                let ret = PinMut::new(&mut StreamExt::<LocalSpawner>::next(&mut rx)).poll(cx);
+                match ret {
+                    Poll::Ready(Some(item)) => received.push(item),
+                    Poll::Ready(None) => return Poll::Ready(mem::replace(&mut received, Vec::new())),
+                    Poll::Pending => {
+                        pendings2.set(pendings2.get() + 1);
+                        return Poll::Pending;
+                    }
+                }
+            }
+        }));
+        assert_eq!(items, vec![10, 20, 30]);
+        assert!(pendings.get() > 0);
+    }
+}
+
+#[test]
+fn stream_obj_from_mut_ref() {
+    let mut inner = iter(vec![1, 2]);
+    let obj: LocalStreamObj<i32, dyn Spawn> = LocalStreamObj::new(&mut inner);
+    pin_mut!(obj);
+    assert_eq!(with_noop_context(|cx| drain(obj.reborrow(), cx)), vec![1, 2]);
+}
+
+#[cfg(feature = "alloc")]
+mod obj {
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use specialized_futures::{Spawn, Stream};
+    use specialized_futures::spawn::NoSpawn;
+    use specialized_futures::stream::{LocalStreamObj, StreamObj, iter};
+    use specialized_futures::task::Poll;
+    use support::{with_counting_context, with_noop_context};
+    use super::drain;
+
+    #[test]
+    fn boxed_stream_round_trips() {
+        let obj: StreamObj<i32, dyn Spawn> = StreamObj::new(Box::new(iter(vec![1, 2, 3])));
+        pin_mut!(obj);
+        assert_eq!(with_noop_context(|cx| drain(obj.reborrow(), cx)), vec![1, 2, 3]);
+
+        let obj: LocalStreamObj<i32, NoSpawn> = LocalStreamObj::new(Box::new(iter(vec![4, 5])));
+        pin_mut!(obj);
+        let (_, items) = with_counting_context(&mut NoSpawn, |cx| drain(obj.reborrow(), cx));
+        assert_eq!(items, vec![4, 5]);
+    }
+
+    #[test]
+    fn local_obj_converts_from_send_obj() {
+        let obj: StreamObj<i32, dyn Spawn> = StreamObj::new(Box::new(iter(vec![7])));
+        let local: LocalStreamObj<i32, dyn Spawn> = obj.into();
+        pin_mut!(local);
+        assert_eq!(with_noop_context(|cx| drain(local.reborrow(), cx)), vec![7]);
+    }
+
+    /// Sets its flag when dropped.
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn dropping_unfinished_obj_runs_destructors() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let flags = vec![DropFlag(dropped.clone())];
+        let obj: StreamObj<DropFlag, dyn Spawn> = StreamObj::new(Box::new(iter(flags)));
+        drop(obj);
+        assert!(dropped.load(Ordering::SeqCst));
+
+        let items = Rc::new(());
+        let captured = items.clone();
+        {
+            let local: LocalStreamObj<(), dyn Spawn> = LocalStreamObj::new(Box::new(iter((0..3).map(move |_| {
+                let _ = &captured;
+            }))));
+            pin_mut!(local);
+            assert_eq!(with_noop_context(|cx| local.reborrow().poll_next(cx)), Poll::Ready(Some(())));
+            assert_eq!(Rc::strong_count(&items), 2);
+        }
+        assert_eq!(Rc::strong_count(&items), 1);
+    }
+}
+
+#[cfg(feature = "std")]
+mod chunks {
+    use std::mem::PinMut;
+    use specialized_futures::{Spawn, Stream, StreamExt};
+    use specialized_futures::channel::mpsc;
+    use specialized_futures::spawn::NoSpawn;
+    use specialized_futures::stream::{FusedStream, iter};
+    use specialized_futures::task::Poll;
+    use support::{with_counting_context, with_noop_context};
+    use super::drain;
+
+    #[test]
+    fn chunks_of_an_exact_multiple() {
+        let chunks = StreamExt::<dyn Spawn>::chunks(iter(1..=6), 3);
+        pin_mut!(chunks);
+        assert_eq!(with_noop_context(|cx| drain(chunks.reborrow(), cx)), vec![vec![1, 2, 3], vec![4, 5, 6]]);
+        assert!(chunks.is_terminated());
+        assert_eq!(with_noop_context(|cx| chunks.reborrow().poll_next(cx)), Poll::Ready(None));
+    }
+
+    #[test]
+    fn chunks_end_with_the_remainder() {
+        let chunks = StreamExt::<dyn Spawn>::chunks(iter(1..=7), 3);
+        pin_mut!(chunks);
+        assert_eq!(with_noop_context(|cx| drain(chunks.reborrow(), cx)), vec![vec![1, 2, 3], vec![4, 5, 6], vec![7]]);
+        assert!(chunks.is_terminated());
+    }
+
+    #[test]
+    fn chunks_wait_for_a_full_chunk() {
+        let (tx, rx) = mpsc::unbounded();
+        let mut chunks = StreamExt::<dyn Spawn>::chunks(rx, 4);
+        for i in 0..3 {
+            tx.unbounded_send(i).unwrap();
+        }
+        let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| PinMut::new(&mut chunks).poll_next(cx));
+        assert_eq!(ret, Poll::Pending);
+        tx.unbounded_send(3).unwrap();
+        assert_eq!(wakes.get(), 1);
+        assert_eq!(with_noop_context(|cx| PinMut::new(&mut chunks).poll_next(cx)), Poll::Ready(Some(vec![0, 1, 2, 3])));
+    }
+
+    #[test]
+    fn ready_chunks_take_each_burst() {
+        let (tx, rx) = mpsc::unbounded();
+        let mut chunks = StreamExt::<dyn Spawn>::ready_chunks(rx, 4);
+        let mut poll = || with_counting_context(&mut NoSpawn, |cx| PinMut::new(&mut chunks).poll_next(cx));
+
+        for burst in 0..3 {
+            // Nothing is ready, so the task is registered rather than handed
+            // an empty chunk.
+            let (wakes, ret) = poll();
+            assert_eq!(ret, Poll::Pending);
+            for i in 0..3 {
+                tx.unbounded_send(burst * 3 + i).unwrap();
+            }
+            assert!(wakes.get() >= 1);
+            let (_, ret) = poll();
+            assert_eq!(ret, Poll::Ready(Some((burst * 3..burst * 3 + 3).collect())));
+        }
+    }
+
+    #[test]
+    fn ready_chunks_are_capped() {
+        let (tx, rx) = mpsc::unbounded();
+        let chunks = StreamExt::<dyn Spawn>::ready_chunks(rx, 2);
+        pin_mut!(chunks);
+        for i in 0..3 {
+            tx.unbounded_send(i).unwrap();
+        }
+        assert_eq!(with_noop_context(|cx| chunks.reborrow().poll_next(cx)), Poll::Ready(Some(vec![0, 1])));
+        assert_eq!(with_noop_context(|cx| chunks.reborrow().poll_next(cx)), Poll::Ready(Some(vec![2])));
+        assert_eq!(with_noop_context(|cx| chunks.reborrow().poll_next(cx)), Poll::Pending);
+    }
+
+    #[test]
+    fn ready_chunks_terminate_after_the_source() {
+        let (tx, rx) = mpsc::unbounded();
+        let chunks = StreamExt::<dyn Spawn>::ready_chunks(rx, 4);
+        pin_mut!(chunks);
+        tx.unbounded_send(1).unwrap();
+        tx.unbounded_send(2).unwrap();
+        drop(tx);
+        // The items buffered before the end still come out first.
+        assert_eq!(with_noop_context(|cx| chunks.reborrow().poll_next(cx)), Poll::Ready(Some(vec![1, 2])));
+        assert!(chunks.is_terminated());
+        assert_eq!(with_noop_context(|cx| chunks.reborrow().poll_next(cx)), Poll::Ready(None));
+        assert_eq!(with_noop_context(|cx| chunks.reborrow().poll_next(cx)), Poll::Ready(None));
+    }
+
+    #[test]
+    #[should_panic(expected = "chunks requires a chunk size of at least 1")]
+    fn chunks_zero() {
+        let _ = StreamExt::<dyn Spawn>::chunks(iter(0..1), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "ready_chunks requires a chunk size of at least 1")]
+    fn ready_chunks_zero() {
+        let _ = StreamExt::<dyn Spawn>::ready_chunks(iter(0..1), 0);
+    }
+}
+
+#[cfg(feature = "std")]
+mod zip {
+    use std::marker::Unpin;
+    use std::mem::PinMut;
+    use std::sync::Arc;
+    use specialized_futures::{Stream, StreamExt};
+    use specialized_futures::channel::mpsc;
+    use specialized_futures::spawn::NoSpawn;
+    use specialized_futures::stream::FusedStream;
+    use specialized_futures::task::Poll;
+    use support::{WakeCounter, with_counting_context};
+
+    fn poll<St>(stream: &mut St) -> (Arc<WakeCounter>, Poll<Option<St::Item>>)
+        where St: Stream<NoSpawn> + Unpin
+    {
+        with_counting_context(&mut NoSpawn, |cx| PinMut::new(stream).poll_next(cx))
+    }
+
+    #[test]
+    fn zip_sides_ready_on_alternating_polls() {
+        let (tx1, rx1) = mpsc::unbounded();
+        let (tx2, rx2) = mpsc::unbounded();
+        let mut zip = StreamExt::<NoSpawn>::zip(rx1, rx2);
+
+        // Both sides register the task while neither is ready.
+        let (wakes, ret) = poll(&mut zip);
+        assert_eq!(ret, Poll::Pending);
+        tx1.unbounded_send(1).unwrap();
+        assert_eq!(wakes.get(), 1);
+        tx2.unbounded_send("a").unwrap();
+        assert_eq!(wakes.get(), 2);
+        assert_eq!(poll(&mut zip).1, Poll::Ready(Some((1, "a"))));
+
+        // Only one side becomes ready on each poll; its item is held until
+        // the other side catches up.
+        for i in 0..4 {
+            let (wakes, ret) = poll(&mut zip);
+            assert_eq!(ret, Poll::Pending);
+            if i % 2 == 0 {
+                tx1.unbounded_send(i).unwrap();
+            } else {
+                tx2.unbounded_send("b").unwrap();
+            }
+            assert_eq!(wakes.get(), 1);
+
+            let (wakes, ret) = poll(&mut zip);
+            assert_eq!(ret, Poll::Pending);
+            if i % 2 == 0 {
+                tx2.unbounded_send("b").unwrap();
+            } else {
+                tx1.unbounded_send(i).unwrap();
+            }
+            assert_eq!(wakes.get(), 1);
+            assert_eq!(poll(&mut zip).1, Poll::Ready(Some((i, "b"))));
+        }
+
+        drop(tx2);
+        assert_eq!(poll(&mut zip).1, Poll::Ready(None));
+        assert!(zip.is_terminated());
+        drop(tx1);
+    }
+}
+
+#[test]
+fn poll_next_unpin_on_plain_binding() {
+    let mut stream = iter(vec![1, 2]);
+    let mut next = || with_noop_context(|cx| stream.poll_next_unpin(cx));
+    assert_eq!(next(), Poll::Ready(Some(1)));
+    assert_eq!(next(), Poll::Ready(Some(2)));
+    assert_eq!(next(), Poll::Ready(None));
+}
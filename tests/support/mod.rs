@@ -0,0 +1,55 @@
+//! Helpers shared by the integration tests.
+//!
+//! Every test file declares `mod support;`, so not all of these are used by
+//! each of them.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Wake, local_waker_from_nonlocal};
+use specialized_futures::{Context, Spawn};
+use specialized_futures::spawn::NoSpawn;
+use specialized_futures::task::{Waker, noop_local_waker, noop_waker};
+
+/// Runs `f` with a context whose wakers do nothing and whose spawner
+/// rejects every task.
+pub fn with_noop_context<R, F>(f: F) -> R
+    where F: FnOnce(&mut Context) -> R
+{
+    let (lw, w) = (noop_local_waker(), noop_waker());
+    let mut spawner = NoSpawn;
+    let mut cx = Context::new(&lw, &w, &mut spawner as &mut dyn Spawn);
+    f(&mut cx)
+}
+
+/// A waker which counts how many times it has been woken.
+#[derive(Default)]
+pub struct WakeCounter {
+    wakes: AtomicUsize,
+}
+
+impl WakeCounter {
+    pub fn get(&self) -> usize {
+        self.wakes.load(Ordering::SeqCst)
+    }
+}
+
+impl Wake for WakeCounter {
+    fn wake(arc_self: &Arc<Self>) {
+        arc_self.wakes.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Runs `f` with a context whose wakers count their wakeups in the returned
+/// counter, and with the given spawner.
+pub fn with_counting_context<Sp, R, F>(spawner: &mut Sp, f: F) -> (Arc<WakeCounter>, R)
+    where Sp: Spawn + ?Sized,
+          F: FnOnce(&mut Context<Sp>) -> R
+{
+    let counter = Arc::new(WakeCounter::default());
+    let lw = local_waker_from_nonlocal(counter.clone());
+    let w = Waker::from(counter.clone());
+    let mut cx = Context::new(&lw, &w, spawner);
+    let ret = f(&mut cx);
+    (counter, ret)
+}
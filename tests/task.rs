@@ -0,0 +1,241 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+
+extern crate specialized_futures;
+
+mod support;
+
+use std::mem::PinMut;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::task::local_waker_from_nonlocal;
+use std::time::{Duration, Instant};
+use specialized_futures::{Context, Future, FutureObj, Spawn};
+use specialized_futures::future::{poll_fn, ready};
+use specialized_futures::spawn::{NoSpawn, SpawnErrorKind, SpawnObjError};
+use specialized_futures::task::{AtomicWaker, LocalWaker, Poll, Waker};
+
+use support::WakeCounter;
+
+fn counting_waker() -> (Arc<WakeCounter>, Waker) {
+    let counter = Arc::new(WakeCounter::default());
+    let waker = Waker::from(counter.clone());
+    (counter, waker)
+}
+
+#[test]
+fn atomic_waker_wake_before_register_is_noop() {
+    let cell = AtomicWaker::new();
+    cell.wake();
+    let (counter, waker) = counting_waker();
+    cell.register(&waker);
+    assert_eq!(counter.get(), 0);
+    cell.wake();
+    assert_eq!(counter.get(), 1);
+    // The waker is consumed by the first wake.
+    cell.wake();
+    assert_eq!(counter.get(), 1);
+}
+
+#[test]
+fn atomic_waker_register_replaces_waker() {
+    let cell = AtomicWaker::new();
+    let (old, old_waker) = counting_waker();
+    let (new, new_waker) = counting_waker();
+    cell.register(&old_waker);
+    cell.register(&new_waker);
+    cell.wake();
+    assert_eq!((old.get(), new.get()), (0, 1));
+}
+
+#[test]
+fn atomic_waker_take() {
+    let cell = AtomicWaker::new();
+    assert!(cell.take().is_none());
+    let (counter, waker) = counting_waker();
+    cell.register(&waker);
+    cell.take().unwrap().wake();
+    assert_eq!(counter.get(), 1);
+    assert!(cell.take().is_none());
+}
+
+#[test]
+fn atomic_waker_never_loses_wakeups() {
+    const ROUNDS: usize = 10_000;
+    let cell = Arc::new(AtomicWaker::new());
+    let produced = Arc::new(AtomicUsize::new(0));
+    let consumed = Arc::new(AtomicUsize::new(0));
+
+    let producer = {
+        let (cell, produced, consumed) = (cell.clone(), produced.clone(), consumed.clone());
+        thread::spawn(move || {
+            for i in 1..=ROUNDS {
+                while consumed.load(Ordering::SeqCst) != i - 1 {
+                    thread::yield_now();
+                }
+                produced.store(i, Ordering::SeqCst);
+                cell.wake();
+            }
+        })
+    };
+
+    let (counter, waker) = counting_waker();
+    for i in 1..=ROUNDS {
+        // Register before checking, as a consumer would; if the value isn't
+        // there yet, the producer's wake must reach us.
+        loop {
+            let wakes = counter.get();
+            cell.register(&waker);
+            if produced.load(Ordering::SeqCst) == i {
+                break;
+            }
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while counter.get() == wakes {
+                assert!(Instant::now() < deadline, "wakeup lost in round {}", i);
+                thread::yield_now();
+            }
+        }
+        consumed.store(i, Ordering::SeqCst);
+    }
+    producer.join().unwrap();
+}
+
+#[test]
+fn atomic_waker_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<AtomicWaker>();
+}
+
+/// A local waker counting into its own counter, separate from the `Waker`'s.
+fn counting_local_waker() -> (Arc<WakeCounter>, LocalWaker) {
+    let counter = Arc::new(WakeCounter::default());
+    let local_waker = local_waker_from_nonlocal(counter.clone());
+    (counter, local_waker)
+}
+
+#[test]
+fn context_waker_wakes_from_another_thread() {
+    let (local_count, lw) = counting_local_waker();
+    let (count, w) = counting_waker();
+    let mut spawner = NoSpawn;
+    let waker = Context::new(&lw, &w, &mut spawner).waker().clone();
+    thread::spawn(move || waker.wake()).join().unwrap();
+    assert_eq!((count.get(), local_count.get()), (1, 0));
+}
+
+#[test]
+fn context_local_waker_wakes_on_this_thread() {
+    let (local_count, lw) = counting_local_waker();
+    let (count, w) = counting_waker();
+    let mut spawner = NoSpawn;
+    let cx = Context::new(&lw, &w, &mut spawner);
+    cx.local_waker().wake();
+    cx.local_waker().clone().wake();
+    assert_eq!((local_count.get(), count.get()), (2, 0));
+}
+
+#[test]
+fn context_with_waker_replaces_both_wakers() {
+    let (_, lw) = counting_local_waker();
+    let (_, w) = counting_waker();
+    let (local_count, new_lw) = counting_local_waker();
+    let (count, new_w) = counting_waker();
+    let mut spawner = NoSpawn;
+    let mut cx = Context::new(&lw, &w, &mut spawner);
+    {
+        let cx = cx.with_waker(&new_lw, &new_w);
+        cx.local_waker().wake();
+        cx.waker().wake();
+    }
+    // `with_spawner` keeps whichever wakers the context it came from has.
+    let mut other = NoSpawn;
+    let mut inner = cx.with_waker(&new_lw, &new_w);
+    let inner = inner.with_spawner(&mut other);
+    inner.local_waker().wake();
+    inner.waker().wake();
+    assert_eq!((local_count.get(), count.get()), (2, 2));
+}
+
+/// A spawner which counts how often it is reached through a context.
+#[derive(Default)]
+struct Counting {
+    uses: usize,
+}
+
+impl Spawn for Counting {
+    fn spawn_obj(
+        &mut self,
+        future: FutureObj<'static, (), dyn Spawn>,
+    ) -> Result<(), SpawnObjError<FutureObj<'static, (), dyn Spawn>>> {
+        Err(SpawnObjError { kind: SpawnErrorKind::shutdown(), future })
+    }
+}
+
+/// Takes a context by value, as helpers which build their own contexts do.
+fn wake_and_use_spawner(mut cx: Context<Counting>) {
+    cx.waker().wake();
+    cx.local_waker().wake();
+    cx.spawner().uses += 1;
+}
+
+#[test]
+fn context_by_ref_keeps_wakers_and_spawner() {
+    let (local_count, lw) = counting_local_waker();
+    let (count, w) = counting_waker();
+    let mut spawner = Counting::default();
+    {
+        let mut cx = Context::new(&lw, &w, &mut spawner);
+        // The original is usable again after each reborrow is consumed.
+        wake_and_use_spawner(cx.by_ref());
+        wake_and_use_spawner(cx.by_ref());
+        cx.waker().wake();
+    }
+    assert_eq!((local_count.get(), count.get(), spawner.uses), (2, 3, 2));
+}
+
+#[test]
+fn context_by_ref_polls_children_in_a_loop() {
+    let (_, lw) = counting_local_waker();
+    let (count, w) = counting_waker();
+    let mut spawner = NoSpawn;
+    let mut cx = Context::new(&lw, &w, &mut spawner);
+    // Wakers taken before a reborrow outlive it, since they borrow for the
+    // context's own lifetime rather than from `cx`.
+    let waker = cx.waker();
+    let mut children = vec![ready(1), ready(2), ready(3)];
+    let mut sum = 0;
+    for child in &mut children {
+        if let Poll::Ready(n) = PinMut::new(child).poll(&mut cx.by_ref()) {
+            sum += n;
+        }
+        waker.wake();
+    }
+    let mut child = poll_fn(|cx: &mut Context<NoSpawn>| {
+        cx.waker().wake();
+        Poll::Ready(())
+    });
+    let _ = PinMut::new(&mut child).poll(&mut cx.by_ref());
+    assert_eq!((sum, count.get()), (6, 4));
+}
+
+#[test]
+fn context_by_ref_chains_into_adapters() {
+    let (_, lw) = counting_local_waker();
+    let (_, w) = counting_waker();
+    let (local_count, new_lw) = counting_local_waker();
+    let (count, new_w) = counting_waker();
+    let mut spawner = Counting::default();
+    let mut other = Counting::default();
+    {
+        let mut cx = Context::new(&lw, &w, &mut spawner);
+        {
+            let mut reborrow = cx.by_ref();
+            let mut waking = reborrow.with_waker(&new_lw, &new_w);
+            wake_and_use_spawner(waking.with_spawner(&mut other));
+        }
+        // The adapters leave the original's spawner in place.
+        cx.spawner().uses += 10;
+    }
+    assert_eq!((local_count.get(), count.get()), (1, 1));
+    assert_eq!((spawner.uses, other.uses), (10, 1));
+}
@@ -0,0 +1,183 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+#![cfg(feature = "test-util")]
+
+#[macro_use]
+extern crate specialized_futures;
+
+use std::cell::Cell;
+use std::marker::Unpin;
+use std::mem::PinMut;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use specialized_futures::{Context, Future, FutureExt, Spawn, SpawnExt};
+use specialized_futures::future::{FusedFuture, Ready, pending, poll_fn, ready};
+use specialized_futures::spawn::NoSpawn;
+use specialized_futures::task::Poll;
+use specialized_futures::test::{PollGuard, RecordingSpawner, TestContext};
+
+#[test]
+fn assert_ready_returns_value() {
+    let mut tcx = TestContext::new();
+    let mut fut = ready(vec![1, 2]);
+    let v = assert_ready!(PinMut::new(&mut fut).poll(&mut tcx.context()));
+    assert_eq!(v, vec![1, 2]);
+}
+
+#[test]
+#[should_panic(expected = "assertion failed: `Poll::Pending::<i32>` is ready, but it was `Pending`")]
+fn assert_ready_fails_on_pending() {
+    assert_ready!(Poll::Pending::<i32>);
+}
+
+#[test]
+#[should_panic(expected = "assertion failed: `Poll::Ready(1)` is ready with `2`, but it was `Ready(1)`")]
+fn assert_ready_eq_fails_on_other_value() {
+    assert_ready_eq!(Poll::Ready(1), 2);
+}
+
+#[test]
+#[should_panic(expected = "assertion failed: `Poll::Pending::<i32>` is ready with `2`, but it was `Pending`")]
+fn assert_ready_eq_fails_on_pending() {
+    assert_ready_eq!(Poll::Pending::<i32>, 2);
+}
+
+#[test]
+#[should_panic(expected = "assertion failed: `Poll::Ready(\"done\")` is pending, but it was `Ready(\"done\")`")]
+fn assert_pending_fails_on_ready() {
+    assert_pending!(Poll::Ready("done"));
+}
+
+#[test]
+fn race_on_test_context() {
+    let mut tcx = TestContext::new();
+    let mut fut = FutureExt::<dyn Spawn>::race(pending(), ready(6));
+    assert_ready_eq!(PinMut::new(&mut fut).poll(&mut tcx.context()), 6);
+    assert!(!tcx.woken());
+    assert_eq!(tcx.spawned(), 0);
+}
+
+#[test]
+fn join_on_test_context() {
+    let mut tcx = TestContext::new();
+    let mut yielded = false;
+    let yield_once = poll_fn(move |cx: &mut Context<RecordingSpawner>| {
+        if yielded {
+            return Poll::Ready('y');
+        }
+        yielded = true;
+        cx.waker().wake();
+        Poll::Pending
+    });
+    let fut = join!(yield_once, ready(1));
+    pin_mut!(fut);
+    assert_pending!(fut.reborrow().poll(&mut tcx.specialized_context()));
+    assert!(tcx.woken());
+    assert_eq!(tcx.flag().reset(), 1);
+    assert_ready_eq!(fut.reborrow().poll(&mut tcx.specialized_context()), ('y', 1));
+    assert!(!tcx.woken());
+}
+
+#[test]
+fn records_spawned_tasks() {
+    let mut tcx = TestContext::new();
+    let mut fut = poll_fn(|cx: &mut Context<RecordingSpawner>| {
+        cx.spawner().spawn(ready(())).unwrap();
+        cx.spawner().spawn(pending()).unwrap();
+        Poll::Ready(())
+    });
+    assert_ready!(PinMut::new(&mut fut).poll(&mut tcx.specialized_context()));
+    assert_eq!(tcx.spawned(), 2);
+    assert_eq!(tcx.spawner().take_spawned().len(), 2);
+    assert_eq!(tcx.spawned(), 0);
+}
+
+#[test]
+fn substituted_spawner() {
+    let mut tcx = TestContext::with_spawner(NoSpawn);
+    let mut fut = poll_fn(|cx: &mut Context<NoSpawn>| {
+        Poll::Ready(cx.spawner().spawn(ready(())).is_err())
+    });
+    assert_ready_eq!(PinMut::new(&mut fut).poll(&mut tcx.specialized_context()), true);
+}
+
+#[test]
+fn poll_guard_is_transparent() {
+    let mut tcx = TestContext::new();
+    let mut yielded = false;
+    let yield_once = poll_fn(move |cx: &mut Context<RecordingSpawner>| {
+        if yielded {
+            return Poll::Ready(7);
+        }
+        yielded = true;
+        cx.waker().wake();
+        Poll::Pending
+    });
+    let mut guard = FutureExt::<RecordingSpawner>::guard_polls(yield_once);
+    assert_pending!(PinMut::new(&mut guard).poll(&mut tcx.specialized_context()));
+    assert!(tcx.woken());
+    assert!(!guard.is_terminated());
+    assert_ready_eq!(PinMut::new(&mut guard).poll(&mut tcx.specialized_context()), 7);
+    assert_eq!(guard.polls(), 2);
+    assert!(guard.is_terminated());
+}
+
+#[test]
+#[should_panic(expected = "Ready<i32>` polled after completion (after 1 polls)")]
+fn poll_guard_panics_when_polled_after_completion() {
+    let mut tcx = TestContext::new();
+    let mut guard = FutureExt::<dyn Spawn>::guard_polls(ready(1));
+    assert_ready_eq!(PinMut::new(&mut guard).poll(&mut tcx.context()), 1);
+    let _ = PinMut::new(&mut guard).poll(&mut tcx.context());
+}
+
+/// A deliberately buggy future which polls the guard wrapping it again from
+/// inside its own `poll`.
+struct Reenter {
+    guard: Rc<Cell<*mut PollGuard<Reenter>>>,
+}
+
+impl<S: Spawn + ?Sized> Future<S> for Reenter {
+    type Output = ();
+
+    fn poll(self: PinMut<Self>, cx: &mut Context<S>) -> Poll<()> {
+        let guard = self.guard.get();
+        unsafe { PinMut::new_unchecked(&mut *guard) }.poll(cx)
+    }
+}
+
+#[test]
+#[should_panic(expected = "Reenter` polled re-entrantly while it was already being polled (after 1 polls)")]
+fn poll_guard_panics_on_reentrant_poll() {
+    let mut tcx = TestContext::new();
+    let slot = Rc::new(Cell::new(ptr::null_mut()));
+    let mut guard = FutureExt::<dyn Spawn>::guard_polls(Reenter { guard: slot.clone() });
+    slot.set(&mut guard as *mut _);
+    let _ = PinMut::new(&mut guard).poll(&mut tcx.context());
+}
+
+static UNPOLLED_DROPS: AtomicUsize = AtomicUsize::new(0);
+
+fn count_unpolled_drop(name: &'static str) {
+    assert!(name.ends_with("Ready<i32>"), "unexpected type name {}", name);
+    UNPOLLED_DROPS.fetch_add(1, Ordering::SeqCst);
+}
+
+#[test]
+fn poll_guard_reports_unpolled_drop() {
+    let guard = FutureExt::<dyn Spawn>::guard_polls(ready(1)).on_unpolled_drop(count_unpolled_drop);
+    drop(guard);
+    assert_eq!(UNPOLLED_DROPS.load(Ordering::SeqCst), 1);
+
+    let mut tcx = TestContext::new();
+    let mut guard = FutureExt::<dyn Spawn>::guard_polls(ready(1)).on_unpolled_drop(count_unpolled_drop);
+    assert_ready!(PinMut::new(&mut guard).poll(&mut tcx.context()));
+    drop(guard);
+    assert_eq!(UNPOLLED_DROPS.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn poll_guard_propagates_auto_traits() {
+    fn assert_send_sync_unpin<T: Send + Sync + Unpin>() {}
+    assert_send_sync_unpin::<PollGuard<Ready<i32>>>();
+}
@@ -0,0 +1,363 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+#![cfg(feature = "std")]
+
+extern crate specialized_futures;
+
+mod support;
+
+use std::cell::RefCell;
+use std::mem::PinMut;
+use std::rc::Rc;
+use std::sync::{Arc, mpsc};
+use std::task::{Wake, local_waker_from_nonlocal};
+use std::time::{Duration, Instant};
+use specialized_futures::{Context, Future, FutureExt, FutureObj, LocalSpawnExt, Stream, TimerSpawn};
+use specialized_futures::executor::{LocalPool, LocalSpawner, ThreadPool, block_on};
+use specialized_futures::future::{pending, poll_fn, ready};
+use specialized_futures::spawn::NoSpawn;
+use specialized_futures::task::{Poll, Waker};
+use specialized_futures::timer::{Clock, DelayQueue, Expired, Interval, MockClock, Timer, TimerHandle, TimerSpawner, interval};
+
+use support::{WakeCounter, with_counting_context, with_noop_context};
+
+#[test]
+fn timeout_fires() {
+    let start = Instant::now();
+    let fut = FutureExt::<LocalSpawner>::timeout(pending::<()>(), Duration::from_millis(20));
+    assert!(block_on(fut).is_err());
+    assert!(start.elapsed() >= Duration::from_millis(20));
+}
+
+#[test]
+fn timeout_does_not_fire() {
+    let fut = FutureExt::<LocalSpawner>::timeout(ready(5), Duration::from_secs(60));
+    assert_eq!(block_on(fut), Ok(5));
+}
+
+#[test]
+fn many_sleeps_complete_in_order() {
+    let mut pool = LocalPool::new();
+    let mut spawner = pool.spawner();
+    let order = Rc::new(RefCell::new(Vec::new()));
+    for &ms in &[50, 10, 40, 20, 30] {
+        let mut sleep = spawner.sleep(Duration::from_millis(ms));
+        let order = order.clone();
+        spawner.spawn_local(poll_fn(move |cx: &mut Context| {
+            match PinMut::new(&mut sleep).poll(cx) {
+                Poll::Ready(()) => {
+                    order.borrow_mut().push(ms);
+                    Poll::Ready(())
+                }
+                Poll::Pending => Poll::Pending,
+            }
+        })).unwrap();
+    }
+    pool.run();
+    assert_eq!(*order.borrow(), vec![10, 20, 30, 40, 50]);
+}
+
+#[test]
+fn thread_pool_timeout_fires() {
+    let pool = ThreadPool::builder().pool_size(1).create().unwrap();
+    let (tx, rx) = mpsc::channel();
+    let mut fut = FutureExt::<ThreadPool>::timeout(pending::<()>(), Duration::from_millis(20));
+    let task = poll_fn(move |cx: &mut Context<ThreadPool>| {
+        match PinMut::new(&mut fut).poll(cx) {
+            Poll::Ready(res) => {
+                tx.send(res).unwrap();
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    });
+    pool.spawn_obj_with_spawner(
+        FutureObj::new(Box::new(task)),
+        |pool| pool,
+    ).unwrap();
+    assert!(rx.recv_timeout(Duration::from_secs(10)).unwrap().is_err());
+}
+
+#[test]
+fn mock_clock_fires_in_deadline_order() {
+    let clock = MockClock::new();
+    let timer = Timer::with_clock(clock.clone());
+    let handle = timer.handle();
+    let mut late = handle.sleep(Duration::from_secs(2));
+    let mut early = handle.sleep(Duration::from_secs(1));
+    assert_eq!(timer.turn(), Some(early.deadline()));
+
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(timer.turn(), Some(late.deadline()));
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut early).poll(cx)), Poll::Ready(()));
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut late).poll(cx)), Poll::Pending);
+
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(timer.turn(), None);
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut late).poll(cx)), Poll::Ready(()));
+}
+
+#[test]
+fn dropped_sleep_is_deregistered() {
+    let timer = Timer::new();
+    let sleep = timer.handle().sleep(Duration::from_secs(3600));
+    assert_eq!(timer.turn(), Some(sleep.deadline()));
+    drop(sleep);
+    assert_eq!(timer.turn(), None);
+}
+
+#[test]
+fn many_cancelled_sleeps_keep_timer_working() {
+    let clock = MockClock::new();
+    let timer = Timer::with_clock(clock.clone());
+    let handle = timer.handle();
+    let mut kept = handle.sleep(Duration::from_secs(1));
+    for _ in 0..10_000 {
+        drop(handle.sleep(Duration::from_secs(2)));
+    }
+    assert_eq!(timer.turn(), Some(kept.deadline()));
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(timer.turn(), None);
+    assert_eq!(with_noop_context(|cx| PinMut::new(&mut kept).poll(cx)), Poll::Ready(()));
+}
+
+/// Creates another sleep on the timer when woken, which deadlocks if the
+/// timer wakes while holding its lock.
+struct SleepOnWake(TimerHandle);
+
+impl Wake for SleepOnWake {
+    fn wake(arc_self: &Arc<Self>) {
+        drop(arc_self.0.sleep(Duration::from_secs(1)));
+    }
+}
+
+#[test]
+fn turn_wakes_outside_the_lock() {
+    let clock = MockClock::new();
+    let timer = Timer::with_clock(clock.clone());
+    let wake = Arc::new(SleepOnWake(timer.handle()));
+    let local_waker = local_waker_from_nonlocal(wake.clone());
+    let waker = Waker::from(wake);
+    let mut sleep = timer.handle().sleep(Duration::from_secs(1));
+    {
+        let mut spawner = NoSpawn;
+        let mut cx = Context::new(&local_waker, &waker, &mut spawner);
+        assert_eq!(PinMut::new(&mut sleep).poll(&mut cx), Poll::Pending);
+    }
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(timer.turn(), None);
+}
+
+type MockSpawner = TimerSpawner<NoSpawn>;
+
+fn poll_interval(interval: &mut Interval<MockSpawner>, spawner: &mut MockSpawner) -> Poll<Option<()>> {
+    with_counting_context(spawner, |cx| PinMut::new(interval).poll_next(cx)).1
+}
+
+#[test]
+fn interval_ticks_once_per_period() {
+    let clock = MockClock::new();
+    let timer = Timer::with_clock(clock.clone());
+    let mut spawner = TimerSpawner::new(NoSpawn, timer.handle());
+    let mut interval = interval(Duration::from_secs(1));
+    let mut ticks = 0;
+    for _ in 0..5 {
+        assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Pending);
+        clock.advance(Duration::from_millis(500));
+        timer.turn();
+        assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Pending);
+        clock.advance(Duration::from_millis(500));
+        timer.turn();
+        if poll_interval(&mut interval, &mut spawner) == Poll::Ready(Some(())) {
+            ticks += 1;
+        }
+    }
+    assert_eq!(ticks, 5);
+}
+
+#[test]
+fn interval_skips_missed_ticks() {
+    let clock = MockClock::new();
+    let timer = Timer::with_clock(clock.clone());
+    let mut spawner = TimerSpawner::new(NoSpawn, timer.handle());
+    let mut interval = interval(Duration::from_secs(1));
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Pending);
+
+    // Several periods pass without the interval being polled, but only one
+    // tick is delivered, and the next one is a full period later.
+    clock.advance(Duration::from_millis(3500));
+    timer.turn();
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Ready(Some(())));
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Pending);
+    clock.advance(Duration::from_millis(999));
+    timer.turn();
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Pending);
+    clock.advance(Duration::from_millis(1));
+    timer.turn();
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Ready(Some(())));
+}
+
+#[test]
+fn interval_reset_restarts_period() {
+    let clock = MockClock::new();
+    let timer = Timer::with_clock(clock.clone());
+    let mut spawner = TimerSpawner::new(NoSpawn, timer.handle());
+    let mut interval = interval(Duration::from_secs(1));
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Pending);
+    clock.advance(Duration::from_millis(600));
+    interval.reset();
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Pending);
+
+    clock.advance(Duration::from_millis(600));
+    timer.turn();
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Pending);
+    clock.advance(Duration::from_millis(400));
+    timer.turn();
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Ready(Some(())));
+}
+
+#[test]
+fn interval_set_period_applies_after_scheduled_tick() {
+    let clock = MockClock::new();
+    let timer = Timer::with_clock(clock.clone());
+    let mut spawner = TimerSpawner::new(NoSpawn, timer.handle());
+    let mut interval = interval(Duration::from_secs(1));
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Pending);
+    interval.set_period(Duration::from_secs(2));
+    assert_eq!(interval.period(), Duration::from_secs(2));
+
+    clock.advance(Duration::from_secs(1));
+    timer.turn();
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Ready(Some(())));
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Pending);
+    clock.advance(Duration::from_secs(1));
+    timer.turn();
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Pending);
+    clock.advance(Duration::from_secs(1));
+    timer.turn();
+    assert_eq!(poll_interval(&mut interval, &mut spawner), Poll::Ready(Some(())));
+}
+
+#[test]
+#[should_panic(expected = "`period` must be non-zero")]
+fn interval_zero_period() {
+    let _ = interval::<MockSpawner>(Duration::from_secs(0));
+}
+
+/// Polls the queue with a counting context, returning the value of the
+/// expired entry, if any.
+fn poll_queue(queue: &mut DelayQueue<&'static str>) -> (Arc<WakeCounter>, Poll<Option<&'static str>>) {
+    let (wakes, ret) = with_counting_context(&mut NoSpawn, |cx| PinMut::new(queue).poll_next(cx));
+    (wakes, ret.map(|expired| expired.map(Expired::into_inner)))
+}
+
+#[test]
+fn delay_queue_yields_in_deadline_order() {
+    let clock = MockClock::new();
+    let timer = Timer::with_clock(clock.clone());
+    let mut queue = DelayQueue::new(timer.handle());
+    queue.insert("c", Duration::from_secs(3));
+    let a = queue.insert("a", Duration::from_secs(1));
+    queue.insert("b", Duration::from_secs(2));
+    assert_eq!(queue.len(), 3);
+
+    // Only the earliest deadline is armed on the timer.
+    let (wakes, ret) = poll_queue(&mut queue);
+    assert_eq!(ret, Poll::Pending);
+    assert_eq!(timer.turn(), Some(clock.now() + Duration::from_secs(1)));
+
+    clock.advance(Duration::from_secs(3));
+    timer.turn();
+    assert_eq!(wakes.get(), 1);
+    let expired = with_noop_context(|cx| PinMut::new(&mut queue).poll_next(cx));
+    match expired {
+        Poll::Ready(Some(expired)) => {
+            assert_eq!(expired.key(), a);
+            assert_eq!(*expired.get_ref(), "a");
+        }
+        other => panic!("unexpected poll: {:?}", other),
+    }
+    // An entry inserted between polls is ordered along with the rest, after
+    // those which expired earlier.
+    queue.insert("now", Duration::from_secs(0));
+    assert_eq!(poll_queue(&mut queue).1, Poll::Ready(Some("b")));
+    assert_eq!(poll_queue(&mut queue).1, Poll::Ready(Some("c")));
+    assert_eq!(poll_queue(&mut queue).1, Poll::Ready(Some("now")));
+    assert_eq!(poll_queue(&mut queue).1, Poll::Pending);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn delay_queue_reset_pushes_entry_later() {
+    let clock = MockClock::new();
+    let timer = Timer::with_clock(clock.clone());
+    let mut queue = DelayQueue::new(timer.handle());
+    let a = queue.insert("a", Duration::from_secs(1));
+    queue.insert("b", Duration::from_secs(2));
+    assert_eq!(poll_queue(&mut queue).1, Poll::Pending);
+    queue.reset(a, Duration::from_secs(3));
+
+    clock.advance(Duration::from_secs(1));
+    timer.turn();
+    assert_eq!(poll_queue(&mut queue).1, Poll::Pending);
+    clock.advance(Duration::from_secs(1));
+    timer.turn();
+    assert_eq!(poll_queue(&mut queue).1, Poll::Ready(Some("b")));
+    let (wakes, ret) = poll_queue(&mut queue);
+    assert_eq!(ret, Poll::Pending);
+    clock.advance(Duration::from_secs(1));
+    timer.turn();
+    assert_eq!(wakes.get(), 1);
+    assert_eq!(poll_queue(&mut queue).1, Poll::Ready(Some("a")));
+}
+
+#[test]
+fn delay_queue_remove_before_expiry() {
+    let clock = MockClock::new();
+    let timer = Timer::with_clock(clock.clone());
+    let mut queue = DelayQueue::new(timer.handle());
+    let a = queue.insert("a", Duration::from_secs(1));
+    let b = queue.insert("b", Duration::from_secs(2));
+    assert_eq!(poll_queue(&mut queue).1, Poll::Pending);
+    assert_eq!(queue.remove(a), Some("a"));
+    assert_eq!(queue.remove(a), None);
+    assert_eq!(queue.len(), 1);
+
+    clock.advance(Duration::from_secs(2));
+    timer.turn();
+    assert_eq!(poll_queue(&mut queue).1, Poll::Ready(Some("b")));
+    assert_eq!(poll_queue(&mut queue).1, Poll::Pending);
+    // A key is no longer in the queue once its entry has been yielded.
+    assert_eq!(queue.remove(b), None);
+}
+
+#[test]
+fn delay_queue_wakes_only_at_the_deadline() {
+    let clock = MockClock::new();
+    let timer = Timer::with_clock(clock.clone());
+    let mut queue = DelayQueue::new(timer.handle());
+
+    // An empty queue pends without ever being woken by the timer.
+    let (wakes, ret) = poll_queue(&mut queue);
+    assert_eq!(ret, Poll::Pending);
+    assert_eq!(timer.turn(), None);
+    clock.advance(Duration::from_secs(10));
+    timer.turn();
+    assert_eq!(wakes.get(), 0);
+
+    // Inserting wakes the task so that it can arm the new deadline.
+    queue.insert("a", Duration::from_secs(1));
+    assert_eq!(wakes.get(), 1);
+
+    let (wakes, ret) = poll_queue(&mut queue);
+    assert_eq!(ret, Poll::Pending);
+    clock.advance(Duration::from_millis(999));
+    timer.turn();
+    assert_eq!(wakes.get(), 0);
+    // A later entry doesn't move the deadline, so doesn't wake the task.
+    queue.insert("b", Duration::from_secs(5));
+    assert_eq!(wakes.get(), 0);
+    clock.advance(Duration::from_millis(1));
+    timer.turn();
+    assert_eq!(wakes.get(), 1);
+    assert_eq!(poll_queue(&mut queue).1, Poll::Ready(Some("a")));
+}
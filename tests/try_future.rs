@@ -0,0 +1,184 @@
+#![feature(pin, arbitrary_self_types, futures_api)]
+
+#[macro_use]
+extern crate specialized_futures;
+
+mod support;
+
+use std::cell::Cell;
+use std::mem::PinMut;
+use specialized_futures::{Context, Future, Spawn, TryFutureExt};
+use specialized_futures::future::{Either, FusedFuture, ready, Ready};
+use specialized_futures::task::Poll;
+use specialized_futures::try_future::{ErrInto, OkInto, UnwrapOrElse, try_select};
+
+use support::with_noop_context;
+
+type Widen = fn(u8) -> u32;
+
+/// Holds conversion combinators by name, which closure-based adapters
+/// couldn't be.
+struct Conversions {
+    err: ErrInto<Ready<Result<u8, u8>>, u32>,
+    ok: OkInto<Ready<Result<u8, u8>>, u32>,
+    unwrap: UnwrapOrElse<Ready<Result<u32, u8>>, Widen>,
+}
+
+fn widen(e: u8) -> u32 {
+    u32::from(e) + 100
+}
+
+#[test]
+fn conversions_nameable_in_struct_fields() {
+    let conv = Conversions {
+        err: TryFutureExt::<dyn Spawn>::err_into(ready(Err(1))),
+        ok: TryFutureExt::<dyn Spawn>::ok_into(ready(Ok(2))),
+        unwrap: TryFutureExt::<dyn Spawn>::unwrap_or_else(ready(Err(3)), widen as Widen),
+    };
+    let Conversions { err, ok, unwrap } = conv;
+    pin_mut!(err, ok, unwrap);
+    with_noop_context(|cx| {
+        assert_eq!(err.reborrow().poll(cx), Poll::Ready(Err(1u32)));
+        assert_eq!(ok.reborrow().poll(cx), Poll::Ready(Ok(2u32)));
+        assert_eq!(unwrap.reborrow().poll(cx), Poll::Ready(103));
+    });
+}
+
+#[test]
+fn err_into_leaves_ok_untouched() {
+    let fut: ErrInto<_, u32> = TryFutureExt::<dyn Spawn>::err_into(ready(Ok::<u8, u8>(7)));
+    pin_mut!(fut);
+    with_noop_context(|cx| assert_eq!(fut.reborrow().poll(cx), Poll::Ready(Ok(7u8))));
+}
+
+#[test]
+fn ok_into_leaves_err_untouched() {
+    let fut: OkInto<_, u32> = TryFutureExt::<dyn Spawn>::ok_into(ready(Err::<u8, u8>(7)));
+    pin_mut!(fut);
+    with_noop_context(|cx| assert_eq!(fut.reborrow().poll(cx), Poll::Ready(Err(7u8))));
+}
+
+#[test]
+fn unwrap_or_else_only_called_on_err() {
+    let calls = Cell::new(0);
+    let fut = TryFutureExt::<dyn Spawn>::unwrap_or_else(ready(Ok::<u32, u8>(5)), |e| {
+        calls.set(calls.get() + 1);
+        u32::from(e)
+    });
+    pin_mut!(fut);
+    with_noop_context(|cx| assert_eq!(fut.reborrow().poll(cx), Poll::Ready(5)));
+    assert_eq!(calls.get(), 0);
+
+    let fut = TryFutureExt::<dyn Spawn>::unwrap_or_else(ready(Err::<u32, u8>(6)), |e| {
+        calls.set(calls.get() + 1);
+        u32::from(e) * 2
+    });
+    pin_mut!(fut);
+    with_noop_context(|cx| assert_eq!(fut.reborrow().poll(cx), Poll::Ready(12)));
+    assert_eq!(calls.get(), 1);
+}
+
+/// Pends for a number of polls, then resolves to its result.
+#[derive(Debug)]
+struct After {
+    polls: usize,
+    result: Option<Result<u32, &'static str>>,
+}
+
+fn after(polls: usize, result: Result<u32, &'static str>) -> After {
+    After { polls, result: Some(result) }
+}
+
+impl Future<dyn Spawn> for After {
+    type Output = Result<u32, &'static str>;
+
+    fn poll(mut self: PinMut<Self>, _cx: &mut Context) -> Poll<Self::Output> {
+        if self.polls == 0 {
+            return Poll::Ready(self.result.take().expect("After polled after completion"));
+        }
+        self.polls -= 1;
+        Poll::Pending
+    }
+}
+
+type SelectOutput = Result<Either<(u32, After), (u32, After)>, Either<(&'static str, After), (&'static str, After)>>;
+
+/// Polls `try_select(a, b)` until it completes.
+fn run_try_select(a: After, b: After) -> SelectOutput {
+    let fut = try_select(a, b);
+    pin_mut!(fut);
+    loop {
+        if let Poll::Ready(output) = with_noop_context(|cx| fut.reborrow().poll(cx)) {
+            assert!(fut.is_terminated());
+            return output;
+        }
+        assert!(!fut.is_terminated());
+    }
+}
+
+/// Polls the future which lost a `try_select` to completion.
+fn finish(mut loser: After) -> Result<u32, &'static str> {
+    loop {
+        if let Poll::Ready(output) = with_noop_context(|cx| PinMut::new(&mut loser).poll(cx)) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn try_select_left_ok() {
+    match run_try_select(after(1, Ok(1)), after(3, Err("b"))) {
+        Ok(Either::Left((1, b))) => assert_eq!(finish(b), Err("b")),
+        other => panic!("unexpected output: {:?}", other),
+    }
+}
+
+#[test]
+fn try_select_left_err() {
+    match run_try_select(after(1, Err("a")), after(3, Ok(2))) {
+        Err(Either::Left(("a", b))) => assert_eq!(finish(b), Ok(2)),
+        other => panic!("unexpected output: {:?}", other),
+    }
+}
+
+#[test]
+fn try_select_right_ok() {
+    match run_try_select(after(3, Err("a")), after(1, Ok(2))) {
+        Ok(Either::Right((2, a))) => assert_eq!(finish(a), Err("a")),
+        other => panic!("unexpected output: {:?}", other),
+    }
+}
+
+#[test]
+fn try_select_right_err() {
+    match run_try_select(after(3, Ok(1)), after(1, Err("b"))) {
+        Err(Either::Right(("b", a))) => assert_eq!(finish(a), Ok(1)),
+        other => panic!("unexpected output: {:?}", other),
+    }
+}
+
+#[test]
+fn try_select_prefers_left_when_both_ready() {
+    match run_try_select(after(2, Ok(1)), after(2, Err("b"))) {
+        Ok(Either::Left((1, b))) => {
+            // The loser was ready too, but wasn't polled on the deciding
+            // poll, so its result is still there to be driven out of it.
+            assert_eq!(b.polls, 0);
+            assert_eq!(finish(b), Err("b"));
+        }
+        other => panic!("unexpected output: {:?}", other),
+    }
+    match run_try_select(after(0, Err("a")), after(0, Ok(2))) {
+        Err(Either::Left(("a", b))) => assert_eq!(finish(b), Ok(2)),
+        other => panic!("unexpected output: {:?}", other),
+    }
+}
+
+#[test]
+#[should_panic(expected = "cannot poll TrySelect twice")]
+fn try_select_polled_after_completion() {
+    let fut = try_select(after(0, Ok(1)), after(0, Ok(2)));
+    pin_mut!(fut);
+    let _ = with_noop_context(|cx| fut.reborrow().poll(cx));
+    let _ = with_noop_context(|cx| fut.reborrow().poll(cx));
+}